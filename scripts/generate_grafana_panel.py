@@ -21,7 +21,6 @@ COUNTER_RATE_METRICS = {
     "rapl_energy_joules",
     "snmp",
     "softnet",
-    "vmstat",
 }
 BYTE_FIELD_TOKENS = ("byte", "bytes", "octet")
 
@@ -22,16 +22,47 @@ PROJECT_ROOT = Path(__file__).resolve().parent.parent
 
 SOFTNET_FIELDS = [
     "softnet_cpu_index",
-    "softnet_processed_counter",
-    "softnet_dropped_counter",
-    "softnet_time_squeeze_counter",
-    "softnet_received_rps_counter",
-    "softnet_flow_limit_count_counter",
     "softnet_backlog_len_total",
     "softnet_input_qlen",
     "softnet_process_qlen",
 ]
 
+# Keep in sync with VMSTAT_COUNTER_PREFIXES in src/datasource_procfs.rs
+VMSTAT_COUNTER_PREFIXES = (
+    "pg",
+    "pswp",
+    "allocstall_",
+    "numa_",
+    "workingset_refault_",
+    "workingset_activate_",
+    "workingset_restore_",
+    "workingset_nodereclaim",
+    "zone_reclaim_",
+    "slabs_scanned",
+    "kswapd_",
+    "pageoutrun",
+    "drop_",
+    "oom_kill",
+    "compact_",
+    "htlb_buddy_alloc_",
+    "unevictable_pgs_",
+    "thp_",
+    "balloon_",
+    "swap_ra",
+    "swpin_zero",
+    "swpout_zero",
+    "ksm_swpin_copy",
+    "cow_ksm",
+    "zswp",
+    "direct_map_level",
+    "nr_dirtied",
+    "nr_written",
+    "nr_throttled_written",
+    "nr_vmscan_write",
+    "nr_vmscan_immediate_reclaim",
+    "nr_foll_pin_",
+)
+
 DISKSTATS_FIELDS = [
     "reads",
     "reads_merged",
@@ -277,9 +308,13 @@ def _append_runtime_fields(schema: dict, args: argparse.Namespace) -> None:
     if not args.with_runtime_fields:
         return
 
+    vmstat_fields = _fields_from_key_value_file("/proc/vmstat", prefix="")
     runtime_fields = {
         "meminfo": _fields_from_key_value_file("/proc/meminfo", prefix=""),
-        "vmstat": _fields_from_key_value_file("/proc/vmstat", prefix=""),
+        "vmstat": [field for field in vmstat_fields if not field.startswith(VMSTAT_COUNTER_PREFIXES)],
+        "vmstat_events_total": [
+            field for field in vmstat_fields if field.startswith(VMSTAT_COUNTER_PREFIXES)
+        ],
         "diskstats": DISKSTATS_FIELDS,
         "netdev": NETDEV_FIELDS,
         "snmp": _fields_from_proc_net_pair_file("/proc/net/snmp", "snmp"),
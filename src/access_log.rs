@@ -0,0 +1,115 @@
+use crate::config::AppConfig;
+use crate::logging::{self, Level};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Access log event classes; each has its own option and sampling counter so
+/// a 404 scan does not crowd denied requests out of the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Authorized request (`log_allowed_requests`)
+    Allowed,
+    /// Request rejected by the token, allowlist, client certificate or rate limit
+    /// checks (`log_denied_requests`)
+    Denied,
+    /// Unknown route (`log_404_requests`)
+    NotFound,
+}
+
+static SEEN: [AtomicU64; 3] = [const { AtomicU64::new(0) }; 3];
+
+impl Event {
+    fn is_enabled(self, config: &AppConfig) -> bool {
+        match self {
+            Event::Allowed => config.log_allowed_requests,
+            Event::Denied => config.log_denied_requests,
+            Event::NotFound => config.log_404_requests,
+        }
+    }
+
+    fn level(self) -> Level {
+        match self {
+            Event::Denied => Level::Warn,
+            Event::Allowed | Event::NotFound => Level::Info,
+        }
+    }
+
+    fn seen(self) -> &'static AtomicU64 {
+        &SEEN[self as usize]
+    }
+}
+
+/// Request fields shared by every access log line
+pub struct Request<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub client_ip: Option<IpAddr>,
+}
+
+/// Whether the `index`th event (from 0) is logged when keeping `rate` of
+/// them; spreads the kept events evenly and always keeps the first one.
+fn is_sampled(index: u64, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    (index as f64 * rate).ceil() < ((index + 1) as f64 * rate).ceil()
+}
+
+/// Logs the event with method, path, status, client_ip and reason fields if
+/// its option is on and it falls in `log_sample_rate`.
+pub fn record(
+    config: &AppConfig,
+    event: Event,
+    request: &Request<'_>,
+    status: u16,
+    reason: &str,
+    message: &str,
+) {
+    if !event.is_enabled(config) {
+        return;
+    }
+    let rate = config.log_sample_rate();
+    let index = event.seen().fetch_add(1, Ordering::Relaxed);
+    if !is_sampled(index, rate) {
+        return;
+    }
+
+    let client_ip = request
+        .client_ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_default();
+    let status = status.to_string();
+    let mut fields = vec![
+        ("method", request.method),
+        ("path", request.path),
+        ("status", status.as_str()),
+        ("client_ip", client_ip.as_str()),
+    ];
+    if !reason.is_empty() {
+        fields.push(("reason", reason));
+    }
+    let rate_field = rate.to_string();
+    if rate < 1.0 {
+        fields.push(("sample_rate", rate_field.as_str()));
+    }
+    logging::log(event.level(), message, &fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampled(rate: f64, count: u64) -> Vec<u64> {
+        (0..count)
+            .filter(|index| is_sampled(*index, rate))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_sampled_spreads_kept_events() {
+        assert_eq!(sampled(1.0, 4), vec![0, 1, 2, 3]);
+        assert_eq!(sampled(0.1, 25), vec![0, 10, 20]);
+        assert_eq!(sampled(0.5, 6), vec![0, 2, 4]);
+        assert_eq!(sampled(0.25, 1000).len(), 250);
+    }
+}
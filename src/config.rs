@@ -1,13 +1,20 @@
+use crate::glob::glob_match;
+use crate::internal_metrics;
+use crate::logging::{self, Level};
+use crate::runtime;
 use ipnet::IpNet;
-use serde::Deserialize;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::ErrorKind;
 use std::net::IpAddr;
 use std::net::SocketAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const DEFAULT_BIND: &str = "127.0.0.1:9100";
+
 /// Subsystem availability checks
 struct SubsystemCheck {
     name: &'static str,
@@ -60,18 +67,138 @@ const SUBSYSTEM_CHECKS: &[SubsystemCheck] = &[
         description: "NVMe devices",
         require_entries: true,
     },
+    SubsystemCheck {
+        name: "pci",
+        path: "/sys/bus/pci/devices",
+        description: "PCI devices",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "usb",
+        path: "/sys/bus/usb/devices",
+        description: "USB devices",
+        require_entries: true,
+    },
     SubsystemCheck {
         name: "ipmi",
         path: "/dev/ipmi0",
         description: "IPMI device",
         require_entries: false,
     },
+    SubsystemCheck {
+        name: "buddyinfo",
+        path: "/proc/buddyinfo",
+        description: "Buddy allocator free lists",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "slabinfo",
+        path: "/proc/slabinfo",
+        description: "Slab allocator statistics",
+        require_entries: false,
+    },
     SubsystemCheck {
         name: "mdraid",
         path: "/proc/mdstat",
         description: "MD RAID status",
         require_entries: false,
     },
+    SubsystemCheck {
+        name: "schedstat",
+        path: "/proc/schedstat",
+        description: "Scheduler statistics",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "dmi",
+        path: "/sys/class/dmi/id",
+        description: "DMI/SMBIOS",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "entropy",
+        path: "/proc/sys/kernel/random/entropy_avail",
+        description: "Kernel entropy pool",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "ksm",
+        path: "/sys/kernel/mm/ksm",
+        description: "Kernel samepage merging",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "watchdog",
+        path: "/sys/class/watchdog",
+        description: "Watchdog devices",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "swaps",
+        path: "/proc/swaps",
+        description: "Swap devices",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "bcache",
+        path: "/sys/fs/bcache",
+        description: "bcache",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "btrfs",
+        path: "/sys/fs/btrfs",
+        description: "btrfs",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "xfs",
+        path: "/proc/fs/xfs/stat",
+        description: "XFS",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "zfs",
+        path: "/proc/spl/kstat/zfs/arcstats",
+        description: "ZFS",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "sctp",
+        path: "/proc/net/sctp",
+        description: "SCTP",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "fibrechannel",
+        path: "/sys/class/fc_host",
+        description: "Fibre channel hosts",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "drbd",
+        path: "/proc/drbd",
+        description: "DRBD",
+        require_entries: false,
+    },
+    SubsystemCheck {
+        name: "block",
+        path: "/sys/block",
+        description: "Block devices",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "cpu_vulns",
+        path: "/sys/devices/system/cpu/vulnerabilities",
+        description: "CPU vulnerability reporting",
+        require_entries: true,
+    },
+    SubsystemCheck {
+        name: "cgroups",
+        path: "/sys/fs/cgroup/cgroup.controllers",
+        description: "cgroup v2 unified hierarchy",
+        require_entries: false,
+    },
     SubsystemCheck {
         name: "netdev_sysfs",
         path: "/sys/class/net",
@@ -80,6 +207,73 @@ const SUBSYSTEM_CHECKS: &[SubsystemCheck] = &[
     },
 ];
 
+/// From linux/capability.h
+const CAP_NET_ADMIN: u32 = 12;
+const CAP_SYS_RAWIO: u32 = 17;
+const CAP_SYS_ADMIN: u32 = 21;
+
+/// Optional collector features that fail at runtime without a capability,
+/// even when their kernel interface exists; the rest of the datasource
+/// keeps running when the feature is switched off
+struct CapabilityCheck {
+    datasource: &'static str,
+    /// Config key shown in the warning, e.g. "[block] smart"
+    feature: &'static str,
+    capability: u32,
+    capability_name: &'static str,
+    enabled: fn(&AppConfig) -> bool,
+    disable: fn(&mut AppConfig),
+}
+
+const CAPABILITY_CHECKS: &[CapabilityCheck] = &[
+    // ATA pass-through over SG_IO
+    CapabilityCheck {
+        datasource: "block",
+        feature: "[block] smart",
+        capability: CAP_SYS_RAWIO,
+        capability_name: "CAP_SYS_RAWIO",
+        enabled: |config| config.block.smart,
+        disable: |config| config.block.smart = false,
+    },
+    // nfnetlink rejects every request from senders without CAP_NET_ADMIN;
+    // per-CPU stats then come from /proc/net/stat/nf_conntrack, but the
+    // table can only be dumped over netlink
+    CapabilityCheck {
+        datasource: "conntrack",
+        feature: "[conntrack] table_summary",
+        capability: CAP_NET_ADMIN,
+        capability_name: "CAP_NET_ADMIN",
+        enabled: |config| config.conntrack.table_summary,
+        disable: |config| config.conntrack.table_summary = false,
+    },
+    // NVME_IOCTL_ADMIN_CMD
+    CapabilityCheck {
+        datasource: "nvme",
+        feature: "[nvme] smart_log",
+        capability: CAP_SYS_ADMIN,
+        capability_name: "CAP_SYS_ADMIN",
+        enabled: |config| config.nvme.smart_log,
+        disable: |config| config.nvme.smart_log = false,
+    },
+];
+
+/// Parses the effective capability mask from /proc/<pid>/status
+fn parse_effective_capabilities(status: &str) -> Option<u64> {
+    let mask = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))?;
+    u64::from_str_radix(mask.trim(), 16).ok()
+}
+
+fn effective_capabilities() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    parse_effective_capabilities(&status)
+}
+
+fn has_capability(effective: u64, capability: u32) -> bool {
+    effective & (1u64 << capability) != 0
+}
+
 fn check_path_available(path: &Path, require_entries: bool) -> bool {
     if !path.exists() {
         return false;
@@ -95,28 +289,358 @@ fn check_path_available(path: &Path, require_entries: bool) -> bool {
     }
 }
 
-fn check_subsystem_available(check: &SubsystemCheck) -> bool {
-    check_path_available(Path::new(check.path), check.require_entries)
+/// A key given either as a single string or as a list of strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::One(value) => vec![value],
+        StringOrList::Many(values) => values,
+    })
+}
+
+/// Metric names (and so namespaces) per the Prometheus data model
+fn is_valid_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == ':' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Label names per the Prometheus data model; `__` is reserved for internal use
+fn is_valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+    !name.starts_with("__") && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
-#[derive(Debug, Deserialize)]
+/// Label removed from matching metrics at encode time; series are summed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LabelDrop {
+    /// Metric name or glob pattern, with or without metric_namespace
+    pub metric: String,
+    pub label: String,
+}
+
+/// Where the mdraid datasource reads array status from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MdraidSource {
+    /// Prefer /sys/block/md*/md, fall back to /proc/mdstat
+    #[default]
+    Auto,
+    Sysfs,
+    Mdstat,
+}
+
+/// Log line format on stderr
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Plain human-readable messages
+    #[default]
+    Text,
+    /// One JSON object per line with `level`, `msg` and context fields
+    Json,
+}
+
+/// `[filesystems]` section
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 #[serde(default)]
-pub struct AppConfig {
+pub struct FilesystemsConfig {
+    pub ignore_ramfs: bool,
+    /// Give up on statvfs after this long (hung NFS mounts); 0 disables the guard
+    pub stat_timeout_ms: u64,
+    /// Glob patterns of mountpoints to skip
+    pub mount_exclude: Vec<String>,
+    /// Filesystem types skipped in addition to the built-in pseudo filesystems
+    pub fs_exclude: Vec<String>,
+}
+
+impl Default for FilesystemsConfig {
+    fn default() -> Self {
+        Self {
+            ignore_ramfs: true,
+            stat_timeout_ms: 2000,
+            mount_exclude: [
+                "/dev", "/dev/*", "/proc", "/proc/*", "/sys", "/sys/*", "/run", "/run/*",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            fs_exclude: Vec::new(),
+        }
+    }
+}
+
+/// `[mdraid]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MdraidConfig {
+    pub source: MdraidSource,
+}
+
+/// `[cgroups]` section
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CgroupsConfig {
+    /// cgroup v2 paths (relative to /sys/fs/cgroup, `*`/`?` per segment) to account
+    pub paths: Vec<String>,
+}
+
+impl Default for CgroupsConfig {
+    fn default() -> Self {
+        Self {
+            paths: vec!["system.slice/*.service".to_string()],
+        }
+    }
+}
+
+/// `[processes]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProcessesConfig {
+    /// Export the N command names using the most resident memory (max 50); 0 disables
+    pub top_n: usize,
+}
+
+/// `[slabinfo]` section
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SlabinfoConfig {
+    /// Export the N slab caches holding the most memory (max 200)
+    pub top_n: usize,
+}
+
+impl Default for SlabinfoConfig {
+    fn default() -> Self {
+        Self { top_n: 20 }
+    }
+}
+
+/// `[procfs]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProcfsConfig {
+    /// Glob patterns of /proc/vmstat keys to export; empty exports all of them
+    pub vmstat_fields: Vec<String>,
+}
+
+/// `[block]` section
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BlockConfig {
+    /// Skip loop* devices in block, diskstats and filesystem metrics
     pub ignore_loop_devices: bool,
-    pub ignore_ramfs_filesystems: bool,
+    /// Read SATA SMART attributes via ATA pass-through (needs CAP_SYS_RAWIO)
+    pub smart: bool,
+}
+
+impl Default for BlockConfig {
+    fn default() -> Self {
+        Self {
+            ignore_loop_devices: true,
+            smart: false,
+        }
+    }
+}
+
+/// `[netdev]` section, shared by the procfs and sysfs network collectors
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NetdevConfig {
+    /// Skip ppp* interfaces
     pub ignore_ppp_interfaces: bool,
+    /// Skip veth* and Docker's br-* interfaces
     pub ignore_veth_interfaces: bool,
+}
+
+impl Default for NetdevConfig {
+    fn default() -> Self {
+        Self {
+            ignore_ppp_interfaces: true,
+            ignore_veth_interfaces: true,
+        }
+    }
+}
+
+/// `[hwmon]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HwmonConfig {
+    /// Glob patterns of chip names (hwmon*/name) to skip, e.g. "nvme"
+    pub chip_exclude: Vec<String>,
+}
+
+/// `[nvme]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NvmeConfig {
+    /// Read the SMART / health log page via the admin ioctl (needs CAP_SYS_ADMIN)
+    pub smart_log: bool,
+}
+
+/// `[conntrack]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConntrackConfig {
+    /// Count table entries by protocol and TCP state; walks the whole table
+    pub table_summary: bool,
+}
+
+/// At most this many `[[custom_file]]` entries are read; the rest are ignored
+pub const MAX_CUSTOM_FILES: usize = 64;
+
+/// `[[custom_file]]` entry: a single-value file exported as a gauge
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CustomFile {
+    /// File holding one number, e.g. under /sys; honours sysfs_root/procfs_root
+    pub path: String,
+    /// Gauge name (without metric_namespace)
+    pub metric: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// `[ipmi]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IpmiConfig {
+    /// Read the BMC's FRU inventory (manufacturer, product, serial, part number)
+    pub read_fru: bool,
+}
+
+/// `[pci]` section
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PciConfig {
+    /// Glob patterns of PCI base class names (e.g. "network", "display") to export
+    pub classes: Vec<String>,
+}
+
+impl Default for PciConfig {
+    fn default() -> Self {
+        Self {
+            classes: vec!["network".to_string(), "storage".to_string()],
+        }
+    }
+}
+
+/// `[usb]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UsbConfig {
+    /// Glob patterns of `vendor:product` IDs (e.g. "0403:*") to export in
+    /// usb_device_info; empty exports every device
+    pub devices: Vec<String>,
+}
+
+/// `[modem]` section
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ModemConfig {
+    /// Query ModemManager over the D-Bus system bus; off by default
+    pub enabled: bool,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Export only the `total` series of per-CPU metrics (procfs cpu times
+    /// and usage, cpufreq time in state and transitions, softnet, schedstat,
+    /// conntrack)
+    pub cpu_aggregate_only: bool,
     #[serde(default)]
     pub disabled_datasources: Vec<String>,
+    /// Datasource -> N: run it on every Nth collection only, e.g. `{ ipmi = 5 }`
+    pub interval_multiplier: BTreeMap<String, u64>,
     pub allowed_ip: Vec<String>,
-    pub bind: String,
+    /// One listen address or a list of them, e.g. `["10.0.0.5:9100", "[fd00::5]:9100"]`
+    #[serde(deserialize_with = "string_or_list")]
+    pub bind: Vec<String>,
     pub log_denied_requests: bool,
     pub log_404_requests: bool,
+    /// Log authorized /metrics, /metrics.json and /collectors requests
+    pub log_allowed_requests: bool,
+    /// Fraction (0, 1] of access log events written, per event class
+    log_sample_rate: f64,
+    pub log_format: LogFormat,
+    /// Per-client-IP limit on /metrics requests; 0 disables the limit
+    pub metrics_rate_limit_per_second: f64,
+    /// Requests allowed to collect at the same time; 0 is unlimited
+    pub max_concurrent_scrapes: usize,
+    /// PEM certificate chain; `tls_cert_path` is accepted as an alias
+    #[serde(alias = "tls_cert_path")]
     pub tls_cert: Option<String>,
+    /// PEM private key (PKCS#8, PKCS#1 RSA or SEC1 EC); alias `tls_key_path`
+    #[serde(alias = "tls_key_path")]
     pub tls_key: Option<String>,
+    /// PEM CA bundle; when set with TLS, clients must present a certificate it signed
+    pub tls_ca_path: Option<String>,
+    /// Client certificate CN/SAN DNS names (globs) allowed under mutual TLS;
+    /// empty allows any verified client
+    pub allowed_client_names: Vec<String>,
     pub auth_token: Option<String>,
+    /// Collect in a background thread every N seconds; 0 collects on each
+    /// request. Read at startup only, a reload keeps the running value
+    pub collection_interval_seconds: u64,
+    /// Append the collection time (ms) to every sample in the text format
+    pub emit_timestamps: bool,
+    /// Seconds in-flight scrapes may take to finish after SIGTERM/SIGINT
+    pub shutdown_timeout_seconds: u64,
+    /// Where sysfs is mounted, e.g. "/host/sys" for a containerized exporter
+    pub sysfs_root: String,
+    /// Where procfs is mounted; only path-based /proc readers honour it
+    pub procfs_root: String,
+    pub filesystems: FilesystemsConfig,
+    pub mdraid: MdraidConfig,
+    pub cgroups: CgroupsConfig,
+    pub processes: ProcessesConfig,
+    pub procfs: ProcfsConfig,
+    pub slabinfo: SlabinfoConfig,
+    pub block: BlockConfig,
+    pub netdev: NetdevConfig,
+    pub hwmon: HwmonConfig,
+    pub nvme: NvmeConfig,
+    pub conntrack: ConntrackConfig,
+    pub ipmi: IpmiConfig,
+    pub pci: PciConfig,
+    pub usb: UsbConfig,
+    pub modem: ModemConfig,
+    pub custom_file: Vec<CustomFile>,
+    /// Prefix prepended as `<namespace>_` to every exported metric name
+    pub metric_namespace: Option<String>,
+    /// Glob patterns of metric names to export; empty exports everything
+    pub metric_allowlist: Vec<String>,
+    /// Glob patterns of metric names to drop; wins over the allowlist
+    pub metric_denylist: Vec<String>,
+    /// Glob patterns of metric names collectors skip; also dropped from output
+    pub disabled_metrics: Vec<String>,
+    pub label_drop: Vec<LabelDrop>,
+    /// Labels added to every exported series, e.g. `datacenter = "dc1"`
+    pub static_labels: BTreeMap<String, String>,
+    // Top-level spellings from before the per-datasource sections; moved into
+    // their section by `apply_legacy_keys` unless the section sets the key too
+    ignore_loop_devices: Option<bool>,
+    ignore_ppp_interfaces: Option<bool>,
+    ignore_veth_interfaces: Option<bool>,
+    ignore_ramfs_filesystems: Option<bool>,
+    /// Disabled datasource -> why it was disabled
     #[serde(skip)]
-    disabled_set: HashSet<String>,
+    disabled_set: HashMap<String, String>,
     #[serde(skip)]
     allowed_metrics_nets: Vec<IpNet>,
 }
@@ -124,30 +648,75 @@ pub struct AppConfig {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            ignore_loop_devices: true,
-            ignore_ramfs_filesystems: true,
-            ignore_ppp_interfaces: true,
-            ignore_veth_interfaces: true,
+            cpu_aggregate_only: false,
             disabled_datasources: Vec::new(),
+            interval_multiplier: BTreeMap::new(),
             allowed_ip: vec!["127.0.0.0/8".to_string()],
-            bind: "127.0.0.1:9100".to_string(),
+            bind: vec![DEFAULT_BIND.to_string()],
             log_denied_requests: true,
             log_404_requests: false,
+            log_allowed_requests: false,
+            log_sample_rate: 1.0,
+            log_format: LogFormat::Text,
+            metrics_rate_limit_per_second: 0.0,
+            max_concurrent_scrapes: 0,
             tls_cert: None,
             tls_key: None,
+            tls_ca_path: None,
+            allowed_client_names: Vec::new(),
             auth_token: None,
-            disabled_set: HashSet::new(),
+            collection_interval_seconds: 0,
+            emit_timestamps: false,
+            shutdown_timeout_seconds: 10,
+            sysfs_root: "/sys".to_string(),
+            procfs_root: "/proc".to_string(),
+            filesystems: FilesystemsConfig::default(),
+            mdraid: MdraidConfig::default(),
+            cgroups: CgroupsConfig::default(),
+            processes: ProcessesConfig::default(),
+            procfs: ProcfsConfig::default(),
+            slabinfo: SlabinfoConfig::default(),
+            block: BlockConfig::default(),
+            netdev: NetdevConfig::default(),
+            hwmon: HwmonConfig::default(),
+            nvme: NvmeConfig::default(),
+            conntrack: ConntrackConfig::default(),
+            ipmi: IpmiConfig::default(),
+            pci: PciConfig::default(),
+            usb: UsbConfig::default(),
+            modem: ModemConfig::default(),
+            custom_file: Vec::new(),
+            metric_namespace: None,
+            metric_allowlist: Vec::new(),
+            metric_denylist: Vec::new(),
+            disabled_metrics: Vec::new(),
+            label_drop: Vec::new(),
+            static_labels: BTreeMap::new(),
+            ignore_loop_devices: None,
+            ignore_ppp_interfaces: None,
+            ignore_veth_interfaces: None,
+            ignore_ramfs_filesystems: None,
+            disabled_set: HashMap::new(),
             allowed_metrics_nets: Vec::new(),
         }
     }
 }
 
 impl AppConfig {
-    pub fn bind_addr(&self) -> SocketAddr {
-        self.bind.parse().unwrap_or_else(|err| {
-            eprintln!("Invalid bind address '{}': {err}", self.bind);
-            "127.0.0.1:9100".parse().expect("default bind")
-        })
+    /// Valid listen addresses, falling back to the default when none parse.
+    pub fn bind_addrs(&self) -> Vec<SocketAddr> {
+        let mut addrs = Vec::new();
+        for bind in &self.bind {
+            match bind.parse::<SocketAddr>() {
+                Ok(addr) if !addrs.contains(&addr) => addrs.push(addr),
+                Ok(_) => {}
+                Err(err) => logging::warn(&format!("Invalid bind address '{bind}': {err}")),
+            }
+        }
+        if addrs.is_empty() {
+            addrs.push(DEFAULT_BIND.parse().expect("default bind"));
+        }
+        addrs
     }
 
     pub fn tls_config(&self) -> Option<(&str, &str)> {
@@ -157,6 +726,175 @@ impl AppConfig {
         }
     }
 
+    /// CA bundle for client certificate verification; only used with TLS
+    pub fn mtls_ca(&self) -> Option<&str> {
+        self.tls_config()?;
+        self.tls_ca_path.as_deref()
+    }
+
+    /// Checks verified client certificate names against allowed_client_names
+    pub fn is_client_identity_allowed(&self, names: &[String]) -> bool {
+        self.allowed_client_names.is_empty()
+            || names.iter().any(|name| {
+                self.allowed_client_names
+                    .iter()
+                    .any(|pattern| glob_match(pattern, name))
+            })
+    }
+
+    pub fn is_mountpoint_excluded(&self, mountpoint: &str) -> bool {
+        self.filesystems
+            .mount_exclude
+            .iter()
+            .any(|pattern| glob_match(pattern, mountpoint))
+    }
+
+    pub fn is_hwmon_chip_excluded(&self, chip: &str) -> bool {
+        self.hwmon
+            .chip_exclude
+            .iter()
+            .any(|pattern| glob_match(pattern, chip))
+    }
+
+    pub fn is_pci_class_enabled(&self, class: &str) -> bool {
+        self.pci
+            .classes
+            .iter()
+            .any(|pattern| glob_match(pattern, class))
+    }
+
+    pub fn is_usb_device_enabled(&self, vendor_id: &str, product_id: &str) -> bool {
+        let id = format!("{vendor_id}:{product_id}");
+        self.usb.devices.is_empty()
+            || self
+                .usb
+                .devices
+                .iter()
+                .any(|pattern| glob_match(pattern, &id))
+    }
+
+    pub fn is_vmstat_field_enabled(&self, field: &str) -> bool {
+        self.procfs.vmstat_fields.is_empty()
+            || self
+                .procfs
+                .vmstat_fields
+                .iter()
+                .any(|pattern| glob_match(pattern, field))
+    }
+
+    /// Maps a /sys or /proc path onto sysfs_root or procfs_root.
+    pub fn host_path(&self, path: &str) -> PathBuf {
+        for (prefix, root) in [("/sys", &self.sysfs_root), ("/proc", &self.procfs_root)] {
+            if let Some(rest) = path.strip_prefix(prefix)
+                && (rest.is_empty() || rest.starts_with('/'))
+            {
+                return PathBuf::from(format!("{}{rest}", root.trim_end_matches('/')));
+            }
+        }
+        PathBuf::from(path)
+    }
+
+    pub fn is_fstype_excluded(&self, fstype: &str) -> bool {
+        self.filesystems
+            .fs_exclude
+            .iter()
+            .any(|excluded| excluded == fstype)
+    }
+
+    /// Validated namespace, or "" when unset or invalid
+    pub fn metric_namespace(&self) -> &str {
+        match &self.metric_namespace {
+            Some(namespace) if is_valid_metric_name(namespace) => namespace,
+            _ => "",
+        }
+    }
+
+    /// Whether collectors should gather a metric (name without namespace);
+    /// false when it matches `disabled_metrics`
+    pub fn is_metric_enabled(&self, name: &str) -> bool {
+        !self
+            .disabled_metrics
+            .iter()
+            .any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Applies metric_allowlist/metric_denylist to a metric name (without namespace).
+    /// Disabled metrics are dropped too, since collectors that do not check
+    /// `is_metric_enabled` still update them and series set before a reload remain
+    pub fn is_metric_exported(&self, name: &str) -> bool {
+        if !self.is_metric_enabled(name)
+            || self
+                .metric_denylist
+                .iter()
+                .any(|pattern| glob_match(pattern, name))
+        {
+            return false;
+        }
+        self.metric_allowlist.is_empty()
+            || self
+                .metric_allowlist
+                .iter()
+                .any(|pattern| glob_match(pattern, name))
+    }
+
+    /// static_labels with valid names, in name order
+    pub fn static_labels(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.static_labels
+            .iter()
+            .filter(|(name, _)| is_valid_label_name(name))
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// `[[custom_file]]` entries that pass validation, in config order
+    pub fn custom_files(&self) -> impl Iterator<Item = &CustomFile> {
+        self.custom_file
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.custom_file_problem(*index).is_none())
+            .map(|(_, entry)| entry)
+    }
+
+    /// Why a `[[custom_file]]` entry is skipped, if it is
+    fn custom_file_problem(&self, index: usize) -> Option<String> {
+        let entry = &self.custom_file[index];
+        if index >= MAX_CUSTOM_FILES {
+            return Some(format!(
+                "{}: more than {MAX_CUSTOM_FILES} entries",
+                entry.path
+            ));
+        }
+        if !is_valid_metric_name(&entry.metric) {
+            return Some(format!(
+                "{}: invalid metric name '{}'",
+                entry.path, entry.metric
+            ));
+        }
+        if let Some(name) = entry.labels.keys().find(|name| !is_valid_label_name(name)) {
+            return Some(format!("{}: invalid label name '{name}'", entry.path));
+        }
+        // All series of one gauge need the same label names
+        if let Some(first) = self.custom_file[..index]
+            .iter()
+            .find(|other| other.metric == entry.metric)
+            && !first.labels.keys().eq(entry.labels.keys())
+        {
+            return Some(format!(
+                "{}: labels differ from an earlier '{}' entry",
+                entry.path, entry.metric
+            ));
+        }
+        None
+    }
+
+    /// log_sample_rate, or 1 (log everything) when it is not in (0, 1]
+    pub fn log_sample_rate(&self) -> f64 {
+        if self.log_sample_rate > 0.0 && self.log_sample_rate <= 1.0 {
+            self.log_sample_rate
+        } else {
+            1.0
+        }
+    }
+
     pub fn is_token_valid(&self, token: Option<&str>) -> bool {
         match &self.auth_token {
             Some(expected) => token == Some(expected.as_str()),
@@ -171,15 +909,38 @@ impl AppConfig {
     }
 
     pub fn is_datasource_enabled(&self, name: &str) -> bool {
-        !self.disabled_set.contains(name)
+        !self.disabled_set.contains_key(name)
     }
 
-    pub fn disable_datasource(&mut self, name: &str) {
-        self.disabled_set.insert(name.to_string());
+    /// Whether a datasource runs in the given collection (counted from 0);
+    /// skipped collections keep exposing its last values.
+    pub fn is_collection_due(&self, name: &str, collection: u64) -> bool {
+        match self.interval_multiplier.get(name) {
+            Some(&multiplier) if multiplier > 1 => collection.is_multiple_of(multiplier),
+            _ => true,
+        }
+    }
+
+    pub fn disable_datasource(&mut self, name: &str, reason: &str) {
+        self.disabled_set
+            .insert(name.to_string(), reason.to_string());
+    }
+
+    /// Why a datasource is disabled, or None while it is enabled.
+    pub fn disabled_reason(&self, name: &str) -> Option<&str> {
+        self.disabled_set.get(name).map(String::as_str)
     }
 
     fn build_disabled_set(&mut self) {
-        self.disabled_set = self.disabled_datasources.iter().cloned().collect();
+        self.disabled_set = self
+            .disabled_datasources
+            .iter()
+            .map(|name| (name.clone(), "disabled in config".to_string()))
+            .collect();
+        // Opt-in datasources
+        if !self.modem.enabled && self.is_datasource_enabled("modem") {
+            self.disable_datasource("modem", "not enabled ([modem] enabled = false)");
+        }
     }
 
     fn build_allowed_metrics_nets(&mut self) {
@@ -192,29 +953,265 @@ impl AppConfig {
                 // Single IP without prefix - convert to /32 (IPv4) or /128 (IPv6)
                 nets.push(IpNet::from(ip));
             } else {
-                eprintln!("Invalid allowed_ip entry {entry}: not a valid IP or CIDR");
+                logging::warn(&format!(
+                    "Invalid allowed_ip entry {entry}: not a valid IP or CIDR"
+                ));
             }
         }
         self.allowed_metrics_nets = nets;
     }
 
     pub fn load() -> Self {
-        let mut config = match fs::read_to_string("config.toml") {
-            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
-                eprintln!("Failed to parse config.toml: {err}");
-                Self::default()
-            }),
-            Err(err) if err.kind() == ErrorKind::NotFound => Self::default(),
+        let loaded = Self::try_load();
+        internal_metrics::record_config_reload(loaded.is_ok());
+        match loaded {
+            Ok(config) => config,
+            Err(err) if runtime::config_path().is_some() || runtime::strict_config() => {
+                // An explicitly requested config must not silently become defaults
+                logging::error(&err);
+                std::process::exit(1);
+            }
             Err(err) => {
-                eprintln!("Failed to read config.toml: {err}");
-                Self::default()
+                logging::error(&err);
+                let mut config = Self::default();
+                config.finalize();
+                config
             }
+        }
+    }
+
+    /// Loads the config file, failing on read or parse errors instead of
+    /// falling back to defaults so a reload can keep the running configuration.
+    pub fn try_load() -> Result<Self, String> {
+        match runtime::config_path() {
+            Some(path) => Self::try_load_from(path, true),
+            None => Self::try_load_from(Path::new(DEFAULT_CONFIG_PATH), false),
+        }
+    }
+
+    fn try_load_from(path: &Path, required: bool) -> Result<Self, String> {
+        let name = path.display().to_string();
+        let mut config = match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents, &name, runtime::strict_config())?,
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => Self::default(),
+            Err(err) => return Err(format!("Failed to read {name}: {err}")),
         };
 
-        config.build_disabled_set();
-        config.build_allowed_metrics_nets();
-        config.check_subsystems();
-        config
+        config.finalize();
+        Ok(config)
+    }
+
+    /// Parses the config text; in strict mode unknown keys and the problems
+    /// from `validate` are errors instead of warnings or silent fallbacks.
+    fn parse(contents: &str, name: &str, strict: bool) -> Result<Self, String> {
+        let deserializer = toml::Deserializer::parse(contents)
+            .map_err(|err| format!("Failed to parse {name}: {err}"))?;
+        let mut unknown = Vec::new();
+        let mut config: Self = serde_ignored::deserialize(deserializer, |path| {
+            unknown.push(path.to_string());
+        })
+        .map_err(|err| format!("Failed to parse {name}: {err}"))?;
+        let table: toml::Table = contents
+            .parse()
+            .map_err(|err| format!("Failed to parse {name}: {err}"))?;
+        config.apply_legacy_keys(&table);
+
+        let mut problems: Vec<String> = unknown
+            .iter()
+            .map(|key| format!("{key}: unknown key"))
+            .collect();
+        if !strict {
+            for problem in &problems {
+                logging::warn(&format!("Ignoring {name}: {problem}"));
+            }
+            return Ok(config);
+        }
+        problems.extend(config.validate());
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(format!("Invalid {name}: {}", problems.join("; ")))
+        }
+    }
+
+    fn apply_legacy_keys(&mut self, table: &toml::Table) {
+        let legacy = [
+            (
+                self.ignore_loop_devices.take(),
+                "ignore_loop_devices",
+                "block",
+                "ignore_loop_devices",
+                &mut self.block.ignore_loop_devices,
+            ),
+            (
+                self.ignore_ppp_interfaces.take(),
+                "ignore_ppp_interfaces",
+                "netdev",
+                "ignore_ppp_interfaces",
+                &mut self.netdev.ignore_ppp_interfaces,
+            ),
+            (
+                self.ignore_veth_interfaces.take(),
+                "ignore_veth_interfaces",
+                "netdev",
+                "ignore_veth_interfaces",
+                &mut self.netdev.ignore_veth_interfaces,
+            ),
+            (
+                self.ignore_ramfs_filesystems.take(),
+                "ignore_ramfs_filesystems",
+                "filesystems",
+                "ignore_ramfs",
+                &mut self.filesystems.ignore_ramfs,
+            ),
+        ];
+        for (value, old_key, section, key, target) in legacy {
+            let Some(value) = value else { continue };
+            let in_section = table
+                .get(section)
+                .and_then(|section| section.get(key))
+                .is_some();
+            if in_section {
+                logging::warn(&format!(
+                    "Both {old_key} and [{section}] {key} are set, using [{section}] {key}."
+                ));
+            } else {
+                *target = value;
+            }
+        }
+    }
+
+    /// Values the lenient mode replaces with defaults or skips.
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.bind.is_empty() {
+            problems.push("bind: no listen address".to_string());
+        }
+        for bind in &self.bind {
+            if let Err(err) = bind.parse::<SocketAddr>() {
+                problems.push(format!("bind: invalid address '{bind}': {err}"));
+            }
+        }
+        for entry in &self.allowed_ip {
+            if IpNet::from_str(entry).is_err() && entry.parse::<IpAddr>().is_err() {
+                problems.push(format!(
+                    "allowed_ip: invalid entry '{entry}': not a valid IP or CIDR"
+                ));
+            }
+        }
+        if let Some(namespace) = &self.metric_namespace
+            && !is_valid_metric_name(namespace)
+        {
+            problems.push(format!(
+                "metric_namespace: '{namespace}' is not a valid metric name prefix"
+            ));
+        }
+        for name in self.static_labels.keys() {
+            if !is_valid_label_name(name) {
+                problems.push(format!("static_labels: '{name}' is not a valid label name"));
+            }
+        }
+        if self.log_sample_rate() != self.log_sample_rate {
+            problems.push(format!(
+                "log_sample_rate: {} is not in (0, 1]",
+                self.log_sample_rate
+            ));
+        }
+        for index in 0..self.custom_file.len() {
+            if let Some(problem) = self.custom_file_problem(index) {
+                problems.push(format!("custom_file: {problem}"));
+            }
+        }
+        problems
+    }
+
+    fn finalize(&mut self) {
+        logging::set_format(self.log_format);
+        // Namespaces must themselves be valid metric name prefixes
+        if let Some(namespace) = &self.metric_namespace
+            && !is_valid_metric_name(namespace)
+        {
+            logging::warn(&format!(
+                "Invalid metric_namespace '{namespace}', metric names will not be prefixed"
+            ));
+        }
+        for name in self.static_labels.keys() {
+            if !is_valid_label_name(name) {
+                logging::warn(&format!(
+                    "Invalid static_labels name '{name}', label will not be added"
+                ));
+            }
+        }
+        if self.log_sample_rate() != self.log_sample_rate {
+            logging::warn(&format!(
+                "Invalid log_sample_rate {}, logging every access log event",
+                self.log_sample_rate
+            ));
+        }
+        for index in 0..self.custom_file.len() {
+            if let Some(problem) = self.custom_file_problem(index) {
+                logging::warn(&format!("Skipping custom_file {problem}"));
+            }
+        }
+        if self.tls_ca_path.is_some() && self.tls_config().is_none() {
+            logging::warn(
+                "tls_ca_path is set but TLS is not enabled, client certificates are not required",
+            );
+        }
+        self.build_disabled_set();
+        self.build_allowed_metrics_nets();
+        self.check_subsystems();
+        self.check_capabilities();
+        self.check_slabinfo_readable();
+    }
+
+    /// /proc/slabinfo is only readable by root (mode 0400)
+    fn check_slabinfo_readable(&mut self) {
+        if !self.is_datasource_enabled("slabinfo") {
+            return;
+        }
+        let path = self.host_path("/proc/slabinfo");
+        if let Err(err) = fs::File::open(&path)
+            && err.kind() == std::io::ErrorKind::PermissionDenied
+        {
+            logging::log(
+                Level::Warn,
+                &format!(
+                    "{} is not readable, disabling slabinfo datasource (run as root).",
+                    path.display()
+                ),
+                &[("collector", "slabinfo")],
+            );
+            let reason = format!("{} not readable", path.display());
+            self.disable_datasource("slabinfo", &reason);
+        }
+    }
+
+    fn check_capabilities(&mut self) {
+        // Unknown capabilities: let the collectors report their own errors
+        if let Some(effective) = effective_capabilities() {
+            self.disable_features_lacking_capabilities(effective);
+        }
+    }
+
+    fn disable_features_lacking_capabilities(&mut self, effective: u64) {
+        for check in CAPABILITY_CHECKS {
+            if !(check.enabled)(self)
+                || !self.is_datasource_enabled(check.datasource)
+                || has_capability(effective, check.capability)
+            {
+                continue;
+            }
+            logging::log(
+                Level::Warn,
+                &format!(
+                    "Process lacks {}, disabling {} (run as root or grant it via AmbientCapabilities={}).",
+                    check.capability_name, check.feature, check.capability_name
+                ),
+                &[("collector", check.datasource)],
+            );
+            (check.disable)(self);
+        }
     }
 
     fn check_subsystems(&mut self) {
@@ -224,12 +1221,24 @@ impl AppConfig {
                 continue;
             }
 
-            if !check_subsystem_available(check) {
-                eprintln!(
-                    "{} subsystem not available ({}), disabling {} datasource.",
-                    check.description, check.path, check.name
+            let path = self.host_path(check.path);
+            if !check_path_available(&path, check.require_entries) {
+                logging::log(
+                    Level::Info,
+                    &format!(
+                        "{} subsystem not available ({}), disabling {} datasource.",
+                        check.description,
+                        path.display(),
+                        check.name
+                    ),
+                    &[("collector", check.name)],
+                );
+                let reason = format!(
+                    "{} subsystem not available ({})",
+                    check.description,
+                    path.display()
                 );
-                self.disable_datasource(check.name);
+                self.disable_datasource(check.name, &reason);
             }
         }
     }
@@ -274,12 +1283,32 @@ mod tests {
         assert!(config.is_datasource_enabled("procfs"));
     }
 
+    #[test]
+    fn test_block_smart_parsing() {
+        assert!(!AppConfig::default().block.smart);
+        let config: AppConfig = toml::from_str("[block]\nsmart = true\n").unwrap();
+        assert!(config.block.smart);
+    }
+
+    #[test]
+    fn test_modem_is_opt_in() {
+        let mut config = AppConfig::default();
+        config.build_disabled_set();
+        assert!(!config.is_datasource_enabled("modem"));
+        assert!(config.disabled_reason("modem").unwrap().contains("[modem]"));
+
+        let mut config: AppConfig = toml::from_str("[modem]\nenabled = true\n").unwrap();
+        config.build_disabled_set();
+        assert!(config.is_datasource_enabled("modem"));
+    }
+
     #[test]
     fn test_disable_datasource() {
         let mut config = AppConfig::default();
         assert!(config.is_datasource_enabled("test"));
-        config.disable_datasource("test");
+        config.disable_datasource("test", "testing");
         assert!(!config.is_datasource_enabled("test"));
+        assert_eq!(config.disabled_reason("test"), Some("testing"));
     }
 
     #[test]
@@ -329,4 +1358,523 @@ mod tests {
         // No token should be denied
         assert!(!config.is_token_valid(None));
     }
+
+    #[test]
+    fn test_tls_path_aliases() {
+        let config: AppConfig =
+            toml::from_str("tls_cert_path = \"/tmp/cert.pem\"\ntls_key_path = \"/tmp/key.pem\"")
+                .unwrap();
+        assert_eq!(config.tls_config(), Some(("/tmp/cert.pem", "/tmp/key.pem")));
+
+        let config: AppConfig = toml::from_str("tls_cert = \"/tmp/cert.pem\"").unwrap();
+        assert_eq!(config.tls_config(), None);
+    }
+
+    #[test]
+    fn test_client_identity_allowlist() {
+        let mut config = AppConfig::default();
+        let names = vec!["prometheus".to_string(), "prom-1.example.com".to_string()];
+        assert!(config.is_client_identity_allowed(&names));
+
+        config.allowed_client_names = vec!["*.example.com".to_string()];
+        assert!(config.is_client_identity_allowed(&names));
+        assert!(!config.is_client_identity_allowed(&["grafana".to_string()]));
+        assert!(!config.is_client_identity_allowed(&[]));
+
+        // The CA only applies when TLS itself is enabled
+        config.tls_ca_path = Some("/tmp/ca.pem".to_string());
+        assert_eq!(config.mtls_ca(), None);
+        config.tls_cert = Some("/tmp/cert.pem".to_string());
+        config.tls_key = Some("/tmp/key.pem".to_string());
+        assert_eq!(config.mtls_ca(), Some("/tmp/ca.pem"));
+    }
+
+    #[test]
+    fn test_effective_capabilities_parsing() {
+        let status = "Name:\texporter\nCapInh:\t0000000000000000\nCapPrm:\t0000000000001000\nCapEff:\t0000000000001000\n";
+        let effective = parse_effective_capabilities(status).unwrap();
+        assert!(has_capability(effective, CAP_NET_ADMIN));
+        assert!(!has_capability(effective, 0));
+
+        let full = parse_effective_capabilities("CapEff:\t000001ffffffffff\n").unwrap();
+        assert!(has_capability(full, CAP_NET_ADMIN));
+        assert!(!has_capability(0, CAP_NET_ADMIN));
+        assert_eq!(parse_effective_capabilities("Name:\texporter\n"), None);
+    }
+
+    #[test]
+    fn test_capability_checks_disable_only_the_feature() {
+        let mut config = AppConfig::default();
+        config.block.smart = true;
+        config.conntrack.table_summary = true;
+        config.nvme.smart_log = true;
+
+        config.disable_features_lacking_capabilities(1 << CAP_NET_ADMIN);
+        assert!(!config.block.smart);
+        assert!(config.conntrack.table_summary);
+        assert!(!config.nvme.smart_log);
+        for datasource in ["block", "conntrack", "nvme", "ethtool"] {
+            assert!(!config.disabled_set.contains_key(datasource));
+        }
+    }
+
+    #[test]
+    fn test_filesystem_exclusions() {
+        let config = AppConfig::default();
+        assert!(config.is_mountpoint_excluded("/proc"));
+        assert!(config.is_mountpoint_excluded("/run/user/1000"));
+        assert!(config.is_mountpoint_excluded("/sys/fs/cgroup"));
+        assert!(!config.is_mountpoint_excluded("/"));
+        assert!(!config.is_mountpoint_excluded("/srv/devdata"));
+        assert!(!config.is_mountpoint_excluded("/runner"));
+        assert!(!config.is_fstype_excluded("nfs4"));
+
+        let config = AppConfig::parse(
+            "[filesystems]\nmount_exclude = [\"/mnt/backup*\"]\nfs_exclude = [\"nfs4\"]",
+            "config.toml",
+            false,
+        )
+        .unwrap();
+        assert!(config.is_mountpoint_excluded("/mnt/backup2"));
+        assert!(!config.is_mountpoint_excluded("/proc"));
+        assert!(config.is_fstype_excluded("nfs4"));
+        assert!(!config.is_fstype_excluded("ext4"));
+    }
+
+    #[test]
+    fn test_mdraid_source_parsing() {
+        let config: AppConfig = toml::from_str("[mdraid]\nsource = \"mdstat\"").unwrap();
+        assert_eq!(config.mdraid.source, MdraidSource::Mdstat);
+
+        let config: AppConfig = toml::from_str("").unwrap();
+        assert_eq!(config.mdraid.source, MdraidSource::Auto);
+
+        assert!(toml::from_str::<AppConfig>("[mdraid]\nsource = \"bogus\"").is_err());
+    }
+
+    #[test]
+    fn test_log_format_parsing() {
+        let config: AppConfig = toml::from_str("log_format = \"json\"").unwrap();
+        assert_eq!(config.log_format, LogFormat::Json);
+
+        let config: AppConfig = toml::from_str("").unwrap();
+        assert_eq!(config.log_format, LogFormat::Text);
+
+        assert!(toml::from_str::<AppConfig>("log_format = \"yaml\"").is_err());
+    }
+
+    #[test]
+    fn test_log_sample_rate() {
+        assert_eq!(AppConfig::default().log_sample_rate(), 1.0);
+        let config: AppConfig = toml::from_str("log_sample_rate = 0.01").unwrap();
+        assert_eq!(config.log_sample_rate(), 0.01);
+        assert!(config.validate().is_empty());
+
+        for invalid in ["0", "1.5", "-0.5", "nan"] {
+            let config: AppConfig =
+                toml::from_str(&format!("log_sample_rate = {invalid}")).unwrap();
+            assert_eq!(config.log_sample_rate(), 1.0);
+            assert_eq!(config.validate().len(), 1, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_metrics_rate_limit_accepts_integers() {
+        let config: AppConfig = toml::from_str("metrics_rate_limit_per_second = 5").unwrap();
+        assert_eq!(config.metrics_rate_limit_per_second, 5.0);
+        assert_eq!(AppConfig::default().metrics_rate_limit_per_second, 0.0);
+    }
+
+    #[test]
+    fn test_vmstat_fields_filter() {
+        let config = AppConfig::default();
+        assert!(config.is_vmstat_field_enabled("nr_free_pages"));
+
+        let config = AppConfig::parse(
+            "[procfs]\nvmstat_fields = [\"pgfault\", \"pgmajfault\", \"nr_*\"]",
+            "config.toml",
+            false,
+        )
+        .unwrap();
+        assert!(config.is_vmstat_field_enabled("pgmajfault"));
+        assert!(config.is_vmstat_field_enabled("nr_dirty"));
+        assert!(!config.is_vmstat_field_enabled("numa_hit"));
+    }
+
+    #[test]
+    fn test_cgroup_paths_parsing() {
+        let config: AppConfig = toml::from_str("").unwrap();
+        assert_eq!(config.cgroups.paths, vec!["system.slice/*.service"]);
+
+        let config = AppConfig::parse(
+            "[cgroups]\npaths = [\"kubepods.slice/*\", \"user.slice\"]",
+            "config.toml",
+            false,
+        )
+        .unwrap();
+        assert_eq!(config.cgroups.paths, vec!["kubepods.slice/*", "user.slice"]);
+    }
+
+    #[test]
+    fn test_interval_multiplier() {
+        let config = AppConfig::parse(
+            "interval_multiplier = { ipmi = 5, conntrack = 0 }\n",
+            "config.toml",
+            true,
+        )
+        .unwrap();
+        let ipmi_runs: Vec<u64> = (0..11)
+            .filter(|&collection| config.is_collection_due("ipmi", collection))
+            .collect();
+        assert_eq!(ipmi_runs, vec![0, 5, 10]);
+        // 0 and 1 mean every collection, as does a missing entry
+        assert!((0..3).all(|collection| config.is_collection_due("conntrack", collection)));
+        assert!((0..3).all(|collection| config.is_collection_due("procfs", collection)));
+    }
+
+    #[test]
+    fn test_host_path_roots() {
+        let config = AppConfig::default();
+        assert_eq!(
+            config.host_path("/sys/class/hwmon"),
+            Path::new("/sys/class/hwmon")
+        );
+
+        let config = AppConfig::parse(
+            "sysfs_root = \"/host/sys/\"\nprocfs_root = \"/host/proc\"\n",
+            "config.toml",
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            config.host_path("/sys/class/hwmon"),
+            Path::new("/host/sys/class/hwmon")
+        );
+        assert_eq!(config.host_path("/sys"), Path::new("/host/sys"));
+        assert_eq!(
+            config.host_path("/proc/mdstat"),
+            Path::new("/host/proc/mdstat")
+        );
+        // Only whole leading components are mapped
+        assert_eq!(config.host_path("/system"), Path::new("/system"));
+        assert_eq!(config.host_path("/dev/ipmi0"), Path::new("/dev/ipmi0"));
+    }
+
+    #[test]
+    fn test_datasource_sections_round_trip() {
+        let mut config = AppConfig::default();
+        config.block.ignore_loop_devices = false;
+        config.block.smart = true;
+        config.netdev.ignore_veth_interfaces = false;
+        config.hwmon.chip_exclude = vec!["nvme".to_string()];
+        config.filesystems.stat_timeout_ms = 500;
+        config.mdraid.source = MdraidSource::Mdstat;
+        config.processes.top_n = 5;
+        config.pci.classes = vec!["display".to_string()];
+        config.custom_file.push(CustomFile {
+            path: "/sys/class/x/value".to_string(),
+            metric: "x_value".to_string(),
+            labels: BTreeMap::from([("unit".to_string(), "a".to_string())]),
+        });
+        config.label_drop.push(LabelDrop {
+            metric: "cpu_seconds_total".to_string(),
+            label: "cpu".to_string(),
+        });
+
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains("[netdev]"), "{serialized}");
+        // Legacy top-level keys are not written back out
+        let top_level = serialized.split("\n[").next().unwrap();
+        assert!(!top_level.contains("ignore_loop_devices"), "{serialized}");
+        let parsed = AppConfig::parse(&serialized, "config.toml", true).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn test_datasource_sections() {
+        let contents = "[filesystems]\n\
+                        stat_timeout_ms = 500\n\
+                        fs_exclude = [\"nfs4\"]\n\
+                        [mdraid]\n\
+                        source = \"sysfs\"\n\
+                        [cgroups]\n\
+                        paths = [\"user.slice\"]\n\
+                        [processes]\n\
+                        top_n = 5\n\
+                        [procfs]\n\
+                        vmstat_fields = [\"pg*\"]\n\
+                        [conntrack]\n\
+                        table_summary = true\n\
+                        [ipmi]\n\
+                        read_fru = true\n\
+                        [pci]\n\
+                        classes = [\"display\"]\n\
+                        [slabinfo]\n\
+                        top_n = 5\n\
+                        [usb]\n\
+                        devices = [\"0403:*\"]\n\
+                        [block]\n\
+                        ignore_loop_devices = false\n\
+                        [netdev]\n\
+                        ignore_ppp_interfaces = false\n\
+                        [hwmon]\n\
+                        chip_exclude = [\"nvme\"]\n";
+        let config = AppConfig::parse(contents, "config.toml", true).unwrap();
+        assert!(!config.block.ignore_loop_devices);
+        assert!(!config.netdev.ignore_ppp_interfaces);
+        assert!(config.netdev.ignore_veth_interfaces);
+        assert!(config.is_hwmon_chip_excluded("nvme"));
+        assert!(!config.is_hwmon_chip_excluded("coretemp"));
+        assert_eq!(config.filesystems.stat_timeout_ms, 500);
+        assert!(config.filesystems.ignore_ramfs);
+        assert!(config.is_fstype_excluded("nfs4"));
+        assert!(config.is_mountpoint_excluded("/proc"));
+        assert_eq!(config.mdraid.source, MdraidSource::Sysfs);
+        assert_eq!(config.cgroups.paths, vec!["user.slice"]);
+        assert_eq!(config.processes.top_n, 5);
+        assert_eq!(config.slabinfo.top_n, 5);
+        assert_eq!(AppConfig::default().slabinfo.top_n, 20);
+        assert!(!config.is_vmstat_field_enabled("nr_dirty"));
+        assert!(config.conntrack.table_summary);
+        assert!(!AppConfig::default().conntrack.table_summary);
+        assert!(config.ipmi.read_fru);
+        assert!(!AppConfig::default().ipmi.read_fru);
+        assert!(config.is_pci_class_enabled("display"));
+        assert!(!config.is_pci_class_enabled("network"));
+        assert!(AppConfig::default().is_pci_class_enabled("storage"));
+        assert!(config.is_usb_device_enabled("0403", "6001"));
+        assert!(!config.is_usb_device_enabled("046d", "c52b"));
+        assert!(AppConfig::default().is_usb_device_enabled("046d", "c52b"));
+
+        // The old top-level spelling still works; the section wins over it
+        let config = AppConfig::parse(
+            "ignore_loop_devices = false\nignore_ppp_interfaces = false\n\
+             ignore_veth_interfaces = false\n[block]\nignore_loop_devices = true\n",
+            "config.toml",
+            true,
+        )
+        .unwrap();
+        assert!(config.block.ignore_loop_devices);
+        assert!(!config.netdev.ignore_ppp_interfaces);
+        assert!(!config.netdev.ignore_veth_interfaces);
+        assert!(AppConfig::parse("process_top_n = 3\n", "config.toml", true).is_err());
+
+        let err =
+            AppConfig::parse("[filesystems]\ntimeout = 1\n", "config.toml", true).unwrap_err();
+        assert!(err.contains("filesystems.timeout: unknown key"), "{err}");
+    }
+
+    #[test]
+    fn test_collection_interval_parsing() {
+        let config: AppConfig = toml::from_str("").unwrap();
+        assert_eq!(config.collection_interval_seconds, 0);
+
+        let config: AppConfig = toml::from_str("collection_interval_seconds = 15").unwrap();
+        assert_eq!(config.collection_interval_seconds, 15);
+    }
+
+    #[test]
+    fn test_try_load_from_rejects_broken_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "allowed_ip = [\"10.0.0.0/8\"\n").unwrap();
+        assert!(AppConfig::try_load_from(&path, false).is_err());
+
+        fs::write(&path, "allowed_ip = [\"10.0.0.0/8\"]\n").unwrap();
+        let config = AppConfig::try_load_from(&path, false).unwrap();
+        assert!(config.is_metrics_ip_allowed("10.1.2.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_strict_reports_offending_fields() {
+        let contents = "bind = \"localhost:9100\"\n\
+                        allowed_ip = [\"10.0.0.0/8\", \"10.0.0.300\"]\n\
+                        log_denied_request = false\n";
+
+        // Lenient mode keeps loading and falls back later
+        assert!(AppConfig::parse(contents, "config.toml", false).is_ok());
+
+        let err = AppConfig::parse(contents, "config.toml", true).unwrap_err();
+        assert!(err.contains("log_denied_request: unknown key"), "{err}");
+        assert!(
+            err.contains("bind: invalid address 'localhost:9100'"),
+            "{err}"
+        );
+        assert!(
+            err.contains("allowed_ip: invalid entry '10.0.0.300'"),
+            "{err}"
+        );
+        assert!(!err.contains("10.0.0.0/8"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_strict_accepts_valid_config() {
+        let contents = "bind = \"[::1]:9100\"\n\
+                        allowed_ip = [\"::1\", \"192.168.0.0/16\"]\n\
+                        tls_cert_path = \"/etc/cert.pem\"\n\
+                        static_labels = { dc = \"dc1\" }\n\
+                        [[label_drop]]\nmetric = \"x\"\nlabel = \"y\"\n";
+        assert!(AppConfig::parse(contents, "config.toml", true).is_ok());
+        assert!(AppConfig::parse("[label_drop", "config.toml", true).is_err());
+    }
+
+    #[test]
+    fn test_bind_accepts_string_or_list() {
+        let config: AppConfig = toml::from_str("bind = \"0.0.0.0:9100\"").unwrap();
+        assert_eq!(config.bind_addrs(), vec!["0.0.0.0:9100".parse().unwrap()]);
+
+        let config: AppConfig =
+            toml::from_str("bind = [\"10.0.0.5:9100\", \"[::]:9100\", \"10.0.0.5:9100\"]").unwrap();
+        let expected: Vec<SocketAddr> = vec![
+            "10.0.0.5:9100".parse().unwrap(),
+            "[::]:9100".parse().unwrap(),
+        ];
+        assert_eq!(config.bind_addrs(), expected);
+
+        // Invalid entries are skipped; nothing valid falls back to the default
+        let config: AppConfig = toml::from_str("bind = [\"nope\", \"[::1]:9100\"]").unwrap();
+        assert_eq!(config.bind_addrs(), vec!["[::1]:9100".parse().unwrap()]);
+        let config: AppConfig = toml::from_str("bind = []").unwrap();
+        assert_eq!(config.bind_addrs(), vec![DEFAULT_BIND.parse().unwrap()]);
+
+        assert!(toml::from_str::<AppConfig>("bind = 9100").is_err());
+    }
+
+    #[test]
+    fn test_try_load_from_missing_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("missing.toml");
+
+        let config = AppConfig::try_load_from(&path, false).unwrap();
+        assert_eq!(config.bind, vec!["127.0.0.1:9100"]);
+
+        let err = AppConfig::try_load_from(&path, true).unwrap_err();
+        assert!(err.contains("missing.toml"));
+    }
+
+    #[test]
+    fn test_static_labels_validation() {
+        let config: AppConfig = toml::from_str(
+            "static_labels = { datacenter = \"dc1\", \"9rack\" = \"r1\", __name__ = \"x\", node = \"web-1\" }",
+        )
+        .unwrap();
+        let labels: Vec<(&str, &str)> = config.static_labels().collect();
+        assert_eq!(labels, vec![("datacenter", "dc1"), ("node", "web-1")]);
+        assert_eq!(config.validate().len(), 2);
+        assert_eq!(AppConfig::default().static_labels().count(), 0);
+    }
+
+    #[test]
+    fn test_custom_file_validation() {
+        let config: AppConfig = toml::from_str(
+            "[[custom_file]]\n\
+             path = \"/sys/class/foo/a\"\n\
+             metric = \"my_foo\"\n\
+             labels = { chip = \"x\" }\n\
+             [[custom_file]]\n\
+             path = \"/sys/class/foo/b\"\n\
+             metric = \"my_foo\"\n\
+             labels = { chip = \"y\" }\n\
+             [[custom_file]]\n\
+             path = \"/sys/class/foo/c\"\n\
+             metric = \"my_foo\"\n\
+             [[custom_file]]\n\
+             path = \"/sys/class/foo/d\"\n\
+             metric = \"9bad-name\"\n\
+             [[custom_file]]\n\
+             path = \"/sys/class/foo/e\"\n\
+             metric = \"my_bar\"\n\
+             labels = { __chip = \"z\" }\n",
+        )
+        .unwrap();
+        let paths: Vec<&str> = config
+            .custom_files()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["/sys/class/foo/a", "/sys/class/foo/b"]);
+        assert_eq!(config.validate().len(), 3);
+
+        let config = AppConfig {
+            custom_file: (0..MAX_CUSTOM_FILES + 1)
+                .map(|index| CustomFile {
+                    path: format!("/sys/class/foo/{index}"),
+                    metric: format!("my_foo_{index}"),
+                    labels: BTreeMap::new(),
+                })
+                .collect(),
+            ..AppConfig::default()
+        };
+        assert_eq!(config.custom_files().count(), MAX_CUSTOM_FILES);
+    }
+
+    #[test]
+    fn test_metric_namespace_validation() {
+        let config: AppConfig = toml::from_str("metric_namespace = \"rslx\"").unwrap();
+        assert_eq!(config.metric_namespace(), "rslx");
+        assert!(config.validate().is_empty());
+
+        let config: AppConfig = toml::from_str("metric_namespace = \"9bad-name\"").unwrap();
+        assert_eq!(config.metric_namespace(), "");
+        assert_eq!(config.validate().len(), 1);
+
+        assert_eq!(AppConfig::default().metric_namespace(), "");
+    }
+
+    #[test]
+    fn test_is_metric_exported() {
+        let config = AppConfig::default();
+        assert!(config.is_metric_exported("interrupts_total"));
+
+        let config: AppConfig = toml::from_str(
+            "metric_allowlist = [\"cpu_*\", \"netdev*\"]\nmetric_denylist = [\"netdev_sysfs_stat\"]",
+        )
+        .unwrap();
+        assert!(config.is_metric_exported("cpu_seconds_total"));
+        assert!(config.is_metric_exported("netdev_carrier"));
+        assert!(!config.is_metric_exported("netdev_sysfs_stat"));
+        assert!(!config.is_metric_exported("meminfo"));
+
+        let config: AppConfig = toml::from_str("metric_denylist = [\"interrupts*\"]").unwrap();
+        assert!(!config.is_metric_exported("interrupts_total"));
+        assert!(config.is_metric_exported("meminfo"));
+    }
+
+    #[test]
+    fn test_disabled_metrics() {
+        let config = AppConfig::default();
+        assert!(config.is_metric_enabled("diskstats"));
+
+        let config: AppConfig =
+            toml::from_str("disabled_metrics = [\"diskstats*\", \"hwmon_voltage_volts\"]").unwrap();
+        assert!(!config.is_metric_enabled("diskstats"));
+        assert!(!config.is_metric_enabled("diskstats_bytes_read"));
+        assert!(!config.is_metric_enabled("hwmon_voltage_volts"));
+        assert!(config.is_metric_enabled("hwmon_temperature_celsius"));
+        assert!(!config.is_metric_exported("diskstats"));
+        assert!(config.is_metric_exported("hwmon_temperature_celsius"));
+    }
+
+    #[test]
+    fn test_label_drop_parsing() {
+        let config: AppConfig = toml::from_str(
+            r#"
+[[label_drop]]
+metric = "cpu_seconds_total"
+label = "cpu"
+
+[[label_drop]]
+metric = "interrupts*"
+label = "irq"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.label_drop.len(), 2);
+        assert_eq!(
+            config.label_drop[0],
+            LabelDrop {
+                metric: "cpu_seconds_total".to_string(),
+                label: "cpu".to_string(),
+            }
+        );
+    }
 }
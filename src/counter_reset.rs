@@ -0,0 +1,179 @@
+use prometheus::IntCounterVec;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Detects sysfs counters going backwards, e.g. when a driver module is
+/// reloaded or a controller resets its error counts. The raw value is still
+/// exported by the caller; this only records that a reset happened.
+struct CounterResetMetrics {
+    resets: IntCounterVec,
+    /// Last value per (metric, labels) series
+    last_values: Mutex<HashMap<(String, String), f64>>,
+}
+
+impl CounterResetMetrics {
+    fn new() -> Self {
+        Self {
+            resets: prometheus::register_int_counter_vec!(
+                "counter_reset_total",
+                "Times a monotonic sysfs counter decreased between collections",
+                &["metric", "labels"]
+            )
+            .expect("register counter_reset_total"),
+            last_values: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static COUNTER_RESET_METRICS: OnceLock<CounterResetMetrics> = OnceLock::new();
+
+fn metrics() -> &'static CounterResetMetrics {
+    COUNTER_RESET_METRICS.get_or_init(CounterResetMetrics::new)
+}
+
+/// Renders labels as `name=value` pairs joined by commas.
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Increase of a raw counter since the previous collection. A lower value
+/// means the counter was reset (driver reload, recreated cgroup, reboot of
+/// the stats), so the whole current value counts as new.
+pub fn counter_delta(current: u64, previous: u64) -> u64 {
+    if current >= previous {
+        current - previous
+    } else {
+        current
+    }
+}
+
+/// Remembers `value` for the series and counts a reset in
+/// `counter_reset_total{metric, labels}` when it is lower than last time.
+/// Returns whether a reset was detected.
+pub fn observe(metric: &str, labels: &[(&str, &str)], value: f64) -> bool {
+    let metrics = metrics();
+    let labels = format_labels(labels);
+    let mut last_values = match metrics.last_values.lock() {
+        Ok(last_values) => last_values,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let previous = last_values.insert((metric.to_string(), labels.clone()), value);
+    let reset = previous.is_some_and(|previous| value < previous);
+    if reset {
+        metrics
+            .resets
+            .with_label_values(&[metric, labels.as_str()])
+            .inc();
+    }
+    reset
+}
+
+/// Drops the remembered value and reset count of every `metric` series
+/// carrying the `name=value` label. Collectors call this when a device
+/// disappears, so state for interfaces or controllers that come and go does
+/// not accumulate for the life of the process.
+pub fn forget(metric: &str, label: (&str, &str)) {
+    let metrics = metrics();
+    let pair = format_labels(&[label]);
+    let mut last_values = match metrics.last_values.lock() {
+        Ok(last_values) => last_values,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    last_values.retain(|(name, labels), _| {
+        let matches = name == metric && labels.split(',').any(|part| part == pair);
+        if matches {
+            let _ = metrics
+                .resets
+                .remove_label_values(&[metric, labels.as_str()]);
+        }
+        !matches
+    });
+}
+
+/// Whether a value is remembered for a series, for collector tests.
+#[cfg(test)]
+pub fn is_tracked(metric: &str, labels: &[(&str, &str)]) -> bool {
+    let last_values = match metrics().last_values.lock() {
+        Ok(last_values) => last_values,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    last_values.contains_key(&(metric.to_string(), format_labels(labels)))
+}
+
+/// Resets counted so far for a series, for collector tests.
+#[cfg(test)]
+pub fn reset_count(metric: &str, labels: &[(&str, &str)]) -> u64 {
+    metrics()
+        .resets
+        .with_label_values(&[metric, format_labels(labels).as_str()])
+        .get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_labels() {
+        assert_eq!(
+            format_labels(&[("controller", "mc0"), ("dimm", "dimm1")]),
+            "controller=mc0,dimm=dimm1"
+        );
+        assert_eq!(format_labels(&[]), "");
+    }
+
+    #[test]
+    fn test_counter_delta_handles_reset() {
+        assert_eq!(counter_delta(150, 100), 50);
+        assert_eq!(counter_delta(100, 100), 0);
+        assert_eq!(counter_delta(20, 100), 20);
+    }
+
+    #[test]
+    fn test_observe_counts_decreases() {
+        // A metric name no real collector uses, so parallel tests do not interfere
+        let labels = [("device", "test0")];
+        assert!(!observe("test_counter_total", &labels, 5.0));
+        assert!(!observe("test_counter_total", &labels, 5.0));
+        assert!(!observe("test_counter_total", &labels, 9.0));
+        assert!(observe("test_counter_total", &labels, 0.0));
+        assert!(!observe("test_counter_total", &labels, 2.0));
+        // Series are tracked independently
+        assert!(!observe("test_counter_total", &[("device", "test1")], 1.0));
+
+        assert_eq!(reset_count("test_counter_total", &labels), 1);
+    }
+
+    #[test]
+    fn test_forget_drops_matching_series() {
+        let gone = [("controller", "test8"), ("dimm", "dimm0")];
+        let kept = [("controller", "test80"), ("dimm", "dimm0")];
+        observe("test_forget_total", &gone, 5.0);
+        observe("test_forget_total", &gone, 1.0);
+        observe("test_forget_total", &kept, 5.0);
+        assert_eq!(reset_count("test_forget_total", &gone), 1);
+
+        forget("test_forget_total", ("controller", "test8"));
+        assert!(!is_tracked("test_forget_total", &gone));
+        assert!(is_tracked("test_forget_total", &kept));
+        let families = prometheus::gather();
+        let resets = families
+            .iter()
+            .find(|family| family.name() == "counter_reset_total")
+            .map(|family| family.get_metric().to_vec())
+            .unwrap_or_default();
+        assert!(!resets.iter().any(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .any(|label| label.value() == "controller=test8,dimm=dimm0")
+        }));
+
+        // A series that reappears starts over instead of counting a reset
+        assert!(!observe("test_forget_total", &gone, 0.0));
+    }
+}
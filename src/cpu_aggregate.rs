@@ -0,0 +1,53 @@
+/// Removes the series the other `cpu_aggregate_only` mode exported, since the
+/// option can change on reload: the series of each of `cpus` when aggregating,
+/// the "total" series when exporting per CPU. `remove` drops every series of
+/// one `cpu` label value.
+pub fn remove_other_mode<'a>(
+    per_cpu: bool,
+    cpus: impl IntoIterator<Item = &'a str>,
+    mut remove: impl FnMut(&str),
+) {
+    if per_cpu {
+        remove("total");
+    } else {
+        for cpu in cpus {
+            remove(cpu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{GaugeVec, Opts};
+
+    #[test]
+    fn test_remove_other_mode_on_switch() {
+        let gauge = GaugeVec::new(Opts::new("test_cpu_aggregate", "test"), &["cpu"]).unwrap();
+        let missing = |cpu: &str| gauge.remove_label_values(&[cpu]).is_err();
+        let cpus = ["cpu0", "cpu1"];
+        let collect = |per_cpu: bool| {
+            if per_cpu {
+                for cpu in cpus {
+                    gauge.with_label_values(&[cpu]).set(1.0);
+                }
+            } else {
+                gauge.with_label_values(&["total"]).set(2.0);
+            }
+            remove_other_mode(per_cpu, cpus, |cpu| {
+                let _ = gauge.remove_label_values(&[cpu]);
+            });
+        };
+
+        collect(true);
+        // Switching on cpu_aggregate_only drops the per-CPU series
+        collect(false);
+        assert_eq!(gauge.with_label_values(&["total"]).get(), 2.0);
+        assert!(missing("cpu0") && missing("cpu1"));
+        // And switching it off again drops the total
+        collect(false);
+        collect(true);
+        assert!(missing("total"));
+        assert!(!missing("cpu0") && !missing("cpu1"));
+    }
+}
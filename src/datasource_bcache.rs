@@ -0,0 +1,271 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::{GaugeVec, IntCounterVec};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// stats_total/ files exported as counters
+const STAT_COUNTERS: &[&str] = &[
+    "cache_hits",
+    "cache_misses",
+    "cache_bypass_hits",
+    "cache_bypass_misses",
+];
+
+struct BcacheMetrics {
+    hit_ratio: GaugeVec,
+    counters: HashMap<&'static str, IntCounterVec>,
+    dirty_data_bytes: GaugeVec,
+    /// (cache_set, backing_device, file) -> last raw counter value
+    previous: Mutex<HashMap<(String, String, &'static str), u64>>,
+    devices: Mutex<HashSet<(String, String)>>,
+}
+
+impl BcacheMetrics {
+    fn new() -> Self {
+        let labels = &["cache_set", "backing_device"];
+        let counter = |name: &str, help: &str| {
+            prometheus::register_int_counter_vec!(name, help, labels)
+                .unwrap_or_else(|err| panic!("register {name}: {err}"))
+        };
+        let counters = HashMap::from([
+            (
+                "cache_hits",
+                counter(
+                    "bcache_cache_hits_total",
+                    "Reads and writes served from the cache",
+                ),
+            ),
+            (
+                "cache_misses",
+                counter(
+                    "bcache_cache_misses_total",
+                    "Reads and writes that missed the cache",
+                ),
+            ),
+            (
+                "cache_bypass_hits",
+                counter(
+                    "bcache_cache_bypass_hits_total",
+                    "Cache hits for I/O that was meant to bypass the cache",
+                ),
+            ),
+            (
+                "cache_bypass_misses",
+                counter(
+                    "bcache_cache_bypass_misses_total",
+                    "Cache misses for I/O that bypassed the cache",
+                ),
+            ),
+        ]);
+
+        Self {
+            hit_ratio: prometheus::register_gauge_vec!(
+                "bcache_cache_hit_ratio",
+                "Cache hit ratio since the device was registered (0-1)",
+                labels
+            )
+            .expect("register bcache_cache_hit_ratio"),
+            counters,
+            dirty_data_bytes: prometheus::register_gauge_vec!(
+                "bcache_dirty_data_bytes",
+                "Data in the cache not yet written back to the backing device",
+                labels
+            )
+            .expect("register bcache_dirty_data_bytes"),
+            previous: Mutex::new(HashMap::new()),
+            devices: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static BCACHE_METRICS: OnceLock<BcacheMetrics> = OnceLock::new();
+
+fn metrics() -> &'static BcacheMetrics {
+    BCACHE_METRICS.get_or_init(BcacheMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
+/// Parses bcache's human readable sizes (`0`, `512`, `1.2k`, `3.5M`, ...)
+fn parse_human_bytes(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last()? {
+        (idx, suffix) if suffix.is_ascii_alphabetic() => {
+            let power = "kMGTPEZY".find(suffix)? as i32 + 1;
+            (&value[..idx], 1024f64.powi(power))
+        }
+        _ => (value, 1.0),
+    };
+    number.parse::<f64>().ok().map(|n| n * multiplier)
+}
+
+/// Backing device kernel name, e.g. `sdb` for a bdevN link to .../block/sdb/bcache
+fn backing_device_name(bdev_link: &Path) -> Option<String> {
+    let target = fs::canonicalize(bdev_link).ok()?;
+    let parent = target.parent()?;
+    parent.file_name()?.to_str().map(str::to_string)
+}
+
+fn update_backing_device(
+    metrics: &BcacheMetrics,
+    previous: &mut HashMap<(String, String, &'static str), u64>,
+    cache_set: &str,
+    device: &str,
+    path: &Path,
+) {
+    let labels = [cache_set, device];
+    let stats = path.join("stats_total");
+
+    if let Some(ratio) = read_u64(&stats.join("cache_hit_ratio")) {
+        metrics
+            .hit_ratio
+            .with_label_values(&labels)
+            .set(ratio as f64 / 100.0);
+    }
+
+    for file in STAT_COUNTERS {
+        let Some(value) = read_u64(&stats.join(file)) else {
+            continue;
+        };
+        let key = (cache_set.to_string(), device.to_string(), *file);
+        let last = previous.insert(key, value).unwrap_or(0);
+        if let Some(counter) = metrics.counters.get(file) {
+            counter
+                .with_label_values(&labels)
+                .inc_by(counter_delta(value, last));
+        }
+    }
+
+    if let Some(dirty) = read_string(&path.join("dirty_data")).and_then(|v| parse_human_bytes(&v)) {
+        metrics
+            .dirty_data_bytes
+            .with_label_values(&labels)
+            .set(dirty);
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/fs/bcache"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let Ok(sets) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut current = HashSet::new();
+    // Cache sets are directories named by UUID; register/pendings_cleanup are files
+    for set in sets.flatten() {
+        let set_path = set.path();
+        if !set_path.is_dir() {
+            continue;
+        }
+        let Ok(cache_set) = set.file_name().into_string() else {
+            continue;
+        };
+        let Ok(entries) = fs::read_dir(&set_path) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !name.starts_with("bdev") {
+                continue;
+            }
+            let device = backing_device_name(&entry.path()).unwrap_or(name);
+            update_backing_device(metrics, &mut previous, &cache_set, &device, &entry.path());
+            current.insert((cache_set.clone(), device));
+        }
+    }
+
+    let mut devices = match metrics.devices.lock() {
+        Ok(devices) => devices,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (cache_set, device) in devices.difference(&current) {
+        let labels = [cache_set.as_str(), device.as_str()];
+        let _ = metrics.hit_ratio.remove_label_values(&labels);
+        let _ = metrics.dirty_data_bytes.remove_label_values(&labels);
+        for counter in metrics.counters.values() {
+            let _ = counter.remove_label_values(&labels);
+        }
+    }
+    previous
+        .retain(|(cache_set, device, _), _| current.contains(&(cache_set.clone(), device.clone())));
+    *devices = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_bcache(root: &Path, hits: u64, dirty: &str) -> std::path::PathBuf {
+        let backing = root.join("devices/block/sdb/bcache");
+        fs::create_dir_all(backing.join("stats_total")).unwrap();
+        fs::write(backing.join("stats_total/cache_hits"), format!("{hits}\n")).unwrap();
+        fs::write(backing.join("stats_total/cache_misses"), "25\n").unwrap();
+        fs::write(backing.join("stats_total/cache_hit_ratio"), "80\n").unwrap();
+        fs::write(backing.join("stats_total/cache_bypass_hits"), "3\n").unwrap();
+        fs::write(backing.join("stats_total/cache_bypass_misses"), "4\n").unwrap();
+        fs::write(backing.join("dirty_data"), format!("{dirty}\n")).unwrap();
+
+        let set = root.join("bcache/5a2e8f9c-1d2b-4c3a-9e8f-0123456789ab");
+        if !set.exists() {
+            fs::create_dir_all(&set).unwrap();
+            std::os::unix::fs::symlink(&backing, set.join("bdev0")).unwrap();
+            fs::write(root.join("bcache/register"), "").unwrap();
+        }
+        root.join("bcache")
+    }
+
+    #[test]
+    fn test_parse_human_bytes() {
+        assert_eq!(parse_human_bytes("0"), Some(0.0));
+        assert_eq!(parse_human_bytes("512"), Some(512.0));
+        assert_eq!(parse_human_bytes("1.5k"), Some(1536.0));
+        assert_eq!(parse_human_bytes("2M"), Some(2.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_human_bytes("bogus"), None);
+        assert_eq!(parse_human_bytes(""), None);
+    }
+
+    #[test]
+    fn test_update_metrics_reads_backing_device_stats() {
+        let dir = TempDir::new().unwrap();
+        let base = create_mock_bcache(dir.path(), 100, "1.5M");
+        update_metrics_from_path(&base);
+
+        let metrics = metrics();
+        let labels = ["5a2e8f9c-1d2b-4c3a-9e8f-0123456789ab", "sdb"];
+        assert_eq!(metrics.hit_ratio.with_label_values(&labels).get(), 0.8);
+        assert_eq!(
+            metrics.dirty_data_bytes.with_label_values(&labels).get(),
+            1.5 * 1024.0 * 1024.0
+        );
+        let hits = &metrics.counters["cache_hits"];
+        assert_eq!(hits.with_label_values(&labels).get(), 100);
+
+        create_mock_bcache(dir.path(), 130, "0");
+        update_metrics_from_path(&base);
+        assert_eq!(hits.with_label_values(&labels).get(), 130);
+        assert_eq!(
+            metrics.dirty_data_bytes.with_label_values(&labels).get(),
+            0.0
+        );
+    }
+}
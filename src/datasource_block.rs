@@ -0,0 +1,734 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::{GaugeVec, IntCounterVec};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// From scsi/sg.h
+const SG_IO: u32 = 0x2285;
+const SG_INTERFACE_ID: i32 = b'S' as i32;
+const SG_DXFER_NONE: i32 = -1;
+const SG_DXFER_FROM_DEV: i32 = -3;
+const SG_TIMEOUT_MS: u32 = 3000;
+
+/// ATA PASS-THROUGH (16) and the ATA SMART command
+const ATA_PASS_THROUGH_16: u8 = 0x85;
+const ATA_SMART: u8 = 0xb0;
+/// Reports the power mode in the count register without spinning the disk up
+const ATA_CHECK_POWER_MODE: u8 = 0xe5;
+const SMART_READ_DATA: u8 = 0xd0;
+const SMART_RETURN_STATUS: u8 = 0xda;
+/// LBA mid/high signature every SMART command carries; flipped to
+/// 0xf4/0x2c by RETURN STATUS when a threshold has been exceeded
+const SMART_LBA_MID: u8 = 0x4f;
+const SMART_LBA_HIGH: u8 = 0xc2;
+const SMART_FAILING_LBA_MID: u8 = 0xf4;
+const SMART_FAILING_LBA_HIGH: u8 = 0x2c;
+
+/// SCSI status and the sense key a SAT layer returns for commands it rejects
+const SCSI_CHECK_CONDITION: u8 = 0x02;
+const SENSE_KEY_ILLEGAL_REQUEST: u8 = 0x05;
+
+const SMART_ATTR_REALLOCATED_SECTORS: u8 = 5;
+const SMART_ATTR_POWER_ON_HOURS: u8 = 9;
+const SMART_ATTR_TEMPERATURE: u8 = 194;
+const SMART_ATTR_PENDING_SECTORS: u8 = 197;
+
+/// sg_io_hdr from scsi/sg.h
+#[repr(C)]
+struct SgIoHdr {
+    interface_id: i32,
+    dxfer_direction: i32,
+    cmd_len: u8,
+    mx_sb_len: u8,
+    iovec_count: u16,
+    dxfer_len: u32,
+    dxferp: *mut libc::c_void,
+    cmdp: *const u8,
+    sbp: *mut u8,
+    timeout: u32,
+    flags: u32,
+    pack_id: i32,
+    usr_ptr: *mut libc::c_void,
+    status: u8,
+    masked_status: u8,
+    msg_status: u8,
+    sb_len_wr: u8,
+    host_status: u16,
+    driver_status: u16,
+    resid: i32,
+    duration: u32,
+    info: u32,
+}
+
+struct BlockMetrics {
+    info: GaugeVec,
+    size_bytes: GaugeVec,
+    sector_size_bytes: GaugeVec,
+    queue_depth: GaugeVec,
+    io_errors_total: IntCounterVec,
+    smart_temperature_celsius: GaugeVec,
+    smart_reallocated_sectors: GaugeVec,
+    smart_pending_sectors: GaugeVec,
+    smart_power_on_hours: GaugeVec,
+    smart_health_ok: GaugeVec,
+    /// device -> last info labels and raw ioerr_cnt
+    devices: Mutex<HashMap<String, DeviceState>>,
+}
+
+struct DeviceState {
+    info: [String; 3],
+    io_errors: Option<u64>,
+    /// None until the device answered SMART; false only once it rejected
+    /// ATA pass-through outright
+    smart_supported: Option<bool>,
+}
+
+impl BlockMetrics {
+    fn new() -> Self {
+        Self {
+            info: prometheus::register_gauge_vec!(
+                "block_device_info",
+                "Block device identification from sysfs (always 1)",
+                &["device", "model", "vendor", "rev"]
+            )
+            .expect("register block_device_info"),
+            size_bytes: prometheus::register_gauge_vec!(
+                "block_device_size_bytes",
+                "Block device size in bytes",
+                &["device"]
+            )
+            .expect("register block_device_size_bytes"),
+            sector_size_bytes: prometheus::register_gauge_vec!(
+                "disk_sector_size_bytes",
+                "Logical sector size of the block device in bytes",
+                &["device"]
+            )
+            .expect("register disk_sector_size_bytes"),
+            queue_depth: prometheus::register_gauge_vec!(
+                "disk_queue_depth",
+                "Device queue depth reported by the SCSI/ATA driver",
+                &["device"]
+            )
+            .expect("register disk_queue_depth"),
+            io_errors_total: prometheus::register_int_counter_vec!(
+                "block_device_io_errors_total",
+                "Commands completed with an error by the SCSI/ATA driver",
+                &["device"]
+            )
+            .expect("register block_device_io_errors_total"),
+            smart_temperature_celsius: prometheus::register_gauge_vec!(
+                "disk_smart_temperature_celsius",
+                "Drive temperature from SMART attribute 194",
+                &["device"]
+            )
+            .expect("register disk_smart_temperature_celsius"),
+            smart_reallocated_sectors: prometheus::register_gauge_vec!(
+                "disk_smart_reallocated_sectors",
+                "Reallocated sector count from SMART attribute 5",
+                &["device"]
+            )
+            .expect("register disk_smart_reallocated_sectors"),
+            smart_pending_sectors: prometheus::register_gauge_vec!(
+                "disk_smart_pending_sectors",
+                "Sectors waiting to be remapped from SMART attribute 197",
+                &["device"]
+            )
+            .expect("register disk_smart_pending_sectors"),
+            smart_power_on_hours: prometheus::register_gauge_vec!(
+                "disk_smart_power_on_hours",
+                "Power-on hours from SMART attribute 9",
+                &["device"]
+            )
+            .expect("register disk_smart_power_on_hours"),
+            smart_health_ok: prometheus::register_gauge_vec!(
+                "disk_smart_health_ok",
+                "SMART overall-health self-assessment (1 = passed, 0 = threshold exceeded)",
+                &["device"]
+            )
+            .expect("register disk_smart_health_ok"),
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn remove_device(&self, device: &str, info: &[String; 3]) {
+        let _ = self
+            .info
+            .remove_label_values(&[device, &info[0], &info[1], &info[2]]);
+        let _ = self.size_bytes.remove_label_values(&[device]);
+        let _ = self.sector_size_bytes.remove_label_values(&[device]);
+        let _ = self.queue_depth.remove_label_values(&[device]);
+        let _ = self.io_errors_total.remove_label_values(&[device]);
+        let _ = self
+            .smart_temperature_celsius
+            .remove_label_values(&[device]);
+        let _ = self
+            .smart_reallocated_sectors
+            .remove_label_values(&[device]);
+        let _ = self.smart_pending_sectors.remove_label_values(&[device]);
+        let _ = self.smart_power_on_hours.remove_label_values(&[device]);
+        let _ = self.smart_health_ok.remove_label_values(&[device]);
+    }
+}
+
+static BLOCK_METRICS: OnceLock<BlockMetrics> = OnceLock::new();
+
+fn metrics() -> &'static BlockMetrics {
+    BLOCK_METRICS.get_or_init(BlockMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
+/// SCSI io*_cnt attributes are hexadecimal (`0x1f`)
+fn read_hex(path: &Path) -> Option<u64> {
+    let value = read_string(path)?;
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+fn is_ignored_device(name: &str, config: &AppConfig) -> bool {
+    (config.block.ignore_loop_devices && name.starts_with("loop"))
+        || name.starts_with("ram")
+        || name.starts_with("zram")
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct BlockDevice {
+    model: String,
+    vendor: String,
+    rev: String,
+    size_bytes: Option<u64>,
+    sector_size_bytes: Option<u64>,
+    queue_depth: Option<u64>,
+    io_errors: Option<u64>,
+}
+
+fn read_block_device(path: &Path) -> BlockDevice {
+    let device = path.join("device");
+    BlockDevice {
+        model: read_string(&device.join("model")).unwrap_or_default(),
+        vendor: read_string(&device.join("vendor")).unwrap_or_default(),
+        rev: read_string(&device.join("rev")).unwrap_or_default(),
+        // size is always in 512-byte units regardless of the sector size
+        size_bytes: read_u64(&path.join("size")).map(|sectors| sectors * 512),
+        sector_size_bytes: read_u64(&path.join("queue/logical_block_size")),
+        queue_depth: read_u64(&device.join("queue_depth")),
+        io_errors: read_hex(&device.join("ioerr_cnt")),
+    }
+}
+
+/// SCSI disks that may answer ATA pass-through; USB bridges often hang or
+/// misreport on it, and virtio/xen/dm/md devices have no SMART at all.
+fn is_smart_candidate(name: &str, path: &Path) -> bool {
+    if !name.starts_with("sd") {
+        return false;
+    }
+    match fs::canonicalize(path) {
+        Ok(resolved) => !resolved.to_string_lossy().contains("/usb"),
+        Err(_) => false,
+    }
+}
+
+/// ATA PASS-THROUGH (16) CDB for a SMART subcommand
+fn smart_cdb(feature: u8) -> [u8; 16] {
+    let mut cdb = [0u8; 16];
+    cdb[0] = ATA_PASS_THROUGH_16;
+    if feature == SMART_READ_DATA {
+        // PIO data-in; one 512-byte block counted in the sector count field
+        cdb[1] = 4 << 1;
+        cdb[2] = 0x0e;
+        cdb[6] = 1;
+    } else {
+        // Non-data; CK_COND returns the result registers in the sense data
+        cdb[1] = 3 << 1;
+        cdb[2] = 0x20;
+    }
+    cdb[4] = feature;
+    cdb[10] = SMART_LBA_MID;
+    cdb[12] = SMART_LBA_HIGH;
+    cdb[14] = ATA_SMART;
+    cdb
+}
+
+/// ATA PASS-THROUGH (16) CDB for CHECK POWER MODE, a non-data command
+fn check_power_mode_cdb() -> [u8; 16] {
+    let mut cdb = [0u8; 16];
+    cdb[0] = ATA_PASS_THROUGH_16;
+    cdb[1] = 3 << 1;
+    cdb[2] = 0x20;
+    cdb[14] = ATA_CHECK_POWER_MODE;
+    cdb
+}
+
+/// Issues one SG_IO request and returns the SCSI status and sense data.
+fn sg_io(fd: i32, cdb: &[u8; 16], data: Option<&mut [u8; 512]>) -> Option<(u8, [u8; 32])> {
+    let mut sense = [0u8; 32];
+    let (direction, len, ptr) = match data {
+        Some(buf) => (
+            SG_DXFER_FROM_DEV,
+            buf.len() as u32,
+            buf.as_mut_ptr() as *mut libc::c_void,
+        ),
+        None => (SG_DXFER_NONE, 0, std::ptr::null_mut()),
+    };
+    let mut hdr = SgIoHdr {
+        interface_id: SG_INTERFACE_ID,
+        dxfer_direction: direction,
+        cmd_len: cdb.len() as u8,
+        mx_sb_len: sense.len() as u8,
+        iovec_count: 0,
+        dxfer_len: len,
+        dxferp: ptr,
+        cmdp: cdb.as_ptr(),
+        sbp: sense.as_mut_ptr(),
+        timeout: SG_TIMEOUT_MS,
+        flags: 0,
+        pack_id: 0,
+        usr_ptr: std::ptr::null_mut(),
+        status: 0,
+        masked_status: 0,
+        msg_status: 0,
+        sb_len_wr: 0,
+        host_status: 0,
+        driver_status: 0,
+        resid: 0,
+        duration: 0,
+        info: 0,
+    };
+    let ret = unsafe { libc::ioctl(fd, SG_IO as _, &mut hdr as *mut SgIoHdr) };
+    if ret < 0 || hdr.host_status != 0 {
+        return None;
+    }
+    Some((hdr.status, sense))
+}
+
+/// Overall health from the ATA Status Return sense descriptor (type 0x09)
+/// of a SMART RETURN STATUS command; None when the device did not answer.
+fn parse_smart_status(sense: &[u8; 32]) -> Option<bool> {
+    if sense[0] & 0x7f != 0x72 || sense[8] != 0x09 {
+        return None;
+    }
+    match (sense[8 + 9], sense[8 + 11]) {
+        (SMART_LBA_MID, SMART_LBA_HIGH) => Some(true),
+        (SMART_FAILING_LBA_MID, SMART_FAILING_LBA_HIGH) => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether CHECK POWER MODE reported standby (count register 0x00, or 0x01
+/// for standby_y); None when the sense data carries no ATA registers.
+fn parse_power_mode_standby(sense: &[u8; 32]) -> Option<bool> {
+    if sense[0] & 0x7f != 0x72 || sense[8] != 0x09 {
+        return None;
+    }
+    Some(matches!(sense[8 + 5], 0x00 | 0x01))
+}
+
+/// Sense key of fixed (0x70/0x71) or descriptor (0x72/0x73) format sense data
+fn sense_key(sense: &[u8; 32]) -> Option<u8> {
+    match sense[0] & 0x7f {
+        0x70 | 0x71 => Some(sense[2] & 0x0f),
+        0x72 | 0x73 => Some(sense[1] & 0x0f),
+        _ => None,
+    }
+}
+
+/// Outcome of one SMART read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SmartOutcome {
+    Read,
+    /// Disk is spun down; left alone so scrapes do not wake it
+    Standby,
+    /// ILLEGAL REQUEST: the device or its SAT layer has no ATA pass-through
+    Unsupported,
+    /// Busy, timed out or otherwise failed; tried again next collection
+    Failed,
+}
+
+impl SmartOutcome {
+    /// Support state kept for the device; only a definitive rejection
+    /// stops further attempts.
+    fn supported(self, previous: Option<bool>) -> Option<bool> {
+        match self {
+            SmartOutcome::Read => Some(true),
+            SmartOutcome::Unsupported => Some(false),
+            SmartOutcome::Standby | SmartOutcome::Failed => previous,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct SmartAttributes {
+    temperature_celsius: Option<u64>,
+    reallocated_sectors: Option<u64>,
+    pending_sectors: Option<u64>,
+    power_on_hours: Option<u64>,
+}
+
+/// Parses the 30 attribute entries of a SMART READ DATA block; None when the
+/// checksum does not match.
+fn parse_smart_attributes(data: &[u8; 512]) -> Option<SmartAttributes> {
+    if data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return None;
+    }
+    let mut attributes = SmartAttributes::default();
+    for entry in data[2..2 + 30 * 12].chunks_exact(12) {
+        // id, flags (2), current, worst, raw (6), reserved
+        let raw = &entry[5..11];
+        let raw48 = raw
+            .iter()
+            .rev()
+            .fold(0u64, |value, byte| (value << 8) | u64::from(*byte));
+        match entry[0] {
+            SMART_ATTR_REALLOCATED_SECTORS => attributes.reallocated_sectors = Some(raw48),
+            SMART_ATTR_PENDING_SECTORS => attributes.pending_sectors = Some(raw48),
+            // Some vendors keep minutes or milliseconds in the upper bytes
+            SMART_ATTR_POWER_ON_HOURS => attributes.power_on_hours = Some(raw48 & 0xffff_ffff),
+            // Lowest byte is the current value, the others hold min/max
+            SMART_ATTR_TEMPERATURE => attributes.temperature_celsius = Some(u64::from(raw[0])),
+            _ => {}
+        }
+    }
+    Some(attributes)
+}
+
+/// Reads SMART over ATA pass-through unless the disk is in standby.
+fn update_smart(metrics: &BlockMetrics, name: &str) -> SmartOutcome {
+    let Ok(device) = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(Path::new("/dev").join(name))
+    else {
+        return SmartOutcome::Failed;
+    };
+    let fd = device.as_raw_fd();
+
+    if sg_io(fd, &check_power_mode_cdb(), None)
+        .and_then(|(_, sense)| parse_power_mode_standby(&sense))
+        == Some(true)
+    {
+        return SmartOutcome::Standby;
+    }
+
+    let mut data = [0u8; 512];
+    let Some((status, sense)) = sg_io(fd, &smart_cdb(SMART_READ_DATA), Some(&mut data)) else {
+        return SmartOutcome::Failed;
+    };
+    if status == SCSI_CHECK_CONDITION && sense_key(&sense) == Some(SENSE_KEY_ILLEGAL_REQUEST) {
+        return SmartOutcome::Unsupported;
+    }
+    let Some(attributes) = (status == 0)
+        .then(|| parse_smart_attributes(&data))
+        .flatten()
+    else {
+        return SmartOutcome::Failed;
+    };
+    for (value, gauge) in [
+        (
+            attributes.temperature_celsius,
+            &metrics.smart_temperature_celsius,
+        ),
+        (
+            attributes.reallocated_sectors,
+            &metrics.smart_reallocated_sectors,
+        ),
+        (attributes.pending_sectors, &metrics.smart_pending_sectors),
+        (attributes.power_on_hours, &metrics.smart_power_on_hours),
+    ] {
+        if let Some(value) = value {
+            gauge.with_label_values(&[name]).set(value as f64);
+        }
+    }
+
+    if let Some(healthy) = sg_io(fd, &smart_cdb(SMART_RETURN_STATUS), None)
+        .and_then(|(_, sense)| parse_smart_status(&sense))
+    {
+        metrics
+            .smart_health_ok
+            .with_label_values(&[name])
+            .set(if healthy { 1.0 } else { 0.0 });
+    }
+    SmartOutcome::Read
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/block"), config);
+}
+
+fn update_metrics_from_path(base: &Path, config: &AppConfig) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut devices = match metrics.devices.lock() {
+        Ok(devices) => devices,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut current = HashMap::new();
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if is_ignored_device(&name, config) {
+            continue;
+        }
+        let block = read_block_device(&entry.path());
+        let info = [block.model, block.vendor, block.rev];
+        let previous = devices.remove(&name);
+
+        if let Some(previous) = &previous
+            && previous.info != info
+        {
+            let _ = metrics.info.remove_label_values(&[
+                &name,
+                &previous.info[0],
+                &previous.info[1],
+                &previous.info[2],
+            ]);
+        }
+        metrics
+            .info
+            .with_label_values(&[&name, &info[0], &info[1], &info[2]])
+            .set(1.0);
+
+        for (value, gauge) in [
+            (block.size_bytes, &metrics.size_bytes),
+            (block.sector_size_bytes, &metrics.sector_size_bytes),
+            (block.queue_depth, &metrics.queue_depth),
+        ] {
+            if let Some(value) = value {
+                gauge.with_label_values(&[&name]).set(value as f64);
+            }
+        }
+
+        if let Some(io_errors) = block.io_errors {
+            let last = previous.as_ref().and_then(|p| p.io_errors).unwrap_or(0);
+            metrics
+                .io_errors_total
+                .with_label_values(&[&name])
+                .inc_by(counter_delta(io_errors, last));
+        }
+
+        let mut smart_supported = previous.as_ref().and_then(|p| p.smart_supported);
+        if config.block.smart
+            && smart_supported != Some(false)
+            && is_smart_candidate(&name, &entry.path())
+        {
+            smart_supported = update_smart(metrics, &name).supported(smart_supported);
+        }
+
+        current.insert(
+            name,
+            DeviceState {
+                info,
+                io_errors: block.io_errors,
+                smart_supported,
+            },
+        );
+    }
+
+    // Whatever is left was hot-unplugged
+    for (name, state) in devices.drain() {
+        metrics.remove_device(&name, &state.info);
+    }
+    *devices = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_disk(base: &Path, name: &str) -> std::path::PathBuf {
+        let disk = base.join(name);
+        fs::create_dir_all(disk.join("device")).unwrap();
+        fs::create_dir_all(disk.join("queue")).unwrap();
+        fs::write(disk.join("size"), "1953525168\n").unwrap();
+        fs::write(disk.join("queue/logical_block_size"), "512\n").unwrap();
+        disk
+    }
+
+    #[test]
+    fn test_read_block_device_scsi() {
+        let dir = TempDir::new().unwrap();
+        let disk = create_mock_disk(dir.path(), "sda");
+        fs::write(disk.join("device/model"), "ST1000NM0033-9ZM\n").unwrap();
+        fs::write(disk.join("device/vendor"), "ATA     \n").unwrap();
+        fs::write(disk.join("device/rev"), "SN04\n").unwrap();
+        fs::write(disk.join("device/queue_depth"), "32\n").unwrap();
+        fs::write(disk.join("device/ioerr_cnt"), "0x1f\n").unwrap();
+
+        assert_eq!(
+            read_block_device(&disk),
+            BlockDevice {
+                model: "ST1000NM0033-9ZM".to_string(),
+                vendor: "ATA".to_string(),
+                rev: "SN04".to_string(),
+                size_bytes: Some(1953525168 * 512),
+                sector_size_bytes: Some(512),
+                queue_depth: Some(32),
+                io_errors: Some(31),
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_block_device_without_scsi_attributes() {
+        let dir = TempDir::new().unwrap();
+        let disk = create_mock_disk(dir.path(), "vda");
+        let block = read_block_device(&disk);
+        assert_eq!(block.model, "");
+        assert_eq!(block.queue_depth, None);
+        assert_eq!(block.io_errors, None);
+        assert_eq!(block.sector_size_bytes, Some(512));
+    }
+
+    fn smart_block(attributes: &[(u8, [u8; 6])]) -> [u8; 512] {
+        let mut data = [0u8; 512];
+        for (index, (id, raw)) in attributes.iter().enumerate() {
+            let entry = &mut data[2 + index * 12..2 + (index + 1) * 12];
+            entry[0] = *id;
+            entry[3] = 100;
+            entry[5..11].copy_from_slice(raw);
+        }
+        let sum = data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        data[511] = 0u8.wrapping_sub(sum);
+        data
+    }
+
+    #[test]
+    fn test_parse_smart_attributes() {
+        let data = smart_block(&[
+            (5, [8, 0, 0, 0, 0, 0]),
+            (9, [0x10, 0x27, 0, 0, 0x2a, 0]),
+            (194, [36, 0, 18, 0, 51, 0]),
+            (197, [2, 1, 0, 0, 0, 0]),
+        ]);
+        assert_eq!(
+            parse_smart_attributes(&data),
+            Some(SmartAttributes {
+                temperature_celsius: Some(36),
+                reallocated_sectors: Some(8),
+                pending_sectors: Some(258),
+                power_on_hours: Some(10000),
+            })
+        );
+
+        let mut corrupt = data;
+        corrupt[100] ^= 1;
+        assert_eq!(parse_smart_attributes(&corrupt), None);
+    }
+
+    #[test]
+    fn test_parse_smart_status() {
+        let mut sense = [0u8; 32];
+        sense[0] = 0x72;
+        sense[8] = 0x09;
+        sense[8 + 9] = 0x4f;
+        sense[8 + 11] = 0xc2;
+        assert_eq!(parse_smart_status(&sense), Some(true));
+
+        sense[8 + 9] = 0xf4;
+        sense[8 + 11] = 0x2c;
+        assert_eq!(parse_smart_status(&sense), Some(false));
+
+        // Fixed-format or empty sense: no ATA registers to read
+        assert_eq!(parse_smart_status(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn test_parse_power_mode_standby() {
+        let mut sense = [0u8; 32];
+        sense[0] = 0x72;
+        sense[8] = 0x09;
+        assert_eq!(parse_power_mode_standby(&sense), Some(true));
+        sense[8 + 5] = 0xff;
+        assert_eq!(parse_power_mode_standby(&sense), Some(false));
+        sense[8 + 5] = 0x80;
+        assert_eq!(parse_power_mode_standby(&sense), Some(false));
+        assert_eq!(parse_power_mode_standby(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn test_sense_key() {
+        let mut fixed = [0u8; 32];
+        fixed[0] = 0x70;
+        fixed[2] = 0x05;
+        assert_eq!(sense_key(&fixed), Some(SENSE_KEY_ILLEGAL_REQUEST));
+        let mut descriptor = [0u8; 32];
+        descriptor[0] = 0x72;
+        descriptor[1] = 0x02;
+        assert_eq!(sense_key(&descriptor), Some(0x02));
+        assert_eq!(sense_key(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn test_smart_outcome_only_latches_unsupported() {
+        assert_eq!(SmartOutcome::Read.supported(None), Some(true));
+        assert_eq!(SmartOutcome::Unsupported.supported(Some(true)), Some(false));
+        // A busy or sleeping disk keeps being asked
+        assert_eq!(SmartOutcome::Failed.supported(None), None);
+        assert_eq!(SmartOutcome::Failed.supported(Some(true)), Some(true));
+        assert_eq!(SmartOutcome::Standby.supported(Some(true)), Some(true));
+    }
+
+    #[test]
+    fn test_check_power_mode_cdb() {
+        let cdb = check_power_mode_cdb();
+        assert_eq!(&cdb[..3], &[0x85, 0x06, 0x20]);
+        assert_eq!(cdb[14], 0xe5);
+    }
+
+    #[test]
+    fn test_smart_cdb() {
+        let read = smart_cdb(SMART_READ_DATA);
+        assert_eq!(&read[..3], &[0x85, 0x08, 0x0e]);
+        assert_eq!(
+            (read[4], read[6], read[10], read[12], read[14]),
+            (0xd0, 1, 0x4f, 0xc2, 0xb0)
+        );
+
+        let status = smart_cdb(SMART_RETURN_STATUS);
+        assert_eq!(&status[..3], &[0x85, 0x06, 0x20]);
+        assert_eq!(status[4], 0xda);
+    }
+
+    #[test]
+    fn test_is_smart_candidate() {
+        let dir = TempDir::new().unwrap();
+        let disk = create_mock_disk(dir.path(), "sda");
+        assert!(is_smart_candidate("sda", &disk));
+
+        let usb = dir.path().join("usb1/1-1/host6/block");
+        fs::create_dir_all(&usb).unwrap();
+        let usb_disk = create_mock_disk(&usb, "sdb");
+        std::os::unix::fs::symlink(&usb_disk, dir.path().join("sdb")).unwrap();
+        assert!(!is_smart_candidate("sdb", &dir.path().join("sdb")));
+
+        let virtio = create_mock_disk(dir.path(), "vda");
+        assert!(!is_smart_candidate("vda", &virtio));
+    }
+
+    #[test]
+    fn test_is_ignored_device() {
+        let config = AppConfig::default();
+        assert!(is_ignored_device("loop0", &config));
+        assert!(is_ignored_device("ram0", &config));
+        assert!(is_ignored_device("zram0", &config));
+        assert!(!is_ignored_device("nvme0n1", &config));
+
+        let mut config = AppConfig::default();
+        config.block.ignore_loop_devices = false;
+        assert!(!is_ignored_device("loop0", &config));
+    }
+}
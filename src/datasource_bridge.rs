@@ -0,0 +1,264 @@
+use crate::config::AppConfig;
+use crate::datasource_netdev_sysfs::should_skip_interface;
+use prometheus::GaugeVec;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const VLAN_CONFIG_PATH: &str = "/proc/net/vlan/config";
+
+struct BridgeMetrics {
+    port: GaugeVec,
+    stp_state: GaugeVec,
+    vlan_info: GaugeVec,
+    /// Label sets exported last time, so removed ports and VLANs disappear
+    exported: Mutex<Exported>,
+}
+
+#[derive(Default)]
+struct Exported {
+    ports: HashSet<[String; 2]>,
+    bridges: HashSet<String>,
+    vlans: HashSet<[String; 3]>,
+}
+
+impl BridgeMetrics {
+    fn new() -> Self {
+        Self {
+            port: prometheus::register_gauge_vec!(
+                "bridge_port",
+                "Interface attached to a Linux bridge (always 1)",
+                &["bridge", "interface"]
+            )
+            .expect("register bridge_port"),
+            stp_state: prometheus::register_gauge_vec!(
+                "bridge_stp_state",
+                "Bridge spanning tree mode (0 = off, 1 = kernel STP, 2 = user space STP)",
+                &["bridge"]
+            )
+            .expect("register bridge_stp_state"),
+            vlan_info: prometheus::register_gauge_vec!(
+                "vlan_info",
+                "802.1Q VLAN interface and its parent device (always 1)",
+                &["interface", "vlan_id", "parent"]
+            )
+            .expect("register vlan_info"),
+            exported: Mutex::new(Exported::default()),
+        }
+    }
+}
+
+static BRIDGE_METRICS: OnceLock<BridgeMetrics> = OnceLock::new();
+
+fn metrics() -> &'static BridgeMetrics {
+    BRIDGE_METRICS.get_or_init(BridgeMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+#[derive(Debug, PartialEq)]
+struct Vlan {
+    interface: String,
+    vlan_id: u16,
+    parent: String,
+}
+
+/// Parses the "eth0.100 | 100 | eth0" rows of /proc/net/vlan/config.
+fn parse_vlan_config(contents: &str) -> Vec<Vlan> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('|').map(str::trim);
+            let interface = fields.next()?;
+            let vlan_id = fields.next()?.parse().ok()?;
+            let parent = fields.next()?;
+            Some(Vlan {
+                interface: interface.to_string(),
+                vlan_id,
+                parent: parent.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn sorted_names(path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let vlans = fs::read_to_string(config.host_path(VLAN_CONFIG_PATH))
+        .map(|contents| parse_vlan_config(&contents))
+        .unwrap_or_default();
+    update_metrics_from_path(&config.host_path(SYS_CLASS_NET), &vlans, config);
+}
+
+fn update_metrics_from_path(base: &Path, vlans: &[Vlan], config: &AppConfig) {
+    let metrics = metrics();
+    let mut current = Exported::default();
+
+    for bridge in sorted_names(base) {
+        let bridge_path = base.join(&bridge);
+        // Only bridge masters have a bridge/ directory
+        if !bridge_path.join("bridge").is_dir() || should_skip_interface(&bridge, config) {
+            continue;
+        }
+        if let Some(stp_state) = read_string(&bridge_path.join("bridge/stp_state"))
+            .and_then(|value| value.parse::<u8>().ok())
+        {
+            metrics
+                .stp_state
+                .with_label_values(&[bridge.as_str()])
+                .set(stp_state as f64);
+            current.bridges.insert(bridge.clone());
+        }
+        for port in sorted_names(&bridge_path.join("brif")) {
+            if should_skip_interface(&port, config) {
+                continue;
+            }
+            metrics
+                .port
+                .with_label_values(&[bridge.as_str(), port.as_str()])
+                .set(1.0);
+            current.ports.insert([bridge.clone(), port]);
+        }
+    }
+
+    for vlan in vlans {
+        let labels = [
+            vlan.interface.clone(),
+            vlan.vlan_id.to_string(),
+            vlan.parent.clone(),
+        ];
+        metrics
+            .vlan_info
+            .with_label_values(&[labels[0].as_str(), &labels[1], &labels[2]])
+            .set(1.0);
+        current.vlans.insert(labels);
+    }
+
+    let mut exported = match metrics.exported.lock() {
+        Ok(exported) => exported,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for [bridge, port] in exported.ports.difference(&current.ports) {
+        let _ = metrics.port.remove_label_values(&[bridge, port]);
+    }
+    for bridge in exported.bridges.difference(&current.bridges) {
+        let _ = metrics.stp_state.remove_label_values(&[bridge]);
+    }
+    for [interface, vlan_id, parent] in exported.vlans.difference(&current.vlans) {
+        let _ = metrics
+            .vlan_info
+            .remove_label_values(&[interface, vlan_id, parent]);
+    }
+    *exported = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const VLAN_CONFIG: &str = "VLAN Dev name\t | VLAN ID\n\
+                               Name-Type: VLAN_NAME_TYPE_RAW_PLUS_VID_NO_PAD\n\
+                               eth0.100       | 100  | eth0\n\
+                               vlan200        | 200  | bond0\n";
+
+    fn create_mock_bridge(base: &Path, bridge: &str, stp_state: &str, ports: &[&str]) {
+        let path = base.join(bridge);
+        fs::create_dir_all(path.join("bridge")).unwrap();
+        fs::create_dir_all(path.join("brif")).unwrap();
+        fs::write(path.join("bridge/stp_state"), format!("{stp_state}\n")).unwrap();
+        for port in ports {
+            fs::create_dir_all(path.join("brif").join(port)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_vlan_config() {
+        assert_eq!(
+            parse_vlan_config(VLAN_CONFIG),
+            vec![
+                Vlan {
+                    interface: "eth0.100".to_string(),
+                    vlan_id: 100,
+                    parent: "eth0".to_string(),
+                },
+                Vlan {
+                    interface: "vlan200".to_string(),
+                    vlan_id: 200,
+                    parent: "bond0".to_string(),
+                },
+            ]
+        );
+        assert!(parse_vlan_config("").is_empty());
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let dir = TempDir::new().unwrap();
+        create_mock_bridge(dir.path(), "brtest0", "1", &["eth1", "vethtest0"]);
+        create_mock_bridge(dir.path(), "br-tst", "0", &["vethtest1"]);
+        // A plain interface is not a bridge
+        fs::create_dir_all(dir.path().join("ethtest9")).unwrap();
+        let config = AppConfig::default();
+        let vlans = parse_vlan_config(VLAN_CONFIG);
+
+        update_metrics_from_path(dir.path(), &vlans, &config);
+        let metrics = metrics();
+        assert_eq!(metrics.stp_state.with_label_values(&["brtest0"]).get(), 1.0);
+        assert_eq!(
+            metrics.port.with_label_values(&["brtest0", "eth1"]).get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .vlan_info
+                .with_label_values(&["eth0.100", "100", "eth0"])
+                .get(),
+            1.0
+        );
+        // veth ports and Docker's br-* bridges follow netdev.ignore_veth_interfaces
+        assert!(
+            metrics
+                .port
+                .remove_label_values(&["brtest0", "vethtest0"])
+                .is_err()
+        );
+        assert!(metrics.stp_state.remove_label_values(&["br-tst"]).is_err());
+        assert!(
+            metrics
+                .stp_state
+                .remove_label_values(&["ethtest9"])
+                .is_err()
+        );
+
+        // Detached ports and removed VLANs disappear on the next collection
+        fs::remove_dir(dir.path().join("brtest0/brif/eth1")).unwrap();
+        update_metrics_from_path(dir.path(), &vlans[1..], &config);
+        assert!(
+            metrics
+                .port
+                .remove_label_values(&["brtest0", "eth1"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .vlan_info
+                .remove_label_values(&["eth0.100", "100", "eth0"])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Chunk types under /sys/fs/btrfs/<uuid>/allocation/
+const CHUNK_TYPES: &[&str] = &["data", "metadata", "system"];
+
+/// (file, field label) read from each chunk type; disk_* include RAID copies
+const ALLOCATION_FIELDS: &[(&str, &str)] = &[
+    ("total_bytes", "total"),
+    ("bytes_used", "used"),
+    ("disk_total", "disk_total"),
+    ("disk_used", "disk_used"),
+];
+
+struct BtrfsMetrics {
+    allocation_bytes: GaugeVec,
+    info: GaugeVec,
+    device_size_bytes: GaugeVec,
+    series: Mutex<Series>,
+}
+
+/// Label sets exported on the previous pass, for removing unmounted filesystems
+#[derive(Default)]
+struct Series {
+    allocation: HashSet<(String, &'static str, &'static str)>,
+    info: HashSet<(String, String)>,
+    devices: HashSet<(String, String)>,
+}
+
+impl BtrfsMetrics {
+    fn new() -> Self {
+        Self {
+            allocation_bytes: prometheus::register_gauge_vec!(
+                "btrfs_allocation_bytes",
+                "Btrfs chunk allocation in bytes by chunk type",
+                &["uuid", "type", "field"]
+            )
+            .expect("register btrfs_allocation_bytes"),
+            info: prometheus::register_gauge_vec!(
+                "btrfs_info",
+                "Btrfs filesystem information (always 1)",
+                &["uuid", "label"]
+            )
+            .expect("register btrfs_info"),
+            device_size_bytes: prometheus::register_gauge_vec!(
+                "btrfs_device_size_bytes",
+                "Size of a device backing the btrfs filesystem in bytes",
+                &["uuid", "device"]
+            )
+            .expect("register btrfs_device_size_bytes"),
+            series: Mutex::new(Series::default()),
+        }
+    }
+}
+
+static BTRFS_METRICS: OnceLock<BtrfsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static BtrfsMetrics {
+    BTRFS_METRICS.get_or_init(BtrfsMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/fs/btrfs"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut current = Series::default();
+
+    for entry in entries.flatten() {
+        let fs_path = entry.path();
+        // Skips `features` and other non-filesystem entries
+        if !fs_path.join("allocation").is_dir() {
+            continue;
+        }
+        let Ok(uuid) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        let label = read_string(&fs_path.join("label")).unwrap_or_default();
+        metrics.info.with_label_values(&[&uuid, &label]).set(1.0);
+        current.info.insert((uuid.clone(), label));
+
+        for chunk_type in CHUNK_TYPES {
+            let chunk_path = fs_path.join("allocation").join(chunk_type);
+            for (file, field) in ALLOCATION_FIELDS {
+                let Some(value) = read_u64(&chunk_path.join(file)) else {
+                    continue;
+                };
+                metrics
+                    .allocation_bytes
+                    .with_label_values(&[uuid.as_str(), chunk_type, field])
+                    .set(value as f64);
+                current.allocation.insert((uuid.clone(), chunk_type, field));
+            }
+        }
+
+        let Ok(devices) = fs::read_dir(fs_path.join("devices")) else {
+            continue;
+        };
+        for device in devices.flatten() {
+            let Ok(name) = device.file_name().into_string() else {
+                continue;
+            };
+            // devices/<name> links to the block device; size is in 512-byte sectors
+            let Some(sectors) = read_u64(&device.path().join("size")) else {
+                continue;
+            };
+            metrics
+                .device_size_bytes
+                .with_label_values(&[&uuid, &name])
+                .set((sectors * 512) as f64);
+            current.devices.insert((uuid.clone(), name));
+        }
+    }
+
+    let mut series = match metrics.series.lock() {
+        Ok(series) => series,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (uuid, chunk_type, field) in series.allocation.difference(&current.allocation) {
+        let _ = metrics
+            .allocation_bytes
+            .remove_label_values(&[uuid.as_str(), chunk_type, field]);
+    }
+    for (uuid, label) in series.info.difference(&current.info) {
+        let _ = metrics.info.remove_label_values(&[uuid, label]);
+    }
+    for (uuid, device) in series.devices.difference(&current.devices) {
+        let _ = metrics
+            .device_size_bytes
+            .remove_label_values(&[uuid, device]);
+    }
+    *series = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const UUID: &str = "0b6f3c2e-8d41-4a5e-9c1b-7f2d3e4a5b6c";
+
+    fn create_mock_btrfs(root: &Path, uuid: &str, data_used: u64) {
+        let fs_path = root.join(uuid);
+        fs::create_dir_all(root.join("features")).unwrap();
+        for chunk_type in CHUNK_TYPES {
+            fs::create_dir_all(fs_path.join("allocation").join(chunk_type)).unwrap();
+        }
+        fs::write(fs_path.join("label"), "pool\n").unwrap();
+        let data = fs_path.join("allocation/data");
+        fs::write(data.join("total_bytes"), "10737418240\n").unwrap();
+        fs::write(data.join("bytes_used"), format!("{data_used}\n")).unwrap();
+        fs::write(data.join("disk_total"), "21474836480\n").unwrap();
+        let metadata = fs_path.join("allocation/metadata");
+        fs::write(metadata.join("total_bytes"), "1073741824\n").unwrap();
+        fs::write(metadata.join("bytes_used"), "268435456\n").unwrap();
+
+        let device = fs_path.join("devices/sda2");
+        fs::create_dir_all(&device).unwrap();
+        fs::write(device.join("size"), "41943040\n").unwrap();
+    }
+
+    #[test]
+    fn test_update_metrics_reads_allocation() {
+        let dir = TempDir::new().unwrap();
+        create_mock_btrfs(dir.path(), UUID, 4294967296);
+        update_metrics_from_path(dir.path());
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .allocation_bytes
+                .with_label_values(&[UUID, "data", "used"])
+                .get(),
+            4294967296.0
+        );
+        assert_eq!(
+            metrics
+                .allocation_bytes
+                .with_label_values(&[UUID, "data", "disk_total"])
+                .get(),
+            21474836480.0
+        );
+        assert_eq!(
+            metrics
+                .allocation_bytes
+                .with_label_values(&[UUID, "metadata", "total"])
+                .get(),
+            1073741824.0
+        );
+        assert_eq!(metrics.info.with_label_values(&[UUID, "pool"]).get(), 1.0);
+        assert_eq!(
+            metrics
+                .device_size_bytes
+                .with_label_values(&[UUID, "sda2"])
+                .get(),
+            41943040.0 * 512.0
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_removes_unmounted_filesystem() {
+        let dir = TempDir::new().unwrap();
+        let uuid = "5d1e2f3a-4b5c-6d7e-8f90-a1b2c3d4e5f6";
+        create_mock_btrfs(dir.path(), uuid, 1024);
+        update_metrics_from_path(dir.path());
+
+        fs::remove_dir_all(dir.path().join(uuid)).unwrap();
+        update_metrics_from_path(dir.path());
+
+        let series = metrics().series.lock().unwrap();
+        assert!(series.allocation.iter().all(|(seen, _, _)| seen != uuid));
+        assert!(series.devices.iter().all(|(seen, _)| seen != uuid));
+    }
+}
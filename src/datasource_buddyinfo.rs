@@ -0,0 +1,118 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::fs;
+use std::sync::OnceLock;
+
+const BUDDYINFO_PATH: &str = "/proc/buddyinfo";
+
+struct BuddyinfoMetrics {
+    free_blocks: GaugeVec,
+}
+
+impl BuddyinfoMetrics {
+    fn new() -> Self {
+        Self {
+            free_blocks: prometheus::register_gauge_vec!(
+                "buddyinfo_free_blocks",
+                "Free blocks of 2^order contiguous pages in the memory zone",
+                &["node", "zone", "order"]
+            )
+            .expect("register buddyinfo_free_blocks"),
+        }
+    }
+}
+
+static BUDDYINFO_METRICS: OnceLock<BuddyinfoMetrics> = OnceLock::new();
+
+fn metrics() -> &'static BuddyinfoMetrics {
+    BUDDYINFO_METRICS.get_or_init(BuddyinfoMetrics::new)
+}
+
+/// Free block counts of one zone, indexed by order
+#[derive(Debug, PartialEq)]
+struct ZoneFreeBlocks {
+    node: String,
+    zone: String,
+    counts: Vec<u64>,
+}
+
+/// Parses "Node 0, zone   Normal    708   1220 ..." lines.
+fn parse_buddyinfo(contents: &str) -> Vec<ZoneFreeBlocks> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (node, rest) = line.strip_prefix("Node ")?.split_once(',')?;
+            let mut fields = rest.split_whitespace();
+            if fields.next()? != "zone" {
+                return None;
+            }
+            let zone = fields.next()?;
+            let counts = fields
+                .map(|count| count.parse().ok())
+                .collect::<Option<_>>()?;
+            Some(ZoneFreeBlocks {
+                node: node.trim().to_string(),
+                zone: zone.to_string(),
+                counts,
+            })
+        })
+        .collect()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let Ok(contents) = fs::read_to_string(config.host_path(BUDDYINFO_PATH)) else {
+        return;
+    };
+    update_from_contents(&contents);
+}
+
+fn update_from_contents(contents: &str) {
+    let metrics = metrics();
+    for zone in parse_buddyinfo(contents) {
+        for (order, count) in zone.counts.iter().enumerate() {
+            metrics
+                .free_blocks
+                .with_label_values(&[zone.node.as_str(), &zone.zone, &order.to_string()])
+                .set(*count as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUDDYINFO: &str = "\
+Node 0, zone      DMA      0      0      0      0      0      0      0      0      1      1      3
+Node 0, zone    DMA32  16731   5370   7885    943   1417    352     67     34     23     11     14
+Node 1, zone   Normal    708   1220    156     50     99     52     39     15      7      0      2
+";
+
+    #[test]
+    fn test_parse_buddyinfo() {
+        let zones = parse_buddyinfo(BUDDYINFO);
+        assert_eq!(zones.len(), 3);
+        assert_eq!(zones[1].node, "0");
+        assert_eq!(zones[1].zone, "DMA32");
+        assert_eq!(zones[1].counts.len(), 11);
+        assert_eq!(zones[1].counts[0], 16731);
+        assert_eq!(zones[2].node, "1");
+        assert_eq!(zones[2].counts[10], 2);
+
+        assert!(parse_buddyinfo("Node 0, zone Normal 1 x 3\ngarbage\n").is_empty());
+    }
+
+    #[test]
+    fn test_update_from_contents() {
+        update_from_contents(BUDDYINFO);
+        let free_blocks = &metrics().free_blocks;
+        assert_eq!(
+            free_blocks.with_label_values(&["1", "Normal", "1"]).get(),
+            1220.0
+        );
+        assert_eq!(
+            free_blocks.with_label_values(&["0", "DMA", "10"]).get(),
+            3.0
+        );
+    }
+}
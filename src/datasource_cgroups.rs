@@ -0,0 +1,303 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use crate::glob::glob_match;
+use prometheus::{CounterVec, GaugeVec};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+struct CgroupsMetrics {
+    cpu_usage_seconds_total: CounterVec,
+    memory_current_bytes: GaugeVec,
+    memory_max_bytes: GaugeVec,
+    /// Last cpu.stat usage_usec per cgroup, used to advance the counter
+    previous_usage: Mutex<HashMap<String, u64>>,
+}
+
+impl CgroupsMetrics {
+    fn new() -> Self {
+        Self {
+            cpu_usage_seconds_total: prometheus::register_counter_vec!(
+                "cgroup_cpu_usage_seconds_total",
+                "Total CPU time consumed by the cgroup in seconds",
+                &["cgroup"]
+            )
+            .expect("register cgroup_cpu_usage_seconds_total"),
+            memory_current_bytes: prometheus::register_gauge_vec!(
+                "cgroup_memory_current_bytes",
+                "Memory currently used by the cgroup in bytes",
+                &["cgroup"]
+            )
+            .expect("register cgroup_memory_current_bytes"),
+            memory_max_bytes: prometheus::register_gauge_vec!(
+                "cgroup_memory_max_bytes",
+                "Memory limit of the cgroup in bytes (absent when unlimited)",
+                &["cgroup"]
+            )
+            .expect("register cgroup_memory_max_bytes"),
+            previous_usage: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static CGROUPS_METRICS: OnceLock<CgroupsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static CgroupsMetrics {
+    CGROUPS_METRICS.get_or_init(CgroupsMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse().ok()
+}
+
+/// Expands a pattern such as `system.slice/*.service` into matching cgroup
+/// directories, returned relative to `root`.
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<String> {
+    let mut current: Vec<PathBuf> = vec![PathBuf::new()];
+
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        let mut next = Vec::new();
+        for relative in &current {
+            if !segment.contains(['*', '?']) {
+                let candidate = relative.join(segment);
+                if root.join(&candidate).is_dir() {
+                    next.push(candidate);
+                }
+                continue;
+            }
+
+            let entries = match fs::read_dir(root.join(relative)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let name = match entry.file_name().into_string() {
+                    Ok(name) => name,
+                    Err(_) => continue,
+                };
+                if entry.path().is_dir() && glob_match(segment, &name) {
+                    next.push(relative.join(name));
+                }
+            }
+        }
+        current = next;
+    }
+
+    current
+        .into_iter()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .filter_map(|relative| relative.to_str().map(str::to_string))
+        .collect()
+}
+
+fn parse_usage_usec(contents: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if key == "usage_usec" {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+fn update_cgroup(
+    metrics: &CgroupsMetrics,
+    previous: &mut HashMap<String, u64>,
+    path: &Path,
+    name: &str,
+) {
+    if let Some(usage) = read_string(&path.join("cpu.stat")).and_then(|s| parse_usage_usec(&s)) {
+        let delta = match previous.get(name) {
+            Some(last) => counter_delta(usage, *last),
+            None => usage,
+        };
+        metrics
+            .cpu_usage_seconds_total
+            .with_label_values(&[name])
+            .inc_by(delta as f64 / 1_000_000.0);
+        previous.insert(name.to_string(), usage);
+    }
+
+    if let Some(current) = read_u64(&path.join("memory.current")) {
+        metrics
+            .memory_current_bytes
+            .with_label_values(&[name])
+            .set(current as f64);
+    }
+
+    // memory.max reads "max" when the cgroup has no limit
+    match read_u64(&path.join("memory.max")) {
+        Some(max) => metrics
+            .memory_max_bytes
+            .with_label_values(&[name])
+            .set(max as f64),
+        None => {
+            let _ = metrics.memory_max_bytes.remove_label_values(&[name]);
+        }
+    }
+}
+
+fn remove_cgroup(metrics: &CgroupsMetrics, name: &str) {
+    let _ = metrics.cpu_usage_seconds_total.remove_label_values(&[name]);
+    let _ = metrics.memory_current_bytes.remove_label_values(&[name]);
+    let _ = metrics.memory_max_bytes.remove_label_values(&[name]);
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path(CGROUP_ROOT), &config.cgroups.paths);
+}
+
+fn update_metrics_from_path(root: &Path, patterns: &[String]) {
+    // Only the unified (v2) hierarchy exposes cgroup.controllers at its root
+    if !root.join("cgroup.controllers").exists() {
+        return;
+    }
+
+    let metrics = metrics();
+    let mut previous = match metrics.previous_usage.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut seen = HashSet::new();
+    for pattern in patterns {
+        for name in expand_pattern(root, pattern) {
+            if seen.insert(name.clone()) {
+                update_cgroup(metrics, &mut previous, &root.join(&name), &name);
+            }
+        }
+    }
+
+    // Drop series for units that have stopped since the previous scrape
+    let stale: Vec<String> = previous
+        .keys()
+        .filter(|name| !seen.contains(*name))
+        .cloned()
+        .collect();
+    for name in stale {
+        remove_cgroup(metrics, &name);
+        previous.remove(&name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Stale-series cleanup touches every cgroup not matched by the current
+    // patterns, so tests that collect must not interleave
+    static COLLECT_LOCK: Mutex<()> = Mutex::new(());
+
+    fn create_mock_cgroup(root: &Path, name: &str, usage_usec: u64, max: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(
+            path.join("cpu.stat"),
+            format!("usage_usec {usage_usec}\nuser_usec 0\nsystem_usec 0\n"),
+        )
+        .unwrap();
+        fs::write(path.join("memory.current"), "1048576\n").unwrap();
+        fs::write(path.join("memory.max"), format!("{max}\n")).unwrap();
+    }
+
+    fn create_mock_root() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("cgroup.controllers"), "cpu memory\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_usage_usec() {
+        let contents = "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\n";
+        assert_eq!(parse_usage_usec(contents), Some(2_500_000));
+        assert_eq!(parse_usage_usec("user_usec 1\n"), None);
+    }
+
+    #[test]
+    fn test_expand_pattern() {
+        let root = create_mock_root();
+        create_mock_cgroup(root.path(), "system.slice/sshd.service", 0, "max");
+        create_mock_cgroup(root.path(), "system.slice/cron.service", 0, "max");
+        create_mock_cgroup(root.path(), "system.slice/dbus.socket", 0, "max");
+
+        let mut names = expand_pattern(root.path(), "system.slice/*.service");
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["system.slice/cron.service", "system.slice/sshd.service"]
+        );
+        assert!(expand_pattern(root.path(), "missing.slice/*").is_empty());
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let root = create_mock_root();
+        create_mock_cgroup(root.path(), "test.slice/app.service", 1_500_000, "max");
+        create_mock_cgroup(root.path(), "test.slice/db.service", 0, "2147483648");
+        let patterns = vec!["test.slice/*.service".to_string()];
+
+        update_metrics_from_path(root.path(), &patterns);
+        create_mock_cgroup(root.path(), "test.slice/app.service", 4_000_000, "max");
+        update_metrics_from_path(root.path(), &patterns);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .cpu_usage_seconds_total
+                .with_label_values(&["test.slice/app.service"])
+                .get(),
+            4.0
+        );
+        assert_eq!(
+            metrics
+                .memory_current_bytes
+                .with_label_values(&["test.slice/app.service"])
+                .get(),
+            1_048_576.0
+        );
+        assert_eq!(
+            metrics
+                .memory_max_bytes
+                .with_label_values(&["test.slice/db.service"])
+                .get(),
+            2_147_483_648.0
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_removes_stopped_cgroups() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let root = create_mock_root();
+        create_mock_cgroup(root.path(), "stale.slice/gone.service", 1_000_000, "max");
+        let patterns = vec!["stale.slice/*.service".to_string()];
+
+        update_metrics_from_path(root.path(), &patterns);
+        fs::remove_dir_all(root.path().join("stale.slice/gone.service")).unwrap();
+        update_metrics_from_path(root.path(), &patterns);
+
+        let previous = metrics().previous_usage.lock().unwrap();
+        assert!(!previous.contains_key("stale.slice/gone.service"));
+    }
+
+    #[test]
+    fn test_update_metrics_skips_cgroup_v1() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new().unwrap();
+        create_mock_cgroup(dir.path(), "v1.slice/app.service", 1_000_000, "max");
+
+        update_metrics_from_path(dir.path(), &["v1.slice/*.service".to_string()]);
+
+        let previous = metrics().previous_usage.lock().unwrap();
+        assert!(!previous.contains_key("v1.slice/app.service"));
+    }
+}
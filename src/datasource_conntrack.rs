@@ -1,15 +1,26 @@
 //! Conntrack statistics collector via netlink protocol.
 //!
 //! This module queries per-CPU conntrack statistics using the netfilter netlink
-//! protocol, similar to `conntrack -S`.
-
+//! protocol, similar to `conntrack -S`, and optionally summarizes the
+//! connection table by protocol and TCP state, similar to `conntrack -L`.
+//! Without CAP_NET_ADMIN the per-CPU statistics are read from
+//! /proc/net/stat/nf_conntrack instead.
+
+use crate::config::AppConfig;
+use crate::cpu_aggregate;
+use crate::internal_metrics;
+use crate::logging::{self, Level};
+use crate::runtime::debug_enabled;
 use prometheus::GaugeVec;
 use std::collections::HashMap;
+use std::fs;
 use std::io::{self, Error};
 use std::mem;
 use std::path::Path;
 use std::sync::OnceLock;
 
+const PROC_NF_CONNTRACK: &str = "/proc/net/stat/nf_conntrack";
+
 // Netlink protocol constants
 const NETLINK_NETFILTER: i32 = 12;
 
@@ -24,8 +35,17 @@ const NLMSG_ERROR: u16 = 2;
 // Netfilter netlink constants
 const NFNL_SUBSYS_CTNETLINK: u8 = 1;
 const NFNETLINK_V0: u8 = 0;
+const IPCTNL_MSG_CT_GET: u8 = 1;
 const IPCTNL_MSG_CT_GET_STATS_CPU: u8 = 4;
 
+// Connection attributes (nested CTA_TUPLE_* and CTA_PROTOINFO_* inside)
+const CTA_TUPLE_ORIG: u16 = 1;
+const CTA_PROTOINFO: u16 = 4;
+const CTA_TUPLE_PROTO: u16 = 2;
+const CTA_PROTO_NUM: u16 = 1;
+const CTA_PROTOINFO_TCP: u16 = 1;
+const CTA_PROTOINFO_TCP_STATE: u16 = 1;
+
 // CTA_STATS attribute IDs (from linux/netfilter/nfnetlink_conntrack.h)
 const CTA_STATS_FOUND: u16 = 2;
 const CTA_STATS_INVALID: u16 = 4;
@@ -72,6 +92,7 @@ pub struct CpuStats {
 
 struct ConntrackMetrics {
     conntrack: GaugeVec,
+    connections: GaugeVec,
 }
 
 impl ConntrackMetrics {
@@ -79,10 +100,16 @@ impl ConntrackMetrics {
         Self {
             conntrack: prometheus::register_gauge_vec!(
                 "conntrack",
-                "Per-CPU conntrack counters via netlink",
+                "Per-CPU conntrack counters via netlink or /proc/net/stat/nf_conntrack",
                 &["cpu", "field"]
             )
             .expect("register conntrack"),
+            connections: prometheus::register_gauge_vec!(
+                "conntrack_connections",
+                "Conntrack table entries by protocol and TCP state ([conntrack] table_summary)",
+                &["protocol", "state"]
+            )
+            .expect("register conntrack_connections"),
         }
     }
 }
@@ -99,9 +126,9 @@ fn nlmsg_align(len: usize) -> usize {
     (len + 3) & !3
 }
 
-/// Build the netlink request message for conntrack stats
-fn create_stats_request(seq: u32) -> Vec<u8> {
-    let nlmsg_type = ((NFNL_SUBSYS_CTNETLINK as u16) << 8) | (IPCTNL_MSG_CT_GET_STATS_CPU as u16);
+/// Build a ctnetlink dump request for every address family
+fn create_dump_request(msg_type: u8, seq: u32) -> Vec<u8> {
+    let nlmsg_type = ((NFNL_SUBSYS_CTNETLINK as u16) << 8) | (msg_type as u16);
     let total_len = mem::size_of::<NlMsgHdr>() + mem::size_of::<NfGenMsg>();
 
     let mut buf = vec![0u8; total_len];
@@ -160,6 +187,52 @@ fn attr_type_to_name(attr_type: u16) -> Option<&'static str> {
     }
 }
 
+/// Map a /proc/net/stat/nf_conntrack column to the netlink counter name;
+/// columns netlink does not report (entries, new, delete, ignore, expect_*)
+/// are skipped so the fallback exports the same fields
+fn proc_column_to_name(column: &str) -> Option<&'static str> {
+    match column {
+        "found" => Some("found"),
+        "invalid" => Some("invalid"),
+        "insert" => Some("insert"),
+        "insert_failed" => Some("insert_failed"),
+        "drop" => Some("drop"),
+        "early_drop" => Some("early_drop"),
+        "icmp_error" => Some("error"),
+        "search_restart" => Some("search_restart"),
+        "clashres" => Some("clash_resolve"),
+        "chaintoolong" | "chainlength" => Some("chain_toolong"),
+        _ => None,
+    }
+}
+
+/// Parses /proc/net/stat/nf_conntrack: a header of column names, then one
+/// line of hex counters per possible CPU, in CPU order
+fn parse_proc_stats(contents: &str) -> Option<Vec<CpuStats>> {
+    let mut lines = contents.lines();
+    let columns: Vec<Option<&str>> = lines
+        .next()?
+        .split_whitespace()
+        .map(proc_column_to_name)
+        .collect();
+
+    let mut all_stats = Vec::new();
+    for (cpu_id, line) in lines.filter(|line| !line.trim().is_empty()).enumerate() {
+        let mut stats = CpuStats {
+            cpu_id: u16::try_from(cpu_id).ok()?,
+            counters: HashMap::new(),
+        };
+        for (name, value) in columns.iter().zip(line.split_whitespace()) {
+            if let Some(name) = name {
+                let value = u64::from_str_radix(value, 16).ok()?;
+                stats.counters.insert(name.to_string(), value);
+            }
+        }
+        all_stats.push(stats);
+    }
+    Some(all_stats)
+}
+
 /// Parse a single netlink message containing per-CPU stats
 fn parse_stats_message(data: &[u8]) -> Result<CpuStats, String> {
     if data.len() < mem::size_of::<NfGenMsg>() {
@@ -208,6 +281,67 @@ fn parse_stats_message(data: &[u8]) -> Result<CpuStats, String> {
     Ok(stats)
 }
 
+/// Payload of the first `attr_type` attribute in a TLV attribute block
+fn find_attr(data: &[u8], attr_type: u16) -> Option<&[u8]> {
+    let mut offset = 0;
+    while offset + mem::size_of::<NlAttr>() <= data.len() {
+        let attr: NlAttr =
+            unsafe { std::ptr::read_unaligned(data.as_ptr().add(offset) as *const NlAttr) };
+        let attr_len = attr.nla_len as usize;
+        if attr_len < mem::size_of::<NlAttr>() || offset + attr_len > data.len() {
+            return None;
+        }
+        if attr.nla_type & 0x7FFF == attr_type {
+            return Some(&data[offset + mem::size_of::<NlAttr>()..offset + attr_len]);
+        }
+        offset += nlmsg_align(attr_len);
+    }
+    None
+}
+
+/// IP protocol number and, for TCP, the conntrack state of one table entry
+fn parse_entry(data: &[u8]) -> Option<(u8, Option<u8>)> {
+    let attrs = data.get(mem::size_of::<NfGenMsg>()..)?;
+    let tuple = find_attr(attrs, CTA_TUPLE_ORIG)?;
+    let protocol = *find_attr(find_attr(tuple, CTA_TUPLE_PROTO)?, CTA_PROTO_NUM)?.first()?;
+    let state = find_attr(attrs, CTA_PROTOINFO)
+        .and_then(|info| find_attr(info, CTA_PROTOINFO_TCP))
+        .and_then(|tcp| find_attr(tcp, CTA_PROTOINFO_TCP_STATE))
+        .and_then(|state| state.first().copied());
+    Some((protocol, state))
+}
+
+fn protocol_name(protocol: u8) -> String {
+    match protocol {
+        1 => "icmp".to_string(),
+        6 => "tcp".to_string(),
+        17 => "udp".to_string(),
+        33 => "dccp".to_string(),
+        47 => "gre".to_string(),
+        58 => "icmpv6".to_string(),
+        132 => "sctp".to_string(),
+        136 => "udplite".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Names from enum tcp_conntrack, as printed by /proc/net/nf_conntrack
+fn tcp_state_name(state: u8) -> &'static str {
+    match state {
+        0 => "NONE",
+        1 => "SYN_SENT",
+        2 => "SYN_RECV",
+        3 => "ESTABLISHED",
+        4 => "FIN_WAIT",
+        5 => "CLOSE_WAIT",
+        6 => "LAST_ACK",
+        7 => "TIME_WAIT",
+        8 => "CLOSE",
+        9 => "SYN_SENT2",
+        _ => "UNKNOWN",
+    }
+}
+
 /// Create a netlink socket for netfilter
 fn create_netlink_socket() -> io::Result<i32> {
     let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW, NETLINK_NETFILTER) };
@@ -252,11 +386,10 @@ fn conntrack_module_loaded() -> bool {
     false
 }
 
-/// Check if conntrack stats collection is available.
-/// Returns true if we can create a netlink socket (requires CAP_NET_ADMIN or root).
-/// Collect conntrack statistics via netlink.
-/// Returns per-CPU statistics or an error.
-pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
+/// Sends a ctnetlink dump request and hands each reply payload (nfgenmsg and
+/// attributes) to `handle` as it arrives, so large dumps are never buffered.
+/// Requires CAP_NET_ADMIN.
+fn netlink_dump(msg_type: u8, mut handle: impl FnMut(&[u8])) -> Result<(), String> {
     // Create socket
     let fd =
         create_netlink_socket().map_err(|e| format!("Failed to create netlink socket: {e}"))?;
@@ -271,7 +404,7 @@ pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
     let _guard = SocketGuard(fd);
 
     // Build and send request
-    let request = create_stats_request(1);
+    let request = create_dump_request(msg_type, 1);
     let sent = unsafe {
         libc::send(
             fd,
@@ -289,7 +422,6 @@ pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
     }
 
     // Receive responses
-    let mut all_stats = Vec::new();
     let mut buffer = vec![0u8; 16384];
 
     loop {
@@ -328,7 +460,7 @@ pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
 
             // Check message type
             if hdr.nlmsg_type == NLMSG_DONE {
-                return Ok(all_stats);
+                return Ok(());
             }
 
             if hdr.nlmsg_type == NLMSG_ERROR {
@@ -349,47 +481,133 @@ pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
                 continue;
             }
 
-            // Parse stats message
             let payload_offset = offset + mem::size_of::<NlMsgHdr>();
             let payload_len = msg_len - mem::size_of::<NlMsgHdr>();
 
             if payload_len > 0 {
-                let payload = &buffer[payload_offset..payload_offset + payload_len];
-                match parse_stats_message(payload) {
-                    Ok(stats) => all_stats.push(stats),
-                    Err(err) => {
-                        eprintln!("Failed to parse conntrack stats message: {err}");
-                    }
-                }
+                handle(&buffer[payload_offset..payload_offset + payload_len]);
             }
 
             offset += nlmsg_align(msg_len);
         }
     }
 
+    Ok(())
+}
+
+/// Collect conntrack statistics via netlink.
+/// Returns per-CPU statistics or an error.
+pub fn collect_stats() -> Result<Vec<CpuStats>, String> {
+    let mut all_stats = Vec::new();
+    netlink_dump(
+        IPCTNL_MSG_CT_GET_STATS_CPU,
+        |payload| match parse_stats_message(payload) {
+            Ok(stats) => all_stats.push(stats),
+            Err(err) => {
+                internal_metrics::collector_error("conntrack");
+                logging::log(
+                    Level::Warn,
+                    &format!("Failed to parse conntrack stats message: {err}"),
+                    &[("collector", "conntrack")],
+                );
+            }
+        },
+    )?;
     Ok(all_stats)
 }
 
-pub fn update_metrics() {
-    if !conntrack_module_loaded() {
+/// Counts table entries without keeping them; walking a busy firewall's
+/// table is expensive, hence opt-in.
+fn update_table_summary() {
+    let mut counts: HashMap<(u8, Option<u8>), u64> = HashMap::new();
+    let dumped = netlink_dump(IPCTNL_MSG_CT_GET, |payload| {
+        if let Some(key) = parse_entry(payload) {
+            *counts.entry(key).or_default() += 1;
+        }
+    });
+    if let Err(err) = dumped {
+        internal_metrics::collector_error("conntrack");
+        logging::log(
+            Level::Warn,
+            &format!("Failed to dump conntrack table: {err}"),
+            &[("collector", "conntrack")],
+        );
         return;
     }
 
-    let metrics = metrics();
-    match collect_stats() {
-        Ok(all_stats) => {
-            for cpu_stats in all_stats {
-                let cpu_label = cpu_stats.cpu_id.to_string();
-                for (name, value) in cpu_stats.counters {
-                    metrics
-                        .conntrack
-                        .with_label_values(&[cpu_label.as_str(), name.as_str()])
-                        .set(value as f64);
-                }
+    let connections = &metrics().connections;
+    // Protocol/state pairs without entries disappear instead of reading 0
+    connections.reset();
+    for ((protocol, state), count) in counts {
+        let state = state.map_or("none", tcp_state_name);
+        connections
+            .with_label_values(&[protocol_name(protocol).as_str(), state])
+            .set(count as f64);
+    }
+}
+
+/// `per_cpu` false sums every CPU into a single "total" series
+fn update_stats(metrics: &ConntrackMetrics, all_stats: Vec<CpuStats>, per_cpu: bool) {
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    let mut cpus = Vec::new();
+    for cpu_stats in all_stats {
+        let cpu_label = cpu_stats.cpu_id.to_string();
+        for (name, value) in cpu_stats.counters {
+            if per_cpu {
+                metrics
+                    .conntrack
+                    .with_label_values(&[cpu_label.as_str(), name.as_str()])
+                    .set(value as f64);
             }
+            *totals.entry(name).or_default() += value;
+        }
+        cpus.push(cpu_label);
+    }
+    cpu_aggregate::remove_other_mode(per_cpu, cpus.iter().map(String::as_str), |cpu| {
+        for name in totals.keys() {
+            let _ = metrics.conntrack.remove_label_values(&[cpu, name.as_str()]);
         }
+    });
+    if !per_cpu {
+        for (name, value) in totals {
+            metrics
+                .conntrack
+                .with_label_values(&["total", name.as_str()])
+                .set(value as f64);
+        }
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    if !conntrack_module_loaded() {
+        return;
+    }
+    if config.conntrack.table_summary {
+        update_table_summary();
+    }
+
+    let all_stats = collect_stats().or_else(|err| {
+        // Without CAP_NET_ADMIN nfnetlink refuses the request
+        let path = config.host_path(PROC_NF_CONNTRACK);
+        let all_stats = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| parse_proc_stats(&contents))
+            .ok_or(err.clone())?;
+        if debug_enabled() {
+            logging::debug("conntrack", &format!("{err}, reading {}", path.display()));
+        }
+        Ok::<_, String>(all_stats)
+    });
+
+    match all_stats {
+        Ok(all_stats) => update_stats(metrics(), all_stats, !config.cpu_aggregate_only),
         Err(err) => {
-            eprintln!("Failed to collect conntrack stats: {err}");
+            internal_metrics::collector_error("conntrack");
+            logging::log(
+                Level::Warn,
+                &format!("Failed to collect conntrack stats: {err}"),
+                &[("collector", "conntrack")],
+            );
         }
     }
 }
@@ -400,7 +618,7 @@ mod tests {
 
     #[test]
     fn test_create_stats_request() {
-        let request = create_stats_request(1);
+        let request = create_dump_request(IPCTNL_MSG_CT_GET_STATS_CPU, 1);
         assert_eq!(request.len(), 20); // 16 (nlmsghdr) + 4 (nfgenmsg)
 
         // Verify nlmsg_type
@@ -412,6 +630,55 @@ mod tests {
         assert_eq!(hdr.nlmsg_flags, NLM_F_REQUEST | NLM_F_DUMP);
     }
 
+    #[test]
+    fn test_parse_proc_stats() {
+        let contents = "entries  clashres found new invalid ignore delete chainlength insert insert_failed drop early_drop icmp_error  expect_new expect_create expect_delete search_restart\n\
+             0000002a  00000001 00000010 00000000 00000003 00000000 00000000 00000000 00000000 00000000 0000000f 00000000 00000002  00000000 00000000 00000000 00000004\n\
+             0000002a  00000000 00000020 00000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000  00000000 00000000 00000000 00000000\n";
+        let all_stats = parse_proc_stats(contents).unwrap();
+        assert_eq!(all_stats.len(), 2);
+        assert_eq!(all_stats[1].cpu_id, 1);
+
+        let counters = &all_stats[0].counters;
+        assert_eq!(counters["found"], 16);
+        assert_eq!(counters["invalid"], 3);
+        assert_eq!(counters["drop"], 15);
+        assert_eq!(counters["error"], 2);
+        assert_eq!(counters["clash_resolve"], 1);
+        assert_eq!(counters["search_restart"], 4);
+        // Global or netlink-less columns are not exported
+        assert!(!counters.contains_key("entries"));
+        assert!(!counters.contains_key("new"));
+
+        assert!(parse_proc_stats("found\nzz\n").is_none());
+    }
+
+    fn cpu_stats(cpu_id: u16, found: u64) -> CpuStats {
+        CpuStats {
+            cpu_id,
+            counters: HashMap::from([("found".to_string(), found)]),
+        }
+    }
+
+    #[test]
+    fn test_update_stats_aggregate_only_sums_cpus() {
+        let metrics = metrics();
+        update_stats(metrics, vec![cpu_stats(90, 3), cpu_stats(91, 4)], false);
+        assert_eq!(
+            metrics
+                .conntrack
+                .with_label_values(&["total", "found"])
+                .get(),
+            7.0
+        );
+        assert!(
+            metrics
+                .conntrack
+                .remove_label_values(&["90", "found"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_attr_type_to_name() {
         assert_eq!(attr_type_to_name(CTA_STATS_FOUND), Some("found"));
@@ -419,4 +686,52 @@ mod tests {
         assert_eq!(attr_type_to_name(0), None);
         assert_eq!(attr_type_to_name(100), None);
     }
+
+    fn push_attr(buf: &mut Vec<u8>, attr_type: u16, payload: &[u8]) {
+        let len = mem::size_of::<NlAttr>() + payload.len();
+        buf.extend_from_slice(&(len as u16).to_ne_bytes());
+        buf.extend_from_slice(&attr_type.to_ne_bytes());
+        buf.extend_from_slice(payload);
+        buf.resize(nlmsg_align(buf.len()), 0);
+    }
+
+    /// nfgenmsg plus the attributes the summary reads, with a CTA_STATUS in between
+    fn entry_message(protocol: u8, tcp_state: Option<u8>) -> Vec<u8> {
+        let mut proto = Vec::new();
+        push_attr(&mut proto, CTA_PROTO_NUM, &[protocol]);
+        let mut tuple = Vec::new();
+        push_attr(&mut tuple, CTA_TUPLE_PROTO, &proto);
+
+        let mut msg = vec![libc::AF_INET as u8, NFNETLINK_V0, 0, 0];
+        push_attr(&mut msg, CTA_TUPLE_ORIG, &tuple);
+        push_attr(&mut msg, 3, &8u32.to_be_bytes());
+        if let Some(state) = tcp_state {
+            let mut tcp = Vec::new();
+            push_attr(&mut tcp, CTA_PROTOINFO_TCP_STATE, &[state]);
+            let mut info = Vec::new();
+            push_attr(&mut info, CTA_PROTOINFO_TCP, &tcp);
+            push_attr(&mut msg, CTA_PROTOINFO, &info);
+        }
+        msg
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(parse_entry(&entry_message(6, Some(3))), Some((6, Some(3))));
+        assert_eq!(parse_entry(&entry_message(17, None)), Some((17, None)));
+        assert_eq!(parse_entry(&[0, 0]), None);
+        // Truncated attribute
+        let msg = entry_message(6, Some(3));
+        assert_eq!(parse_entry(&msg[..10]), None);
+    }
+
+    #[test]
+    fn test_entry_labels() {
+        assert_eq!(protocol_name(6), "tcp");
+        assert_eq!(protocol_name(58), "icmpv6");
+        assert_eq!(protocol_name(253), "253");
+        assert_eq!(tcp_state_name(3), "ESTABLISHED");
+        assert_eq!(tcp_state_name(7), "TIME_WAIT");
+        assert_eq!(tcp_state_name(42), "UNKNOWN");
+    }
 }
@@ -0,0 +1,168 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const VULNERABILITIES_PATH: &str = "/sys/devices/system/cpu/vulnerabilities";
+const VULN_STATES: [&str; 4] = ["Not affected", "Vulnerable", "Mitigation", "Unknown"];
+
+struct CpuVulnsMetrics {
+    vulnerability: GaugeVec,
+}
+
+impl CpuVulnsMetrics {
+    fn new() -> Self {
+        Self {
+            vulnerability: prometheus::register_gauge_vec!(
+                "cpu_vulnerability",
+                "CPU vulnerability status (always 1, summarized state in label)",
+                &["name", "state"]
+            )
+            .expect("register cpu_vulnerability"),
+        }
+    }
+}
+
+static CPU_VULNS_METRICS: OnceLock<CpuVulnsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static CpuVulnsMetrics {
+    CPU_VULNS_METRICS.get_or_init(CpuVulnsMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Reduces a vulnerability file such as "Mitigation: PTI" to its summary state.
+fn summarize_state(contents: &str) -> &'static str {
+    let contents = contents.trim();
+    // itlb_multihit reports "KVM: Mitigation: ..." on hosts running KVM
+    let contents = contents.strip_prefix("KVM: ").unwrap_or(contents);
+
+    if contents.starts_with("Not affected") {
+        "Not affected"
+    } else if contents.starts_with("Mitigation") {
+        "Mitigation"
+    } else if contents.to_ascii_lowercase().contains("vulnerable") {
+        "Vulnerable"
+    } else {
+        "Unknown"
+    }
+}
+
+fn update_vulnerability(path: &Path, name: &str) {
+    let Some(contents) = read_string(path) else {
+        return;
+    };
+
+    let metrics = metrics();
+    let state = summarize_state(&contents);
+    for known_state in VULN_STATES {
+        if known_state == state {
+            metrics
+                .vulnerability
+                .with_label_values(&[name, known_state])
+                .set(1.0);
+        } else {
+            let _ = metrics
+                .vulnerability
+                .remove_label_values(&[name, known_state]);
+        }
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path(VULNERABILITIES_PATH));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        if entry.path().is_file() {
+            update_vulnerability(&entry.path(), &name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_summarize_state() {
+        assert_eq!(summarize_state("Not affected\n"), "Not affected");
+        assert_eq!(summarize_state("Mitigation: PTI"), "Mitigation");
+        assert_eq!(
+            summarize_state("Vulnerable: Clear CPU buffers attempted, no microcode"),
+            "Vulnerable"
+        );
+        assert_eq!(
+            summarize_state("KVM: Mitigation: VMX disabled"),
+            "Mitigation"
+        );
+        assert_eq!(summarize_state("Processor vulnerable"), "Vulnerable");
+        assert_eq!(summarize_state("Unknown: No mitigations"), "Unknown");
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("meltdown"), "Mitigation: PTI\n").unwrap();
+        fs::write(dir.path().join("l1tf"), "Not affected\n").unwrap();
+
+        update_metrics_from_path(dir.path());
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .vulnerability
+                .with_label_values(&["meltdown", "Mitigation"])
+                .get(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_update_vulnerability_replaces_previous_state() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("test_vuln_transition");
+        fs::write(&file, "Vulnerable\n").unwrap();
+        update_vulnerability(&file, "test_vuln_transition");
+
+        fs::write(&file, "Mitigation: Enhanced IBRS\n").unwrap();
+        update_vulnerability(&file, "test_vuln_transition");
+
+        let families = metrics().vulnerability.collect();
+        let states: Vec<String> = families[0]
+            .get_metric()
+            .iter()
+            .filter(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.value() == "test_vuln_transition")
+            })
+            .flat_map(|metric| metric.get_label().iter())
+            .filter(|label| label.name() == "state")
+            .map(|label| label.value().to_string())
+            .collect();
+        assert_eq!(states, vec!["Mitigation".to_string()]);
+    }
+
+    #[test]
+    fn test_update_metrics_from_path_handles_missing_dir() {
+        update_metrics_from_path(Path::new("/nonexistent/path/that/does/not/exist"));
+    }
+}
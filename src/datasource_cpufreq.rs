@@ -1,10 +1,23 @@
-use prometheus::GaugeVec;
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use crate::cpu_aggregate;
+use prometheus::{CounterVec, GaugeVec, IntCounterVec};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 
 struct CpuFreqMetrics {
     cpu_frequency_hz: GaugeVec,
+    cpu_frequency_min_hz: GaugeVec,
+    cpu_frequency_max_hz: GaugeVec,
+    cpu_frequency_base_hz: GaugeVec,
+    cpu_scaling_governor: GaugeVec,
+    cpu_frequency_time_seconds_total: CounterVec,
+    cpu_frequency_transitions_total: IntCounterVec,
+    /// Last raw stats/ values, keyed by (cpu, frequency) and cpu respectively
+    previous_time_in_state: Mutex<HashMap<(String, String), u64>>,
+    previous_transitions: Mutex<HashMap<String, u64>>,
 }
 
 impl CpuFreqMetrics {
@@ -16,23 +29,177 @@ impl CpuFreqMetrics {
                 &["cpu", "source"]
             )
             .expect("register cpu_frequency_hz"),
+            cpu_frequency_min_hz: prometheus::register_gauge_vec!(
+                "cpu_frequency_min_hz",
+                "Minimum scaling frequency per core",
+                &["cpu"]
+            )
+            .expect("register cpu_frequency_min_hz"),
+            cpu_frequency_max_hz: prometheus::register_gauge_vec!(
+                "cpu_frequency_max_hz",
+                "Maximum scaling frequency per core",
+                &["cpu"]
+            )
+            .expect("register cpu_frequency_max_hz"),
+            cpu_frequency_base_hz: prometheus::register_gauge_vec!(
+                "cpu_frequency_base_hz",
+                "Maximum hardware frequency per core (cpuinfo_max_freq)",
+                &["cpu"]
+            )
+            .expect("register cpu_frequency_base_hz"),
+            cpu_scaling_governor: prometheus::register_gauge_vec!(
+                "cpu_scaling_governor",
+                "CPU frequency scaling governor (1 for the active governor)",
+                &["cpu", "governor"]
+            )
+            .expect("register cpu_scaling_governor"),
+            cpu_frequency_time_seconds_total: prometheus::register_counter_vec!(
+                "cpu_frequency_time_seconds_total",
+                "Time spent at each frequency per core in seconds",
+                &["cpu", "frequency"]
+            )
+            .expect("register cpu_frequency_time_seconds_total"),
+            cpu_frequency_transitions_total: prometheus::register_int_counter_vec!(
+                "cpu_frequency_transitions_total",
+                "Number of frequency transitions per core",
+                &["cpu"]
+            )
+            .expect("register cpu_frequency_transitions_total"),
+            previous_time_in_state: Mutex::new(HashMap::new()),
+            previous_transitions: Mutex::new(HashMap::new()),
         }
     }
 }
 
 static CPUFREQ_METRICS: OnceLock<CpuFreqMetrics> = OnceLock::new();
+static CLOCK_TICKS: OnceLock<f64> = OnceLock::new();
 
 fn metrics() -> &'static CpuFreqMetrics {
     CPUFREQ_METRICS.get_or_init(CpuFreqMetrics::new)
 }
 
+/// USER_HZ, the unit of time_in_state
+fn clock_ticks_per_second() -> f64 {
+    *CLOCK_TICKS.get_or_init(|| {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 { ticks as f64 } else { 100.0 }
+    })
+}
+
 fn parse_khz(path: &Path) -> Option<u64> {
     let contents = fs::read_to_string(path).ok()?;
     contents.trim().parse::<u64>().ok()
 }
 
-fn update_cpu(cpu_name: &str, cpufreq_dir: &Path) {
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn set_khz(metric: &GaugeVec, cpu_name: &str, path: &Path) {
+    if let Some(khz) = parse_khz(path) {
+        metric
+            .with_label_values(&[cpu_name])
+            .set((khz * 1000) as f64);
+    }
+}
+
+fn update_governor(cpu_name: &str, cpufreq_dir: &Path) {
+    let Some(current) = read_string(&cpufreq_dir.join("scaling_governor")) else {
+        return;
+    };
+
+    let mut governors: Vec<String> = read_string(&cpufreq_dir.join("scaling_available_governors"))
+        .map(|list| list.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    if !governors.contains(&current) {
+        governors.push(current.clone());
+    }
+
+    let metrics = metrics();
+    for governor in &governors {
+        metrics
+            .cpu_scaling_governor
+            .with_label_values(&[cpu_name, governor])
+            .set(if *governor == current { 1.0 } else { 0.0 });
+    }
+}
+
+/// Parses time_in_state lines of "<frequency kHz> <ticks>".
+fn parse_time_in_state(contents: &str) -> Vec<(u64, u64)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let khz = parts.next()?.parse().ok()?;
+            let ticks = parts.next()?.parse().ok()?;
+            Some((khz, ticks))
+        })
+        .collect()
+}
+
+/// `per_cpu` false adds every CPU's time and transitions to the "total" series
+fn update_stats(cpu_name: &str, cpufreq_dir: &Path, per_cpu: bool) {
+    // intel_pstate in active mode does not provide a stats directory
+    let stats_dir = cpufreq_dir.join("stats");
+    if !stats_dir.is_dir() {
+        return;
+    }
+
     let metrics = metrics();
+    let series = if per_cpu { cpu_name } else { "total" };
+    if let Some(contents) = read_string(&stats_dir.join("time_in_state")) {
+        let mut previous = match metrics.previous_time_in_state.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for (khz, ticks) in parse_time_in_state(&contents) {
+            let frequency = (khz * 1000).to_string();
+            let key = (cpu_name.to_string(), frequency);
+            let delta = counter_delta(ticks, previous.get(&key).copied().unwrap_or(0));
+            metrics
+                .cpu_frequency_time_seconds_total
+                .with_label_values(&[series, &key.1])
+                .inc_by(delta as f64 / clock_ticks_per_second());
+            previous.insert(key, ticks);
+        }
+    }
+
+    if let Some(total) =
+        read_string(&stats_dir.join("total_trans")).and_then(|s| s.parse::<u64>().ok())
+    {
+        let mut previous = match metrics.previous_transitions.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let delta = counter_delta(total, previous.get(cpu_name).copied().unwrap_or(0));
+        metrics
+            .cpu_frequency_transitions_total
+            .with_label_values(&[series])
+            .inc_by(delta);
+        previous.insert(cpu_name.to_string(), total);
+    }
+}
+
+fn update_cpu(cpu_name: &str, cpufreq_dir: &Path, per_cpu: bool) {
+    let metrics = metrics();
+    set_khz(
+        &metrics.cpu_frequency_min_hz,
+        cpu_name,
+        &cpufreq_dir.join("scaling_min_freq"),
+    );
+    set_khz(
+        &metrics.cpu_frequency_max_hz,
+        cpu_name,
+        &cpufreq_dir.join("scaling_max_freq"),
+    );
+    set_khz(
+        &metrics.cpu_frequency_base_hz,
+        cpu_name,
+        &cpufreq_dir.join("cpuinfo_max_freq"),
+    );
+    update_governor(cpu_name, cpufreq_dir);
+    update_stats(cpu_name, cpufreq_dir, per_cpu);
+
     let scaling_path = cpufreq_dir.join("scaling_cur_freq");
     if let Some(khz) = parse_khz(&scaling_path) {
         metrics
@@ -51,13 +218,37 @@ fn update_cpu(cpu_name: &str, cpufreq_dir: &Path) {
     }
 }
 
-pub fn update_metrics() {
-    let base = Path::new("/sys/devices/system/cpu");
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(
+        &config.host_path("/sys/devices/system/cpu"),
+        !config.cpu_aggregate_only,
+    );
+}
+
+/// Drops the stats series of the `cpu` label, a CPU or "total"
+fn remove_stats_series(metrics: &CpuFreqMetrics, cpu: &str) {
+    let previous = match metrics.previous_time_in_state.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (_, frequency) in previous.keys() {
+        let _ = metrics
+            .cpu_frequency_time_seconds_total
+            .remove_label_values(&[cpu, frequency]);
+    }
+    let _ = metrics
+        .cpu_frequency_transitions_total
+        .remove_label_values(&[cpu]);
+}
+
+/// The frequency gauges stay per CPU in both modes: they cannot be summed
+fn update_metrics_from_path(base: &Path, per_cpu: bool) {
     let entries = match fs::read_dir(base) {
         Ok(entries) => entries,
         Err(_) => return,
     };
 
+    let mut cpus = Vec::new();
     for entry in entries.flatten() {
         let name = entry.file_name();
         let name = match name.to_str() {
@@ -73,7 +264,188 @@ pub fn update_metrics() {
 
         let cpufreq_dir = entry.path().join("cpufreq");
         if cpufreq_dir.is_dir() {
-            update_cpu(name, &cpufreq_dir);
+            update_cpu(name, &cpufreq_dir, per_cpu);
+            cpus.push(name.to_string());
         }
     }
+
+    cpu_aggregate::remove_other_mode(per_cpu, cpus.iter().map(String::as_str), |cpu| {
+        remove_stats_series(metrics(), cpu)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Collecting removes the "total" or per-CPU stats series of the other
+    // mode, so tests that update stats must not interleave
+    static COLLECT_LOCK: Mutex<()> = Mutex::new(());
+
+    fn create_mock_cpufreq(base: &Path, cpu: &str) -> std::path::PathBuf {
+        let dir = base.join(cpu).join("cpufreq");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("scaling_cur_freq"), "1800000\n").unwrap();
+        fs::write(dir.join("scaling_min_freq"), "400000\n").unwrap();
+        fs::write(dir.join("scaling_max_freq"), "3600000\n").unwrap();
+        fs::write(dir.join("cpuinfo_max_freq"), "4200000\n").unwrap();
+        fs::write(dir.join("scaling_governor"), "powersave\n").unwrap();
+        fs::write(
+            dir.join("scaling_available_governors"),
+            "performance powersave\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new().unwrap();
+        create_mock_cpufreq(dir.path(), "cpu90");
+        fs::create_dir_all(dir.path().join("cpufreq")).unwrap();
+
+        update_metrics_from_path(dir.path(), true);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .cpu_frequency_hz
+                .with_label_values(&["cpu90", "scaling_cur_freq"])
+                .get(),
+            1_800_000_000.0
+        );
+        assert_eq!(
+            metrics
+                .cpu_frequency_min_hz
+                .with_label_values(&["cpu90"])
+                .get(),
+            400_000_000.0
+        );
+        assert_eq!(
+            metrics
+                .cpu_frequency_max_hz
+                .with_label_values(&["cpu90"])
+                .get(),
+            3_600_000_000.0
+        );
+        assert_eq!(
+            metrics
+                .cpu_frequency_base_hz
+                .with_label_values(&["cpu90"])
+                .get(),
+            4_200_000_000.0
+        );
+    }
+
+    #[test]
+    fn test_update_governor_marks_active() {
+        let dir = TempDir::new().unwrap();
+        let cpufreq = create_mock_cpufreq(dir.path(), "cpu91");
+
+        update_governor("cpu91", &cpufreq);
+        fs::write(cpufreq.join("scaling_governor"), "performance\n").unwrap();
+        update_governor("cpu91", &cpufreq);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .cpu_scaling_governor
+                .with_label_values(&["cpu91", "performance"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .cpu_scaling_governor
+                .with_label_values(&["cpu91", "powersave"])
+                .get(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_parse_time_in_state() {
+        let parsed = parse_time_in_state("800000 1500\n1600000 250\nbogus\n");
+        assert_eq!(parsed, vec![(800_000, 1500), (1_600_000, 250)]);
+    }
+
+    #[test]
+    fn test_update_stats_accumulates() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new().unwrap();
+        let cpufreq = create_mock_cpufreq(dir.path(), "cpu92");
+        let stats = cpufreq.join("stats");
+        fs::create_dir_all(&stats).unwrap();
+        let ticks = clock_ticks_per_second() as u64;
+
+        fs::write(stats.join("time_in_state"), format!("800000 {ticks}\n")).unwrap();
+        fs::write(stats.join("total_trans"), "10\n").unwrap();
+        update_stats("cpu92", &cpufreq, true);
+
+        fs::write(
+            stats.join("time_in_state"),
+            format!("800000 {}\n", ticks * 3),
+        )
+        .unwrap();
+        fs::write(stats.join("total_trans"), "14\n").unwrap();
+        update_stats("cpu92", &cpufreq, true);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .cpu_frequency_time_seconds_total
+                .with_label_values(&["cpu92", "800000000"])
+                .get(),
+            3.0
+        );
+        assert_eq!(
+            metrics
+                .cpu_frequency_transitions_total
+                .with_label_values(&["cpu92"])
+                .get(),
+            14
+        );
+    }
+
+    #[test]
+    fn test_update_stats_aggregate_only() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new().unwrap();
+        let ticks = clock_ticks_per_second() as u64;
+        let metrics = metrics();
+        let total = || {
+            metrics
+                .cpu_frequency_time_seconds_total
+                .with_label_values(&["total", "700000000"])
+                .get()
+        };
+        let before = total();
+        for cpu in ["cpu94", "cpu95"] {
+            let cpufreq = create_mock_cpufreq(dir.path(), cpu);
+            let stats = cpufreq.join("stats");
+            fs::create_dir_all(&stats).unwrap();
+            fs::write(stats.join("time_in_state"), format!("700000 {ticks}\n")).unwrap();
+            update_stats(cpu, &cpufreq, false);
+        }
+
+        assert_eq!(total() - before, 2.0);
+        assert!(
+            metrics
+                .cpu_frequency_time_seconds_total
+                .remove_label_values(&["cpu94", "700000000"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_update_stats_without_stats_dir() {
+        let dir = TempDir::new().unwrap();
+        let cpufreq = create_mock_cpufreq(dir.path(), "cpu93");
+        update_stats("cpu93", &cpufreq, true);
+
+        let previous = metrics().previous_transitions.lock().unwrap();
+        assert!(!previous.contains_key("cpu93"));
+    }
 }
@@ -0,0 +1,151 @@
+use crate::config::{AppConfig, CustomFile};
+use crate::internal_metrics;
+use crate::logging;
+use crate::runtime::debug_enabled;
+use prometheus::GaugeVec;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Gauges registered on demand for `[[custom_file]]` metric names
+#[derive(Default)]
+struct CustomMetrics {
+    /// None when registration failed, e.g. the name clashes with a built-in metric
+    gauges: HashMap<String, Option<GaugeVec>>,
+    /// (metric, label values) exported last time, so dropped entries disappear
+    exported: HashSet<(String, Vec<String>)>,
+}
+
+static CUSTOM_METRICS: OnceLock<Mutex<CustomMetrics>> = OnceLock::new();
+
+fn read_value(path: &Path) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse::<f64>().ok()
+}
+
+fn register_gauge(entry: &CustomFile) -> Option<GaugeVec> {
+    let label_names: Vec<&str> = entry.labels.keys().map(String::as_str).collect();
+    let help = format!("Value read from {}", entry.path);
+    match prometheus::register_gauge_vec!(entry.metric.as_str(), help.as_str(), &label_names) {
+        Ok(gauge) => Some(gauge),
+        Err(err) => {
+            logging::warn(&format!(
+                "Cannot register custom_file metric '{}': {err}",
+                entry.metric
+            ));
+            internal_metrics::collector_error("custom");
+            None
+        }
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let metrics = CUSTOM_METRICS.get_or_init(Mutex::default);
+    let mut metrics = match metrics.lock() {
+        Ok(metrics) => metrics,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut exported = HashSet::new();
+
+    for entry in config.custom_files() {
+        let gauge = metrics
+            .gauges
+            .entry(entry.metric.clone())
+            .or_insert_with(|| register_gauge(entry));
+        let Some(gauge) = gauge else {
+            continue;
+        };
+        // Unreadable or non-numeric files are skipped, like missing sysfs files
+        let Some(value) = read_value(&config.host_path(&entry.path)) else {
+            if debug_enabled() {
+                logging::debug(
+                    "custom",
+                    &format!("custom_file: no numeric value in {}", entry.path),
+                );
+            }
+            continue;
+        };
+        let label_values: Vec<String> = entry.labels.values().cloned().collect();
+        let Ok(series) = gauge.get_metric_with_label_values(
+            &label_values.iter().map(String::as_str).collect::<Vec<_>>(),
+        ) else {
+            // Label names changed on reload; the registered gauge keeps the old ones
+            continue;
+        };
+        series.set(value);
+        exported.insert((entry.metric.clone(), label_values));
+    }
+
+    for (metric, label_values) in metrics.exported.difference(&exported) {
+        if let Some(Some(gauge)) = metrics.gauges.get(metric) {
+            let label_values: Vec<&str> = label_values.iter().map(String::as_str).collect();
+            let _ = gauge.remove_label_values(&label_values);
+        }
+    }
+    metrics.exported = exported;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn custom_file(path: &Path, metric: &str, chip: &str) -> CustomFile {
+        CustomFile {
+            path: path.to_string_lossy().into_owned(),
+            metric: metric.to_string(),
+            labels: BTreeMap::from([("chip".to_string(), chip.to_string())]),
+        }
+    }
+
+    fn gauge_value(metric: &str, chip: &str) -> Option<f64> {
+        let metrics = CUSTOM_METRICS.get()?.lock().ok()?;
+        // with_label_values would create a missing series, so check first
+        if !metrics
+            .exported
+            .contains(&(metric.to_string(), vec![chip.to_string()]))
+        {
+            return None;
+        }
+        let gauge = metrics.gauges.get(metric)?.as_ref()?;
+        Some(gauge.with_label_values(&[chip]).get())
+    }
+
+    #[test]
+    fn test_read_value() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("value");
+        fs::write(&file, " 1.5e3\n").unwrap();
+        assert_eq!(read_value(&file), Some(1500.0));
+        fs::write(&file, "[enabled] disabled\n").unwrap();
+        assert_eq!(read_value(&file), None);
+        assert_eq!(read_value(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_update_metrics() {
+        let dir = TempDir::new().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        fs::write(&first, "42\n").unwrap();
+        fs::write(&second, "-3\n").unwrap();
+        let mut config = AppConfig::default();
+        config.custom_file = vec![
+            custom_file(&first, "custom_test_value", "a"),
+            custom_file(&second, "custom_test_value", "b"),
+            custom_file(&dir.path().join("missing"), "custom_test_value", "c"),
+        ];
+        update_metrics(&config);
+
+        assert_eq!(gauge_value("custom_test_value", "a"), Some(42.0));
+        assert_eq!(gauge_value("custom_test_value", "b"), Some(-3.0));
+        assert_eq!(gauge_value("custom_test_value", "c"), None);
+
+        // Entries removed on reload stop being exported
+        config.custom_file.remove(1);
+        update_metrics(&config);
+        assert_eq!(gauge_value("custom_test_value", "a"), Some(42.0));
+        assert_eq!(gauge_value("custom_test_value", "b"), None);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// World-readable attributes only; product_serial, product_uuid and friends
+/// are root-only and would fail on every scrape for unprivileged runs.
+const DMI_FIELDS: &[&str] = &[
+    "product_name",
+    "sys_vendor",
+    "board_name",
+    "bios_version",
+    "bios_date",
+    "chassis_type",
+];
+
+struct DmiMetrics {
+    info: GaugeVec,
+    labels: Mutex<Option<Vec<String>>>,
+}
+
+impl DmiMetrics {
+    fn new() -> Self {
+        Self {
+            info: prometheus::register_gauge_vec!(
+                "node_dmi_info",
+                "Hardware information from DMI/SMBIOS (always 1)",
+                DMI_FIELDS
+            )
+            .expect("register node_dmi_info"),
+            labels: Mutex::new(None),
+        }
+    }
+}
+
+static DMI_METRICS: OnceLock<DmiMetrics> = OnceLock::new();
+
+fn metrics() -> &'static DmiMetrics {
+    DMI_METRICS.get_or_init(DmiMetrics::new)
+}
+
+fn read_world_readable(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.permissions().mode() & 0o004 == 0 {
+        return None;
+    }
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Label values in DMI_FIELDS order; missing or restricted files read as ""
+fn read_dmi(base: &Path) -> Vec<String> {
+    DMI_FIELDS
+        .iter()
+        .map(|field| read_world_readable(&base.join(field)).unwrap_or_default())
+        .collect()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/class/dmi/id"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    if !base.is_dir() {
+        return;
+    }
+    let metrics = metrics();
+    let values = read_dmi(base);
+
+    let mut previous = match metrics.labels.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Firmware updates change bios_version; replace rather than accumulate series
+    if let Some(old) = previous.as_ref()
+        && *old != values
+    {
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let _ = metrics.info.remove_label_values(&old);
+    }
+    let labels: Vec<&str> = values.iter().map(String::as_str).collect();
+    metrics.info.with_label_values(&labels).set(1.0);
+    *previous = Some(values);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_dmi_skips_restricted_and_missing_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("product_name"), "PowerEdge R750\n").unwrap();
+        fs::write(dir.path().join("sys_vendor"), "Dell Inc.\n").unwrap();
+        fs::write(dir.path().join("bios_version"), "1.8.2\n").unwrap();
+        fs::write(dir.path().join("chassis_type"), "23\n").unwrap();
+        let restricted = dir.path().join("bios_date");
+        fs::write(&restricted, "09/15/2023\n").unwrap();
+        fs::set_permissions(&restricted, fs::Permissions::from_mode(0o400)).unwrap();
+
+        assert_eq!(
+            read_dmi(dir.path()),
+            vec!["PowerEdge R750", "Dell Inc.", "", "1.8.2", "", "23"]
+        );
+    }
+}
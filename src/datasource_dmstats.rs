@@ -0,0 +1,131 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct DmMetrics {
+    info: GaugeVec,
+    labels: Mutex<HashSet<[String; 3]>>,
+}
+
+impl DmMetrics {
+    fn new() -> Self {
+        Self {
+            info: prometheus::register_gauge_vec!(
+                "dm_info",
+                "Device-mapper device name and UUID (always 1)",
+                &["dm_device", "name", "uuid"]
+            )
+            .expect("register dm_info"),
+            labels: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static DM_METRICS: OnceLock<DmMetrics> = OnceLock::new();
+
+fn metrics() -> &'static DmMetrics {
+    DM_METRICS.get_or_init(DmMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// `[dm_device, name, uuid]` for every dm-N device under `base`
+fn read_dm_devices(base: &Path) -> Vec<[String; 3]> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<[String; 3]> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let device = entry.file_name().into_string().ok()?;
+            if !device.starts_with("dm-") {
+                return None;
+            }
+            let dm = entry.path().join("dm");
+            let name = read_string(&dm.join("name"))?;
+            // uuid is empty for devices created without one
+            let uuid = read_string(&dm.join("uuid")).unwrap_or_default();
+            Some([device, name, uuid])
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/block"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let metrics = metrics();
+    let current: HashSet<[String; 3]> = read_dm_devices(base).into_iter().collect();
+
+    let mut labels = match metrics.labels.lock() {
+        Ok(labels) => labels,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Volumes can be renamed (lvrename) or removed
+    for old in labels.difference(&current) {
+        let _ = metrics
+            .info
+            .remove_label_values(&[&old[0], &old[1], &old[2]]);
+    }
+    for device in &current {
+        metrics
+            .info
+            .with_label_values(&[&device[0], &device[1], &device[2]])
+            .set(1.0);
+    }
+    *labels = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_dm(base: &Path, device: &str, name: &str, uuid: Option<&str>) {
+        let dm = base.join(device).join("dm");
+        fs::create_dir_all(&dm).unwrap();
+        fs::write(dm.join("name"), format!("{name}\n")).unwrap();
+        if let Some(uuid) = uuid {
+            fs::write(dm.join("uuid"), format!("{uuid}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_dm_devices() {
+        let dir = TempDir::new().unwrap();
+        create_mock_dm(
+            dir.path(),
+            "dm-0",
+            "vg0-root",
+            Some("LVM-k1Xh3qYpWq0sN8bQ2JwT5mR7vL9cF4dA"),
+        );
+        create_mock_dm(dir.path(), "dm-3", "luks-home", None);
+        fs::create_dir_all(dir.path().join("sda")).unwrap();
+
+        assert_eq!(
+            read_dm_devices(dir.path()),
+            vec![
+                [
+                    "dm-0".to_string(),
+                    "vg0-root".to_string(),
+                    "LVM-k1Xh3qYpWq0sN8bQ2JwT5mR7vL9cF4dA".to_string()
+                ],
+                ["dm-3".to_string(), "luks-home".to_string(), String::new()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_dm_devices_missing_base() {
+        let dir = TempDir::new().unwrap();
+        assert!(read_dm_devices(&dir.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct DrbdMetrics {
+    connection_state: GaugeVec,
+    disk_state: GaugeVec,
+    role: GaugeVec,
+    out_of_sync_bytes: GaugeVec,
+    /// device -> state exported on the previous pass
+    devices: Mutex<HashMap<String, DrbdDevice>>,
+}
+
+impl DrbdMetrics {
+    fn new() -> Self {
+        Self {
+            connection_state: prometheus::register_gauge_vec!(
+                "drbd_connection_state",
+                "DRBD connection state of the device (always 1)",
+                &["device", "state"]
+            )
+            .expect("register drbd_connection_state"),
+            disk_state: prometheus::register_gauge_vec!(
+                "drbd_disk_state",
+                "DRBD disk state of the local and peer disk (always 1)",
+                &["device", "side", "state"]
+            )
+            .expect("register drbd_disk_state"),
+            role: prometheus::register_gauge_vec!(
+                "drbd_role",
+                "DRBD role of the local and peer node (always 1)",
+                &["device", "side", "role"]
+            )
+            .expect("register drbd_role"),
+            out_of_sync_bytes: prometheus::register_gauge_vec!(
+                "drbd_out_of_sync_bytes",
+                "Data not yet replicated to the peer in bytes",
+                &["device"]
+            )
+            .expect("register drbd_out_of_sync_bytes"),
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static DRBD_METRICS: OnceLock<DrbdMetrics> = OnceLock::new();
+
+fn metrics() -> &'static DrbdMetrics {
+    DRBD_METRICS.get_or_init(DrbdMetrics::new)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DrbdDevice {
+    connection: String,
+    /// (local, peer)
+    roles: (String, String),
+    /// (local, peer)
+    disks: (String, String),
+    out_of_sync_kib: Option<u64>,
+}
+
+fn split_pair(value: &str) -> (String, String) {
+    match value.split_once('/') {
+        Some((local, peer)) => (local.to_string(), peer.to_string()),
+        None => (value.to_string(), String::new()),
+    }
+}
+
+/// Parses the DRBD 8 /proc/drbd format:
+///
+/// ```text
+///  0: cs:Connected ro:Primary/Secondary ds:UpToDate/UpToDate C r-----
+///     ns:0 nr:0 dw:0 dr:0 al:0 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:0
+/// ```
+///
+/// DRBD 9 only prints the version here; its state lives in drbdsetup/debugfs.
+fn parse_proc_drbd(contents: &str) -> HashMap<String, DrbdDevice> {
+    let mut devices = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if let Some((minor, rest)) = trimmed.split_once(": ")
+            && minor.chars().all(|c| c.is_ascii_digit())
+            && !minor.is_empty()
+        {
+            current = None;
+            let mut device = DrbdDevice {
+                connection: String::new(),
+                roles: Default::default(),
+                disks: Default::default(),
+                out_of_sync_kib: None,
+            };
+            for field in rest.split_whitespace() {
+                if let Some(value) = field.strip_prefix("cs:") {
+                    device.connection = value.to_string();
+                } else if let Some(value) = field.strip_prefix("ro:") {
+                    device.roles = split_pair(value);
+                } else if let Some(value) = field.strip_prefix("ds:") {
+                    device.disks = split_pair(value);
+                }
+            }
+            // Minors that exist but have no resource attached
+            if device.connection.is_empty() || device.connection == "Unconfigured" {
+                continue;
+            }
+            let name = format!("drbd{minor}");
+            devices.insert(name.clone(), device);
+            current = Some(name);
+            continue;
+        }
+
+        let Some(device) = current.as_ref().and_then(|name| devices.get_mut(name)) else {
+            continue;
+        };
+        for field in trimmed.split_whitespace() {
+            if let Some(value) = field.strip_prefix("oos:") {
+                device.out_of_sync_kib = value.parse::<u64>().ok();
+            }
+        }
+    }
+    devices
+}
+
+fn remove_device(metrics: &DrbdMetrics, name: &str, device: &DrbdDevice) {
+    let _ = metrics
+        .connection_state
+        .remove_label_values(&[name, &device.connection]);
+    let _ = metrics
+        .role
+        .remove_label_values(&[name, "local", &device.roles.0]);
+    let _ = metrics
+        .role
+        .remove_label_values(&[name, "peer", &device.roles.1]);
+    let _ = metrics
+        .disk_state
+        .remove_label_values(&[name, "local", &device.disks.0]);
+    let _ = metrics
+        .disk_state
+        .remove_label_values(&[name, "peer", &device.disks.1]);
+    let _ = metrics.out_of_sync_bytes.remove_label_values(&[name]);
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/drbd"));
+}
+
+fn update_metrics_from_path(path: &Path) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+    let metrics = metrics();
+    let current = parse_proc_drbd(&contents);
+
+    let mut devices = match metrics.devices.lock() {
+        Ok(devices) => devices,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // State changes replace the labelled series, so drop the old ones first
+    for (name, previous) in devices.iter() {
+        if current.get(name) != Some(previous) {
+            remove_device(metrics, name, previous);
+        }
+    }
+
+    for (name, device) in &current {
+        metrics
+            .connection_state
+            .with_label_values(&[name, &device.connection])
+            .set(1.0);
+        metrics
+            .role
+            .with_label_values(&[name, "local", &device.roles.0])
+            .set(1.0);
+        metrics
+            .role
+            .with_label_values(&[name, "peer", &device.roles.1])
+            .set(1.0);
+        metrics
+            .disk_state
+            .with_label_values(&[name, "local", &device.disks.0])
+            .set(1.0);
+        metrics
+            .disk_state
+            .with_label_values(&[name, "peer", &device.disks.1])
+            .set(1.0);
+        if let Some(kib) = device.out_of_sync_kib {
+            metrics
+                .out_of_sync_bytes
+                .with_label_values(&[name])
+                .set((kib * 1024) as f64);
+        }
+    }
+    *devices = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PROC_DRBD: &str = "version: 8.4.11 (api:1/proto:86-101)\n\
+                             srcversion: 96ED19D4C144624490A9AB1\n \
+                             0: cs:Connected ro:Primary/Secondary ds:UpToDate/UpToDate C r-----\n    \
+                             ns:1048576 nr:0 dw:1048576 dr:2048 al:8 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:0\n \
+                             1: cs:Unconfigured\n \
+                             2: cs:StandAlone ro:Primary/Unknown ds:UpToDate/DUnknown   r-----\n    \
+                             ns:0 nr:0 dw:4096 dr:0 al:1 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:4096\n";
+
+    #[test]
+    fn test_parse_proc_drbd() {
+        let devices = parse_proc_drbd(PROC_DRBD);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(
+            devices["drbd0"],
+            DrbdDevice {
+                connection: "Connected".to_string(),
+                roles: ("Primary".to_string(), "Secondary".to_string()),
+                disks: ("UpToDate".to_string(), "UpToDate".to_string()),
+                out_of_sync_kib: Some(0),
+            }
+        );
+        assert_eq!(devices["drbd2"].connection, "StandAlone");
+        assert_eq!(devices["drbd2"].disks.1, "DUnknown");
+        assert_eq!(devices["drbd2"].out_of_sync_kib, Some(4096));
+
+        // DRBD 9 keeps only the version banner in /proc/drbd
+        assert!(parse_proc_drbd("version: 9.2.8 (api:2/proto:86-122)\n").is_empty());
+    }
+
+    #[test]
+    fn test_update_metrics_replaces_changed_state() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("drbd");
+        fs::write(&path, PROC_DRBD).unwrap();
+        update_metrics_from_path(&path);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .out_of_sync_bytes
+                .with_label_values(&["drbd2"])
+                .get(),
+            4096.0 * 1024.0
+        );
+
+        fs::write(&path, PROC_DRBD.replace("cs:StandAlone", "cs:SyncSource")).unwrap();
+        update_metrics_from_path(&path);
+
+        let devices = metrics.devices.lock().unwrap();
+        assert_eq!(devices["drbd2"].connection, "SyncSource");
+        assert!(
+            metrics
+                .connection_state
+                .remove_label_values(&["drbd2", "StandAlone"])
+                .is_err()
+        );
+    }
+}
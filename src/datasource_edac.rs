@@ -1,7 +1,21 @@
+use crate::config::AppConfig;
+use crate::counter_reset;
 use prometheus::GaugeVec;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
+
+/// Counters checked for resets, forgotten when their controller disappears
+const RESET_TRACKED: [&str; 7] = [
+    "edac_mc_correctable_errors_total",
+    "edac_mc_uncorrectable_errors_total",
+    "edac_mc_correctable_errors_noinfo_total",
+    "edac_mc_uncorrectable_errors_noinfo_total",
+    "edac_dimm_correctable_errors_total",
+    "edac_dimm_uncorrectable_errors_total",
+    "edac_csrow_correctable_errors_total",
+];
 
 struct EdacMetrics {
     mc_info: GaugeVec,
@@ -14,6 +28,9 @@ struct EdacMetrics {
     dimm_ce_count: GaugeVec,
     dimm_ue_count: GaugeVec,
     dimm_size_mb: GaugeVec,
+    csrow_ce_count: GaugeVec,
+    /// Controllers seen in the previous collection
+    controllers: Mutex<HashSet<String>>,
 }
 
 impl EdacMetrics {
@@ -88,6 +105,15 @@ impl EdacMetrics {
                 &["controller", "dimm", "dimm_label"]
             )
             .expect("register edac_dimm_size_mb"),
+
+            csrow_ce_count: prometheus::register_gauge_vec!(
+                "edac_csrow_correctable_errors_total",
+                "Correctable errors on this csrow channel",
+                &["controller", "csrow", "channel"]
+            )
+            .expect("register edac_csrow_correctable_errors_total"),
+
+            controllers: Mutex::new(HashSet::new()),
         }
     }
 }
@@ -112,7 +138,18 @@ fn update_dimm(mc_path: &Path, mc_name: &str, dimm_name: &str) {
 
     let dimm_label = read_string(&dimm_path.join("dimm_label")).unwrap_or_default();
 
+    let dimm_labels = [
+        ("controller", mc_name),
+        ("dimm", dimm_name),
+        ("dimm_label", dimm_label.as_str()),
+    ];
+
     if let Some(ce) = read_u64(&dimm_path.join("dimm_ce_count")) {
+        counter_reset::observe(
+            "edac_dimm_correctable_errors_total",
+            &dimm_labels,
+            ce as f64,
+        );
         metrics
             .dimm_ce_count
             .with_label_values(&[mc_name, dimm_name, &dimm_label])
@@ -120,6 +157,11 @@ fn update_dimm(mc_path: &Path, mc_name: &str, dimm_name: &str) {
     }
 
     if let Some(ue) = read_u64(&dimm_path.join("dimm_ue_count")) {
+        counter_reset::observe(
+            "edac_dimm_uncorrectable_errors_total",
+            &dimm_labels,
+            ue as f64,
+        );
         metrics
             .dimm_ue_count
             .with_label_values(&[mc_name, dimm_name, &dimm_label])
@@ -134,6 +176,72 @@ fn update_dimm(mc_path: &Path, mc_name: &str, dimm_name: &str) {
     }
 }
 
+/// Per-memory-controller sysfs layout
+#[derive(Debug, PartialEq)]
+enum EdacLayout {
+    /// dimmN/ or rankN/ directories (EDAC API since kernel 3.x)
+    Dimm,
+    /// Legacy csrowN/ directories with chX_ce_count files
+    Csrow,
+}
+
+fn detect_layout(mc_path: &Path) -> Option<EdacLayout> {
+    let mut has_csrow = false;
+    for entry in fs::read_dir(mc_path).ok()?.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        if !entry.path().is_dir() {
+            continue;
+        }
+        if name.starts_with("dimm") || name.starts_with("rank") {
+            return Some(EdacLayout::Dimm);
+        }
+        if name.starts_with("csrow") {
+            has_csrow = true;
+        }
+    }
+    has_csrow.then_some(EdacLayout::Csrow)
+}
+
+fn update_csrow(mc_path: &Path, mc_name: &str, csrow_name: &str) {
+    let csrow_path = mc_path.join(csrow_name);
+    let metrics = metrics();
+
+    let entries = match fs::read_dir(&csrow_path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let file_name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        // Match chN_ce_count files
+        if let Some(channel) = file_name.strip_suffix("_ce_count")
+            && channel.starts_with("ch")
+            && let Some(ce) = read_u64(&entry.path())
+        {
+            counter_reset::observe(
+                "edac_csrow_correctable_errors_total",
+                &[
+                    ("controller", mc_name),
+                    ("csrow", csrow_name),
+                    ("channel", channel),
+                ],
+                ce as f64,
+            );
+            metrics
+                .csrow_ce_count
+                .with_label_values(&[mc_name, csrow_name, channel])
+                .set(ce as f64);
+        }
+    }
+}
+
 fn update_memory_controller(mc_path: &Path, mc_name: &str) {
     let metrics = metrics();
 
@@ -146,8 +254,11 @@ fn update_memory_controller(mc_path: &Path, mc_name: &str) {
         .with_label_values(&[mc_name, &controller_type])
         .set(1.0);
 
-    // Read error counters
+    // Read error counters; they restart from 0 when the EDAC driver is
+    // reloaded or reset_counters is written
+    let mc_labels = [("controller", mc_name)];
     if let Some(ce) = read_u64(&mc_path.join("ce_count")) {
+        counter_reset::observe("edac_mc_correctable_errors_total", &mc_labels, ce as f64);
         metrics
             .mc_ce_count
             .with_label_values(&[mc_name])
@@ -155,6 +266,7 @@ fn update_memory_controller(mc_path: &Path, mc_name: &str) {
     }
 
     if let Some(ue) = read_u64(&mc_path.join("ue_count")) {
+        counter_reset::observe("edac_mc_uncorrectable_errors_total", &mc_labels, ue as f64);
         metrics
             .mc_ue_count
             .with_label_values(&[mc_name])
@@ -162,6 +274,11 @@ fn update_memory_controller(mc_path: &Path, mc_name: &str) {
     }
 
     if let Some(ce_noinfo) = read_u64(&mc_path.join("ce_noinfo_count")) {
+        counter_reset::observe(
+            "edac_mc_correctable_errors_noinfo_total",
+            &mc_labels,
+            ce_noinfo as f64,
+        );
         metrics
             .mc_ce_noinfo_count
             .with_label_values(&[mc_name])
@@ -169,6 +286,11 @@ fn update_memory_controller(mc_path: &Path, mc_name: &str) {
     }
 
     if let Some(ue_noinfo) = read_u64(&mc_path.join("ue_noinfo_count")) {
+        counter_reset::observe(
+            "edac_mc_uncorrectable_errors_noinfo_total",
+            &mc_labels,
+            ue_noinfo as f64,
+        );
         metrics
             .mc_ue_noinfo_count
             .with_label_values(&[mc_name])
@@ -191,29 +313,56 @@ fn update_memory_controller(mc_path: &Path, mc_name: &str) {
             .set(seconds as f64);
     }
 
-    // Process DIMMs and ranks
+    // Process DIMMs and ranks, or csrows on controllers using the legacy layout
+    let layout = detect_layout(mc_path);
     if let Ok(entries) = fs::read_dir(mc_path) {
         for entry in entries.flatten() {
             let name = match entry.file_name().into_string() {
                 Ok(name) => name,
                 Err(_) => continue,
             };
+            if !entry.path().is_dir() {
+                continue;
+            }
 
-            if (name.starts_with("dimm") || name.starts_with("rank")) && entry.path().is_dir() {
-                update_dimm(mc_path, mc_name, &name);
+            match layout {
+                Some(EdacLayout::Dimm) if name.starts_with("dimm") || name.starts_with("rank") => {
+                    update_dimm(mc_path, mc_name, &name);
+                }
+                Some(EdacLayout::Csrow) if name.starts_with("csrow") => {
+                    update_csrow(mc_path, mc_name, &name);
+                }
+                _ => {}
             }
         }
     }
 }
 
-pub fn update_metrics() {
-    update_metrics_from_path(Path::new("/sys/devices/system/edac/mc"));
+pub fn update_metrics(config: &AppConfig) {
+    let current = update_metrics_from_path(&config.host_path("/sys/devices/system/edac/mc"));
+    let mut controllers = match metrics().controllers.lock() {
+        Ok(controllers) => controllers,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    forget_removed_controllers(&mut controllers, current);
+}
+
+/// Unloading the EDAC driver removes its controllers; drop their reset state
+fn forget_removed_controllers(previous: &mut HashSet<String>, current: HashSet<String>) {
+    for name in previous.difference(&current) {
+        for metric in RESET_TRACKED {
+            counter_reset::forget(metric, ("controller", name));
+        }
+    }
+    *previous = current;
 }
 
-fn update_metrics_from_path(base: &Path) {
+/// Returns the controllers found, so the caller can forget removed ones
+fn update_metrics_from_path(base: &Path) -> HashSet<String> {
+    let mut current = HashSet::new();
     let entries = match fs::read_dir(base) {
         Ok(entries) => entries,
-        Err(_) => return,
+        Err(_) => return current,
     };
 
     for entry in entries.flatten() {
@@ -229,8 +378,10 @@ fn update_metrics_from_path(base: &Path) {
                 Err(_) => continue,
             };
             update_memory_controller(&path, &name);
+            current.insert(name);
         }
     }
+    current
 }
 
 #[cfg(test)]
@@ -265,6 +416,26 @@ mod tests {
         fs::write(dimm_dir.join("size"), format!("{}\n", size)).unwrap();
     }
 
+    fn create_mock_csrow(mc_dir: &Path, name: &str, channel_ce: &[u64]) {
+        let csrow_dir = mc_dir.join(name);
+        fs::create_dir_all(&csrow_dir).unwrap();
+        let total: u64 = channel_ce.iter().sum();
+        fs::write(csrow_dir.join("ce_count"), format!("{}\n", total)).unwrap();
+        fs::write(csrow_dir.join("ue_count"), "0\n").unwrap();
+        for (idx, ce) in channel_ce.iter().enumerate() {
+            fs::write(
+                csrow_dir.join(format!("ch{}_ce_count", idx)),
+                format!("{}\n", ce),
+            )
+            .unwrap();
+            fs::write(
+                csrow_dir.join(format!("ch{}_dimm_label", idx)),
+                format!("CPU_SrcID#0_Channel#{}_DIMM#0\n", idx),
+            )
+            .unwrap();
+        }
+    }
+
     #[test]
     fn test_read_string_trims_whitespace() {
         let dir = TempDir::new().unwrap();
@@ -316,6 +487,35 @@ mod tests {
         update_memory_controller(&mc, "mc0");
     }
 
+    #[test]
+    fn test_update_memory_controller_detects_counter_reset() {
+        let dir = TempDir::new().unwrap();
+        let mc = create_mock_mc(dir.path(), "mc90", "ie31200_edac", 7, 0, 16384);
+        update_memory_controller(&mc, "mc90");
+
+        // The driver was reloaded: the raw value is still exported as is
+        fs::write(mc.join("ce_count"), "1\n").unwrap();
+        update_memory_controller(&mc, "mc90");
+        assert_eq!(
+            metrics().mc_ce_count.with_label_values(&["mc90"]).get(),
+            1.0
+        );
+        assert_eq!(
+            counter_reset::reset_count(
+                "edac_mc_correctable_errors_total",
+                &[("controller", "mc90")]
+            ),
+            1
+        );
+        assert_eq!(
+            counter_reset::reset_count(
+                "edac_mc_uncorrectable_errors_total",
+                &[("controller", "mc90")]
+            ),
+            0
+        );
+    }
+
     #[test]
     fn test_update_metrics_from_path_filters_non_mc() {
         let dir = TempDir::new().unwrap();
@@ -325,6 +525,30 @@ mod tests {
         update_metrics_from_path(dir.path());
     }
 
+    #[test]
+    fn test_update_metrics_from_path_forgets_removed_controllers() {
+        let dir = TempDir::new().unwrap();
+        create_mock_mc(dir.path(), "mc91", "ie31200_edac", 3, 0, 8192);
+        let labels = [("controller", "mc91")];
+        let mut controllers = HashSet::new();
+
+        let current = update_metrics_from_path(dir.path());
+        assert_eq!(current, HashSet::from(["mc91".to_string()]));
+        forget_removed_controllers(&mut controllers, current);
+        assert!(counter_reset::is_tracked(
+            "edac_mc_correctable_errors_total",
+            &labels
+        ));
+
+        fs::remove_dir_all(dir.path().join("mc91")).unwrap();
+        let current = update_metrics_from_path(dir.path());
+        forget_removed_controllers(&mut controllers, current);
+        assert!(!counter_reset::is_tracked(
+            "edac_mc_correctable_errors_total",
+            &labels
+        ));
+    }
+
     #[test]
     fn test_update_metrics_from_path_handles_empty_dir() {
         let dir = TempDir::new().unwrap();
@@ -339,4 +563,45 @@ mod tests {
 
         update_dimm(&mc, "mc0", "dimm0");
     }
+
+    #[test]
+    fn test_detect_layout() {
+        let dir = TempDir::new().unwrap();
+        let dimm_mc = create_mock_mc(dir.path(), "mc0", "skx_edac", 0, 0, 8192);
+        create_mock_dimm(&dimm_mc, "dimm0", "DIMM_A1", 0, 0, 8192);
+        assert_eq!(detect_layout(&dimm_mc), Some(EdacLayout::Dimm));
+
+        let csrow_mc = create_mock_mc(dir.path(), "mc1", "i5000_edac", 0, 0, 8192);
+        create_mock_csrow(&csrow_mc, "csrow0", &[1, 2]);
+        assert_eq!(detect_layout(&csrow_mc), Some(EdacLayout::Csrow));
+
+        let empty_mc = create_mock_mc(dir.path(), "mc2", "i5000_edac", 0, 0, 8192);
+        assert_eq!(detect_layout(&empty_mc), None);
+    }
+
+    #[test]
+    fn test_update_memory_controller_with_csrows() {
+        let dir = TempDir::new().unwrap();
+        let mc = create_mock_mc(dir.path(), "mc7", "i5000_edac", 7, 0, 16384);
+        create_mock_csrow(&mc, "csrow0", &[3, 4]);
+        create_mock_csrow(&mc, "csrow1", &[0]);
+
+        update_memory_controller(&mc, "mc7");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .csrow_ce_count
+                .with_label_values(&["mc7", "csrow0", "ch1"])
+                .get(),
+            4.0
+        );
+        assert_eq!(
+            metrics
+                .csrow_ce_count
+                .with_label_values(&["mc7", "csrow1", "ch0"])
+                .get(),
+            0.0
+        );
+    }
 }
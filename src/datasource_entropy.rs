@@ -0,0 +1,68 @@
+use crate::config::AppConfig;
+use prometheus::Gauge;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+struct EntropyMetrics {
+    available_bits: Gauge,
+    pool_size_bits: Gauge,
+}
+
+impl EntropyMetrics {
+    fn new() -> Self {
+        Self {
+            available_bits: prometheus::register_gauge!(
+                "node_entropy_available_bits",
+                "Bits of entropy available in the kernel random pool"
+            )
+            .expect("register node_entropy_available_bits"),
+            pool_size_bits: prometheus::register_gauge!(
+                "node_entropy_pool_size_bits",
+                "Size of the kernel random pool in bits"
+            )
+            .expect("register node_entropy_pool_size_bits"),
+        }
+    }
+}
+
+static ENTROPY_METRICS: OnceLock<EntropyMetrics> = OnceLock::new();
+
+fn metrics() -> &'static EntropyMetrics {
+    ENTROPY_METRICS.get_or_init(EntropyMetrics::new)
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse::<u64>().ok()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/sys/kernel/random"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let metrics = metrics();
+    if let Some(available) = read_u64(&base.join("entropy_avail")) {
+        metrics.available_bits.set(available as f64);
+    }
+    if let Some(pool_size) = read_u64(&base.join("poolsize")) {
+        metrics.pool_size_bits.set(pool_size as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_u64() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("entropy_avail");
+        fs::write(&file, "256\n").unwrap();
+        assert_eq!(read_u64(&file), Some(256));
+        fs::write(&file, "bogus\n").unwrap();
+        assert_eq!(read_u64(&file), None);
+        assert_eq!(read_u64(&dir.path().join("missing")), None);
+    }
+}
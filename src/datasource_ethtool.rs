@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use crate::internal_metrics;
+use crate::logging;
 use crate::runtime::debug_enabled;
 use prometheus::GaugeVec;
 use std::collections::HashMap;
@@ -375,7 +377,10 @@ fn get_ethtool_family_id(fd: i32, seq: &mut u32) -> io::Result<u16> {
     send_message(fd, &msg)?;
     let replies = recv_messages(fd, *seq)?;
     if debug_enabled() {
-        eprintln!("ethtool: ctrl getfamily replies={}", replies.len());
+        logging::debug(
+            "ethtool",
+            &format!("ethtool: ctrl getfamily replies={}", replies.len()),
+        );
     }
     for reply in replies {
         if reply.len() < mem::size_of::<GenlMsgHdr>() {
@@ -394,7 +399,10 @@ fn get_ethtool_family_id(fd: i32, seq: &mut u32) -> io::Result<u16> {
                     summary.push(format!("attr={attr_type}"));
                 }
             }
-            eprintln!("ethtool: ctrl attrs {}", summary.join(", "));
+            logging::debug(
+                "ethtool",
+                &format!("ethtool: ctrl attrs {}", summary.join(", ")),
+            );
         }
         for (attr_type, payload) in attrs {
             if attr_type == CTRL_ATTR_FAMILY_ID
@@ -574,7 +582,10 @@ fn request_stats(fd: i32, family_id: u16, seq: &mut u32, dev: &str) -> io::Resul
                     .iter()
                     .map(|(t, v)| format!("{t}:{len}", len = v.len()))
                     .collect();
-                eprintln!("ethtool: grp attrs {dev}: {}", attr_types.join(", "));
+                logging::debug(
+                    "ethtool",
+                    &format!("ethtool: grp attrs {dev}: {}", attr_types.join(", ")),
+                );
             }
             for (grp_attr, grp_payload) in parse_attrs(payload) {
                 if grp_attr == ETHTOOL_A_STATS_GRP_ID {
@@ -587,7 +598,10 @@ fn request_stats(fd: i32, family_id: u16, seq: &mut u32, dev: &str) -> io::Resul
                             .iter()
                             .map(|(t, v)| format!("{t}:{len}", len = v.len()))
                             .collect();
-                        eprintln!("ethtool: grp stat inner {dev}: {}", inner.join(", "));
+                        logging::debug(
+                            "ethtool",
+                            &format!("ethtool: grp stat inner {dev}: {}", inner.join(", ")),
+                        );
                     }
                     for (stat_attr, stat_payload) in parse_attrs(grp_payload) {
                         if let Some(value) = parse_u64(stat_payload) {
@@ -668,8 +682,12 @@ pub fn update_metrics() {
     let family_id = match get_ethtool_family_id(fd, &mut seq) {
         Ok(id) => id,
         Err(err) => {
+            internal_metrics::collector_error("ethtool");
             if debug_enabled() {
-                eprintln!("ethtool: failed to resolve family id: {err}");
+                logging::debug(
+                    "ethtool",
+                    &format!("ethtool: failed to resolve family id: {err}"),
+                );
             }
             return;
         }
@@ -677,14 +695,21 @@ pub fn update_metrics() {
 
     let ifaces = list_ethernet_interfaces();
     if debug_enabled() {
-        eprintln!("ethtool: ethernet interfaces {}", ifaces.len());
+        logging::debug(
+            "ethtool",
+            &format!("ethtool: ethernet interfaces {}", ifaces.len()),
+        );
     }
     for iface in ifaces {
         let stringsets = match request_stringsets(fd, family_id, &mut seq, &iface) {
             Ok(stringsets) => stringsets,
             Err(err) => {
+                internal_metrics::collector_error("ethtool");
                 if debug_enabled() {
-                    eprintln!("ethtool: stringset request failed for {iface}: {err}");
+                    logging::debug(
+                        "ethtool",
+                        &format!("ethtool: stringset request failed for {iface}: {err}"),
+                    );
                 }
                 continue;
             }
@@ -694,19 +719,29 @@ pub fn update_metrics() {
                 .iter()
                 .map(|(id, strings)| format!("{id}:{len}", len = strings.len()))
                 .collect();
-            eprintln!("ethtool: stringsets for {iface}: {}", summary.join(", "));
+            logging::debug(
+                "ethtool",
+                &format!("ethtool: stringsets for {iface}: {}", summary.join(", ")),
+            );
         }
         let groups = match request_stats(fd, family_id, &mut seq, &iface) {
             Ok(groups) => groups,
             Err(err) => {
+                internal_metrics::collector_error("ethtool");
                 if debug_enabled() {
-                    eprintln!("ethtool: stats request failed for {iface}: {err}");
+                    logging::debug(
+                        "ethtool",
+                        &format!("ethtool: stats request failed for {iface}: {err}"),
+                    );
                 }
                 continue;
             }
         };
         if debug_enabled() {
-            eprintln!("ethtool: stats groups for {iface}: {}", groups.len());
+            logging::debug(
+                "ethtool",
+                &format!("ethtool: stats groups for {iface}: {}", groups.len()),
+            );
         }
         let metric = &metrics().ethtool_stats;
         let mut emitted = 0usize;
@@ -720,7 +755,10 @@ pub fn update_metrics() {
             }
         }
         if debug_enabled() {
-            eprintln!("ethtool: emitted {emitted} metrics for {iface}");
+            logging::debug(
+                "ethtool",
+                &format!("ethtool: emitted {emitted} metrics for {iface}"),
+            );
         }
     }
 }
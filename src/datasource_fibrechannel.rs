@@ -0,0 +1,270 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::{GaugeVec, IntCounterVec};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// port_state values from drivers/scsi/scsi_transport_fc.c
+const PORT_STATES: &[&str] = &[
+    "Online",
+    "Offline",
+    "Linkdown",
+    "Blocked",
+    "Bypassed",
+    "Diagnostics",
+    "Error",
+    "Loopback",
+    "Unknown",
+    "Not Present",
+];
+
+/// statistics/ files exported as counters
+const STAT_COUNTERS: &[(&str, &str, &str)] = &[
+    ("rx_frames", "fc_rx_frames_total", "Frames received"),
+    ("tx_frames", "fc_tx_frames_total", "Frames transmitted"),
+    (
+        "error_frames",
+        "fc_error_frames_total",
+        "Frames received in error",
+    ),
+    (
+        "invalid_crc_count",
+        "fc_invalid_crc_total",
+        "Frames received with an invalid CRC",
+    ),
+    (
+        "link_failure_count",
+        "fc_link_failure_total",
+        "Link failures",
+    ),
+    (
+        "loss_of_sync_count",
+        "fc_loss_of_sync_total",
+        "Loss of synchronization events",
+    ),
+    (
+        "loss_of_signal_count",
+        "fc_loss_of_signal_total",
+        "Loss of signal events",
+    ),
+];
+
+struct FibreChannelMetrics {
+    port_state: GaugeVec,
+    speed_gbps: GaugeVec,
+    counters: HashMap<&'static str, IntCounterVec>,
+    /// (host, file) -> last raw counter value
+    previous: Mutex<HashMap<(String, &'static str), u64>>,
+    hosts: Mutex<HashSet<String>>,
+}
+
+impl FibreChannelMetrics {
+    fn new() -> Self {
+        let counters = STAT_COUNTERS
+            .iter()
+            .map(|(file, name, help)| {
+                let counter = prometheus::register_int_counter_vec!(*name, *help, &["host"])
+                    .unwrap_or_else(|err| panic!("register {name}: {err}"));
+                (*file, counter)
+            })
+            .collect();
+        Self {
+            port_state: prometheus::register_gauge_vec!(
+                "fc_host_port_state",
+                "Fibre channel port state (1 for the current state, 0 otherwise)",
+                &["host", "state"]
+            )
+            .expect("register fc_host_port_state"),
+            speed_gbps: prometheus::register_gauge_vec!(
+                "fc_host_speed_gbps",
+                "Negotiated fibre channel port speed in Gbit/s",
+                &["host"]
+            )
+            .expect("register fc_host_speed_gbps"),
+            counters,
+            previous: Mutex::new(HashMap::new()),
+            hosts: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static FIBRECHANNEL_METRICS: OnceLock<FibreChannelMetrics> = OnceLock::new();
+
+fn metrics() -> &'static FibreChannelMetrics {
+    FIBRECHANNEL_METRICS.get_or_init(FibreChannelMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Statistics are `0x`-prefixed hex; all ones means the driver does not track it
+fn parse_hex_counter(value: &str) -> Option<u64> {
+    let value = u64::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()?;
+    (value != u64::MAX).then_some(value)
+}
+
+/// Parses speeds like `16 Gbit`; `unknown` while the link is down
+fn parse_speed_gbps(speed: &str) -> Option<f64> {
+    let (value, unit) = speed.trim().split_once(' ')?;
+    let value = value.parse::<f64>().ok()?;
+    match unit {
+        "Gbit" => Some(value),
+        "Mbit" => Some(value / 1000.0),
+        _ => None,
+    }
+}
+
+fn update_host(
+    metrics: &FibreChannelMetrics,
+    previous: &mut HashMap<(String, &'static str), u64>,
+    host: &str,
+    path: &Path,
+) {
+    if let Some(state) = read_string(&path.join("port_state")) {
+        for known in PORT_STATES {
+            metrics
+                .port_state
+                .with_label_values(&[host, known])
+                .set(if *known == state { 1.0 } else { 0.0 });
+        }
+    }
+
+    match read_string(&path.join("speed")).and_then(|speed| parse_speed_gbps(&speed)) {
+        Some(speed) => metrics.speed_gbps.with_label_values(&[host]).set(speed),
+        None => {
+            let _ = metrics.speed_gbps.remove_label_values(&[host]);
+        }
+    }
+
+    let statistics = path.join("statistics");
+    for (file, _, _) in STAT_COUNTERS {
+        let Some(value) = read_string(&statistics.join(file)).and_then(|v| parse_hex_counter(&v))
+        else {
+            continue;
+        };
+        let last = previous
+            .insert((host.to_string(), *file), value)
+            .unwrap_or(0);
+        if let Some(counter) = metrics.counters.get(file) {
+            counter
+                .with_label_values(&[host])
+                .inc_by(counter_delta(value, last));
+        }
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/class/fc_host"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut current = HashSet::new();
+    for entry in entries.flatten() {
+        let Ok(host) = entry.file_name().into_string() else {
+            continue;
+        };
+        update_host(metrics, &mut previous, &host, &entry.path());
+        current.insert(host);
+    }
+
+    let mut hosts = match metrics.hosts.lock() {
+        Ok(hosts) => hosts,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for host in hosts.difference(&current) {
+        for known in PORT_STATES {
+            let _ = metrics
+                .port_state
+                .remove_label_values(&[host.as_str(), known]);
+        }
+        let _ = metrics.speed_gbps.remove_label_values(&[host]);
+        for counter in metrics.counters.values() {
+            let _ = counter.remove_label_values(&[host]);
+        }
+    }
+    previous.retain(|(host, _), _| current.contains(host));
+    *hosts = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_host(root: &Path, state: &str, speed: &str, rx_frames: &str) {
+        let host = root.join("host7");
+        fs::create_dir_all(host.join("statistics")).unwrap();
+        fs::write(host.join("port_state"), format!("{state}\n")).unwrap();
+        fs::write(host.join("speed"), format!("{speed}\n")).unwrap();
+        let stats = host.join("statistics");
+        fs::write(stats.join("rx_frames"), format!("{rx_frames}\n")).unwrap();
+        fs::write(stats.join("tx_frames"), "0x2a\n").unwrap();
+        fs::write(stats.join("error_frames"), "0x0\n").unwrap();
+        fs::write(stats.join("invalid_crc_count"), "0xffffffffffffffff\n").unwrap();
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_hex_counter("0x1f4"), Some(500));
+        assert_eq!(parse_hex_counter("0xffffffffffffffff"), None);
+        assert_eq!(parse_hex_counter("bogus"), None);
+        assert_eq!(parse_speed_gbps("16 Gbit"), Some(16.0));
+        assert_eq!(parse_speed_gbps("100 Gbit"), Some(100.0));
+        assert_eq!(parse_speed_gbps("unknown"), None);
+    }
+
+    #[test]
+    fn test_update_metrics_reads_host() {
+        let dir = TempDir::new().unwrap();
+        create_mock_host(dir.path(), "Online", "16 Gbit", "0x1f4");
+        update_metrics_from_path(dir.path());
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .port_state
+                .with_label_values(&["host7", "Online"])
+                .get(),
+            1.0
+        );
+        assert_eq!(metrics.speed_gbps.with_label_values(&["host7"]).get(), 16.0);
+        let rx = &metrics.counters["rx_frames"];
+        assert_eq!(rx.with_label_values(&["host7"]).get(), 500);
+        assert_eq!(
+            metrics.counters["tx_frames"]
+                .with_label_values(&["host7"])
+                .get(),
+            42
+        );
+
+        create_mock_host(dir.path(), "Linkdown", "unknown", "0x200");
+        update_metrics_from_path(dir.path());
+        assert_eq!(
+            metrics
+                .port_state
+                .with_label_values(&["host7", "Online"])
+                .get(),
+            0.0
+        );
+        assert_eq!(rx.with_label_values(&["host7"]).get(), 512);
+        assert!(
+            !metrics
+                .previous
+                .lock()
+                .unwrap()
+                .contains_key(&("host7".to_string(), "invalid_crc_count"))
+        );
+    }
+}
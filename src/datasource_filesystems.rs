@@ -2,7 +2,9 @@ use crate::config::AppConfig;
 use prometheus::GaugeVec;
 use std::collections::HashSet;
 use std::ffi::CString;
-use std::sync::OnceLock;
+use std::sync::mpsc;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 struct FilesystemMetrics {
     filesystem_size_bytes: GaugeVec,
@@ -12,6 +14,9 @@ struct FilesystemMetrics {
     filesystem_files: GaugeVec,
     filesystem_files_free: GaugeVec,
     filesystem_files_used: GaugeVec,
+    filesystem_readonly: GaugeVec,
+    filesystem_device_error: GaugeVec,
+    filesystem_stat_timeout: GaugeVec,
 }
 
 impl FilesystemMetrics {
@@ -59,6 +64,24 @@ impl FilesystemMetrics {
                 &["mountpoint", "device", "fstype"]
             )
             .expect("register filesystem_files_used"),
+            filesystem_readonly: prometheus::register_gauge_vec!(
+                "filesystem_readonly",
+                "Filesystem is mounted read-only (1 = read-only)",
+                &["mountpoint", "device", "fstype"]
+            )
+            .expect("register filesystem_readonly"),
+            filesystem_device_error: prometheus::register_gauge_vec!(
+                "filesystem_device_error",
+                "statvfs failed for the mountpoint (1 = error)",
+                &["mountpoint", "device", "fstype"]
+            )
+            .expect("register filesystem_device_error"),
+            filesystem_stat_timeout: prometheus::register_gauge_vec!(
+                "filesystem_stat_timeout",
+                "statvfs did not return within [filesystems] stat_timeout_ms (1 = timed out)",
+                &["mountpoint"]
+            )
+            .expect("register filesystem_stat_timeout"),
         }
     }
 }
@@ -115,6 +138,110 @@ fn remove_metrics(metrics: &FilesystemMetrics, labels: &[&str; 3]) {
     let _ = metrics.filesystem_files.remove_label_values(labels);
     let _ = metrics.filesystem_files_free.remove_label_values(labels);
     let _ = metrics.filesystem_files_used.remove_label_values(labels);
+    let _ = metrics.filesystem_readonly.remove_label_values(labels);
+    let _ = metrics.filesystem_device_error.remove_label_values(labels);
+    let _ = metrics
+        .filesystem_stat_timeout
+        .remove_label_values(&[labels[0]]);
+}
+
+#[derive(Debug)]
+struct FsStat {
+    total_bytes: u64,
+    free_bytes: u64,
+    avail_bytes: u64,
+    files_total: u64,
+    files_free: u64,
+    readonly: bool,
+}
+
+fn stat_filesystem(mountpoint: &str) -> Option<FsStat> {
+    let mount_cstring = CString::new(mountpoint.as_bytes()).ok()?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::statvfs(mount_cstring.as_ptr(), &mut stat) };
+    if rc != 0 {
+        return None;
+    }
+
+    let block_size = if stat.f_frsize > 0 {
+        stat.f_frsize as u64
+    } else {
+        stat.f_bsize as u64
+    };
+
+    Some(FsStat {
+        total_bytes: stat.f_blocks as u64 * block_size,
+        free_bytes: stat.f_bfree as u64 * block_size,
+        avail_bytes: stat.f_bavail as u64 * block_size,
+        files_total: stat.f_files as u64,
+        files_free: stat.f_ffree as u64,
+        readonly: stat.f_flag & libc::ST_RDONLY != 0,
+    })
+}
+
+/// Mountpoints whose statvfs worker has not returned yet
+fn pending_stats() -> &'static Mutex<HashSet<String>> {
+    static PENDING: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+#[derive(Debug)]
+enum StatOutcome {
+    Done(Option<FsStat>),
+    TimedOut,
+}
+
+/// Runs `stat` on a worker thread and stops waiting after `timeout`. A hung
+/// worker only owns its own copies and a channel sender, so abandoning it is
+/// safe; while it is stuck the mountpoint is not retried.
+fn stat_with_timeout(
+    mountpoint: &str,
+    timeout: Duration,
+    stat: fn(&str) -> Option<FsStat>,
+) -> StatOutcome {
+    if timeout.is_zero() {
+        return StatOutcome::Done(stat(mountpoint));
+    }
+
+    {
+        let mut pending = match pending_stats().lock() {
+            Ok(pending) => pending,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if !pending.insert(mountpoint.to_string()) {
+            return StatOutcome::TimedOut;
+        }
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let owned = mountpoint.to_string();
+    let spawned = std::thread::Builder::new()
+        .name("statvfs".to_string())
+        .spawn(move || {
+            let result = stat(&owned);
+            let mut pending = match pending_stats().lock() {
+                Ok(pending) => pending,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            pending.remove(&owned);
+            // The receiver is gone if we already timed out
+            let _ = tx.send(result);
+        });
+
+    if spawned.is_err() {
+        let mut pending = match pending_stats().lock() {
+            Ok(pending) => pending,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        pending.remove(mountpoint);
+        return StatOutcome::Done(stat(mountpoint));
+    }
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => StatOutcome::Done(result),
+        Err(_) => StatOutcome::TimedOut,
+    }
 }
 
 pub fn update_metrics(config: &AppConfig) {
@@ -131,42 +258,67 @@ pub fn update_metrics(config: &AppConfig) {
             mount.fs_vfstype.as_str(),
         ];
         if is_pseudo_fs(&mount.fs_vfstype)
-            || (config.ignore_ramfs_filesystems && mount.fs_vfstype == "ramfs")
+            || (config.filesystems.ignore_ramfs && mount.fs_vfstype == "ramfs")
+            || config.is_fstype_excluded(&mount.fs_vfstype)
+            || config.is_mountpoint_excluded(&mount.fs_file)
         {
             remove_metrics(metrics, &labels);
             continue;
         }
-        if config.ignore_loop_devices
+        if config.block.ignore_loop_devices
             && (mount.fs_spec.starts_with("/dev/loop") || mount.fs_spec == "loop")
         {
             remove_metrics(metrics, &labels);
             continue;
         }
 
-        let mount_cstring = match CString::new(mount.fs_file.as_bytes()) {
-            Ok(value) => value,
-            Err(_) => continue,
-        };
-
-        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
-        let rc = unsafe { libc::statvfs(mount_cstring.as_ptr(), &mut stat) };
-        if rc != 0 {
+        if mount.fs_file.contains('\0') {
             continue;
         }
-
-        let block_size = if stat.f_frsize > 0 {
-            stat.f_frsize as u64
-        } else {
-            stat.f_bsize as u64
+        let timeout = Duration::from_millis(config.filesystems.stat_timeout_ms);
+        let result = match stat_with_timeout(&mount.fs_file, timeout, stat_filesystem) {
+            StatOutcome::Done(result) => result,
+            StatOutcome::TimedOut => {
+                metrics
+                    .filesystem_stat_timeout
+                    .with_label_values(&[labels[0]])
+                    .set(1.0);
+                continue;
+            }
         };
+        metrics
+            .filesystem_stat_timeout
+            .with_label_values(&[labels[0]])
+            .set(0.0);
+        let Some(stat) = result else {
+            // Stale or failing device; keep the last values but flag the error
+            metrics
+                .filesystem_device_error
+                .with_label_values(&labels)
+                .set(1.0);
+            let readonly = mount.fs_mntops.contains_key("ro");
+            metrics
+                .filesystem_readonly
+                .with_label_values(&labels)
+                .set(if readonly { 1.0 } else { 0.0 });
+            continue;
+        };
+        metrics
+            .filesystem_device_error
+            .with_label_values(&labels)
+            .set(0.0);
+        metrics
+            .filesystem_readonly
+            .with_label_values(&labels)
+            .set(if stat.readonly { 1.0 } else { 0.0 });
 
-        let total_bytes = stat.f_blocks as u64 * block_size;
-        let free_bytes = stat.f_bfree as u64 * block_size;
-        let avail_bytes = stat.f_bavail as u64 * block_size;
+        let total_bytes = stat.total_bytes;
+        let free_bytes = stat.free_bytes;
+        let avail_bytes = stat.avail_bytes;
         let used_bytes = total_bytes.saturating_sub(free_bytes);
 
-        let files_total = stat.f_files as u64;
-        let files_free = stat.f_ffree as u64;
+        let files_total = stat.files_total;
+        let files_free = stat.files_free;
         let files_used = files_total.saturating_sub(files_free);
 
         metrics
@@ -199,3 +351,59 @@ pub fn update_metrics(config: &AppConfig) {
             .set(files_used as f64);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_filesystem_root() {
+        let stat = stat_filesystem("/").expect("statvfs /");
+        assert!(stat.total_bytes > 0);
+        assert!(stat.free_bytes <= stat.total_bytes);
+    }
+
+    fn slow_stat(mountpoint: &str) -> Option<FsStat> {
+        std::thread::sleep(Duration::from_millis(300));
+        stat_filesystem(mountpoint)
+    }
+
+    #[test]
+    fn test_stat_with_timeout_returns_result() {
+        match stat_with_timeout("/", Duration::from_secs(5), stat_filesystem) {
+            StatOutcome::Done(Some(stat)) => assert!(stat.total_bytes > 0),
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+        assert!(matches!(
+            stat_with_timeout("/", Duration::ZERO, stat_filesystem),
+            StatOutcome::Done(Some(_))
+        ));
+    }
+
+    #[test]
+    fn test_stat_with_timeout_skips_hung_mount() {
+        // A path no real mount uses, so concurrent collections cannot interfere
+        let dir = tempfile::TempDir::new().unwrap();
+        let mountpoint = dir.path().to_str().unwrap();
+        assert!(matches!(
+            stat_with_timeout(mountpoint, Duration::from_millis(10), slow_stat),
+            StatOutcome::TimedOut
+        ));
+        // The first worker is still stuck, so no second one is started
+        assert!(matches!(
+            stat_with_timeout(mountpoint, Duration::from_secs(5), slow_stat),
+            StatOutcome::TimedOut
+        ));
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(matches!(
+            stat_with_timeout(mountpoint, Duration::from_secs(5), slow_stat),
+            StatOutcome::Done(Some(_))
+        ));
+    }
+
+    #[test]
+    fn test_stat_filesystem_missing_path() {
+        assert!(stat_filesystem("/nonexistent/rs-linux-exporter").is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::AppConfig;
 use prometheus::GaugeVec;
 use std::fs;
 use std::path::Path;
@@ -73,11 +74,14 @@ fn get_sensor_label(hwmon_dir: &Path, sensor_type: &str, index: &str) -> String
     read_string(&label_path).unwrap_or_else(|| format!("{}_{}", sensor_type, index))
 }
 
-fn update_hwmon_device(hwmon_dir: &Path) {
+fn update_hwmon_device(hwmon_dir: &Path, config: &AppConfig) {
     let chip_name = match read_string(&hwmon_dir.join("name")) {
         Some(name) => name,
         None => return,
     };
+    if config.is_hwmon_chip_excluded(&chip_name) {
+        return;
+    }
 
     let entries = match fs::read_dir(hwmon_dir) {
         Ok(entries) => entries,
@@ -95,7 +99,9 @@ fn update_hwmon_device(hwmon_dir: &Path) {
         // Temperature sensors: temp[1-*]_input (millidegrees Celsius)
         if file_name.starts_with("temp") && file_name.ends_with("_input") {
             let index = &file_name[4..file_name.len() - 6];
-            if let Some(millidegrees) = read_value(&entry.path()) {
+            if config.is_metric_enabled("hwmon_temperature_celsius")
+                && let Some(millidegrees) = read_value(&entry.path())
+            {
                 let label = get_sensor_label(hwmon_dir, "temp", index);
                 metrics
                     .temperature_celsius
@@ -106,7 +112,9 @@ fn update_hwmon_device(hwmon_dir: &Path) {
         // Fan sensors: fan[1-*]_input (RPM)
         else if file_name.starts_with("fan") && file_name.ends_with("_input") {
             let index = &file_name[3..file_name.len() - 6];
-            if let Some(rpm) = read_value(&entry.path()) {
+            if config.is_metric_enabled("hwmon_fan_rpm")
+                && let Some(rpm) = read_value(&entry.path())
+            {
                 let label = get_sensor_label(hwmon_dir, "fan", index);
                 metrics
                     .fan_rpm
@@ -118,6 +126,7 @@ fn update_hwmon_device(hwmon_dir: &Path) {
         else if file_name.starts_with("in") && file_name.ends_with("_input") {
             let index = &file_name[2..file_name.len() - 6];
             if index.chars().all(|c| c.is_ascii_digit())
+                && config.is_metric_enabled("hwmon_voltage_volts")
                 && let Some(millivolts) = read_value(&entry.path())
             {
                 let label = get_sensor_label(hwmon_dir, "in", index);
@@ -130,7 +139,9 @@ fn update_hwmon_device(hwmon_dir: &Path) {
         // Power sensors: power[1-*]_input (microwatts)
         else if file_name.starts_with("power") && file_name.ends_with("_input") {
             let index = &file_name[5..file_name.len() - 6];
-            if let Some(microwatts) = read_value(&entry.path()) {
+            if config.is_metric_enabled("hwmon_power_watts")
+                && let Some(microwatts) = read_value(&entry.path())
+            {
                 let label = get_sensor_label(hwmon_dir, "power", index);
                 metrics
                     .power_watts
@@ -141,7 +152,9 @@ fn update_hwmon_device(hwmon_dir: &Path) {
         // Current sensors: curr[1-*]_input (milliamps)
         else if file_name.starts_with("curr") && file_name.ends_with("_input") {
             let index = &file_name[4..file_name.len() - 6];
-            if let Some(milliamps) = read_value(&entry.path()) {
+            if config.is_metric_enabled("hwmon_current_amps")
+                && let Some(milliamps) = read_value(&entry.path())
+            {
                 let label = get_sensor_label(hwmon_dir, "curr", index);
                 metrics
                     .current_amps
@@ -152,11 +165,11 @@ fn update_hwmon_device(hwmon_dir: &Path) {
     }
 }
 
-pub fn update_metrics() {
-    update_metrics_from_path(Path::new("/sys/class/hwmon"));
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/class/hwmon"), config);
 }
 
-fn update_metrics_from_path(base: &Path) {
+fn update_metrics_from_path(base: &Path, config: &AppConfig) {
     let entries = match fs::read_dir(base) {
         Ok(entries) => entries,
         Err(_) => return,
@@ -170,7 +183,7 @@ fn update_metrics_from_path(base: &Path) {
                 Ok(p) => p,
                 Err(_) => continue,
             };
-            update_hwmon_device(&resolved);
+            update_hwmon_device(&resolved, config);
         }
     }
 }
@@ -232,7 +245,7 @@ mod tests {
         fs::write(hwmon.join("temp1_input"), "45000\n").unwrap();
 
         // Should not panic
-        update_hwmon_device(&hwmon);
+        update_hwmon_device(&hwmon, &AppConfig::default());
     }
 
     #[test]
@@ -241,7 +254,7 @@ mod tests {
         let hwmon = create_mock_hwmon(dir.path(), "hwmon0", "nct6775");
         fs::write(hwmon.join("fan1_input"), "1200\n").unwrap();
 
-        update_hwmon_device(&hwmon);
+        update_hwmon_device(&hwmon, &AppConfig::default());
     }
 
     #[test]
@@ -250,7 +263,50 @@ mod tests {
         let hwmon = create_mock_hwmon(dir.path(), "hwmon0", "nct6775");
         fs::write(hwmon.join("in0_input"), "1200\n").unwrap();
 
-        update_hwmon_device(&hwmon);
+        update_hwmon_device(&hwmon, &AppConfig::default());
+    }
+
+    #[test]
+    fn test_update_hwmon_device_skips_disabled_metrics() {
+        let dir = TempDir::new().unwrap();
+        let hwmon = create_mock_hwmon(dir.path(), "hwmon0", "testdisabled");
+        fs::write(hwmon.join("temp1_input"), "45000\n").unwrap();
+        fs::write(hwmon.join("in0_input"), "1200\n").unwrap();
+        let mut config = AppConfig::default();
+        config.disabled_metrics = vec!["hwmon_voltage_volts".to_string()];
+
+        update_hwmon_device(&hwmon, &config);
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .temperature_celsius
+                .with_label_values(&["testdisabled", "temp_1"])
+                .get(),
+            45.0
+        );
+        assert!(
+            metrics
+                .voltage_volts
+                .remove_label_values(&["testdisabled", "in_0"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_update_hwmon_device_skips_excluded_chip() {
+        let dir = TempDir::new().unwrap();
+        let hwmon = create_mock_hwmon(dir.path(), "hwmon0", "testexcluded");
+        fs::write(hwmon.join("temp1_input"), "45000\n").unwrap();
+        let mut config = AppConfig::default();
+        config.hwmon.chip_exclude = vec!["test*".to_string()];
+
+        update_hwmon_device(&hwmon, &config);
+        assert!(
+            metrics()
+                .temperature_celsius
+                .remove_label_values(&["testexcluded", "temp_1"])
+                .is_err()
+        );
     }
 
     #[test]
@@ -262,13 +318,13 @@ mod tests {
         fs::write(hwmon.join("temp1_input"), "45000\n").unwrap();
 
         // Should return early without panicking
-        update_hwmon_device(&hwmon);
+        update_hwmon_device(&hwmon, &AppConfig::default());
     }
 
     #[test]
     fn test_update_metrics_from_path_handles_empty_dir() {
         let dir = TempDir::new().unwrap();
         // Empty directory - should not panic
-        update_metrics_from_path(dir.path());
+        update_metrics_from_path(dir.path(), &AppConfig::default());
     }
 }
@@ -1,18 +1,29 @@
+use crate::config::AppConfig;
+use crate::internal_metrics;
+use crate::logging;
 use crate::runtime::debug_enabled;
+use ipmi_rs::connection::{IpmiCommand, Message, NetFn, NotEnoughData};
 use ipmi_rs::sensor_event::{GetSensorReading, ThresholdReading};
 use ipmi_rs::storage::sdr::record::{
     DataFormat, FullSensorRecord, IdentifiableSensor, InstancedSensor, WithSensorRecordCommon,
 };
 use ipmi_rs::{File, Ipmi};
 use prometheus::GaugeVec;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
 
 const IPMI_DEVICE: &str = "/dev/ipmi0";
 const IPMI_TIMEOUT_MS: u64 = 2000;
+/// FRU device 0 is the BMC's own FRU, holding the board and product areas
+const DEFAULT_FRU_ID: u8 = 0;
+/// Bytes per Read FRU Data request; larger reads overflow some BMCs' buffers
+const FRU_READ_CHUNK: u16 = 32;
 
 struct IpmiMetrics {
     sensor_reading: GaugeVec,
+    fru_info: GaugeVec,
+    /// FRU identity exported once read; inventory does not change while running
+    fru: Mutex<Option<FruInfo>>,
 }
 
 impl IpmiMetrics {
@@ -24,6 +35,19 @@ impl IpmiMetrics {
                 &["sensor", "type", "unit"]
             )
             .expect("register ipmi_sensor_reading"),
+            fru_info: prometheus::register_gauge_vec!(
+                "ipmi_fru_info",
+                "IPMI FRU inventory from the BMC (always 1)",
+                &[
+                    "fru_id",
+                    "manufacturer",
+                    "product_name",
+                    "serial",
+                    "part_number"
+                ]
+            )
+            .expect("register ipmi_fru_info"),
+            fru: Mutex::new(None),
         }
     }
 }
@@ -39,14 +63,257 @@ fn open_ipmi() -> Option<Ipmi<File>> {
     match File::new(IPMI_DEVICE, timeout) {
         Ok(file) => Some(Ipmi::new(file)),
         Err(err) => {
+            internal_metrics::collector_error("ipmi");
             if debug_enabled() {
-                eprintln!("ipmi: failed to open {IPMI_DEVICE}: {err}");
+                logging::debug(
+                    "ipmi",
+                    &format!("ipmi: failed to open {IPMI_DEVICE}: {err}"),
+                );
             }
             None
         }
     }
 }
 
+/// Get FRU Inventory Area Info: the FRU's size in bytes and access unit
+struct GetFruInventoryAreaInfo {
+    fru_id: u8,
+}
+
+struct FruAreaInfo {
+    size: u16,
+    /// Offsets and counts are in 16-bit words instead of bytes
+    word_access: bool,
+}
+
+impl From<GetFruInventoryAreaInfo> for Message {
+    fn from(value: GetFruInventoryAreaInfo) -> Self {
+        Message::new_request(NetFn::Storage, 0x10, vec![value.fru_id])
+    }
+}
+
+impl IpmiCommand for GetFruInventoryAreaInfo {
+    type Output = FruAreaInfo;
+    type Error = NotEnoughData;
+
+    fn parse_success_response(data: &[u8]) -> Result<Self::Output, Self::Error> {
+        match data {
+            [lsb, msb, access, ..] => Ok(FruAreaInfo {
+                size: u16::from_le_bytes([*lsb, *msb]),
+                word_access: access & 0x01 == 0x01,
+            }),
+            _ => Err(NotEnoughData),
+        }
+    }
+}
+
+/// Read FRU Data: up to `count` bytes at `offset`
+struct ReadFruData {
+    fru_id: u8,
+    offset: u16,
+    count: u8,
+}
+
+impl From<ReadFruData> for Message {
+    fn from(value: ReadFruData) -> Self {
+        let [lsb, msb] = value.offset.to_le_bytes();
+        Message::new_request(
+            NetFn::Storage,
+            0x11,
+            vec![value.fru_id, lsb, msb, value.count],
+        )
+    }
+}
+
+impl IpmiCommand for ReadFruData {
+    type Output = Vec<u8>;
+    type Error = NotEnoughData;
+
+    fn parse_success_response(data: &[u8]) -> Result<Self::Output, Self::Error> {
+        let (count, bytes) = data.split_first().ok_or(NotEnoughData)?;
+        bytes
+            .get(..*count as usize)
+            .map(<[u8]>::to_vec)
+            .ok_or(NotEnoughData)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FruInfo {
+    manufacturer: String,
+    product_name: String,
+    serial: String,
+    part_number: String,
+}
+
+/// Decodes one type/length field; returns the text and the bytes consumed, or
+/// None at the end-of-fields marker (0xC1) or when the area is truncated.
+fn decode_field(data: &[u8]) -> Option<(String, usize)> {
+    let type_length = *data.first()?;
+    if type_length == 0xC1 {
+        return None;
+    }
+    let length = (type_length & 0x3F) as usize;
+    let bytes = data.get(1..1 + length)?;
+    let text: String = match type_length >> 6 {
+        // Binary or unspecified
+        0 => bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+        // BCD plus
+        1 => bytes
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0x0F])
+            .filter_map(|digit| match digit {
+                0..=9 => Some(char::from(b'0' + digit)),
+                0x0A => Some(' '),
+                0x0B => Some('-'),
+                0x0C => Some('.'),
+                _ => None,
+            })
+            .collect(),
+        // 6-bit packed ASCII, four characters per three bytes, LSB first
+        2 => bytes
+            .chunks(3)
+            .flat_map(|group| {
+                let bits = group
+                    .iter()
+                    .rev()
+                    .fold(0u32, |acc, byte| (acc << 8) | *byte as u32);
+                (0..group.len() * 8 / 6)
+                    .map(move |index| char::from(((bits >> (6 * index)) & 0x3F) as u8 + 0x20))
+            })
+            .collect(),
+        // 8-bit ASCII + Latin 1
+        _ => bytes.iter().map(|byte| char::from(*byte)).collect(),
+    };
+    // Fixed-width fields are often padded with spaces or NULs
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    Some((text.to_string(), 1 + length))
+}
+
+/// The type/length fields of a board or product area, after `skip` fixed bytes
+fn area_fields(data: &[u8], offset: usize, skip: usize) -> Vec<String> {
+    let mut fields = Vec::new();
+    let Some(mut rest) = data.get(offset + skip..) else {
+        return fields;
+    };
+    while let Some((text, used)) = decode_field(rest) {
+        fields.push(text);
+        rest = &rest[used..];
+    }
+    fields
+}
+
+/// Parses the FRU common header and its board and product info areas. Product
+/// fields win; board fields fill in what the product area leaves empty.
+fn parse_fru(data: &[u8]) -> Option<FruInfo> {
+    let header = data.get(..8)?;
+    if header[0] != 0x01 || header.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return None;
+    }
+    let area = |index: usize| (header[index] as usize) * 8;
+
+    let mut info = FruInfo::default();
+    // Product area: version, length, language, then manufacturer, name,
+    // part/model number, version, serial
+    if header[4] != 0 {
+        let fields = area_fields(data, area(4), 3);
+        let field = |index: usize| fields.get(index).cloned().unwrap_or_default();
+        info.manufacturer = field(0);
+        info.product_name = field(1);
+        info.part_number = field(2);
+        info.serial = field(4);
+    }
+    // Board area: version, length, language, 3-byte date, then manufacturer,
+    // product name, serial, part number
+    if header[3] != 0 {
+        let fields = area_fields(data, area(3), 6);
+        let fill = |value: &mut String, index: usize| {
+            if value.is_empty()
+                && let Some(field) = fields.get(index)
+            {
+                *value = field.clone();
+            }
+        };
+        fill(&mut info.manufacturer, 0);
+        fill(&mut info.product_name, 1);
+        fill(&mut info.serial, 2);
+        fill(&mut info.part_number, 3);
+    }
+    (info != FruInfo::default()).then_some(info)
+}
+
+fn read_fru_data(ipmi: &mut Ipmi<File>, fru_id: u8) -> Result<Vec<u8>, String> {
+    let info = ipmi
+        .send_recv(GetFruInventoryAreaInfo { fru_id })
+        .map_err(|err| format!("Get FRU Inventory Area Info failed: {err:?}"))?;
+    if info.word_access {
+        return Err("word-addressed FRU devices are not supported".to_string());
+    }
+    let mut data = Vec::with_capacity(info.size as usize);
+    while data.len() < info.size as usize {
+        let offset = data.len() as u16;
+        let count = FRU_READ_CHUNK.min(info.size - offset) as u8;
+        let chunk = ipmi
+            .send_recv(ReadFruData {
+                fru_id,
+                offset,
+                count,
+            })
+            .map_err(|err| format!("Read FRU Data at {offset} failed: {err:?}"))?;
+        if chunk.is_empty() {
+            break;
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Reads the default FRU once and keeps exporting it; failures are retried on
+/// the next collection and never stop sensor collection.
+fn update_fru(ipmi: &mut Ipmi<File>) {
+    let metrics = metrics();
+    let mut fru = match metrics.fru.lock() {
+        Ok(fru) => fru,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if fru.is_none() {
+        match read_fru_data(ipmi, DEFAULT_FRU_ID) {
+            Ok(data) => match parse_fru(&data) {
+                Some(info) => *fru = Some(info),
+                None => {
+                    internal_metrics::collector_error("ipmi");
+                    if debug_enabled() {
+                        logging::debug(
+                            "ipmi",
+                            &format!(
+                                "ipmi: FRU {DEFAULT_FRU_ID} has no parsable board or product area"
+                            ),
+                        );
+                    }
+                }
+            },
+            Err(err) => {
+                internal_metrics::collector_error("ipmi");
+                if debug_enabled() {
+                    logging::debug("ipmi", &format!("ipmi: FRU {DEFAULT_FRU_ID}: {err}"));
+                }
+            }
+        }
+    }
+    if let Some(info) = fru.as_ref() {
+        metrics
+            .fru_info
+            .with_label_values(&[
+                DEFAULT_FRU_ID.to_string().as_str(),
+                &info.manufacturer,
+                &info.product_name,
+                &info.serial,
+                &info.part_number,
+            ])
+            .set(1.0);
+    }
+}
+
 fn convert_reading(sensor: &FullSensorRecord, reading: u8) -> Option<f64> {
     let format = sensor.analog_data_format?;
     let m = sensor.m as f64;
@@ -71,12 +338,16 @@ fn unit_label(sensor: &FullSensorRecord) -> String {
     }
 }
 
-pub fn update_metrics() {
+pub fn update_metrics(config: &AppConfig) {
     let mut ipmi = match open_ipmi() {
         Some(ipmi) => ipmi,
         None => return,
     };
 
+    if config.ipmi.read_fru {
+        update_fru(&mut ipmi);
+    }
+
     let metrics = metrics();
 
     let records: Vec<_> = ipmi.sdrs().collect();
@@ -89,8 +360,12 @@ pub fn update_metrics() {
         let raw_reading = match ipmi.send_recv(GetSensorReading::for_sensor_key(full.key_data())) {
             Ok(reading) => reading,
             Err(err) => {
+                internal_metrics::collector_error("ipmi");
                 if debug_enabled() {
-                    eprintln!("ipmi: failed reading {}: {err:?}", full.id_string());
+                    logging::debug(
+                        "ipmi",
+                        &format!("ipmi: failed reading {}: {err:?}", full.id_string()),
+                    );
                 }
                 continue;
             }
@@ -117,3 +392,129 @@ pub fn update_metrics() {
             .set(value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends an 8-bit ASCII type/length field
+    fn push_ascii(area: &mut Vec<u8>, text: &str) {
+        area.push(0xC0 | text.len() as u8);
+        area.extend_from_slice(text.as_bytes());
+    }
+
+    /// Pads an info area to 8-byte multiples with a trailing checksum
+    fn finish_area(mut area: Vec<u8>) -> Vec<u8> {
+        area.push(0xC1);
+        while !(area.len() + 1).is_multiple_of(8) {
+            area.push(0);
+        }
+        area[1] = ((area.len() + 1) / 8) as u8;
+        let sum = area.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        area.push(0u8.wrapping_sub(sum));
+        area
+    }
+
+    fn build_fru(board: &[&str], product: Option<&[&str]>) -> Vec<u8> {
+        // version, length, language, manufacturing date
+        let mut board_area = vec![0x01, 0, 0x00, 0x10, 0x20, 0x30];
+        for field in board {
+            push_ascii(&mut board_area, field);
+        }
+        let board_area = finish_area(board_area);
+        let product_area = product.map(|fields| {
+            let mut area = vec![0x01, 0, 0x00];
+            for field in fields {
+                push_ascii(&mut area, field);
+            }
+            finish_area(area)
+        });
+
+        let board_offset = 1u8;
+        let product_offset = product_area
+            .as_ref()
+            .map_or(0, |_| board_offset + (board_area.len() / 8) as u8);
+        let mut header = vec![0x01, 0, 0, board_offset, product_offset, 0, 0];
+        let sum = header.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        header.push(0u8.wrapping_sub(sum));
+
+        let mut data = header;
+        data.extend(board_area);
+        data.extend(product_area.unwrap_or_default());
+        data
+    }
+
+    #[test]
+    fn test_decode_field_encodings() {
+        assert_eq!(decode_field(b"\xC4Dell!"), Some(("Dell".to_string(), 5)));
+        // BCD plus "12-3"
+        assert_eq!(
+            decode_field(&[0x42, 0x12, 0xB3]),
+            Some(("12-3".to_string(), 3))
+        );
+        // 6-bit ASCII "ABCD": 0x21, 0x22, 0x23, 0x24 packed LSB first
+        assert_eq!(
+            decode_field(&[0x83, 0xA1, 0x38, 0x92]),
+            Some(("ABCD".to_string(), 4))
+        );
+        assert_eq!(
+            decode_field(&[0x02, 0xAB, 0xCD]),
+            Some(("abcd".to_string(), 3))
+        );
+        assert_eq!(decode_field(&[0xC1]), None);
+        // Truncated area
+        assert_eq!(decode_field(&[0xC5, b'D']), None);
+    }
+
+    #[test]
+    fn test_parse_fru_prefers_product_area() {
+        let data = build_fru(
+            &["Board Inc", "X11DPi", "BSN123", "BPN-1"],
+            Some(&["Acme", "Server 9000", "PN-42", "v1", "SN42", "ASSET"]),
+        );
+        assert_eq!(
+            parse_fru(&data),
+            Some(FruInfo {
+                manufacturer: "Acme".to_string(),
+                product_name: "Server 9000".to_string(),
+                serial: "SN42".to_string(),
+                part_number: "PN-42".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_fru_falls_back_to_board_area() {
+        let data = build_fru(&["Board Inc", "X11DPi", "BSN123", "BPN-1"], None);
+        assert_eq!(
+            parse_fru(&data),
+            Some(FruInfo {
+                manufacturer: "Board Inc".to_string(),
+                product_name: "X11DPi".to_string(),
+                serial: "BSN123".to_string(),
+                part_number: "BPN-1".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_fru_rejects_bad_header() {
+        let mut data = build_fru(&["Board Inc"], None);
+        data[7] ^= 0xFF;
+        assert_eq!(parse_fru(&data), None);
+        assert_eq!(parse_fru(&[0x01, 0, 0]), None);
+        assert_eq!(parse_fru(&[0xFF; 16]), None);
+    }
+
+    #[test]
+    fn test_read_fru_data_response_parsing() {
+        assert_eq!(
+            ReadFruData::parse_success_response(&[2, 0xAA, 0xBB, 0xCC]).unwrap(),
+            vec![0xAA, 0xBB]
+        );
+        assert!(ReadFruData::parse_success_response(&[3, 0xAA]).is_err());
+        let info = GetFruInventoryAreaInfo::parse_success_response(&[0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(info.size, 256);
+        assert!(!info.word_access);
+    }
+}
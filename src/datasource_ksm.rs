@@ -0,0 +1,148 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::{Gauge, IntCounter};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct KsmMetrics {
+    pages_shared: Gauge,
+    pages_sharing: Gauge,
+    pages_unshared: Gauge,
+    full_scans_total: IntCounter,
+    run: Gauge,
+    previous_full_scans: Mutex<Option<u64>>,
+}
+
+impl KsmMetrics {
+    fn new() -> Self {
+        Self {
+            pages_shared: prometheus::register_gauge!(
+                "ksm_pages_shared",
+                "Number of shared KSM pages in use"
+            )
+            .expect("register ksm_pages_shared"),
+            pages_sharing: prometheus::register_gauge!(
+                "ksm_pages_sharing",
+                "Number of additional sites sharing KSM pages (pages saved)"
+            )
+            .expect("register ksm_pages_sharing"),
+            pages_unshared: prometheus::register_gauge!(
+                "ksm_pages_unshared",
+                "Number of pages unique but repeatedly checked for merging"
+            )
+            .expect("register ksm_pages_unshared"),
+            full_scans_total: prometheus::register_int_counter!(
+                "ksm_full_scans_total",
+                "Number of times all mergeable areas have been scanned"
+            )
+            .expect("register ksm_full_scans_total"),
+            run: prometheus::register_gauge!(
+                "ksm_run",
+                "Whether KSM is merging pages (1 = run is set to 1)"
+            )
+            .expect("register ksm_run"),
+            previous_full_scans: Mutex::new(None),
+        }
+    }
+}
+
+static KSM_METRICS: OnceLock<KsmMetrics> = OnceLock::new();
+
+fn metrics() -> &'static KsmMetrics {
+    KSM_METRICS.get_or_init(KsmMetrics::new)
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse::<u64>().ok()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/kernel/mm/ksm"));
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct KsmStats {
+    pages_shared: Option<u64>,
+    pages_sharing: Option<u64>,
+    pages_unshared: Option<u64>,
+    full_scans: Option<u64>,
+    /// 0 (stopped), 1 (merging) or 2 (stopped and unmerged)
+    run: Option<u64>,
+}
+
+fn read_stats(base: &Path) -> KsmStats {
+    KsmStats {
+        pages_shared: read_u64(&base.join("pages_shared")),
+        pages_sharing: read_u64(&base.join("pages_sharing")),
+        pages_unshared: read_u64(&base.join("pages_unshared")),
+        full_scans: read_u64(&base.join("full_scans")),
+        run: read_u64(&base.join("run")),
+    }
+}
+
+fn update_metrics_from_path(base: &Path) {
+    if !base.is_dir() {
+        return;
+    }
+    let metrics = metrics();
+    let stats = read_stats(base);
+
+    for (value, gauge) in [
+        (stats.pages_shared, &metrics.pages_shared),
+        (stats.pages_sharing, &metrics.pages_sharing),
+        (stats.pages_unshared, &metrics.pages_unshared),
+    ] {
+        if let Some(value) = value {
+            gauge.set(value as f64);
+        }
+    }
+
+    if let Some(run) = stats.run {
+        metrics.run.set(if run == 1 { 1.0 } else { 0.0 });
+    }
+
+    if let Some(full_scans) = stats.full_scans {
+        let mut previous = match metrics.previous_full_scans.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        metrics
+            .full_scans_total
+            .inc_by(counter_delta(full_scans, previous.unwrap_or(0)));
+        *previous = Some(full_scans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_ksm(dir: &Path, run: &str, full_scans: &str) {
+        fs::write(dir.join("pages_shared"), "120\n").unwrap();
+        fs::write(dir.join("pages_sharing"), "4500\n").unwrap();
+        fs::write(dir.join("pages_unshared"), "30\n").unwrap();
+        fs::write(dir.join("run"), format!("{run}\n")).unwrap();
+        fs::write(dir.join("full_scans"), format!("{full_scans}\n")).unwrap();
+    }
+
+    #[test]
+    fn test_read_stats_from_mock_ksm() {
+        let dir = TempDir::new().unwrap();
+        write_ksm(dir.path(), "1", "7");
+        assert_eq!(
+            read_stats(dir.path()),
+            KsmStats {
+                pages_shared: Some(120),
+                pages_sharing: Some(4500),
+                pages_unshared: Some(30),
+                full_scans: Some(7),
+                run: Some(1),
+            }
+        );
+
+        let empty = TempDir::new().unwrap();
+        assert_eq!(read_stats(empty.path()), KsmStats::default());
+    }
+}
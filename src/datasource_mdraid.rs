@@ -1,14 +1,40 @@
+use crate::config::{AppConfig, MdraidSource};
 use prometheus::GaugeVec;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::sync::OnceLock;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
 const MDSTAT_PATH: &str = "/proc/mdstat";
+const SYS_BLOCK_PATH: &str = "/sys/block";
+const DISK_STATES: [&str; 3] = ["active", "faulty", "spare"];
 
 struct MdraidMetrics {
     array_state: GaugeVec,
     array_disks: GaugeVec,
     array_degraded: GaugeVec,
     array_sync_progress: GaugeVec,
+    disk: GaugeVec,
+    bitmap_chunk_size_bytes: GaugeVec,
+    bitmap_pages_dirty: GaugeVec,
+    mismatch_count: GaugeVec,
+    /// Series exported per array last time, so stale state labels and
+    /// removed members disappear
+    exported: Mutex<HashMap<String, ArraySeries>>,
+}
+
+#[derive(Default)]
+struct ArraySeries {
+    /// (state, level) of the mdraid_array_state series
+    states: HashSet<[String; 2]>,
+    /// Actions with an mdraid_array_sync_progress series
+    sync_actions: HashSet<String>,
+    /// (device, slot) of each member with mdraid_disk series
+    disks: HashSet<[String; 2]>,
+    /// Whether mdraid_bitmap_pages_dirty and mdraid_bitmap_chunk_size_bytes
+    /// were set
+    bitmap_pages: bool,
+    bitmap_chunk_size: bool,
 }
 
 impl MdraidMetrics {
@@ -38,6 +64,31 @@ impl MdraidMetrics {
                 &["array", "action"]
             )
             .expect("register mdraid_array_sync_progress"),
+            disk: prometheus::register_gauge_vec!(
+                "mdraid_disk",
+                "MD RAID member disk state (1 for current state label)",
+                &["array", "device", "slot", "state"]
+            )
+            .expect("register mdraid_disk"),
+            bitmap_chunk_size_bytes: prometheus::register_gauge_vec!(
+                "mdraid_bitmap_chunk_size_bytes",
+                "Bytes of the array covered by one write-intent bitmap bit",
+                &["array"]
+            )
+            .expect("register mdraid_bitmap_chunk_size_bytes"),
+            bitmap_pages_dirty: prometheus::register_gauge_vec!(
+                "mdraid_bitmap_pages_dirty",
+                "In-memory write-intent bitmap pages in use, from /proc/mdstat",
+                &["array"]
+            )
+            .expect("register mdraid_bitmap_pages_dirty"),
+            mismatch_count: prometheus::register_gauge_vec!(
+                "mdraid_mismatch_count",
+                "Sectors found inconsistent by the last check or repair (md/mismatch_cnt)",
+                &["array"]
+            )
+            .expect("register mdraid_mismatch_count"),
+            exported: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -61,6 +112,43 @@ fn parse_level(tokens: &[&str]) -> String {
     "unknown".to_string()
 }
 
+#[derive(Debug, PartialEq)]
+struct MdDisk {
+    device: String,
+    slot: String,
+    state: &'static str,
+}
+
+/// Parses a member device token such as `sda1[0]`, `sdb1[1](F)` or `sdc1[2](S)`.
+fn parse_disk_token(token: &str) -> Option<MdDisk> {
+    let (device, rest) = token.split_once('[')?;
+    let (slot, suffix) = rest.split_once(']')?;
+    if device.is_empty() || slot.is_empty() || !slot.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut state = "active";
+    let mut flags = suffix;
+    while let Some(inner) = flags.strip_prefix('(') {
+        let (flag, remaining) = inner.split_once(')')?;
+        match flag {
+            "F" => state = "faulty",
+            "S" if state != "faulty" => state = "spare",
+            _ => {}
+        }
+        flags = remaining;
+    }
+    if !flags.is_empty() {
+        return None;
+    }
+
+    Some(MdDisk {
+        device: device.to_string(),
+        slot: slot.to_string(),
+        state,
+    })
+}
+
 fn parse_counts_token(token: &str) -> Option<(u64, u64)> {
     if !(token.starts_with('[') && token.ends_with(']')) {
         return None;
@@ -99,13 +187,383 @@ fn parse_sync_progress(line: &str) -> Option<(String, f64)> {
     Some(((*action).to_string(), value / 100.0))
 }
 
-pub fn update_metrics() {
-    let contents = match fs::read_to_string(MDSTAT_PATH) {
-        Ok(contents) => contents,
-        Err(_) => return,
+#[derive(Debug, PartialEq)]
+struct MdBitmap {
+    pages_dirty: u64,
+    chunk_bytes: Option<u64>,
+}
+
+/// Parses "bitmap: 2/8 pages [8KB], 65536KB chunk[, file: /path]".
+fn parse_bitmap_line(line: &str) -> Option<MdBitmap> {
+    let tokens: Vec<&str> = line
+        .trim()
+        .strip_prefix("bitmap:")?
+        .split_whitespace()
+        .collect();
+    let pages_dirty = tokens.first()?.split_once('/')?.0.parse().ok()?;
+    let chunk_bytes = tokens
+        .windows(2)
+        .find(|pair| pair[1].trim_end_matches(',') == "chunk")
+        .and_then(|pair| {
+            let size = pair[0];
+            match size.strip_suffix("KB") {
+                Some(kb) => kb.parse::<u64>().ok().map(|kb| kb * 1024),
+                None => size.strip_suffix('B')?.parse().ok(),
+            }
+        });
+    Some(MdBitmap {
+        pages_dirty,
+        chunk_bytes,
+    })
+}
+
+/// Bitmap usage per array; sysfs has no equivalent of the page count.
+fn mdstat_bitmaps(contents: &str) -> HashMap<String, MdBitmap> {
+    let mut bitmaps = HashMap::new();
+    let mut array = None;
+    for line in contents.lines() {
+        if line.starts_with("md") {
+            array = line.split_whitespace().next();
+        } else if let (Some(name), Some(bitmap)) = (array, parse_bitmap_line(line)) {
+            bitmaps.insert(name.to_string(), bitmap);
+        }
+    }
+    bitmaps
+}
+
+fn set_bitmap(metrics: &MdraidMetrics, array: &str, bitmap: &MdBitmap, series: &mut ArraySeries) {
+    metrics
+        .bitmap_pages_dirty
+        .with_label_values(&[array])
+        .set(bitmap.pages_dirty as f64);
+    series.bitmap_pages = true;
+    if let Some(chunk_bytes) = bitmap.chunk_bytes {
+        set_bitmap_chunk_size(metrics, array, chunk_bytes, series);
+    }
+}
+
+fn set_bitmap_chunk_size(
+    metrics: &MdraidMetrics,
+    array: &str,
+    chunk_bytes: u64,
+    series: &mut ArraySeries,
+) {
+    metrics
+        .bitmap_chunk_size_bytes
+        .with_label_values(&[array])
+        .set(chunk_bytes as f64);
+    series.bitmap_chunk_size = true;
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
+/// Parses md/sync_completed, which is either "none" or "<done> / <total>" sectors.
+fn parse_sync_completed(value: &str) -> Option<f64> {
+    let (done, total) = value.split_once('/')?;
+    let done = done.trim().parse::<u64>().ok()?;
+    let total = total.trim().parse::<u64>().ok()?;
+    if total == 0 {
+        return None;
+    }
+    Some(done as f64 / total as f64)
+}
+
+/// Maps the comma-separated dev-*/state flags onto the mdstat disk states.
+fn sysfs_disk_state(flags: &str) -> &'static str {
+    let flags: Vec<&str> = flags.split(',').map(str::trim).collect();
+    if flags.contains(&"faulty") {
+        "faulty"
+    } else if flags.contains(&"spare") {
+        "spare"
+    } else {
+        "active"
+    }
+}
+
+fn set_disk_state(metrics: &MdraidMetrics, array: &str, disk: &MdDisk, series: &mut ArraySeries) {
+    for known_state in DISK_STATES {
+        metrics
+            .disk
+            .with_label_values(&[array, &disk.device, &disk.slot, known_state])
+            .set(if disk.state == known_state { 1.0 } else { 0.0 });
+    }
+    series
+        .disks
+        .insert([disk.device.clone(), disk.slot.clone()]);
+}
+
+fn set_array_state(
+    metrics: &MdraidMetrics,
+    array: &str,
+    state: &str,
+    level: &str,
+    series: &mut ArraySeries,
+) {
+    metrics
+        .array_state
+        .with_label_values(&[array, state, level])
+        .set(1.0);
+    series.states.insert([state.to_string(), level.to_string()]);
+}
+
+fn set_sync_progress(
+    metrics: &MdraidMetrics,
+    array: &str,
+    action: &str,
+    progress: f64,
+    series: &mut ArraySeries,
+) {
+    metrics
+        .array_sync_progress
+        .with_label_values(&[array, action])
+        .set(progress);
+    series.sync_actions.insert(action.to_string());
+}
+
+fn remove_disk(metrics: &MdraidMetrics, array: &str, [device, slot]: &[String; 2]) {
+    for known_state in DISK_STATES {
+        let _ = metrics
+            .disk
+            .remove_label_values(&[array, device, slot, known_state]);
+    }
+}
+
+fn lock_exported(
+    metrics: &MdraidMetrics,
+) -> std::sync::MutexGuard<'_, HashMap<String, ArraySeries>> {
+    match metrics.exported.lock() {
+        Ok(exported) => exported,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Records the series refreshed for `array` and removes the ones from the
+/// previous collection that were not: an array_state that went from clean
+/// to active, a finished sync action, a member taken out with
+/// `mdadm --remove`, a spare that got a slot or a bitmap removed with
+/// `mdadm --grow --bitmap=none`.
+fn finish_array(metrics: &MdraidMetrics, array: &str, current: ArraySeries) {
+    let mut exported = lock_exported(metrics);
+    let previous = exported.remove(array).unwrap_or_default();
+    for [state, level] in previous.states.difference(&current.states) {
+        let _ = metrics
+            .array_state
+            .remove_label_values(&[array, state, level]);
+    }
+    for action in previous.sync_actions.difference(&current.sync_actions) {
+        let _ = metrics
+            .array_sync_progress
+            .remove_label_values(&[array, action]);
+    }
+    for disk in previous.disks.difference(&current.disks) {
+        remove_disk(metrics, array, disk);
+    }
+    if previous.bitmap_pages && !current.bitmap_pages {
+        let _ = metrics.bitmap_pages_dirty.remove_label_values(&[array]);
+    }
+    if previous.bitmap_chunk_size && !current.bitmap_chunk_size {
+        let _ = metrics
+            .bitmap_chunk_size_bytes
+            .remove_label_values(&[array]);
+    }
+    exported.insert(array.to_string(), current);
+}
+
+/// Removes every series of arrays that were not seen in this collection.
+fn forget_missing_arrays(metrics: &MdraidMetrics, seen: &HashSet<String>) {
+    lock_exported(metrics).retain(|array, series| {
+        if seen.contains(array) {
+            return true;
+        }
+        for [state, level] in &series.states {
+            let _ = metrics
+                .array_state
+                .remove_label_values(&[array, state, level]);
+        }
+        for action in &series.sync_actions {
+            let _ = metrics
+                .array_sync_progress
+                .remove_label_values(&[array, action]);
+        }
+        for disk in &series.disks {
+            remove_disk(metrics, array, disk);
+        }
+        for role in ["total", "active", "working"] {
+            let _ = metrics.array_disks.remove_label_values(&[array, role]);
+        }
+        let _ = metrics.array_degraded.remove_label_values(&[array]);
+        let _ = metrics.bitmap_pages_dirty.remove_label_values(&[array]);
+        let _ = metrics
+            .bitmap_chunk_size_bytes
+            .remove_label_values(&[array]);
+        let _ = metrics.mismatch_count.remove_label_values(&[array]);
+        false
+    });
+}
+
+fn read_sysfs_disks(md_path: &Path) -> Vec<MdDisk> {
+    let entries = match fs::read_dir(md_path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut disks = Vec::new();
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let Some(device) = name.strip_prefix("dev-") else {
+            continue;
+        };
+
+        let flags = read_string(&entry.path().join("state")).unwrap_or_default();
+        let slot = read_string(&entry.path().join("slot")).unwrap_or_else(|| "none".to_string());
+        disks.push(MdDisk {
+            device: device.to_string(),
+            slot,
+            state: sysfs_disk_state(&flags),
+        });
+    }
+    disks
+}
+
+fn update_sysfs_array(md_path: &Path, name: &str, bitmap: Option<&MdBitmap>) {
+    let metrics = metrics();
+    let mut series = ArraySeries::default();
+
+    let state = read_string(&md_path.join("array_state")).unwrap_or_else(|| "unknown".to_string());
+    let level = read_string(&md_path.join("level"))
+        .filter(|level| !level.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    set_array_state(metrics, name, &state, &level, &mut series);
+
+    let disks = read_sysfs_disks(md_path);
+    let working = disks.iter().filter(|disk| disk.state == "active").count() as u64;
+    let raid_disks = read_u64(&md_path.join("raid_disks"));
+    let degraded = read_u64(&md_path.join("degraded"));
+
+    if let Some(total) = raid_disks {
+        metrics
+            .array_disks
+            .with_label_values(&[name, "total"])
+            .set(total as f64);
+        if let Some(degraded) = degraded {
+            metrics
+                .array_disks
+                .with_label_values(&[name, "active"])
+                .set(total.saturating_sub(degraded) as f64);
+        }
+    }
+    metrics
+        .array_disks
+        .with_label_values(&[name, "working"])
+        .set(working as f64);
+
+    metrics
+        .array_degraded
+        .with_label_values(&[name])
+        .set(if degraded.unwrap_or(0) > 0 { 1.0 } else { 0.0 });
+
+    let action = read_string(&md_path.join("sync_action")).unwrap_or_default();
+    if action != "idle"
+        && action != "frozen"
+        && !action.is_empty()
+        && let Some(progress) = read_string(&md_path.join("sync_completed"))
+            .as_deref()
+            .and_then(parse_sync_completed)
+    {
+        // Keep the mdstat action naming so series match across sources
+        let action = if action == "recover" {
+            "recovery"
+        } else {
+            action.as_str()
+        };
+        set_sync_progress(metrics, name, action, progress, &mut series);
+    }
+
+    for disk in &disks {
+        set_disk_state(metrics, name, disk, &mut series);
+    }
+
+    // Only arrays that run check/repair (redundant levels) have mismatch_cnt
+    if let Some(mismatches) = read_u64(&md_path.join("mismatch_cnt")) {
+        metrics
+            .mismatch_count
+            .with_label_values(&[name])
+            .set(mismatches as f64);
+    }
+
+    let location = read_string(&md_path.join("bitmap/location"));
+    if location.is_some_and(|location| location != "none") {
+        if let Some(bitmap) = bitmap {
+            set_bitmap(metrics, name, bitmap, &mut series);
+        }
+        if let Some(chunk_bytes) = read_u64(&md_path.join("bitmap/chunksize")) {
+            set_bitmap_chunk_size(metrics, name, chunk_bytes, &mut series);
+        }
+    }
+
+    finish_array(metrics, name, series);
+}
+
+/// Collects arrays from /sys/block/md*/md, with bitmap page usage from the
+/// /proc/mdstat contents. Returns the names of the arrays found.
+fn update_metrics_from_sysfs(base: &Path, mdstat: &str) -> HashSet<String> {
+    let bitmaps = mdstat_bitmaps(mdstat);
+    let mut found = HashSet::new();
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return found,
     };
 
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        if !name.starts_with("md") {
+            continue;
+        }
+
+        let md_path = entry.path().join("md");
+        if md_path.is_dir() {
+            update_sysfs_array(&md_path, &name, bitmaps.get(&name));
+            found.insert(name);
+        }
+    }
+    found
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let sys_block = config.host_path(SYS_BLOCK_PATH);
+    let sys_block = sys_block.as_path();
+    let mdstat = fs::read_to_string(config.host_path(MDSTAT_PATH)).unwrap_or_default();
+    let arrays = match config.mdraid.source {
+        MdraidSource::Sysfs => update_metrics_from_sysfs(sys_block, &mdstat),
+        MdraidSource::Mdstat => update_metrics_from_mdstat(&mdstat),
+        MdraidSource::Auto => {
+            let arrays = update_metrics_from_sysfs(sys_block, &mdstat);
+            if arrays.is_empty() {
+                update_metrics_from_mdstat(&mdstat)
+            } else {
+                arrays
+            }
+        }
+    };
+    forget_missing_arrays(metrics(), &arrays);
+}
+
+/// Collects arrays from /proc/mdstat. Returns the names of the arrays found.
+fn update_metrics_from_mdstat(contents: &str) -> HashSet<String> {
     let metrics = metrics();
+    let mut found = HashSet::new();
     let mut lines = contents.lines().peekable();
 
     while let Some(line) = lines.next() {
@@ -126,12 +584,17 @@ pub fn update_metrics() {
         let state = parts.next().unwrap_or("unknown").to_string();
         let remainder: Vec<&str> = parts.collect();
         let level = parse_level(&remainder);
+        let disks: Vec<MdDisk> = remainder
+            .iter()
+            .filter_map(|token| parse_disk_token(token))
+            .collect();
 
         let mut total: Option<u64> = None;
         let mut active: Option<u64> = None;
         let mut working: Option<u64> = None;
         let mut sync_action: Option<String> = None;
         let mut sync_progress: Option<f64> = None;
+        let mut bitmap: Option<MdBitmap> = None;
 
         while let Some(next_line) = lines.peek() {
             if next_line.starts_with("md") {
@@ -143,36 +606,38 @@ pub fn update_metrics() {
             }
 
             for token in detail.split_whitespace() {
-                if active.is_none() {
-                    if let Some((t, a)) = parse_counts_token(token) {
-                        total = Some(t);
-                        active = Some(a);
-                        continue;
-                    }
+                if active.is_none()
+                    && let Some((t, a)) = parse_counts_token(token)
+                {
+                    total = Some(t);
+                    active = Some(a);
+                    continue;
                 }
 
-                if working.is_none() {
-                    if let Some((t, w)) = parse_working_token(token) {
-                        working = Some(w);
-                        if total.is_none() {
-                            total = Some(t);
-                        }
+                if working.is_none()
+                    && let Some((t, w)) = parse_working_token(token)
+                {
+                    working = Some(w);
+                    if total.is_none() {
+                        total = Some(t);
                     }
                 }
             }
 
-            if sync_action.is_none() {
-                if let Some((action, progress)) = parse_sync_progress(detail) {
-                    sync_action = Some(action);
-                    sync_progress = Some(progress);
-                }
+            if bitmap.is_none() {
+                bitmap = parse_bitmap_line(detail);
+            }
+
+            if sync_action.is_none()
+                && let Some((action, progress)) = parse_sync_progress(detail)
+            {
+                sync_action = Some(action);
+                sync_progress = Some(progress);
             }
         }
 
-        metrics
-            .array_state
-            .with_label_values(&[&name, &state, &level])
-            .set(1.0);
+        let mut series = ArraySeries::default();
+        set_array_state(metrics, &name, &state, &level, &mut series);
 
         if let Some(total) = total {
             let role = "total".to_string();
@@ -206,10 +671,429 @@ pub fn update_metrics() {
             .set(degraded as f64);
 
         if let (Some(action), Some(progress)) = (sync_action, sync_progress) {
+            set_sync_progress(metrics, &name, &action, progress, &mut series);
+        }
+
+        for disk in &disks {
+            set_disk_state(metrics, &name, disk, &mut series);
+        }
+
+        if let Some(bitmap) = &bitmap {
+            set_bitmap(metrics, &name, bitmap, &mut series);
+        }
+
+        finish_array(metrics, &name, series);
+        found.insert(name);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MDSTAT_DEGRADED: &str = r#"Personalities : [raid1] [raid6] [raid5] [raid4]
+md1 : active raid5 sdd1[3](S) sdc1[2] sdb1[1](F) sda1[0]
+      1953260544 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/2] [U_U]
+      bitmap: 2/8 pages [8KB], 65536KB chunk
+
+md0 : active raid1 nvme1n1p2[1] nvme0n1p2[0]
+      976628736 blocks super 1.2 [2/2] [UU]
+      [==>..................]  resync = 12.6% (123456/976628736) finish=80.1min speed=180000K/sec
+
+unused devices: <none>
+"#;
+
+    #[test]
+    fn test_parse_disk_token_active() {
+        assert_eq!(
+            parse_disk_token("sda1[0]"),
+            Some(MdDisk {
+                device: "sda1".to_string(),
+                slot: "0".to_string(),
+                state: "active",
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_disk_token_faulty_and_spare() {
+        let faulty = parse_disk_token("sdb1[1](F)").unwrap();
+        assert_eq!(faulty.device, "sdb1");
+        assert_eq!(faulty.slot, "1");
+        assert_eq!(faulty.state, "faulty");
+
+        let spare = parse_disk_token("sdd1[3](S)").unwrap();
+        assert_eq!(spare.state, "spare");
+    }
+
+    #[test]
+    fn test_parse_disk_token_other_flags() {
+        // Write-mostly members are still active
+        assert_eq!(parse_disk_token("sde1[4](W)").unwrap().state, "active");
+        assert_eq!(parse_disk_token("sde1[4](W)(F)").unwrap().state, "faulty");
+    }
+
+    #[test]
+    fn test_parse_disk_token_rejects_non_disks() {
+        assert_eq!(parse_disk_token("raid5"), None);
+        assert_eq!(parse_disk_token("[3/2]"), None);
+        assert_eq!(parse_disk_token("[U_U]"), None);
+        assert_eq!(parse_disk_token("sda1[x]"), None);
+        assert_eq!(parse_disk_token("sda1[0]junk"), None);
+    }
+
+    #[test]
+    fn test_update_metrics_from_mdstat_disks() {
+        update_metrics_from_mdstat(MDSTAT_DEGRADED);
+
+        let metrics = metrics();
+        let faulty = metrics
+            .disk
+            .with_label_values(&["md1", "sdb1", "1", "faulty"])
+            .get();
+        let spare = metrics
+            .disk
+            .with_label_values(&["md1", "sdd1", "3", "spare"])
+            .get();
+        let active = metrics
+            .disk
+            .with_label_values(&["md0", "nvme0n1p2", "0", "active"])
+            .get();
+        let not_active = metrics
+            .disk
+            .with_label_values(&["md1", "sdb1", "1", "active"])
+            .get();
+        assert_eq!(faulty, 1.0);
+        assert_eq!(spare, 1.0);
+        assert_eq!(active, 1.0);
+        assert_eq!(not_active, 0.0);
+    }
+
+    #[test]
+    fn test_update_metrics_from_mdstat_handles_empty() {
+        assert!(update_metrics_from_mdstat("").is_empty());
+    }
+
+    fn create_mock_md(
+        dir: &Path,
+        name: &str,
+        array_state: &str,
+        degraded: u64,
+        sync_action: &str,
+        sync_completed: &str,
+    ) -> std::path::PathBuf {
+        let md_dir = dir.join(name).join("md");
+        fs::create_dir_all(&md_dir).unwrap();
+        fs::write(md_dir.join("array_state"), format!("{}\n", array_state)).unwrap();
+        fs::write(md_dir.join("level"), "raid1\n").unwrap();
+        fs::write(md_dir.join("raid_disks"), "2\n").unwrap();
+        fs::write(md_dir.join("degraded"), format!("{}\n", degraded)).unwrap();
+        fs::write(md_dir.join("sync_action"), format!("{}\n", sync_action)).unwrap();
+        fs::write(
+            md_dir.join("sync_completed"),
+            format!("{}\n", sync_completed),
+        )
+        .unwrap();
+        md_dir
+    }
+
+    fn create_mock_md_disk(md_dir: &Path, device: &str, slot: &str, state: &str) {
+        let dev_dir = md_dir.join(format!("dev-{}", device));
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::write(dev_dir.join("slot"), format!("{}\n", slot)).unwrap();
+        fs::write(dev_dir.join("state"), format!("{}\n", state)).unwrap();
+    }
+
+    #[test]
+    fn test_parse_sync_completed() {
+        assert_eq!(parse_sync_completed("1024 / 4096"), Some(0.25));
+        assert_eq!(parse_sync_completed("none"), None);
+        assert_eq!(parse_sync_completed("5 / 0"), None);
+    }
+
+    #[test]
+    fn test_sysfs_disk_state() {
+        assert_eq!(sysfs_disk_state("in_sync"), "active");
+        assert_eq!(sysfs_disk_state("in_sync,write_mostly"), "active");
+        assert_eq!(sysfs_disk_state("faulty,write_error"), "faulty");
+        assert_eq!(sysfs_disk_state("spare"), "spare");
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_degraded_recovery() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md5", "clean", 1, "recover", "512 / 2048");
+        create_mock_md_disk(&md, "sda1", "0", "in_sync");
+        create_mock_md_disk(&md, "sdb1", "none", "faulty");
+        create_mock_md_disk(&md, "sdc1", "1", "spare");
+
+        assert!(!update_metrics_from_sysfs(dir.path(), "").is_empty());
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .array_state
+                .with_label_values(&["md5", "clean", "raid1"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics.array_degraded.with_label_values(&["md5"]).get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .array_disks
+                .with_label_values(&["md5", "active"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
             metrics
                 .array_sync_progress
-                .with_label_values(&[&name, &action])
-                .set(progress);
+                .with_label_values(&["md5", "recovery"])
+                .get(),
+            0.25
+        );
+        assert_eq!(
+            metrics
+                .disk
+                .with_label_values(&["md5", "sdb1", "none", "faulty"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .disk
+                .with_label_values(&["md5", "sdc1", "1", "spare"])
+                .get(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_removed_disk() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md10", "clean", 0, "idle", "none");
+        create_mock_md_disk(&md, "sda3", "0", "in_sync");
+        create_mock_md_disk(&md, "sdb3", "1", "faulty");
+        update_metrics_from_sysfs(dir.path(), "");
+
+        // mdadm --remove drops the dev-* entry
+        fs::remove_dir_all(md.join("dev-sdb3")).unwrap();
+        update_metrics_from_sysfs(dir.path(), "");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .disk
+                .with_label_values(&["md10", "sda3", "0", "active"])
+                .get(),
+            1.0
+        );
+        for state in DISK_STATES {
+            assert!(
+                metrics
+                    .disk
+                    .remove_label_values(&["md10", "sdb3", "1", state])
+                    .is_err()
+            );
         }
     }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_clean_to_active() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md11", "clean", 0, "check", "100 / 400");
+        create_mock_md_disk(&md, "sda4", "0", "in_sync");
+        update_metrics_from_sysfs(dir.path(), "");
+
+        // A write burst flips array_state and the check has finished
+        fs::write(md.join("array_state"), "active\n").unwrap();
+        fs::write(md.join("sync_action"), "idle\n").unwrap();
+        fs::write(md.join("sync_completed"), "none\n").unwrap();
+        update_metrics_from_sysfs(dir.path(), "");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .array_state
+                .with_label_values(&["md11", "active", "raid1"])
+                .get(),
+            1.0
+        );
+        assert!(
+            metrics
+                .array_state
+                .remove_label_values(&["md11", "clean", "raid1"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .array_sync_progress
+                .remove_label_values(&["md11", "check"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_spare_to_active() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md12", "clean", 1, "recover", "0 / 100");
+        create_mock_md_disk(&md, "sda5", "0", "in_sync");
+        create_mock_md_disk(&md, "sdb5", "none", "spare");
+        update_metrics_from_sysfs(dir.path(), "");
+
+        // Recovery finished: the spare took slot 1 and is in sync
+        fs::write(md.join("degraded"), "0\n").unwrap();
+        fs::write(md.join("sync_action"), "idle\n").unwrap();
+        create_mock_md_disk(&md, "sdb5", "1", "in_sync");
+        update_metrics_from_sysfs(dir.path(), "");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .disk
+                .with_label_values(&["md12", "sdb5", "1", "active"])
+                .get(),
+            1.0
+        );
+        for state in DISK_STATES {
+            assert!(
+                metrics
+                    .disk
+                    .remove_label_values(&["md12", "sdb5", "none", state])
+                    .is_err()
+            );
+        }
+        assert!(
+            metrics
+                .array_sync_progress
+                .remove_label_values(&["md12", "recovery"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_bitmap_line() {
+        assert_eq!(
+            parse_bitmap_line("      bitmap: 2/8 pages [8KB], 65536KB chunk"),
+            Some(MdBitmap {
+                pages_dirty: 2,
+                chunk_bytes: Some(64 * 1024 * 1024),
+            })
+        );
+        assert_eq!(
+            parse_bitmap_line("      bitmap: 0/1 pages [0KB], 512B chunk, file: /bitmap/md0"),
+            Some(MdBitmap {
+                pages_dirty: 0,
+                chunk_bytes: Some(512),
+            })
+        );
+        assert_eq!(parse_bitmap_line("      [2/2] [UU]"), None);
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_raid1_dirty_bitmap() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md7", "active", 0, "idle", "none");
+        create_mock_md_disk(&md, "sda2", "0", "in_sync");
+        create_mock_md_disk(&md, "sdb2", "1", "in_sync");
+        fs::write(md.join("mismatch_cnt"), "256\n").unwrap();
+        fs::create_dir_all(md.join("bitmap")).unwrap();
+        fs::write(md.join("bitmap/location"), "+8\n").unwrap();
+        fs::write(md.join("bitmap/chunksize"), "67108864\n").unwrap();
+        // No bitmap on this array: location is "none"
+        let plain = create_mock_md(dir.path(), "md8", "clean", 0, "idle", "none");
+        fs::create_dir_all(plain.join("bitmap")).unwrap();
+        fs::write(plain.join("bitmap/location"), "none\n").unwrap();
+        fs::write(plain.join("bitmap/chunksize"), "0\n").unwrap();
+
+        let mdstat = "md7 : active raid1 sdb2[1] sda2[0]\n      \
+                      976628736 blocks super 1.2 [2/2] [UU]\n      \
+                      bitmap: 3/8 pages [12KB], 65536KB chunk\n";
+        assert!(!update_metrics_from_sysfs(dir.path(), mdstat).is_empty());
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics.mismatch_count.with_label_values(&["md7"]).get(),
+            256.0
+        );
+        assert_eq!(
+            metrics.bitmap_pages_dirty.with_label_values(&["md7"]).get(),
+            3.0
+        );
+        assert_eq!(
+            metrics
+                .bitmap_chunk_size_bytes
+                .with_label_values(&["md7"])
+                .get(),
+            67108864.0
+        );
+        assert!(
+            metrics
+                .bitmap_chunk_size_bytes
+                .remove_label_values(&["md8"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .mismatch_count
+                .remove_label_values(&["md8"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_bitmap_removed() {
+        let dir = TempDir::new().unwrap();
+        let md = create_mock_md(dir.path(), "md13", "clean", 0, "idle", "none");
+        fs::create_dir_all(md.join("bitmap")).unwrap();
+        fs::write(md.join("bitmap/location"), "+8\n").unwrap();
+        fs::write(md.join("bitmap/chunksize"), "67108864\n").unwrap();
+        let mdstat = "md13 : active raid1 sdb3[1] sda3[0]\n      \
+                      976628736 blocks super 1.2 [2/2] [UU]\n      \
+                      bitmap: 1/8 pages [4KB], 65536KB chunk\n";
+        update_metrics_from_sysfs(dir.path(), mdstat);
+
+        // mdadm --grow --bitmap=none
+        fs::write(md.join("bitmap/location"), "none\n").unwrap();
+        let mdstat = "md13 : active raid1 sdb3[1] sda3[0]\n      \
+                      976628736 blocks super 1.2 [2/2] [UU]\n";
+        update_metrics_from_sysfs(dir.path(), mdstat);
+
+        let metrics = metrics();
+        assert!(
+            metrics
+                .bitmap_pages_dirty
+                .remove_label_values(&["md13"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .bitmap_chunk_size_bytes
+                .remove_label_values(&["md13"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_skips_non_md() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("sda")).unwrap();
+        // md device without the md/ subdirectory is not an array
+        fs::create_dir_all(dir.path().join("md9")).unwrap();
+
+        assert!(update_metrics_from_sysfs(dir.path(), "").is_empty());
+    }
+
+    #[test]
+    fn test_update_metrics_from_sysfs_handles_missing_dir() {
+        assert!(
+            update_metrics_from_sysfs(Path::new("/nonexistent/path/that/does/not/exist"), "")
+                .is_empty()
+        );
+    }
 }
@@ -0,0 +1,505 @@
+use crate::counter_reset::counter_delta;
+use crate::dbus::{Connection, Value};
+use crate::internal_metrics;
+use crate::logging;
+use crate::runtime::debug_enabled;
+use prometheus::{GaugeVec, IntCounterVec};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::{Mutex, OnceLock};
+
+const MM_SERVICE: &str = "org.freedesktop.ModemManager1";
+const MM_PATH: &str = "/org/freedesktop/ModemManager1";
+const MODEM_INTERFACE: &str = "org.freedesktop.ModemManager1.Modem";
+const SIGNAL_INTERFACE: &str = "org.freedesktop.ModemManager1.Modem.Signal";
+const BEARER_INTERFACE: &str = "org.freedesktop.ModemManager1.Bearer";
+
+/// MMModemState values, starting at MM_MODEM_STATE_FAILED (-1)
+const MODEM_STATES: &[&str] = &[
+    "failed",
+    "unknown",
+    "initializing",
+    "locked",
+    "disabled",
+    "disabling",
+    "enabling",
+    "enabled",
+    "searching",
+    "registered",
+    "disconnecting",
+    "connecting",
+    "connected",
+];
+
+/// Modem.Signal properties and their `technology` label
+const SIGNAL_TECHNOLOGIES: &[(&str, &str)] = &[
+    ("Gsm", "gsm"),
+    ("Umts", "umts"),
+    ("Lte", "lte"),
+    ("Nr5g", "5gnr"),
+    ("Cdma", "cdma"),
+    ("Evdo", "evdo"),
+];
+
+struct ModemMetrics {
+    signal_quality_percent: GaugeVec,
+    signal_rssi_dbm: GaugeVec,
+    signal_rsrp_dbm: GaugeVec,
+    connection_state: GaugeVec,
+    data_bytes_total: IntCounterVec,
+    /// bearer path and direction -> last raw byte count
+    data_previous: Mutex<HashMap<(String, &'static str), u64>>,
+    /// (modem, imei) of the modems seen in the previous collection
+    data_modems: Mutex<HashSet<(String, String)>>,
+}
+
+impl ModemMetrics {
+    fn new() -> Self {
+        Self {
+            signal_quality_percent: prometheus::register_gauge_vec!(
+                "modem_signal_quality_percent",
+                "Signal quality reported by ModemManager (0-100)",
+                &["modem", "imei"]
+            )
+            .expect("register modem_signal_quality_percent"),
+
+            signal_rssi_dbm: prometheus::register_gauge_vec!(
+                "modem_signal_rssi_dbm",
+                "Received signal strength indicator in dBm (needs ModemManager signal polling)",
+                &["modem", "imei", "technology"]
+            )
+            .expect("register modem_signal_rssi_dbm"),
+
+            signal_rsrp_dbm: prometheus::register_gauge_vec!(
+                "modem_signal_rsrp_dbm",
+                "Reference signal received power in dBm (LTE and 5G, needs ModemManager signal polling)",
+                &["modem", "imei", "technology"]
+            )
+            .expect("register modem_signal_rsrp_dbm"),
+
+            connection_state: prometheus::register_gauge_vec!(
+                "modem_connection_state",
+                "Modem state reported by ModemManager (1 = active for given state)",
+                &["modem", "imei", "state"]
+            )
+            .expect("register modem_connection_state"),
+
+            data_bytes_total: prometheus::register_int_counter_vec!(
+                "modem_data_bytes_total",
+                "Bytes transferred over the modem's data bearers",
+                &["modem", "imei", "direction"]
+            )
+            .expect("register modem_data_bytes_total"),
+
+            data_previous: Mutex::new(HashMap::new()),
+            data_modems: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static MODEM_METRICS: OnceLock<ModemMetrics> = OnceLock::new();
+
+fn metrics() -> &'static ModemMetrics {
+    MODEM_METRICS.get_or_init(ModemMetrics::new)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Signal {
+    technology: &'static str,
+    rssi: Option<f64>,
+    rsrp: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Modem {
+    /// Trailing index of the object path, as used by `mmcli -m`
+    index: String,
+    imei: String,
+    state: Option<i64>,
+    signal_quality: Option<u64>,
+    signals: Vec<Signal>,
+    bearers: Vec<String>,
+}
+
+fn state_label(state: i64) -> &'static str {
+    usize::try_from(state + 1)
+        .ok()
+        .and_then(|index| MODEM_STATES.get(index))
+        .copied()
+        .unwrap_or("unknown")
+}
+
+/// Builds modems from an ObjectManager.GetManagedObjects reply (`a{oa{sa{sv}}}`)
+fn parse_modems(objects: &Value) -> Vec<Modem> {
+    let mut modems = Vec::new();
+    for (path, interfaces) in objects.entries() {
+        let (Some(path), Some(modem)) = (path.as_str(), interfaces.get(MODEM_INTERFACE)) else {
+            continue;
+        };
+        let mut entry = Modem {
+            index: path.rsplit('/').next().unwrap_or(path).to_string(),
+            imei: modem
+                .get("EquipmentIdentifier")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            state: modem.get("State").and_then(Value::as_i64),
+            // (ub): percentage and whether it was recently taken
+            signal_quality: modem
+                .get("SignalQuality")
+                .and_then(|quality| quality.as_array().first())
+                .and_then(Value::as_u64),
+            signals: Vec::new(),
+            bearers: modem
+                .get("Bearers")
+                .map(|bearers| {
+                    bearers
+                        .as_array()
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        if let Some(signal) = interfaces.get(SIGNAL_INTERFACE) {
+            for (property, technology) in SIGNAL_TECHNOLOGIES {
+                let Some(values) = signal.get(property) else {
+                    continue;
+                };
+                let rssi = values.get("rssi").and_then(Value::as_f64);
+                let rsrp = values.get("rsrp").and_then(Value::as_f64);
+                if rssi.is_some() || rsrp.is_some() {
+                    entry.signals.push(Signal {
+                        technology,
+                        rssi,
+                        rsrp,
+                    });
+                }
+            }
+        }
+        modems.push(entry);
+    }
+    modems
+}
+
+/// Received and transmitted bytes from Bearer properties; newer
+/// ModemManager also keeps totals across reconnections
+fn bearer_bytes(properties: &Value) -> Option<(u64, u64)> {
+    let stats = properties.get("Stats")?;
+    let read = |total: &str, current: &str| {
+        stats
+            .get(total)
+            .or_else(|| stats.get(current))
+            .and_then(Value::as_u64)
+    };
+    Some((
+        read("total-rx-bytes", "rx-bytes")?,
+        read("total-tx-bytes", "tx-bytes")?,
+    ))
+}
+
+fn update_modem(connection: &mut Connection, modem: &Modem) {
+    let metrics = metrics();
+    let labels = [modem.index.as_str(), modem.imei.as_str()];
+
+    if let Some(quality) = modem.signal_quality {
+        metrics
+            .signal_quality_percent
+            .with_label_values(&labels)
+            .set(quality as f64);
+    }
+    for signal in &modem.signals {
+        let labels = [labels[0], labels[1], signal.technology];
+        if let Some(rssi) = signal.rssi {
+            metrics.signal_rssi_dbm.with_label_values(&labels).set(rssi);
+        }
+        if let Some(rsrp) = signal.rsrp {
+            metrics.signal_rsrp_dbm.with_label_values(&labels).set(rsrp);
+        }
+    }
+    if let Some(state) = modem.state {
+        let current = state_label(state);
+        for known_state in MODEM_STATES {
+            metrics
+                .connection_state
+                .with_label_values(&[labels[0], labels[1], known_state])
+                .set(if current == *known_state { 1.0 } else { 0.0 });
+        }
+    }
+
+    let mut previous = match metrics.data_previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for bearer in &modem.bearers {
+        let reply = connection.call(
+            MM_SERVICE,
+            bearer,
+            "org.freedesktop.DBus.Properties",
+            "GetAll",
+            &[BEARER_INTERFACE],
+        );
+        let Some((rx, tx)) = reply
+            .ok()
+            .and_then(|body| body.first().and_then(bearer_bytes))
+        else {
+            continue;
+        };
+        for (direction, value) in [("receive", rx), ("transmit", tx)] {
+            let last = previous
+                .insert((bearer.clone(), direction), value)
+                .unwrap_or(0);
+            // Per-connection counters restart when the bearer reconnects
+            metrics
+                .data_bytes_total
+                .with_label_values(&[labels[0], labels[1], direction])
+                .inc_by(counter_delta(value, last));
+        }
+    }
+}
+
+/// Drops the last byte counts of bearers that are gone, since ModemManager
+/// creates a new Bearer/N path on every reconnect, and the byte counters of
+/// modems that are gone.
+fn forget_missing(metrics: &ModemMetrics, modems: &[Modem]) {
+    let bearers: HashSet<&str> = modems
+        .iter()
+        .flat_map(|modem| modem.bearers.iter().map(String::as_str))
+        .collect();
+    let mut previous = match metrics.data_previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    previous.retain(|(bearer, _), _| bearers.contains(bearer.as_str()));
+
+    let current: HashSet<(String, String)> = modems
+        .iter()
+        .map(|modem| (modem.index.clone(), modem.imei.clone()))
+        .collect();
+    let mut data_modems = match metrics.data_modems.lock() {
+        Ok(data_modems) => data_modems,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (index, imei) in data_modems.difference(&current) {
+        for direction in ["receive", "transmit"] {
+            let _ = metrics
+                .data_bytes_total
+                .remove_label_values(&[index, imei, direction]);
+        }
+    }
+    *data_modems = current;
+}
+
+fn collect() -> io::Result<()> {
+    let mut connection = Connection::system()?;
+    let reply = connection.call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "NameHasOwner",
+        &[MM_SERVICE],
+    )?;
+
+    let metrics = metrics();
+    // Modems come and go (USB, ModemManager restarts); drop gone ones
+    metrics.signal_quality_percent.reset();
+    metrics.signal_rssi_dbm.reset();
+    metrics.signal_rsrp_dbm.reset();
+    metrics.connection_state.reset();
+    if reply.first().and_then(Value::as_bool) != Some(true) {
+        forget_missing(metrics, &[]);
+        return Ok(());
+    }
+
+    let objects = connection.call(
+        MM_SERVICE,
+        MM_PATH,
+        "org.freedesktop.DBus.ObjectManager",
+        "GetManagedObjects",
+        &[],
+    )?;
+    let Some(objects) = objects.first() else {
+        return Ok(());
+    };
+    let modems = parse_modems(objects);
+    for modem in &modems {
+        update_modem(&mut connection, modem);
+    }
+    forget_missing(metrics, &modems);
+    Ok(())
+}
+
+pub fn update_metrics() {
+    if let Err(err) = collect() {
+        internal_metrics::collector_error("modem");
+        if debug_enabled() {
+            logging::debug("modem", &format!("modem: D-Bus query failed: {err}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict(entries: Vec<(&str, Value)>) -> Value {
+        Value::Array(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    Value::DictEntry(
+                        Box::new(Value::Str(key.to_string())),
+                        Box::new(Value::Variant(Box::new(value))),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn object(path: &str, interfaces: Vec<(&str, Value)>) -> Value {
+        let interfaces = Value::Array(
+            interfaces
+                .into_iter()
+                .map(|(name, properties)| {
+                    Value::DictEntry(Box::new(Value::Str(name.to_string())), Box::new(properties))
+                })
+                .collect(),
+        );
+        Value::DictEntry(Box::new(Value::Str(path.to_string())), Box::new(interfaces))
+    }
+
+    #[test]
+    fn test_state_label() {
+        assert_eq!(state_label(-1), "failed");
+        assert_eq!(state_label(8), "registered");
+        assert_eq!(state_label(11), "connected");
+        assert_eq!(state_label(42), "unknown");
+    }
+
+    #[test]
+    fn test_parse_modems() {
+        let modem = dict(vec![
+            (
+                "EquipmentIdentifier",
+                Value::Str("860000000000001".to_string()),
+            ),
+            ("State", Value::I32(11)),
+            (
+                "SignalQuality",
+                Value::Struct(vec![Value::U32(67), Value::Bool(true)]),
+            ),
+            (
+                "Bearers",
+                Value::Array(vec![Value::Str(
+                    "/org/freedesktop/ModemManager1/Bearer/3".to_string(),
+                )]),
+            ),
+        ]);
+        let signal = dict(vec![
+            (
+                "Lte",
+                dict(vec![
+                    ("rssi", Value::Double(-65.0)),
+                    ("rsrp", Value::Double(-95.0)),
+                ]),
+            ),
+            ("Umts", dict(vec![])),
+        ]);
+        let objects = Value::Array(vec![
+            object(
+                "/org/freedesktop/ModemManager1/Modem/0",
+                vec![(MODEM_INTERFACE, modem), (SIGNAL_INTERFACE, signal)],
+            ),
+            object("/org/freedesktop/ModemManager1/Other/1", vec![]),
+        ]);
+
+        let modems = parse_modems(&objects);
+        assert_eq!(
+            modems,
+            vec![Modem {
+                index: "0".to_string(),
+                imei: "860000000000001".to_string(),
+                state: Some(11),
+                signal_quality: Some(67),
+                signals: vec![Signal {
+                    technology: "lte",
+                    rssi: Some(-65.0),
+                    rsrp: Some(-95.0),
+                }],
+                bearers: vec!["/org/freedesktop/ModemManager1/Bearer/3".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_bearer_bytes_prefers_totals() {
+        let properties = dict(vec![(
+            "Stats",
+            dict(vec![
+                ("rx-bytes", Value::U64(10)),
+                ("tx-bytes", Value::U64(20)),
+                ("total-rx-bytes", Value::U64(1000)),
+                ("total-tx-bytes", Value::U64(2000)),
+            ]),
+        )]);
+        assert_eq!(bearer_bytes(&properties), Some((1000, 2000)));
+
+        let properties = dict(vec![(
+            "Stats",
+            dict(vec![
+                ("rx-bytes", Value::U64(10)),
+                ("tx-bytes", Value::U64(20)),
+            ]),
+        )]);
+        assert_eq!(bearer_bytes(&properties), Some((10, 20)));
+        assert_eq!(bearer_bytes(&dict(vec![])), None);
+    }
+
+    #[test]
+    fn test_forget_missing_prunes_bearers_and_modems() {
+        let metrics = metrics();
+        let modem = |bearer: &str| Modem {
+            index: "90".to_string(),
+            imei: "860000000000090".to_string(),
+            bearers: vec![bearer.to_string()],
+            ..Modem::default()
+        };
+        let labels = ["90", "860000000000090", "receive"];
+        metrics
+            .data_bytes_total
+            .with_label_values(&labels)
+            .inc_by(5);
+        metrics.data_previous.lock().unwrap().insert(
+            (
+                "/org/freedesktop/ModemManager1/Bearer/90".to_string(),
+                "receive",
+            ),
+            5,
+        );
+
+        // A reconnect replaces the bearer; the modem and its counter remain
+        forget_missing(
+            metrics,
+            &[modem("/org/freedesktop/ModemManager1/Bearer/91")],
+        );
+        assert!(
+            !metrics
+                .data_previous
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|(bearer, _)| bearer.ends_with("/Bearer/90"))
+        );
+        assert_eq!(metrics.data_bytes_total.with_label_values(&labels).get(), 5);
+
+        forget_missing(metrics, &[]);
+        assert!(
+            metrics
+                .data_bytes_total
+                .remove_label_values(&labels)
+                .is_err()
+        );
+    }
+}
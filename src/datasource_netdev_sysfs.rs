@@ -1,8 +1,10 @@
 use crate::config::AppConfig;
+use crate::counter_reset;
 use prometheus::GaugeVec;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 
 const SYS_CLASS_NET: &str = "/sys/class/net";
 const OPERSTATES: [&str; 7] = [
@@ -25,6 +27,15 @@ struct NetdevSysfsMetrics {
     speed_mbps: GaugeVec,
     duplex: GaugeVec,
     autoneg: GaugeVec,
+    mtu_bytes: GaugeVec,
+    tx_queues: GaugeVec,
+    rx_queues: GaugeVec,
+    address_info: GaugeVec,
+    /// Current address_info labels per interface, replaced when the address changes
+    address_labels: Mutex<HashMap<String, AddressLabels>>,
+    stat: GaugeVec,
+    /// statistics/ fields exported per interface, used to drop removed interfaces
+    stat_fields: Mutex<HashMap<String, Vec<String>>>,
 }
 
 impl NetdevSysfsMetrics {
@@ -72,10 +83,55 @@ impl NetdevSysfsMetrics {
                 &["interface", "state"]
             )
             .expect("register netdev_autoneg"),
+            mtu_bytes: prometheus::register_gauge_vec!(
+                "netdev_mtu_bytes",
+                "Network interface MTU in bytes",
+                &["interface"]
+            )
+            .expect("register netdev_mtu_bytes"),
+            tx_queues: prometheus::register_gauge_vec!(
+                "netdev_tx_queues",
+                "Number of transmit queues of the network interface",
+                &["interface"]
+            )
+            .expect("register netdev_tx_queues"),
+            rx_queues: prometheus::register_gauge_vec!(
+                "netdev_rx_queues",
+                "Number of receive queues of the network interface",
+                &["interface"]
+            )
+            .expect("register netdev_rx_queues"),
+            address_info: prometheus::register_gauge_vec!(
+                "netdev_address_info",
+                "Network interface hardware address (always 1, address in labels)",
+                &["interface", "address", "broadcast", "ifindex"]
+            )
+            .expect("register netdev_address_info"),
+            address_labels: Mutex::new(HashMap::new()),
+            stat: prometheus::register_gauge_vec!(
+                "netdev_sysfs_stat",
+                "Raw network interface counters from /sys/class/net/*/statistics",
+                &["interface", "field"]
+            )
+            .expect("register netdev_sysfs_stat"),
+            stat_fields: Mutex::new(HashMap::new()),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct AddressLabels {
+    address: String,
+    broadcast: String,
+    ifindex: String,
+}
+
+impl AddressLabels {
+    fn values<'a>(&'a self, iface: &'a str) -> [&'a str; 4] {
+        [iface, &self.address, &self.broadcast, &self.ifindex]
+    }
+}
+
 static NETDEV_SYSFS_METRICS: OnceLock<NetdevSysfsMetrics> = OnceLock::new();
 
 fn metrics() -> &'static NetdevSysfsMetrics {
@@ -90,8 +146,12 @@ fn read_i64(path: &Path) -> Option<i64> {
     read_string(path)?.parse::<i64>().ok()
 }
 
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
 fn normalized_state<'a>(value: &'a str, known: &[&'a str]) -> &'a str {
-    if known.iter().any(|state| *state == value) {
+    if known.contains(&value) {
         value
     } else {
         "unknown"
@@ -107,11 +167,12 @@ fn set_state_metric(metric: &GaugeVec, iface: &str, value: &str, known: &[&str])
     }
 }
 
-fn should_skip_interface(name: &str, config: &AppConfig) -> bool {
-    if config.ignore_ppp_interfaces && name.starts_with("ppp") {
+pub(crate) fn should_skip_interface(name: &str, config: &AppConfig) -> bool {
+    if config.netdev.ignore_ppp_interfaces && name.starts_with("ppp") {
         return true;
     }
-    if config.ignore_veth_interfaces && (name.starts_with("veth") || name.starts_with("br-")) {
+    if config.netdev.ignore_veth_interfaces && (name.starts_with("veth") || name.starts_with("br-"))
+    {
         return true;
     }
     false
@@ -124,40 +185,46 @@ fn update_interface(metrics: &NetdevSysfsMetrics, iface_path: &Path, iface: &str
         set_state_metric(&metrics.operstate, iface, &state, &OPERSTATES);
     }
 
-    if let Some(carrier) = read_i64(&iface_path.join("carrier")) {
-        if carrier >= 0 {
-            metrics
-                .carrier
-                .with_label_values(&[iface])
-                .set(carrier as f64);
-        }
+    if let Some(carrier) = read_i64(&iface_path.join("carrier"))
+        && carrier >= 0
+    {
+        metrics
+            .carrier
+            .with_label_values(&[iface])
+            .set(carrier as f64);
     }
 
-    if let Some(changes) = read_i64(&iface_path.join("carrier_changes")) {
-        if changes >= 0 {
-            metrics
-                .carrier_changes
-                .with_label_values(&[iface])
-                .set(changes as f64);
-        }
+    if let Some(changes) = read_i64(&iface_path.join("carrier_changes"))
+        && changes >= 0
+    {
+        // Recreating the device (e.g. driver reload) restarts the count
+        counter_reset::observe(
+            "netdev_carrier_changes",
+            &[("interface", iface)],
+            changes as f64,
+        );
+        metrics
+            .carrier_changes
+            .with_label_values(&[iface])
+            .set(changes as f64);
     }
 
-    if let Some(dormant) = read_i64(&iface_path.join("dormant")) {
-        if dormant >= 0 {
-            metrics
-                .dormant
-                .with_label_values(&[iface])
-                .set(dormant as f64);
-        }
+    if let Some(dormant) = read_i64(&iface_path.join("dormant"))
+        && dormant >= 0
+    {
+        metrics
+            .dormant
+            .with_label_values(&[iface])
+            .set(dormant as f64);
     }
 
-    if let Some(speed) = read_i64(&iface_path.join("speed")) {
-        if speed >= 0 {
-            metrics
-                .speed_mbps
-                .with_label_values(&[iface])
-                .set(speed as f64);
-        }
+    if let Some(speed) = read_i64(&iface_path.join("speed"))
+        && speed >= 0
+    {
+        metrics
+            .speed_mbps
+            .with_label_values(&[iface])
+            .set(speed as f64);
     }
 
     if let Some(duplex) = read_string(&iface_path.join("duplex")).map(|value| value.to_lowercase())
@@ -172,13 +239,107 @@ fn update_interface(metrics: &NetdevSysfsMetrics, iface_path: &Path, iface: &str
     }
 }
 
+fn update_address(
+    metrics: &NetdevSysfsMetrics,
+    address_labels: &mut HashMap<String, AddressLabels>,
+    iface_path: &Path,
+    iface: &str,
+) {
+    if let Some(mtu) = read_u64(&iface_path.join("mtu")) {
+        metrics
+            .mtu_bytes
+            .with_label_values(&[iface])
+            .set(mtu as f64);
+    }
+
+    let Some(address) = read_string(&iface_path.join("address")) else {
+        return;
+    };
+    let labels = AddressLabels {
+        address,
+        broadcast: read_string(&iface_path.join("broadcast")).unwrap_or_default(),
+        ifindex: read_string(&iface_path.join("ifindex")).unwrap_or_default(),
+    };
+
+    if let Some(previous) = address_labels.get(iface)
+        && *previous != labels
+    {
+        let _ = metrics
+            .address_info
+            .remove_label_values(&previous.values(iface));
+    }
+    metrics
+        .address_info
+        .with_label_values(&labels.values(iface))
+        .set(1.0);
+    address_labels.insert(iface.to_string(), labels);
+}
+
+/// Counts the tx-N and rx-N directories under queues/.
+fn count_queues(iface_path: &Path) -> Option<(usize, usize)> {
+    let entries = fs::read_dir(iface_path.join("queues")).ok()?;
+    let (mut tx, mut rx) = (0, 0);
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("tx-") {
+            tx += 1;
+        } else if name.starts_with("rx-") {
+            rx += 1;
+        }
+    }
+    Some((tx, rx))
+}
+
+fn update_queues(metrics: &NetdevSysfsMetrics, iface_path: &Path, iface: &str) {
+    if let Some((tx, rx)) = count_queues(iface_path) {
+        metrics.tx_queues.with_label_values(&[iface]).set(tx as f64);
+        metrics.rx_queues.with_label_values(&[iface]).set(rx as f64);
+    }
+}
+
+/// Exports every counter under statistics/, returning the field names seen.
+fn update_statistics(metrics: &NetdevSysfsMetrics, iface_path: &Path, iface: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let entries = match fs::read_dir(iface_path.join("statistics")) {
+        Ok(entries) => entries,
+        Err(_) => return fields,
+    };
+
+    for entry in entries.flatten() {
+        let field = match entry.file_name().into_string() {
+            Ok(field) => field,
+            Err(_) => continue,
+        };
+        // Some drivers return EINVAL for counters they do not implement
+        if let Some(value) = read_u64(&entry.path()) {
+            metrics
+                .stat
+                .with_label_values(&[iface, &field])
+                .set(value as f64);
+            fields.push(field);
+        }
+    }
+
+    fields
+}
+
 pub fn update_metrics(config: &AppConfig) {
-    let entries = match fs::read_dir(SYS_CLASS_NET) {
+    update_metrics_from_path(&config.host_path(SYS_CLASS_NET), config);
+}
+
+fn update_metrics_from_path(base: &Path, config: &AppConfig) {
+    let entries = match fs::read_dir(base) {
         Ok(entries) => entries,
         Err(_) => return,
     };
 
     let metrics = metrics();
+    let mut current: HashMap<String, Vec<String>> = HashMap::new();
+    let mut address_labels = match metrics.address_labels.lock() {
+        Ok(address_labels) => address_labels,
+        Err(poisoned) => poisoned.into_inner(),
+    };
 
     for entry in entries.flatten() {
         let name = entry.file_name().to_string_lossy().to_string();
@@ -186,5 +347,171 @@ pub fn update_metrics(config: &AppConfig) {
             continue;
         }
         update_interface(metrics, &entry.path(), &name);
+        update_address(metrics, &mut address_labels, &entry.path(), &name);
+        update_queues(metrics, &entry.path(), &name);
+        let fields = if config.is_metric_enabled("netdev_sysfs_stat") {
+            update_statistics(metrics, &entry.path(), &name)
+        } else {
+            Vec::new()
+        };
+        current.insert(name, fields);
+    }
+
+    let mut stat_fields = match metrics.stat_fields.lock() {
+        Ok(stat_fields) => stat_fields,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (iface, fields) in stat_fields.iter() {
+        let kept: HashSet<&String> = current
+            .get(iface)
+            .map(|fields| fields.iter().collect())
+            .unwrap_or_default();
+        for field in fields.iter().filter(|field| !kept.contains(field)) {
+            let _ = metrics.stat.remove_label_values(&[iface, field]);
+        }
+    }
+    for iface in stat_fields.keys() {
+        if !current.contains_key(iface) {
+            let _ = metrics.tx_queues.remove_label_values(&[iface]);
+            let _ = metrics.rx_queues.remove_label_values(&[iface]);
+            counter_reset::forget("netdev_carrier_changes", ("interface", iface));
+        }
+    }
+    *stat_fields = current;
+
+    address_labels.retain(|iface, labels| {
+        let present = stat_fields.contains_key(iface);
+        if !present {
+            let _ = metrics.mtu_bytes.remove_label_values(&[iface]);
+            let _ = metrics
+                .address_info
+                .remove_label_values(&labels.values(iface));
+        }
+        present
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+    use tempfile::TempDir;
+
+    fn create_mock_iface(base: &Path, name: &str, stats: &[(&str, &str)]) {
+        let stats_dir = base.join(name).join("statistics");
+        fs::create_dir_all(&stats_dir).unwrap();
+        fs::write(base.join(name).join("operstate"), "up\n").unwrap();
+        fs::write(base.join(name).join("mtu"), "1500\n").unwrap();
+        fs::write(base.join(name).join("ifindex"), "42\n").unwrap();
+        fs::write(base.join(name).join("address"), "52:54:00:12:34:56\n").unwrap();
+        fs::write(base.join(name).join("broadcast"), "ff:ff:ff:ff:ff:ff\n").unwrap();
+        for (field, value) in stats {
+            fs::write(stats_dir.join(field), format!("{value}\n")).unwrap();
+        }
+    }
+
+    fn stat_series(iface: &str) -> Vec<String> {
+        metrics().stat.collect()[0]
+            .get_metric()
+            .iter()
+            .filter(|metric| metric.get_label().iter().any(|l| l.value() == iface))
+            .flat_map(|metric| metric.get_label().iter())
+            .filter(|label| label.name() == "field")
+            .map(|label| label.value().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_update_statistics_reads_all_fields() {
+        let dir = TempDir::new().unwrap();
+        create_mock_iface(
+            dir.path(),
+            "teststat0",
+            &[
+                ("rx_bytes", "1000"),
+                ("rx_over_errors", "3"),
+                ("collisions", "7"),
+                ("tx_heartbeat_errors", "unsupported"),
+            ],
+        );
+
+        let fields = update_statistics(metrics(), &dir.path().join("teststat0"), "teststat0");
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(
+            metrics()
+                .stat
+                .with_label_values(&["teststat0", "rx_over_errors"])
+                .get(),
+            3.0
+        );
+    }
+
+    #[test]
+    fn test_update_metrics_removes_stale_interfaces() {
+        let dir = TempDir::new().unwrap();
+        let config = AppConfig::default();
+        create_mock_iface(dir.path(), "teststale0", &[("rx_dropped", "5")]);
+        create_mock_iface(dir.path(), "vethstale0", &[("rx_dropped", "5")]);
+        fs::write(dir.path().join("teststale0").join("carrier_changes"), "3\n").unwrap();
+        let labels = [("interface", "teststale0")];
+
+        update_metrics_from_path(dir.path(), &config);
+        assert_eq!(stat_series("teststale0"), vec!["rx_dropped"]);
+        assert!(stat_series("vethstale0").is_empty());
+        assert!(counter_reset::is_tracked("netdev_carrier_changes", &labels));
+
+        fs::remove_dir_all(dir.path().join("teststale0")).unwrap();
+        update_metrics_from_path(dir.path(), &config);
+        assert!(stat_series("teststale0").is_empty());
+        assert!(!counter_reset::is_tracked(
+            "netdev_carrier_changes",
+            &labels
+        ));
+    }
+
+    #[test]
+    fn test_count_queues() {
+        let dir = TempDir::new().unwrap();
+        let iface_path = dir.path().join("testqueue0");
+        for queue in ["tx-0", "tx-1", "tx-2", "tx-3", "rx-0", "rx-1"] {
+            fs::create_dir_all(iface_path.join("queues").join(queue)).unwrap();
+        }
+        assert_eq!(count_queues(&iface_path), Some((4, 2)));
+        assert_eq!(count_queues(&dir.path().join("missing")), None);
+
+        update_queues(metrics(), &iface_path, "testqueue0");
+        assert_eq!(
+            metrics().tx_queues.with_label_values(&["testqueue0"]).get(),
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_update_address_replaces_changed_mac() {
+        let dir = TempDir::new().unwrap();
+        create_mock_iface(dir.path(), "testaddr0", &[]);
+        let iface_path = dir.path().join("testaddr0");
+        let mut address_labels = HashMap::new();
+
+        update_address(metrics(), &mut address_labels, &iface_path, "testaddr0");
+        assert_eq!(
+            metrics().mtu_bytes.with_label_values(&["testaddr0"]).get(),
+            1500.0
+        );
+
+        fs::write(iface_path.join("address"), "52:54:00:ab:cd:ef\n").unwrap();
+        update_address(metrics(), &mut address_labels, &iface_path, "testaddr0");
+
+        let addresses: Vec<String> = metrics().address_info.collect()[0]
+            .get_metric()
+            .iter()
+            .filter(|metric| metric.get_label().iter().any(|l| l.value() == "testaddr0"))
+            .flat_map(|metric| metric.get_label().iter())
+            .filter(|label| label.name() == "address")
+            .map(|label| label.value().to_string())
+            .collect();
+        assert_eq!(addresses, vec!["52:54:00:ab:cd:ef"]);
+        assert_eq!(address_labels["testaddr0"].ifindex, "42");
     }
 }
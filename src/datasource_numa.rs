@@ -1,3 +1,4 @@
+use crate::config::AppConfig;
 use prometheus::{Gauge, GaugeVec};
 use std::fs;
 use std::path::Path;
@@ -7,6 +8,10 @@ struct NumaMetrics {
     node_count: Gauge,
     meminfo: GaugeVec,
     numastat: GaugeVec,
+    node_cpu: GaugeVec,
+    node_distance: GaugeVec,
+    hugepages_total: GaugeVec,
+    hugepages_free: GaugeVec,
 }
 
 impl NumaMetrics {
@@ -28,6 +33,34 @@ impl NumaMetrics {
                 &["node", "type"]
             )
             .expect("register numa_node_stat_pages"),
+
+            node_cpu: prometheus::register_gauge_vec!(
+                "numa_node_cpu",
+                "CPUs belonging to each NUMA node (always 1)",
+                &["node", "cpu"]
+            )
+            .expect("register numa_node_cpu"),
+
+            node_distance: prometheus::register_gauge_vec!(
+                "numa_node_distance",
+                "Relative distance between NUMA nodes as reported by firmware",
+                &["node", "target"]
+            )
+            .expect("register numa_node_distance"),
+
+            hugepages_total: prometheus::register_gauge_vec!(
+                "numa_node_hugepages_total",
+                "Hugepages allocated on the NUMA node",
+                &["node", "size"]
+            )
+            .expect("register numa_node_hugepages_total"),
+
+            hugepages_free: prometheus::register_gauge_vec!(
+                "numa_node_hugepages_free",
+                "Free hugepages on the NUMA node",
+                &["node", "size"]
+            )
+            .expect("register numa_node_hugepages_free"),
         }
     }
 }
@@ -90,7 +123,99 @@ fn parse_numastat(content: &str, node_name: &str) {
     }
 }
 
-fn update_numa_node(node_path: &Path, node_name: &str) {
+/// Expands a kernel cpulist such as "0-3,8-11" into individual CPU ids.
+pub(crate) fn parse_cpulist(content: &str) -> Vec<u32> {
+    let mut cpus = Vec::new();
+
+    for range in content.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<u32>(), end.parse::<u32>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => {
+                if let Ok(cpu) = range.parse::<u32>() {
+                    cpus.push(cpu);
+                }
+            }
+        }
+    }
+
+    cpus
+}
+
+fn parse_cpulist_into_metrics(content: &str, node_name: &str) {
+    let metrics = metrics();
+
+    for cpu in parse_cpulist(content) {
+        metrics
+            .node_cpu
+            .with_label_values(&[node_name, &cpu.to_string()])
+            .set(1.0);
+    }
+}
+
+/// Parses a node's distance row. Columns follow the online nodes in ascending
+/// order, which only matches node ids when there are no gaps.
+fn parse_distance(content: &str, node_name: &str, targets: &[String]) {
+    let metrics = metrics();
+
+    for (index, value) in content.split_whitespace().enumerate() {
+        let distance: u64 = match value.parse() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let target = match targets.get(index) {
+            Some(target) => target.clone(),
+            None => format!("node{index}"),
+        };
+
+        metrics
+            .node_distance
+            .with_label_values(&[node_name, &target])
+            .set(distance as f64);
+    }
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse().ok()
+}
+
+fn update_hugepages(node_path: &Path, node_name: &str) {
+    let entries = match fs::read_dir(node_path.join("hugepages")) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let metrics = metrics();
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        // hugepages-2048kB -> 2048kB
+        let Some(size) = name.strip_prefix("hugepages-") else {
+            continue;
+        };
+
+        let path = entry.path();
+        if let Some(total) = read_u64(&path.join("nr_hugepages")) {
+            metrics
+                .hugepages_total
+                .with_label_values(&[node_name, size])
+                .set(total as f64);
+        }
+        if let Some(free) = read_u64(&path.join("free_hugepages")) {
+            metrics
+                .hugepages_free
+                .with_label_values(&[node_name, size])
+                .set(free as f64);
+        }
+    }
+}
+
+fn update_numa_node(node_path: &Path, node_name: &str, targets: &[String]) {
     // Read meminfo
     if let Some(meminfo) = read_string(&node_path.join("meminfo")) {
         parse_meminfo(&meminfo, node_name);
@@ -100,10 +225,20 @@ fn update_numa_node(node_path: &Path, node_name: &str) {
     if let Some(numastat) = read_string(&node_path.join("numastat")) {
         parse_numastat(&numastat, node_name);
     }
+
+    if let Some(cpulist) = read_string(&node_path.join("cpulist")) {
+        parse_cpulist_into_metrics(&cpulist, node_name);
+    }
+
+    if let Some(distance) = read_string(&node_path.join("distance")) {
+        parse_distance(&distance, node_name, targets);
+    }
+
+    update_hugepages(node_path, node_name);
 }
 
-pub fn update_metrics() {
-    update_metrics_from_path(Path::new("/sys/devices/system/node"));
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/devices/system/node"));
 }
 
 fn update_metrics_from_path(base: &Path) {
@@ -113,7 +248,7 @@ fn update_metrics_from_path(base: &Path) {
     };
 
     let metrics = metrics();
-    let mut node_count = 0;
+    let mut nodes: Vec<(u32, String, std::path::PathBuf)> = Vec::new();
 
     for entry in entries.flatten() {
         let name = match entry.file_name().into_string() {
@@ -122,17 +257,25 @@ fn update_metrics_from_path(base: &Path) {
         };
 
         // Match node0, node1, etc.
-        if name.starts_with("node") && name[4..].chars().all(|c| c.is_ascii_digit()) {
+        if let Some(id) = name
+            .strip_prefix("node")
+            .and_then(|id| id.parse::<u32>().ok())
+        {
             let path = match fs::canonicalize(entry.path()) {
                 Ok(p) => p,
                 Err(_) => continue,
             };
-            update_numa_node(&path, &name);
-            node_count += 1;
+            nodes.push((id, name, path));
         }
     }
 
-    metrics.node_count.set(node_count as f64);
+    nodes.sort_by_key(|(id, _, _)| *id);
+    let targets: Vec<String> = nodes.iter().map(|(_, name, _)| name.clone()).collect();
+    for (_, name, path) in &nodes {
+        update_numa_node(path, name, &targets);
+    }
+
+    metrics.node_count.set(nodes.len() as f64);
 }
 
 #[cfg(test)]
@@ -175,6 +318,8 @@ other_node 789
         fs::create_dir_all(&node_dir).unwrap();
         fs::write(node_dir.join("meminfo"), MOCK_MEMINFO).unwrap();
         fs::write(node_dir.join("numastat"), MOCK_NUMASTAT).unwrap();
+        fs::write(node_dir.join("cpulist"), "0-3,8-11\n").unwrap();
+        fs::write(node_dir.join("distance"), "10 21\n").unwrap();
         node_dir
     }
 
@@ -217,11 +362,112 @@ other_node 789
         parse_numastat("invalid\nno_value", "node0");
     }
 
+    #[test]
+    fn test_parse_cpulist() {
+        assert_eq!(parse_cpulist("0-3,8-11\n"), vec![0, 1, 2, 3, 8, 9, 10, 11]);
+        assert_eq!(parse_cpulist("5"), vec![5]);
+        assert_eq!(parse_cpulist("0,2,4-5"), vec![0, 2, 4, 5]);
+        assert!(parse_cpulist("").is_empty());
+        assert!(parse_cpulist("x-y").is_empty());
+    }
+
+    #[test]
+    fn test_parse_distance() {
+        parse_distance("20 10 21", "node91", &[]);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .node_distance
+                .with_label_values(&["node91", "node1"])
+                .get(),
+            10.0
+        );
+        assert_eq!(
+            metrics
+                .node_distance
+                .with_label_values(&["node91", "node2"])
+                .get(),
+            21.0
+        );
+    }
+
+    #[test]
+    fn test_parse_distance_sparse_nodes() {
+        let targets = vec!["node92".to_string(), "node94".to_string()];
+        parse_distance("10 32", "node92", &targets);
+
+        assert_eq!(
+            metrics()
+                .node_distance
+                .with_label_values(&["node92", "node94"])
+                .get(),
+            32.0
+        );
+    }
+
+    #[test]
+    fn test_update_numa_node_topology() {
+        let dir = TempDir::new().unwrap();
+        let node = create_mock_node(dir.path(), "node90");
+        update_numa_node(&node, "node90", &[]);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics.node_cpu.with_label_values(&["node90", "9"]).get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .node_distance
+                .with_label_values(&["node90", "node0"])
+                .get(),
+            10.0
+        );
+    }
+
+    #[test]
+    fn test_update_hugepages() {
+        let dir = TempDir::new().unwrap();
+        let node = create_mock_node(dir.path(), "node93");
+        for (size, total, free) in [("2048kB", 512, 128), ("1048576kB", 4, 4)] {
+            let size_dir = node.join("hugepages").join(format!("hugepages-{size}"));
+            fs::create_dir_all(&size_dir).unwrap();
+            fs::write(size_dir.join("nr_hugepages"), format!("{total}\n")).unwrap();
+            fs::write(size_dir.join("free_hugepages"), format!("{free}\n")).unwrap();
+        }
+
+        update_hugepages(&node, "node93");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .hugepages_total
+                .with_label_values(&["node93", "2048kB"])
+                .get(),
+            512.0
+        );
+        assert_eq!(
+            metrics
+                .hugepages_free
+                .with_label_values(&["node93", "2048kB"])
+                .get(),
+            128.0
+        );
+        assert_eq!(
+            metrics
+                .hugepages_total
+                .with_label_values(&["node93", "1048576kB"])
+                .get(),
+            4.0
+        );
+    }
+
     #[test]
     fn test_update_numa_node() {
         let dir = TempDir::new().unwrap();
         let node = create_mock_node(dir.path(), "node0");
-        update_numa_node(&node, "node0");
+        update_numa_node(&node, "node0", &[]);
     }
 
     #[test]
@@ -230,7 +476,7 @@ other_node 789
         let node_dir = dir.path().join("node0");
         fs::create_dir_all(&node_dir).unwrap();
         // No meminfo or numastat files
-        update_numa_node(&node_dir, "node0");
+        update_numa_node(&node_dir, "node0", &[]);
     }
 
     #[test]
@@ -1,11 +1,76 @@
+use crate::config::AppConfig;
 use prometheus::GaugeVec;
-use std::fs;
+use std::fs::{self, File};
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
 use std::sync::OnceLock;
 
+/// _IOWR('N', 0x41, struct nvme_admin_cmd) from linux/nvme_ioctl.h
+const NVME_IOCTL_ADMIN_CMD: u32 = 0xc048_4e41;
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+const NVME_LOG_SMART: u32 = 0x02;
+/// The controller-wide log rather than a single namespace
+const NVME_NSID_ALL: u32 = 0xffff_ffff;
+const NVME_TIMEOUT_MS: u32 = 3000;
+/// Data units in the SMART log count thousands of 512-byte blocks
+const NVME_DATA_UNIT_BYTES: f64 = 512_000.0;
+
+/// nvme_admin_cmd (nvme_passthru_cmd) from linux/nvme_ioctl.h
+#[repr(C)]
+#[derive(Default)]
+struct NvmeAdminCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+/// Fields of the SMART / Health Information log page (log identifier 02h)
+#[derive(Debug, PartialEq)]
+struct SmartLog {
+    critical_warning: u8,
+    temperature_kelvin: u16,
+    available_spare: u8,
+    available_spare_threshold: u8,
+    percentage_used: u8,
+    data_units_read: u128,
+    data_units_written: u128,
+    power_on_hours: u128,
+    unsafe_shutdowns: u128,
+    media_errors: u128,
+}
+
 struct NvmeMetrics {
     info: GaugeVec,
     state: GaugeVec,
+    pcie_link_speed_gts: GaugeVec,
+    pcie_link_width: GaugeVec,
+    pcie_link_max_speed_gts: GaugeVec,
+    pcie_link_max_width: GaugeVec,
+    critical_warning: GaugeVec,
+    temperature_celsius: GaugeVec,
+    available_spare_ratio: GaugeVec,
+    available_spare_threshold_ratio: GaugeVec,
+    percentage_used_ratio: GaugeVec,
+    data_read_bytes_total: GaugeVec,
+    data_written_bytes_total: GaugeVec,
+    power_on_hours: GaugeVec,
+    unsafe_shutdowns_total: GaugeVec,
+    media_errors_total: GaugeVec,
 }
 
 impl NvmeMetrics {
@@ -24,6 +89,104 @@ impl NvmeMetrics {
                 &["device", "state"]
             )
             .expect("register nvme_state"),
+
+            pcie_link_speed_gts: prometheus::register_gauge_vec!(
+                "nvme_pcie_link_speed_gts",
+                "Negotiated PCIe link speed of the NVMe controller in GT/s",
+                &["device"]
+            )
+            .expect("register nvme_pcie_link_speed_gts"),
+
+            pcie_link_width: prometheus::register_gauge_vec!(
+                "nvme_pcie_link_width",
+                "Negotiated PCIe link width of the NVMe controller in lanes",
+                &["device"]
+            )
+            .expect("register nvme_pcie_link_width"),
+
+            pcie_link_max_speed_gts: prometheus::register_gauge_vec!(
+                "nvme_pcie_link_max_speed_gts",
+                "Maximum PCIe link speed supported by the NVMe controller in GT/s",
+                &["device"]
+            )
+            .expect("register nvme_pcie_link_max_speed_gts"),
+
+            pcie_link_max_width: prometheus::register_gauge_vec!(
+                "nvme_pcie_link_max_width",
+                "Maximum PCIe link width supported by the NVMe controller in lanes",
+                &["device"]
+            )
+            .expect("register nvme_pcie_link_max_width"),
+
+            critical_warning: prometheus::register_gauge_vec!(
+                "nvme_critical_warning",
+                "Critical warning bits from the SMART log (0 = no warning)",
+                &["device"]
+            )
+            .expect("register nvme_critical_warning"),
+
+            temperature_celsius: prometheus::register_gauge_vec!(
+                "nvme_temperature_celsius",
+                "Composite temperature from the SMART log in degrees Celsius",
+                &["device"]
+            )
+            .expect("register nvme_temperature_celsius"),
+
+            available_spare_ratio: prometheus::register_gauge_vec!(
+                "nvme_available_spare_ratio",
+                "Remaining spare capacity from the SMART log (0-1)",
+                &["device"]
+            )
+            .expect("register nvme_available_spare_ratio"),
+
+            available_spare_threshold_ratio: prometheus::register_gauge_vec!(
+                "nvme_available_spare_threshold_ratio",
+                "Spare capacity below which the controller raises a critical warning (0-1)",
+                &["device"]
+            )
+            .expect("register nvme_available_spare_threshold_ratio"),
+
+            percentage_used_ratio: prometheus::register_gauge_vec!(
+                "nvme_percentage_used_ratio",
+                "Vendor estimate of the endurance used from the SMART log; may exceed 1",
+                &["device"]
+            )
+            .expect("register nvme_percentage_used_ratio"),
+
+            data_read_bytes_total: prometheus::register_gauge_vec!(
+                "nvme_data_read_bytes_total",
+                "Bytes read by the host from the SMART log data units",
+                &["device"]
+            )
+            .expect("register nvme_data_read_bytes_total"),
+
+            data_written_bytes_total: prometheus::register_gauge_vec!(
+                "nvme_data_written_bytes_total",
+                "Bytes written by the host from the SMART log data units",
+                &["device"]
+            )
+            .expect("register nvme_data_written_bytes_total"),
+
+            power_on_hours: prometheus::register_gauge_vec!(
+                "nvme_power_on_hours",
+                "Power-on hours from the SMART log",
+                &["device"]
+            )
+            .expect("register nvme_power_on_hours"),
+
+            unsafe_shutdowns_total: prometheus::register_gauge_vec!(
+                "nvme_unsafe_shutdowns_total",
+                "Shutdowns without a prior shutdown notification from the SMART log",
+                &["device"]
+            )
+            .expect("register nvme_unsafe_shutdowns_total"),
+
+            media_errors_total: prometheus::register_gauge_vec!(
+                "nvme_media_errors_total",
+                "Unrecovered data integrity errors from the SMART log",
+                &["device"]
+            )
+            .expect("register nvme_media_errors_total"),
         }
     }
 }
@@ -38,6 +201,122 @@ fn read_string(path: &Path) -> Option<String> {
     fs::read_to_string(path).ok().map(|s| s.trim().to_string())
 }
 
+/// Parses sysfs link speeds such as "16.0 GT/s PCIe" or "8 GT/s"; "Unknown" yields None
+pub(crate) fn parse_link_speed(value: &str) -> Option<f64> {
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Exports the controller's PCIe link from its PCI device directory; fabrics
+/// controllers have no such files and are skipped.
+fn update_pcie_link(device_path: &Path, device_name: &str) {
+    let metrics = metrics();
+    let pci = device_path.join("device");
+
+    let speeds = [
+        ("current_link_speed", &metrics.pcie_link_speed_gts),
+        ("max_link_speed", &metrics.pcie_link_max_speed_gts),
+    ];
+    for (file, gauge) in speeds {
+        if let Some(speed) = read_string(&pci.join(file)).and_then(|v| parse_link_speed(&v)) {
+            gauge.with_label_values(&[device_name]).set(speed);
+        }
+    }
+
+    let widths = [
+        ("current_link_width", &metrics.pcie_link_width),
+        ("max_link_width", &metrics.pcie_link_max_width),
+    ];
+    for (file, gauge) in widths {
+        if let Some(width) = read_string(&pci.join(file)).and_then(|v| v.parse::<f64>().ok()) {
+            gauge.with_label_values(&[device_name]).set(width);
+        }
+    }
+}
+
+fn le_u128(bytes: &[u8]) -> u128 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u128, |value, byte| (value << 8) | u128::from(*byte))
+}
+
+fn parse_smart_log(data: &[u8; 512]) -> SmartLog {
+    SmartLog {
+        critical_warning: data[0],
+        temperature_kelvin: u16::from_le_bytes([data[1], data[2]]),
+        available_spare: data[3],
+        available_spare_threshold: data[4],
+        percentage_used: data[5],
+        data_units_read: le_u128(&data[32..48]),
+        data_units_written: le_u128(&data[48..64]),
+        power_on_hours: le_u128(&data[128..144]),
+        unsafe_shutdowns: le_u128(&data[144..160]),
+        media_errors: le_u128(&data[160..176]),
+    }
+}
+
+/// Fetches the SMART log page through the controller's character device;
+/// None when the ioctl fails, e.g. on fabrics controllers.
+fn read_smart_log(device_name: &str) -> Option<SmartLog> {
+    let device = File::open(Path::new("/dev").join(device_name)).ok()?;
+    let mut data = [0u8; 512];
+    let mut cmd = NvmeAdminCmd {
+        opcode: NVME_ADMIN_GET_LOG_PAGE,
+        nsid: NVME_NSID_ALL,
+        addr: data.as_mut_ptr() as u64,
+        data_len: data.len() as u32,
+        // Number of dwords minus one in the upper half, log identifier below
+        cdw10: ((data.len() as u32 / 4 - 1) << 16) | NVME_LOG_SMART,
+        timeout_ms: NVME_TIMEOUT_MS,
+        ..NvmeAdminCmd::default()
+    };
+    let ret = unsafe {
+        libc::ioctl(
+            device.as_raw_fd(),
+            NVME_IOCTL_ADMIN_CMD as _,
+            &mut cmd as *mut NvmeAdminCmd,
+        )
+    };
+    // A positive return is an NVMe status code
+    (ret == 0).then(|| parse_smart_log(&data))
+}
+
+fn set_smart_log(metrics: &NvmeMetrics, device_name: &str, log: &SmartLog) {
+    let values = [
+        (&metrics.critical_warning, f64::from(log.critical_warning)),
+        (
+            &metrics.temperature_celsius,
+            f64::from(log.temperature_kelvin) - 273.15,
+        ),
+        (
+            &metrics.available_spare_ratio,
+            f64::from(log.available_spare) / 100.0,
+        ),
+        (
+            &metrics.available_spare_threshold_ratio,
+            f64::from(log.available_spare_threshold) / 100.0,
+        ),
+        (
+            &metrics.percentage_used_ratio,
+            f64::from(log.percentage_used) / 100.0,
+        ),
+        (
+            &metrics.data_read_bytes_total,
+            log.data_units_read as f64 * NVME_DATA_UNIT_BYTES,
+        ),
+        (
+            &metrics.data_written_bytes_total,
+            log.data_units_written as f64 * NVME_DATA_UNIT_BYTES,
+        ),
+        (&metrics.power_on_hours, log.power_on_hours as f64),
+        (&metrics.unsafe_shutdowns_total, log.unsafe_shutdowns as f64),
+        (&metrics.media_errors_total, log.media_errors as f64),
+    ];
+    for (gauge, value) in values {
+        gauge.with_label_values(&[device_name]).set(value);
+    }
+}
+
 fn update_nvme_device(device_path: &Path, device_name: &str) {
     let metrics = metrics();
 
@@ -60,13 +339,15 @@ fn update_nvme_device(device_path: &Path, device_name: &str) {
             .with_label_values(&[device_name, known_state])
             .set(if state == known_state { 1.0 } else { 0.0 });
     }
+
+    update_pcie_link(device_path, device_name);
 }
 
-pub fn update_metrics() {
-    update_metrics_from_path(Path::new("/sys/class/nvme"));
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/class/nvme"), config.nvme.smart_log);
 }
 
-fn update_metrics_from_path(base: &Path) {
+fn update_metrics_from_path(base: &Path, smart_log: bool) {
     let entries = match fs::read_dir(base) {
         Ok(entries) => entries,
         Err(_) => return,
@@ -90,6 +371,9 @@ fn update_metrics_from_path(base: &Path) {
 
         if path.is_dir() {
             update_nvme_device(&path, &name);
+            if smart_log && let Some(log) = read_smart_log(&name) {
+                set_smart_log(metrics(), &name, &log);
+            }
         }
     }
 }
@@ -141,6 +425,45 @@ mod tests {
         update_nvme_device(&nvme_path, "nvme0");
     }
 
+    #[test]
+    fn test_parse_link_speed() {
+        assert_eq!(parse_link_speed("16.0 GT/s PCIe"), Some(16.0));
+        assert_eq!(parse_link_speed("8 GT/s"), Some(8.0));
+        assert_eq!(parse_link_speed("2.5 GT/s PCIe"), Some(2.5));
+        assert_eq!(parse_link_speed("Unknown"), None);
+        assert_eq!(parse_link_speed(""), None);
+    }
+
+    #[test]
+    fn test_update_pcie_link_reads_device_files() {
+        let dir = TempDir::new().unwrap();
+        create_mock_nvme(dir.path(), "nvme97", "Model", "SN", "FW", "live");
+        let pci = dir.path().join("nvme97/device");
+        fs::create_dir_all(&pci).unwrap();
+        fs::write(pci.join("current_link_speed"), "8.0 GT/s PCIe\n").unwrap();
+        fs::write(pci.join("current_link_width"), "2\n").unwrap();
+        fs::write(pci.join("max_link_speed"), "16.0 GT/s PCIe\n").unwrap();
+        fs::write(pci.join("max_link_width"), "4\n").unwrap();
+
+        update_nvme_device(&dir.path().join("nvme97"), "nvme97");
+        let metrics = metrics();
+        let get = |gauge: &GaugeVec| gauge.with_label_values(&["nvme97"]).get();
+        assert_eq!(get(&metrics.pcie_link_speed_gts), 8.0);
+        assert_eq!(get(&metrics.pcie_link_width), 2.0);
+        assert_eq!(get(&metrics.pcie_link_max_speed_gts), 16.0);
+        assert_eq!(get(&metrics.pcie_link_max_width), 4.0);
+
+        // Controllers without a PCI device (e.g. NVMe over fabrics) export nothing
+        create_mock_nvme(dir.path(), "nvme98", "Model", "SN", "FW", "live");
+        update_nvme_device(&dir.path().join("nvme98"), "nvme98");
+        assert!(
+            metrics
+                .pcie_link_width
+                .remove_label_values(&["nvme98"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_update_metrics_from_path_filters_non_nvme() {
         let dir = TempDir::new().unwrap();
@@ -148,6 +471,49 @@ mod tests {
         fs::create_dir_all(dir.path().join("not_nvme")).unwrap();
 
         // Should only process nvme0, not "not_nvme"
-        update_metrics_from_path(dir.path());
+        update_metrics_from_path(dir.path(), false);
+    }
+
+    #[test]
+    fn test_admin_cmd_layout() {
+        assert_eq!(std::mem::size_of::<NvmeAdminCmd>(), 72);
+    }
+
+    #[test]
+    fn test_parse_smart_log() {
+        let mut data = [0u8; 512];
+        data[0] = 0x04;
+        data[1..3].copy_from_slice(&310u16.to_le_bytes());
+        data[3] = 100;
+        data[4] = 10;
+        data[5] = 3;
+        data[32..40].copy_from_slice(&1_000u64.to_le_bytes());
+        data[48..56].copy_from_slice(&2_000u64.to_le_bytes());
+        data[128..136].copy_from_slice(&5_000u64.to_le_bytes());
+        data[144] = 7;
+        data[160] = 1;
+
+        let log = parse_smart_log(&data);
+        assert_eq!(
+            log,
+            SmartLog {
+                critical_warning: 4,
+                temperature_kelvin: 310,
+                available_spare: 100,
+                available_spare_threshold: 10,
+                percentage_used: 3,
+                data_units_read: 1_000,
+                data_units_written: 2_000,
+                power_on_hours: 5_000,
+                unsafe_shutdowns: 7,
+                media_errors: 1,
+            }
+        );
+
+        set_smart_log(metrics(), "nvme96", &log);
+        let get = |gauge: &GaugeVec| gauge.with_label_values(&["nvme96"]).get();
+        assert!((get(&metrics().temperature_celsius) - 36.85).abs() < 1e-9);
+        assert_eq!(get(&metrics().available_spare_threshold_ratio), 0.1);
+        assert_eq!(get(&metrics().data_read_bytes_total), 512_000_000.0);
     }
 }
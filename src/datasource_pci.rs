@@ -0,0 +1,279 @@
+use crate::config::AppConfig;
+use crate::datasource_nvme::parse_link_speed;
+use prometheus::GaugeVec;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const PCI_DEVICES: &str = "/sys/bus/pci/devices";
+
+struct PciMetrics {
+    link_speed_gts: GaugeVec,
+    link_width: GaugeVec,
+    link_max_speed_gts: GaugeVec,
+    link_max_width: GaugeVec,
+    /// device -> (vendor, class) exported last time, so unplugged devices disappear
+    exported: Mutex<HashMap<String, (String, String)>>,
+}
+
+impl PciMetrics {
+    fn new() -> Self {
+        Self {
+            link_speed_gts: prometheus::register_gauge_vec!(
+                "pci_device_link_speed_gts",
+                "Negotiated PCIe link speed in GT/s",
+                &["device", "vendor", "class"]
+            )
+            .expect("register pci_device_link_speed_gts"),
+            link_width: prometheus::register_gauge_vec!(
+                "pci_device_link_width",
+                "Negotiated PCIe link width in lanes",
+                &["device"]
+            )
+            .expect("register pci_device_link_width"),
+            link_max_speed_gts: prometheus::register_gauge_vec!(
+                "pci_device_link_max_speed_gts",
+                "Maximum PCIe link speed supported by the device in GT/s",
+                &["device"]
+            )
+            .expect("register pci_device_link_max_speed_gts"),
+            link_max_width: prometheus::register_gauge_vec!(
+                "pci_device_link_max_width",
+                "Maximum PCIe link width supported by the device in lanes",
+                &["device"]
+            )
+            .expect("register pci_device_link_max_width"),
+            exported: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static PCI_METRICS: OnceLock<PciMetrics> = OnceLock::new();
+
+fn metrics() -> &'static PciMetrics {
+    PCI_METRICS.get_or_init(PciMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Name of the PCI base class in the top byte of sysfs `class` (e.g. "0x020000")
+fn class_name(class: &str) -> Option<&'static str> {
+    let code = u32::from_str_radix(class.strip_prefix("0x")?, 16).ok()?;
+    Some(match code >> 16 {
+        0x00 => "unclassified",
+        0x01 => "storage",
+        0x02 => "network",
+        0x03 => "display",
+        0x04 => "multimedia",
+        0x05 => "memory",
+        0x06 => "bridge",
+        0x07 => "communication",
+        0x08 => "system",
+        0x09 => "input",
+        0x0a => "docking",
+        0x0b => "processor",
+        0x0c => "serial_bus",
+        0x0d => "wireless",
+        0x0e => "intelligent_io",
+        0x0f => "satellite",
+        0x10 => "encryption",
+        0x11 => "signal_processing",
+        0x12 => "accelerator",
+        0x13 => "instrumentation",
+        0x40 => "coprocessor",
+        _ => "other",
+    })
+}
+
+/// Exports the device's link; returns false when it has no usable link attributes
+fn update_device(path: &Path, device: &str, vendor: &str, class: &str) -> bool {
+    let metrics = metrics();
+    let Some(speed) =
+        read_string(&path.join("current_link_speed")).and_then(|value| parse_link_speed(&value))
+    else {
+        return false;
+    };
+    metrics
+        .link_speed_gts
+        .with_label_values(&[device, vendor, class])
+        .set(speed);
+
+    if let Some(speed) =
+        read_string(&path.join("max_link_speed")).and_then(|value| parse_link_speed(&value))
+    {
+        metrics
+            .link_max_speed_gts
+            .with_label_values(&[device])
+            .set(speed);
+    }
+    let widths = [
+        ("current_link_width", &metrics.link_width),
+        ("max_link_width", &metrics.link_max_width),
+    ];
+    for (file, gauge) in widths {
+        // Functions without a link report width 0 or 255
+        if let Some(width) = read_string(&path.join(file)).and_then(|v| v.parse::<u8>().ok())
+            && width > 0
+            && width < 255
+        {
+            gauge.with_label_values(&[device]).set(width as f64);
+        }
+    }
+    true
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path(PCI_DEVICES), config);
+}
+
+fn update_metrics_from_path(base: &Path, config: &AppConfig) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut current = HashMap::new();
+
+    for entry in entries.flatten() {
+        let Ok(device) = entry.file_name().into_string() else {
+            continue;
+        };
+        let path = entry.path();
+        let Some(class) = read_string(&path.join("class"))
+            .as_deref()
+            .and_then(class_name)
+        else {
+            continue;
+        };
+        if !config.is_pci_class_enabled(class) {
+            continue;
+        }
+        let vendor = read_string(&path.join("vendor")).unwrap_or_default();
+        if update_device(&path, &device, &vendor, class) {
+            current.insert(device, (vendor, class.to_string()));
+        }
+    }
+
+    let mut exported = match metrics.exported.lock() {
+        Ok(exported) => exported,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (device, (vendor, class)) in exported.iter() {
+        if current.get(device) == Some(&(vendor.clone(), class.clone())) {
+            continue;
+        }
+        let _ = metrics
+            .link_speed_gts
+            .remove_label_values(&[device, vendor, class]);
+        if !current.contains_key(device) {
+            let _ = metrics.link_width.remove_label_values(&[device]);
+            let _ = metrics.link_max_speed_gts.remove_label_values(&[device]);
+            let _ = metrics.link_max_width.remove_label_values(&[device]);
+        }
+    }
+    *exported = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_device(base: &Path, name: &str, class: &str, link: Option<(&str, &str)>) {
+        let path = base.join(name);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("class"), format!("{class}\n")).unwrap();
+        fs::write(path.join("vendor"), "0x8086\n").unwrap();
+        if let Some((speed, width)) = link {
+            fs::write(path.join("current_link_speed"), format!("{speed}\n")).unwrap();
+            fs::write(path.join("current_link_width"), format!("{width}\n")).unwrap();
+            fs::write(path.join("max_link_speed"), "16.0 GT/s PCIe\n").unwrap();
+            fs::write(path.join("max_link_width"), "16\n").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_class_name() {
+        assert_eq!(class_name("0x020000"), Some("network"));
+        assert_eq!(class_name("0x010802"), Some("storage"));
+        assert_eq!(class_name("0x030000"), Some("display"));
+        assert_eq!(class_name("0xff0000"), Some("other"));
+        assert_eq!(class_name("garbage"), None);
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let dir = TempDir::new().unwrap();
+        // A downtrained NIC: 8 of 16 lanes at 8 GT/s
+        create_mock_device(
+            dir.path(),
+            "0000:91:00.0",
+            "0x020000",
+            Some(("8.0 GT/s PCIe", "8")),
+        );
+        // GPUs are not in the default classes
+        create_mock_device(
+            dir.path(),
+            "0000:92:00.0",
+            "0x030000",
+            Some(("16.0 GT/s PCIe", "16")),
+        );
+        // Legacy PCI storage controller without link attributes
+        create_mock_device(dir.path(), "0000:93:00.0", "0x010000", None);
+        let config = AppConfig::default();
+
+        update_metrics_from_path(dir.path(), &config);
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .link_speed_gts
+                .with_label_values(&["0000:91:00.0", "0x8086", "network"])
+                .get(),
+            8.0
+        );
+        assert_eq!(
+            metrics
+                .link_width
+                .with_label_values(&["0000:91:00.0"])
+                .get(),
+            8.0
+        );
+        assert_eq!(
+            metrics
+                .link_max_width
+                .with_label_values(&["0000:91:00.0"])
+                .get(),
+            16.0
+        );
+        assert!(
+            metrics
+                .link_width
+                .remove_label_values(&["0000:92:00.0"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .link_width
+                .remove_label_values(&["0000:93:00.0"])
+                .is_err()
+        );
+
+        // Unplugged devices disappear
+        fs::remove_dir_all(dir.path().join("0000:91:00.0")).unwrap();
+        update_metrics_from_path(dir.path(), &config);
+        assert!(
+            metrics
+                .link_speed_gts
+                .remove_label_values(&["0000:91:00.0", "0x8086", "network"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .link_max_width
+                .remove_label_values(&["0000:91:00.0"])
+                .is_err()
+        );
+    }
+}
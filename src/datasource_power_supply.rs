@@ -1,7 +1,11 @@
+use crate::config::AppConfig;
 use prometheus::GaugeVec;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
+
+const BATTERY_INFO_FIELDS: [&str; 4] = ["technology", "manufacturer", "model_name", "health"];
 
 struct PowerSupplyMetrics {
     info: GaugeVec,
@@ -14,6 +18,14 @@ struct PowerSupplyMetrics {
     energy_wh: GaugeVec,
     charge_ah: GaugeVec,
     temperature_celsius: GaugeVec,
+    time_to_empty_seconds: GaugeVec,
+    time_to_full_seconds: GaugeVec,
+    charge_control_start_threshold: GaugeVec,
+    charge_control_end_threshold: GaugeVec,
+    cycle_count: GaugeVec,
+    battery_info: GaugeVec,
+    /// Current battery_info label values per supply, replaced when they change
+    battery_info_labels: Mutex<HashMap<String, Vec<String>>>,
 }
 
 impl PowerSupplyMetrics {
@@ -88,6 +100,50 @@ impl PowerSupplyMetrics {
                 &["name"]
             )
             .expect("register power_supply_temperature_celsius"),
+
+            time_to_empty_seconds: prometheus::register_gauge_vec!(
+                "power_supply_time_to_empty_seconds",
+                "Estimated time until the battery is empty in seconds",
+                &["name"]
+            )
+            .expect("register power_supply_time_to_empty_seconds"),
+
+            time_to_full_seconds: prometheus::register_gauge_vec!(
+                "power_supply_time_to_full_seconds",
+                "Estimated time until the battery is fully charged in seconds",
+                &["name"]
+            )
+            .expect("register power_supply_time_to_full_seconds"),
+
+            charge_control_start_threshold: prometheus::register_gauge_vec!(
+                "power_supply_charge_control_start_threshold",
+                "Battery capacity in percent below which charging starts",
+                &["name"]
+            )
+            .expect("register power_supply_charge_control_start_threshold"),
+
+            charge_control_end_threshold: prometheus::register_gauge_vec!(
+                "power_supply_charge_control_end_threshold",
+                "Battery capacity in percent at which charging stops",
+                &["name"]
+            )
+            .expect("register power_supply_charge_control_end_threshold"),
+
+            cycle_count: prometheus::register_gauge_vec!(
+                "power_supply_cycle_count",
+                "Battery charge/discharge cycle count",
+                &["name"]
+            )
+            .expect("register power_supply_cycle_count"),
+
+            battery_info: prometheus::register_gauge_vec!(
+                "power_supply_battery_info",
+                "Battery information (always 1, details in labels)",
+                &["name", "technology", "manufacturer", "model_name", "health"]
+            )
+            .expect("register power_supply_battery_info"),
+
+            battery_info_labels: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -106,6 +162,34 @@ fn read_i64(path: &Path) -> Option<i64> {
     read_string(path)?.parse::<i64>().ok()
 }
 
+fn update_battery_info(metrics: &PowerSupplyMetrics, supply_path: &Path, supply_name: &str) {
+    let values: Vec<Option<String>> = BATTERY_INFO_FIELDS
+        .iter()
+        .map(|field| read_string(&supply_path.join(field)))
+        .collect();
+    if values.iter().all(Option::is_none) {
+        return;
+    }
+
+    let mut labels = vec![supply_name.to_string()];
+    labels.extend(values.into_iter().map(Option::unwrap_or_default));
+    let label_refs: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+    let mut previous = match metrics.battery_info_labels.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // health moves between Good/Overheat/Dead; keep only the current series
+    if let Some(old) = previous.get(supply_name)
+        && *old != labels
+    {
+        let old_refs: Vec<&str> = old.iter().map(String::as_str).collect();
+        let _ = metrics.battery_info.remove_label_values(&old_refs);
+    }
+    metrics.battery_info.with_label_values(&label_refs).set(1.0);
+    previous.insert(supply_name.to_string(), labels);
+}
+
 fn update_power_supply(supply_path: &Path, supply_name: &str) {
     let metrics = metrics();
 
@@ -222,11 +306,48 @@ fn update_power_supply(supply_path: &Path, supply_name: &str) {
             .with_label_values(&[supply_name])
             .set(temp as f64 / 10.0);
     }
+
+    // Time remaining (seconds)
+    if let Some(seconds) = read_i64(&supply_path.join("time_to_empty_now")) {
+        metrics
+            .time_to_empty_seconds
+            .with_label_values(&[supply_name])
+            .set(seconds as f64);
+    }
+    if let Some(seconds) = read_i64(&supply_path.join("time_to_full_now")) {
+        metrics
+            .time_to_full_seconds
+            .with_label_values(&[supply_name])
+            .set(seconds as f64);
+    }
+
+    // Charge limiting thresholds (percent)
+    if let Some(threshold) = read_i64(&supply_path.join("charge_control_start_threshold")) {
+        metrics
+            .charge_control_start_threshold
+            .with_label_values(&[supply_name])
+            .set(threshold as f64);
+    }
+    if let Some(threshold) = read_i64(&supply_path.join("charge_control_end_threshold")) {
+        metrics
+            .charge_control_end_threshold
+            .with_label_values(&[supply_name])
+            .set(threshold as f64);
+    }
+
+    if let Some(cycles) = read_i64(&supply_path.join("cycle_count")) {
+        metrics
+            .cycle_count
+            .with_label_values(&[supply_name])
+            .set(cycles as f64);
+    }
+
+    update_battery_info(metrics, supply_path, supply_name);
 }
 
-pub fn update_metrics() {
-    let base = Path::new("/sys/class/power_supply");
-    let entries = match fs::read_dir(base) {
+pub fn update_metrics(config: &AppConfig) {
+    let base = config.host_path("/sys/class/power_supply");
+    let entries = match fs::read_dir(&base) {
         Ok(entries) => entries,
         Err(_) => return,
     };
@@ -327,6 +448,70 @@ mod tests {
         update_power_supply(&supply, "BAT0");
     }
 
+    #[test]
+    fn test_update_power_supply_with_runtime() {
+        let dir = TempDir::new().unwrap();
+        let supply = create_battery(dir.path(), "BATRT", 60, "Discharging");
+        fs::write(supply.join("time_to_empty_now"), "5400\n").unwrap();
+        fs::write(supply.join("charge_control_start_threshold"), "40\n").unwrap();
+        fs::write(supply.join("charge_control_end_threshold"), "80\n").unwrap();
+        fs::write(supply.join("cycle_count"), "312\n").unwrap();
+
+        update_power_supply(&supply, "BATRT");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .time_to_empty_seconds
+                .with_label_values(&["BATRT"])
+                .get(),
+            5400.0
+        );
+        assert_eq!(
+            metrics
+                .charge_control_end_threshold
+                .with_label_values(&["BATRT"])
+                .get(),
+            80.0
+        );
+        assert_eq!(
+            metrics.cycle_count.with_label_values(&["BATRT"]).get(),
+            312.0
+        );
+    }
+
+    #[test]
+    fn test_update_battery_info_tracks_health() {
+        use prometheus::core::Collector;
+
+        let dir = TempDir::new().unwrap();
+        let supply = create_battery(dir.path(), "BATINFO", 90, "Full");
+        fs::write(supply.join("technology"), "Li-ion\n").unwrap();
+        fs::write(supply.join("model_name"), "5B10W13930\n").unwrap();
+        fs::write(supply.join("health"), "Good\n").unwrap();
+        update_power_supply(&supply, "BATINFO");
+
+        fs::write(supply.join("health"), "Overheat\n").unwrap();
+        update_power_supply(&supply, "BATINFO");
+
+        let families = metrics().battery_info.collect();
+        let series: Vec<Vec<(String, String)>> = families[0]
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .map(|l| (l.name().to_string(), l.value().to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|labels| labels.iter().any(|(_, v)| v == "BATINFO"))
+            .collect();
+        assert_eq!(series.len(), 1);
+        assert!(series[0].contains(&("health".to_string(), "Overheat".to_string())));
+        assert!(series[0].contains(&("manufacturer".to_string(), String::new())));
+    }
+
     #[test]
     fn test_update_power_supply_missing_type() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,176 @@
+use crate::config::AppConfig;
+use procfs::WithCurrentSystemInfo;
+use prometheus::{Gauge, GaugeVec};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+/// Process states from /proc/<pid>/stat; all are exported so emptied states read 0
+const STATES: &[char] = &['R', 'S', 'D', 'Z', 'T', 't', 'I', 'X'];
+
+/// Upper bound for [processes] top_n, keeping the comm label bounded
+const MAX_TOP_N: usize = 50;
+
+struct ProcessesMetrics {
+    state: GaugeVec,
+    threads_total: Gauge,
+    top_memory_bytes: GaugeVec,
+    top_comms: Mutex<HashSet<String>>,
+}
+
+impl ProcessesMetrics {
+    fn new() -> Self {
+        Self {
+            state: prometheus::register_gauge_vec!(
+                "processes_state",
+                "Number of processes in each scheduler state",
+                &["state"]
+            )
+            .expect("register processes_state"),
+            threads_total: prometheus::register_gauge!(
+                "processes_threads_total",
+                "Number of threads across all processes"
+            )
+            .expect("register processes_threads_total"),
+            top_memory_bytes: prometheus::register_gauge_vec!(
+                "process_top_memory_bytes",
+                "Resident memory of the largest process groups by command name",
+                &["comm"]
+            )
+            .expect("register process_top_memory_bytes"),
+            top_comms: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static PROCESSES_METRICS: OnceLock<ProcessesMetrics> = OnceLock::new();
+
+fn metrics() -> &'static ProcessesMetrics {
+    PROCESSES_METRICS.get_or_init(ProcessesMetrics::new)
+}
+
+#[derive(Debug, Default)]
+struct ProcessSummary {
+    states: HashMap<char, u64>,
+    threads: u64,
+    /// Resident bytes summed per comm, so same-named workers count together
+    memory_by_comm: HashMap<String, u64>,
+}
+
+impl ProcessSummary {
+    fn add(&mut self, state: char, threads: i64, comm: &str, rss_bytes: u64) {
+        *self.states.entry(state).or_default() += 1;
+        self.threads += threads.max(0) as u64;
+        *self.memory_by_comm.entry(comm.to_string()).or_default() += rss_bytes;
+    }
+
+    /// Largest `n` comm groups by resident memory, ties broken by name
+    fn top_memory(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut groups: Vec<(&str, u64)> = self
+            .memory_by_comm
+            .iter()
+            .filter(|(_, bytes)| **bytes > 0)
+            .map(|(comm, bytes)| (comm.as_str(), *bytes))
+            .collect();
+        groups.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        groups.truncate(n);
+        groups
+    }
+}
+
+fn read_summary() -> Option<ProcessSummary> {
+    let processes = procfs::process::all_processes().ok()?;
+    let mut summary = ProcessSummary::default();
+    for process in processes {
+        // Processes can exit between the directory listing and reading stat
+        let Ok(stat) = process.and_then(|process| process.stat()) else {
+            continue;
+        };
+        summary.add(
+            stat.state,
+            stat.num_threads,
+            &stat.comm,
+            stat.rss_bytes().get(),
+        );
+    }
+    Some(summary)
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let Some(summary) = read_summary() else {
+        return;
+    };
+    let metrics = metrics();
+
+    for state in STATES {
+        let count = summary.states.get(state).copied().unwrap_or(0);
+        metrics
+            .state
+            .with_label_values(&[state.to_string().as_str()])
+            .set(count as f64);
+    }
+    metrics.threads_total.set(summary.threads as f64);
+
+    let mut current = HashSet::new();
+    for (comm, bytes) in summary.top_memory(config.processes.top_n.min(MAX_TOP_N)) {
+        metrics
+            .top_memory_bytes
+            .with_label_values(&[comm])
+            .set(bytes as f64);
+        current.insert(comm.to_string());
+    }
+
+    let mut top_comms = match metrics.top_comms.lock() {
+        Ok(top_comms) => top_comms,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Drop groups that fell out of the top N
+    for comm in top_comms.difference(&current) {
+        let _ = metrics.top_memory_bytes.remove_label_values(&[comm]);
+    }
+    *top_comms = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_counts_states_and_threads() {
+        let mut summary = ProcessSummary::default();
+        summary.add('S', 1, "sshd", 4096);
+        summary.add('R', 8, "postgres", 1 << 20);
+        summary.add('S', 4, "postgres", 3 << 20);
+        summary.add('Z', 0, "defunct", 0);
+        summary.add('I', 1, "kworker/0:1", 0);
+
+        assert_eq!(summary.states[&'S'], 2);
+        assert_eq!(summary.states[&'Z'], 1);
+        assert_eq!(summary.states.get(&'D'), None);
+        assert_eq!(summary.threads, 14);
+    }
+
+    #[test]
+    fn test_top_memory_groups_by_comm() {
+        let mut summary = ProcessSummary::default();
+        summary.add('S', 1, "sshd", 4096);
+        summary.add('S', 1, "postgres", 1 << 20);
+        summary.add('S', 1, "postgres", 3 << 20);
+        summary.add('S', 1, "java", 2 << 20);
+        summary.add('I', 1, "kworker/0:1", 0);
+
+        assert_eq!(
+            summary.top_memory(2),
+            vec![("postgres", 4 << 20), ("java", 2 << 20)]
+        );
+        // Kernel threads without resident memory are never reported
+        assert_eq!(summary.top_memory(10).len(), 3);
+        assert!(summary.top_memory(0).is_empty());
+    }
+
+    #[test]
+    fn test_read_summary_sees_this_process() {
+        let summary = read_summary().unwrap();
+        assert!(summary.threads > 0);
+        assert!(summary.states.values().sum::<u64>() > 0);
+    }
+}
@@ -1,11 +1,76 @@
 use crate::config::AppConfig;
+use crate::counter_reset;
+use crate::cpu_aggregate;
+use crate::datasource_numa::parse_cpulist;
 use procfs::net::{TcpState, UdpState};
 use procfs::prelude::{Current, CurrentSI};
 use procfs::{CpuTime, KernelStats, LoadAverage, Meminfo, Uptime};
-use prometheus::{Gauge, GaugeVec};
+use prometheus::{Gauge, GaugeVec, IntCounter, IntCounterVec};
 use std::collections::HashMap;
 use std::fs;
-use std::sync::OnceLock;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// /proc/vmstat keys that only ever increase (events and cumulative page counts);
+/// everything else is a current level
+const VMSTAT_COUNTER_PREFIXES: &[&str] = &[
+    "pg",
+    "pswp",
+    "allocstall_",
+    "numa_",
+    "workingset_refault_",
+    "workingset_activate_",
+    "workingset_restore_",
+    "workingset_nodereclaim",
+    "zone_reclaim_",
+    "slabs_scanned",
+    "kswapd_",
+    "pageoutrun",
+    "drop_",
+    "oom_kill",
+    "compact_",
+    "htlb_buddy_alloc_",
+    "unevictable_pgs_",
+    "thp_",
+    "balloon_",
+    "swap_ra",
+    "swpin_zero",
+    "swpout_zero",
+    "ksm_swpin_copy",
+    "cow_ksm",
+    "zswp",
+    "direct_map_level",
+    "nr_dirtied",
+    "nr_written",
+    "nr_throttled_written",
+    "nr_vmscan_write",
+    "nr_vmscan_immediate_reclaim",
+    "nr_foll_pin_",
+];
+
+const CPU_ONLINE_PATH: &str = "/sys/devices/system/cpu/online";
+const KHUGEPAGED_DIR: &str = "/sys/kernel/mm/transparent_hugepage/khugepaged";
+
+/// /proc/stat cpu columns, in order, that add up to the elapsed time; guest
+/// and guest_nice are already included in user and nice
+const CPU_USAGE_MODES: &[&str] = &[
+    "user", "nice", "system", "idle", "iowait", "irq", "softirq", "steal",
+];
+
+/// Every mode label set_cpu_time may export
+const CPU_TIME_MODES: &[&str] = &[
+    "user",
+    "nice",
+    "system",
+    "idle",
+    "iowait",
+    "irq",
+    "softirq",
+    "steal",
+    "guest",
+    "guest_nice",
+];
 
 struct ProcfsMetrics {
     uptime_seconds: Gauge,
@@ -13,17 +78,51 @@ struct ProcfsMetrics {
     load_average: GaugeVec,
     load_processes: GaugeVec,
     cpu_seconds_total: GaugeVec,
+    cpu_usage_ratio: GaugeVec,
+    /// cpu label -> mode ticks from the previous scrape
+    cpu_previous: Mutex<HashMap<String, Vec<u64>>>,
     cpu_context_switches_total: Gauge,
+    cpu_interrupts_total: Gauge,
+    cpu_softirqs_total: Gauge,
     cpu_boot_time_seconds: Gauge,
     processes_forked_total: Gauge,
     processes_running: Gauge,
     processes_blocked: Gauge,
+    online_cpu_count: Gauge,
+    cpu_runqueue_length: Gauge,
     meminfo: GaugeVec,
+    memory_used_bytes: Gauge,
+    memory_utilization_ratio: Gauge,
+    swap_used_bytes: Gauge,
     vmstat: GaugeVec,
+    vmstat_events_total: IntCounterVec,
+    /// vmstat key -> last raw counter value
+    vmstat_previous: Mutex<HashMap<String, u64>>,
+    thp_anon_bytes: Gauge,
+    thp_fault_alloc_total: IntCounter,
+    thp_fault_fallback_total: IntCounter,
+    thp_collapse_alloc_total: IntCounter,
+    thp_collapse_alloc_failed_total: IntCounter,
+    thp_khugepaged_pages_collapsed_total: IntCounter,
+    thp_khugepaged_full_scans_total: IntCounter,
+    /// vmstat key or khugepaged file -> last raw THP counter value
+    thp_previous: Mutex<HashMap<String, u64>>,
     diskstats: GaugeVec,
+    diskstats_bytes_read: GaugeVec,
+    diskstats_bytes_written: GaugeVec,
+    diskstats_read_latency_seconds: GaugeVec,
+    diskstats_write_latency_seconds: GaugeVec,
+    /// device -> I/O counts and times from the previous scrape
+    diskstats_previous: Mutex<HashMap<String, DiskLatencySample>>,
     netdev: GaugeVec,
+    netdev_receive_bytes_per_second: GaugeVec,
+    netdev_transmit_bytes_per_second: GaugeVec,
+    /// interface -> byte counters from the previous scrape
+    netdev_previous: Mutex<HashMap<String, NetdevSample>>,
     tcp_sockets: GaugeVec,
     udp_sockets: GaugeVec,
+    tcp6_sockets: GaugeVec,
+    udp6_sockets: GaugeVec,
     arp_entries: GaugeVec,
     snmp: GaugeVec,
     netstat: GaugeVec,
@@ -60,11 +159,28 @@ impl ProcfsMetrics {
                 &["cpu", "mode"]
             )
             .expect("register cpu_seconds_total"),
+            cpu_usage_ratio: prometheus::register_gauge_vec!(
+                "cpu_usage_ratio",
+                "Fraction of CPU time spent in each mode since the previous scrape",
+                &["cpu", "mode"]
+            )
+            .expect("register cpu_usage_ratio"),
+            cpu_previous: Mutex::new(HashMap::new()),
             cpu_context_switches_total: prometheus::register_gauge!(
                 "cpu_context_switches_total",
                 "Number of context switches since boot"
             )
             .expect("register cpu_context_switches_total"),
+            cpu_interrupts_total: prometheus::register_gauge!(
+                "cpu_interrupts_total",
+                "Number of interrupts serviced since boot, from the intr line of /proc/stat"
+            )
+            .expect("register cpu_interrupts_total"),
+            cpu_softirqs_total: prometheus::register_gauge!(
+                "cpu_softirqs_total",
+                "Number of softirqs serviced since boot, from the softirq line of /proc/stat"
+            )
+            .expect("register cpu_softirqs_total"),
             cpu_boot_time_seconds: prometheus::register_gauge!(
                 "cpu_boot_time_seconds",
                 "Boot time, in seconds since the epoch"
@@ -85,30 +201,136 @@ impl ProcfsMetrics {
                 "Number of processes blocked waiting for I/O"
             )
             .expect("register processes_blocked"),
+            online_cpu_count: prometheus::register_gauge!(
+                "online_cpu_count",
+                "Number of online CPUs"
+            )
+            .expect("register online_cpu_count"),
+            cpu_runqueue_length: prometheus::register_gauge!(
+                "cpu_runqueue_length",
+                "Runnable tasks per online CPU right now (processes_running / online_cpu_count)"
+            )
+            .expect("register cpu_runqueue_length"),
             meminfo: prometheus::register_gauge_vec!(
                 "meminfo",
                 "Raw values from /proc/meminfo (bytes unless otherwise noted)",
                 &["field"]
             )
             .expect("register meminfo"),
+            memory_used_bytes: prometheus::register_gauge!(
+                "memory_used_bytes",
+                "Memory in use: MemTotal - MemAvailable (MemTotal - MemFree - Buffers - Cached before Linux 3.14)"
+            )
+            .expect("register memory_used_bytes"),
+            memory_utilization_ratio: prometheus::register_gauge!(
+                "memory_utilization_ratio",
+                "memory_used_bytes as a fraction of MemTotal"
+            )
+            .expect("register memory_utilization_ratio"),
+            swap_used_bytes: prometheus::register_gauge!(
+                "swap_used_bytes",
+                "Swap in use: SwapTotal - SwapFree"
+            )
+            .expect("register swap_used_bytes"),
             vmstat: prometheus::register_gauge_vec!(
                 "vmstat",
-                "Raw values from /proc/vmstat",
+                "Current levels from /proc/vmstat",
                 &["field"]
             )
             .expect("register vmstat"),
+            vmstat_events_total: prometheus::register_int_counter_vec!(
+                "vmstat_events_total",
+                "Monotonic counters from /proc/vmstat",
+                &["field"]
+            )
+            .expect("register vmstat_events_total"),
+            vmstat_previous: Mutex::new(HashMap::new()),
+            thp_anon_bytes: prometheus::register_gauge!(
+                "thp_anon_bytes",
+                "Anonymous memory backed by transparent huge pages (AnonHugePages)"
+            )
+            .expect("register thp_anon_bytes"),
+            thp_fault_alloc_total: prometheus::register_int_counter!(
+                "thp_fault_alloc_total",
+                "Page faults served with a transparent huge page"
+            )
+            .expect("register thp_fault_alloc_total"),
+            thp_fault_fallback_total: prometheus::register_int_counter!(
+                "thp_fault_fallback_total",
+                "Page faults that wanted a transparent huge page but fell back to small pages"
+            )
+            .expect("register thp_fault_fallback_total"),
+            thp_collapse_alloc_total: prometheus::register_int_counter!(
+                "thp_collapse_alloc_total",
+                "Transparent huge pages allocated by khugepaged to collapse small pages"
+            )
+            .expect("register thp_collapse_alloc_total"),
+            thp_collapse_alloc_failed_total: prometheus::register_int_counter!(
+                "thp_collapse_alloc_failed_total",
+                "khugepaged collapses that failed to allocate a huge page"
+            )
+            .expect("register thp_collapse_alloc_failed_total"),
+            thp_khugepaged_pages_collapsed_total: prometheus::register_int_counter!(
+                "thp_khugepaged_pages_collapsed_total",
+                "Huge pages collapsed by khugepaged, from khugepaged/pages_collapsed"
+            )
+            .expect("register thp_khugepaged_pages_collapsed_total"),
+            thp_khugepaged_full_scans_total: prometheus::register_int_counter!(
+                "thp_khugepaged_full_scans_total",
+                "Complete passes of khugepaged over all mappings, from khugepaged/full_scans"
+            )
+            .expect("register thp_khugepaged_full_scans_total"),
+            thp_previous: Mutex::new(HashMap::new()),
             diskstats: prometheus::register_gauge_vec!(
                 "diskstats",
                 "Raw disk statistics from /proc/diskstats",
                 &["device", "field"]
             )
             .expect("register diskstats"),
+            diskstats_bytes_read: prometheus::register_gauge_vec!(
+                "diskstats_bytes_read",
+                "Bytes read from the device, from /proc/diskstats sectors",
+                &["device"]
+            )
+            .expect("register diskstats_bytes_read"),
+            diskstats_bytes_written: prometheus::register_gauge_vec!(
+                "diskstats_bytes_written",
+                "Bytes written to the device, from /proc/diskstats sectors",
+                &["device"]
+            )
+            .expect("register diskstats_bytes_written"),
+            diskstats_read_latency_seconds: prometheus::register_gauge_vec!(
+                "diskstats_read_latency_seconds",
+                "Average time per completed read since the previous scrape, from /proc/diskstats",
+                &["device"]
+            )
+            .expect("register diskstats_read_latency_seconds"),
+            diskstats_write_latency_seconds: prometheus::register_gauge_vec!(
+                "diskstats_write_latency_seconds",
+                "Average time per completed write since the previous scrape, from /proc/diskstats",
+                &["device"]
+            )
+            .expect("register diskstats_write_latency_seconds"),
+            diskstats_previous: Mutex::new(HashMap::new()),
             netdev: prometheus::register_gauge_vec!(
                 "netdev",
                 "Raw network device stats from /proc/net/dev",
                 &["interface", "field"]
             )
             .expect("register netdev"),
+            netdev_receive_bytes_per_second: prometheus::register_gauge_vec!(
+                "netdev_receive_bytes_per_second",
+                "Bytes received per second since the previous scrape",
+                &["interface"]
+            )
+            .expect("register netdev_receive_bytes_per_second"),
+            netdev_transmit_bytes_per_second: prometheus::register_gauge_vec!(
+                "netdev_transmit_bytes_per_second",
+                "Bytes transmitted per second since the previous scrape",
+                &["interface"]
+            )
+            .expect("register netdev_transmit_bytes_per_second"),
+            netdev_previous: Mutex::new(HashMap::new()),
             tcp_sockets: prometheus::register_gauge_vec!(
                 "tcp_sockets",
                 "TCP socket counts by state from /proc/net/tcp",
@@ -121,6 +343,18 @@ impl ProcfsMetrics {
                 &["state"]
             )
             .expect("register udp_sockets"),
+            tcp6_sockets: prometheus::register_gauge_vec!(
+                "tcp6_sockets",
+                "TCP socket counts by state from /proc/net/tcp6",
+                &["state"]
+            )
+            .expect("register tcp6_sockets"),
+            udp6_sockets: prometheus::register_gauge_vec!(
+                "udp6_sockets",
+                "UDP socket counts by state from /proc/net/udp6",
+                &["state"]
+            )
+            .expect("register udp6_sockets"),
             arp_entries: prometheus::register_gauge_vec!(
                 "arp_entries",
                 "ARP table entries by device from /proc/net/arp",
@@ -205,7 +439,117 @@ fn set_meminfo_optional(metrics: &GaugeVec, name: &str, value: Option<u64>) {
     }
 }
 
+fn is_vmstat_counter(key: &str) -> bool {
+    VMSTAT_COUNTER_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+}
+
+fn update_vmstat(metrics: &ProcfsMetrics, vmstat: &HashMap<String, i64>, config: &AppConfig) {
+    let mut previous = match metrics.vmstat_previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (key, value) in vmstat {
+        if !config.is_vmstat_field_enabled(key) {
+            continue;
+        }
+        if !is_vmstat_counter(key) {
+            metrics
+                .vmstat
+                .with_label_values(&[key.as_str()])
+                .set(*value as f64);
+            continue;
+        }
+        let delta = counter_delta(&mut previous, key, u64::try_from(*value).unwrap_or(0));
+        metrics
+            .vmstat_events_total
+            .with_label_values(&[key.as_str()])
+            .inc_by(delta);
+    }
+}
+
+/// Records `value` as the latest raw reading of `key` and returns the increase.
+fn counter_delta(previous: &mut HashMap<String, u64>, key: &str, value: u64) -> u64 {
+    let last = previous.insert(key.to_string(), value).unwrap_or(0);
+    counter_reset::counter_delta(value, last)
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// THP activity is exported whatever vmstat_fields selects; kernels built
+/// without THP have neither the vmstat keys nor the khugepaged directory.
+fn update_thp(metrics: &ProcfsMetrics, vmstat: &HashMap<String, i64>, khugepaged: &Path) {
+    let mut previous = match metrics.thp_previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let vmstat_counters = [
+        ("thp_fault_alloc", &metrics.thp_fault_alloc_total),
+        ("thp_fault_fallback", &metrics.thp_fault_fallback_total),
+        ("thp_collapse_alloc", &metrics.thp_collapse_alloc_total),
+        (
+            "thp_collapse_alloc_failed",
+            &metrics.thp_collapse_alloc_failed_total,
+        ),
+    ];
+    for (key, counter) in vmstat_counters {
+        if let Some(value) = vmstat.get(key) {
+            counter.inc_by(counter_delta(
+                &mut previous,
+                key,
+                u64::try_from(*value).unwrap_or(0),
+            ));
+        }
+    }
+
+    let khugepaged_counters = [
+        (
+            "pages_collapsed",
+            &metrics.thp_khugepaged_pages_collapsed_total,
+        ),
+        ("full_scans", &metrics.thp_khugepaged_full_scans_total),
+    ];
+    for (file, counter) in khugepaged_counters {
+        if let Some(value) = read_u64(&khugepaged.join(file)) {
+            counter.inc_by(counter_delta(&mut previous, file, value));
+        }
+    }
+}
+
+/// Kernels without MemAvailable (before 3.14) approximate it from free,
+/// buffers and page cache.
+fn memory_used_bytes(meminfo: &Meminfo) -> u64 {
+    match meminfo.mem_available {
+        Some(available) => meminfo.mem_total.saturating_sub(available),
+        None => meminfo
+            .mem_total
+            .saturating_sub(meminfo.mem_free)
+            .saturating_sub(meminfo.buffers)
+            .saturating_sub(meminfo.cached),
+    }
+}
+
+fn update_memory_usage(metrics: &ProcfsMetrics, meminfo: &Meminfo) {
+    let used = memory_used_bytes(meminfo);
+    metrics.memory_used_bytes.set(used as f64);
+    if meminfo.mem_total > 0 {
+        metrics
+            .memory_utilization_ratio
+            .set(used as f64 / meminfo.mem_total as f64);
+    }
+    metrics
+        .swap_used_bytes
+        .set(meminfo.swap_total.saturating_sub(meminfo.swap_free) as f64);
+}
+
 fn update_meminfo(metrics: &ProcfsMetrics, meminfo: &Meminfo) {
+    update_memory_usage(metrics, meminfo);
+    if let Some(anon_hugepages) = meminfo.anon_hugepages {
+        metrics.thp_anon_bytes.set(anon_hugepages as f64);
+    }
     set_meminfo_value(&metrics.meminfo, "mem_total", meminfo.mem_total);
     set_meminfo_value(&metrics.meminfo, "mem_free", meminfo.mem_free);
     set_meminfo_optional(&metrics.meminfo, "mem_available", meminfo.mem_available);
@@ -283,12 +627,25 @@ fn update_meminfo(metrics: &ProcfsMetrics, meminfo: &Meminfo) {
     set_meminfo_optional(&metrics.meminfo, "z_swapped", meminfo.z_swapped);
 }
 
-fn update_kernel_stats(metrics: &ProcfsMetrics, stats: &KernelStats) {
+fn update_kernel_stats(metrics: &ProcfsMetrics, stats: &KernelStats, per_cpu: bool) {
     set_cpu_time(&metrics.cpu_seconds_total, "total", &stats.total);
-    for (idx, cpu) in stats.cpu_time.iter().enumerate() {
-        let label = format!("cpu{}", idx);
-        set_cpu_time(&metrics.cpu_seconds_total, &label, cpu);
+    let labels: Vec<String> = (0..stats.cpu_time.len())
+        .map(|idx| format!("cpu{idx}"))
+        .collect();
+    if per_cpu {
+        for (label, cpu) in labels.iter().zip(&stats.cpu_time) {
+            set_cpu_time(&metrics.cpu_seconds_total, label, cpu);
+        }
     }
+    cpu_aggregate::remove_other_mode(per_cpu, labels.iter().map(String::as_str), |cpu| {
+        // The total is exported in both modes
+        if cpu == "total" {
+            return;
+        }
+        for mode in CPU_TIME_MODES {
+            let _ = metrics.cpu_seconds_total.remove_label_values(&[cpu, mode]);
+        }
+    });
 
     metrics.cpu_context_switches_total.set(stats.ctxt as f64);
     metrics.cpu_boot_time_seconds.set(stats.btime as f64);
@@ -302,78 +659,373 @@ fn update_kernel_stats(metrics: &ProcfsMetrics, stats: &KernelStats) {
     }
 }
 
-fn update_diskstats(metrics: &ProcfsMetrics, stats: &[procfs::DiskStat], config: &AppConfig) {
-    for stat in stats {
-        let device = stat.name.as_str();
-        if config.ignore_loop_devices && device.starts_with("loop") {
+/// Online CPUs from the sysfs cpulist, falling back to the per-CPU lines of
+/// /proc/stat (which only lists online CPUs) when sysfs is unavailable.
+fn online_cpu_count(cpulist: Option<&str>, stat_cpus: usize) -> usize {
+    match cpulist.map(|list| parse_cpulist(list).len()) {
+        Some(count) if count > 0 => count,
+        _ => stat_cpus,
+    }
+}
+
+fn update_runqueue(metrics: &ProcfsMetrics, procs_running: Option<u32>, online_cpus: usize) {
+    if online_cpus == 0 {
+        return;
+    }
+    metrics.online_cpu_count.set(online_cpus as f64);
+    if let Some(running) = procs_running {
+        metrics
+            .cpu_runqueue_length
+            .set(f64::from(running) / online_cpus as f64);
+    }
+}
+
+/// First column (the total) of a /proc/stat line such as `intr 680178 0 0 ...`
+fn stat_line_total(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let mut columns = line.split_whitespace();
+        if columns.next() != Some(key) {
+            return None;
+        }
+        columns.next().and_then(|value| value.parse().ok())
+    })
+}
+
+/// Mode ticks per `cpu`/`cpuN` line of /proc/stat, labelled like
+/// cpu_seconds_total; offline CPUs have no line.
+fn parse_cpu_ticks(contents: &str) -> Vec<(String, Vec<u64>)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let id = name.strip_prefix("cpu")?;
+            let label = if id.is_empty() {
+                "total".to_string()
+            } else if id.bytes().all(|b| b.is_ascii_digit()) {
+                name.to_string()
+            } else {
+                return None;
+            };
+            let ticks = fields
+                .take(CPU_USAGE_MODES.len())
+                .map_while(|field| field.parse().ok())
+                .collect();
+            Some((label, ticks))
+        })
+        .collect()
+}
+
+/// Share of each mode in the ticks elapsed between two snapshots; None when
+/// no time has passed. Counters that went backwards count as zero.
+fn cpu_usage_ratios(previous: &[u64], current: &[u64]) -> Option<Vec<f64>> {
+    let deltas: Vec<u64> = current
+        .iter()
+        .zip(previous)
+        .map(|(now, before)| now.saturating_sub(*before))
+        .collect();
+    let total: u64 = deltas.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    Some(
+        deltas
+            .iter()
+            .map(|delta| *delta as f64 / total as f64)
+            .collect(),
+    )
+}
+
+fn update_cpu_usage(
+    usage: &GaugeVec,
+    previous: &mut HashMap<String, Vec<u64>>,
+    contents: &str,
+    per_cpu: bool,
+) {
+    let mut current = HashMap::new();
+    for (cpu, ticks) in parse_cpu_ticks(contents) {
+        // Skipped CPUs are dropped below like offline ones
+        if !per_cpu && cpu != "total" {
             continue;
         }
-        let diskstats = &metrics.diskstats;
-        diskstats
-            .with_label_values(&[device, "reads"])
-            .set(stat.reads as f64);
-        diskstats
-            .with_label_values(&[device, "reads_merged"])
-            .set(stat.merged as f64);
-        diskstats
-            .with_label_values(&[device, "sectors_read"])
-            .set(stat.sectors_read as f64);
-        diskstats
-            .with_label_values(&[device, "time_reading_ms"])
-            .set(stat.time_reading as f64);
-        diskstats
-            .with_label_values(&[device, "writes"])
-            .set(stat.writes as f64);
+        // A CPU seen for the first time (startup or hotplug) has no baseline yet
+        if let Some(before) = previous.remove(&cpu)
+            && before.len() == ticks.len()
+            && let Some(ratios) = cpu_usage_ratios(&before, &ticks)
+        {
+            for (mode, ratio) in CPU_USAGE_MODES.iter().zip(ratios) {
+                usage.with_label_values(&[cpu.as_str(), mode]).set(ratio);
+            }
+        }
+        current.insert(cpu, ticks);
+    }
+
+    // CPUs that went offline
+    for cpu in previous.keys() {
+        for mode in CPU_USAGE_MODES {
+            let _ = usage.remove_label_values(&[cpu.as_str(), mode]);
+        }
+    }
+    *previous = current;
+}
+
+// procfs' KernelStats skips the intr and softirq lines
+fn update_interrupt_totals(metrics: &ProcfsMetrics, contents: &str) {
+    if let Some(total) = stat_line_total(contents, "intr") {
+        metrics.cpu_interrupts_total.set(total as f64);
+    }
+    if let Some(total) = stat_line_total(contents, "softirq") {
+        metrics.cpu_softirqs_total.set(total as f64);
+    }
+}
+
+/// /proc/diskstats counts 512-byte sectors whatever the device's hw_sector_size
+/// (see Documentation/block/stat.rst), so bytes never depend on the device
+const DISKSTATS_SECTOR_BYTES: u64 = 512;
+
+fn set_raw_diskstats(diskstats: &GaugeVec, device: &str, stat: &procfs::DiskStat) {
+    diskstats
+        .with_label_values(&[device, "reads"])
+        .set(stat.reads as f64);
+    diskstats
+        .with_label_values(&[device, "reads_merged"])
+        .set(stat.merged as f64);
+    diskstats
+        .with_label_values(&[device, "sectors_read"])
+        .set(stat.sectors_read as f64);
+    diskstats
+        .with_label_values(&[device, "time_reading_ms"])
+        .set(stat.time_reading as f64);
+    diskstats
+        .with_label_values(&[device, "writes"])
+        .set(stat.writes as f64);
+    diskstats
+        .with_label_values(&[device, "writes_merged"])
+        .set(stat.writes_merged as f64);
+    diskstats
+        .with_label_values(&[device, "sectors_written"])
+        .set(stat.sectors_written as f64);
+    diskstats
+        .with_label_values(&[device, "time_writing_ms"])
+        .set(stat.time_writing as f64);
+    diskstats
+        .with_label_values(&[device, "in_progress"])
+        .set(stat.in_progress as f64);
+    diskstats
+        .with_label_values(&[device, "time_in_progress_ms"])
+        .set(stat.time_in_progress as f64);
+    diskstats
+        .with_label_values(&[device, "weighted_time_in_progress_ms"])
+        .set(stat.weighted_time_in_progress as f64);
+
+    if let Some(value) = stat.discards {
         diskstats
-            .with_label_values(&[device, "writes_merged"])
-            .set(stat.writes_merged as f64);
+            .with_label_values(&[device, "discards"])
+            .set(value as f64);
+    }
+    if let Some(value) = stat.discards_merged {
         diskstats
-            .with_label_values(&[device, "sectors_written"])
-            .set(stat.sectors_written as f64);
+            .with_label_values(&[device, "discards_merged"])
+            .set(value as f64);
+    }
+    if let Some(value) = stat.sectors_discarded {
         diskstats
-            .with_label_values(&[device, "time_writing_ms"])
-            .set(stat.time_writing as f64);
+            .with_label_values(&[device, "sectors_discarded"])
+            .set(value as f64);
+    }
+    if let Some(value) = stat.time_discarding {
         diskstats
-            .with_label_values(&[device, "in_progress"])
-            .set(stat.in_progress as f64);
+            .with_label_values(&[device, "time_discarding_ms"])
+            .set(value as f64);
+    }
+    if let Some(value) = stat.flushes {
         diskstats
-            .with_label_values(&[device, "time_in_progress_ms"])
-            .set(stat.time_in_progress as f64);
+            .with_label_values(&[device, "flushes"])
+            .set(value as f64);
+    }
+    if let Some(value) = stat.time_flushing {
         diskstats
-            .with_label_values(&[device, "weighted_time_in_progress_ms"])
-            .set(stat.weighted_time_in_progress as f64);
+            .with_label_values(&[device, "time_flushing_ms"])
+            .set(value as f64);
+    }
+}
+
+/// Metrics derived from /proc/diskstats, which is skipped when all are disabled
+const DISKSTATS_METRICS: [&str; 5] = [
+    "diskstats",
+    "diskstats_bytes_read",
+    "diskstats_bytes_written",
+    "diskstats_read_latency_seconds",
+    "diskstats_write_latency_seconds",
+];
+
+/// Completed I/Os and milliseconds spent on them for one device at one scrape
+struct DiskLatencySample {
+    reads: u64,
+    time_reading_ms: u64,
+    writes: u64,
+    time_writing_ms: u64,
+}
 
-        if let Some(value) = stat.discards {
-            diskstats
-                .with_label_values(&[device, "discards"])
-                .set(value as f64);
+/// Average seconds per I/O between two readings ("await"); None when no I/O
+/// completed or a counter went backwards (the device was recreated)
+fn average_latency_seconds(ios: (u64, u64), time_ms: (u64, u64)) -> Option<f64> {
+    let (current_ios, previous_ios) = ios;
+    let (current_ms, previous_ms) = time_ms;
+    let ios = current_ios
+        .checked_sub(previous_ios)
+        .filter(|ios| *ios > 0)?;
+    let ms = current_ms.checked_sub(previous_ms)?;
+    Some(ms as f64 / ios as f64 / 1000.0)
+}
+
+fn update_disk_latency(
+    read: &GaugeVec,
+    write: &GaugeVec,
+    previous: &mut HashMap<String, DiskLatencySample>,
+    current: HashMap<String, DiskLatencySample>,
+) {
+    for (device, sample) in &current {
+        // A device seen for the first time has no baseline yet
+        let Some(before) = previous.remove(device) else {
+            continue;
+        };
+        let labels = [device.as_str()];
+        let latencies = [
+            (
+                read,
+                (sample.reads, before.reads),
+                (sample.time_reading_ms, before.time_reading_ms),
+            ),
+            (
+                write,
+                (sample.writes, before.writes),
+                (sample.time_writing_ms, before.time_writing_ms),
+            ),
+        ];
+        for (gauge, ios, time_ms) in latencies {
+            // Idle intervals have no latency; drop the series instead of
+            // repeating an old value
+            match average_latency_seconds(ios, time_ms) {
+                Some(seconds) => gauge.with_label_values(&labels).set(seconds),
+                None => {
+                    let _ = gauge.remove_label_values(&labels);
+                }
+            }
+        }
+    }
+
+    // Devices that were removed
+    for device in previous.keys() {
+        let _ = read.remove_label_values(&[device.as_str()]);
+        let _ = write.remove_label_values(&[device.as_str()]);
+    }
+    *previous = current;
+}
+
+fn update_diskstats(metrics: &ProcfsMetrics, stats: &[procfs::DiskStat], config: &AppConfig) {
+    let raw = config.is_metric_enabled("diskstats");
+    let bytes_read = config.is_metric_enabled("diskstats_bytes_read");
+    let bytes_written = config.is_metric_enabled("diskstats_bytes_written");
+    let latency = config.is_metric_enabled("diskstats_read_latency_seconds")
+        || config.is_metric_enabled("diskstats_write_latency_seconds");
+    let mut current = HashMap::new();
+    for stat in stats {
+        let device = stat.name.as_str();
+        if config.block.ignore_loop_devices && device.starts_with("loop") {
+            continue;
+        }
+        if raw {
+            set_raw_diskstats(&metrics.diskstats, device, stat);
         }
-        if let Some(value) = stat.discards_merged {
-            diskstats
-                .with_label_values(&[device, "discards_merged"])
-                .set(value as f64);
+        if bytes_read {
+            metrics
+                .diskstats_bytes_read
+                .with_label_values(&[device])
+                .set((stat.sectors_read * DISKSTATS_SECTOR_BYTES) as f64);
         }
-        if let Some(value) = stat.sectors_discarded {
-            diskstats
-                .with_label_values(&[device, "sectors_discarded"])
-                .set(value as f64);
+        if bytes_written {
+            metrics
+                .diskstats_bytes_written
+                .with_label_values(&[device])
+                .set((stat.sectors_written * DISKSTATS_SECTOR_BYTES) as f64);
         }
-        if let Some(value) = stat.time_discarding {
-            diskstats
-                .with_label_values(&[device, "time_discarding_ms"])
-                .set(value as f64);
+        if latency {
+            current.insert(
+                device.to_string(),
+                DiskLatencySample {
+                    reads: stat.reads,
+                    time_reading_ms: stat.time_reading,
+                    writes: stat.writes,
+                    time_writing_ms: stat.time_writing,
+                },
+            );
         }
-        if let Some(value) = stat.flushes {
-            diskstats
-                .with_label_values(&[device, "flushes"])
-                .set(value as f64);
+    }
+
+    if latency {
+        let mut previous = match metrics.diskstats_previous.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        update_disk_latency(
+            &metrics.diskstats_read_latency_seconds,
+            &metrics.diskstats_write_latency_seconds,
+            &mut previous,
+            current,
+        );
+    }
+}
+
+/// Byte counters of one interface at one scrape
+struct NetdevSample {
+    at: Instant,
+    recv_bytes: u64,
+    sent_bytes: u64,
+}
+
+/// Bytes per second between two readings; None when no time passed or the
+/// counter went backwards (32-bit wrap, or the interface was recreated)
+fn bytes_per_second(current: u64, previous: u64, seconds: f64) -> Option<f64> {
+    if seconds <= 0.0 || current < previous {
+        return None;
+    }
+    Some((current - previous) as f64 / seconds)
+}
+
+fn update_netdev_rates(
+    receive: &GaugeVec,
+    transmit: &GaugeVec,
+    previous: &mut HashMap<String, NetdevSample>,
+    current: HashMap<String, NetdevSample>,
+) {
+    for (iface, sample) in &current {
+        // An interface seen for the first time has no baseline yet
+        let Some(before) = previous.remove(iface) else {
+            continue;
+        };
+        let seconds = sample.at.duration_since(before.at).as_secs_f64();
+        let labels = [iface.as_str()];
+        match bytes_per_second(sample.recv_bytes, before.recv_bytes, seconds) {
+            Some(rate) => receive.with_label_values(&labels).set(rate),
+            None => {
+                let _ = receive.remove_label_values(&labels);
+            }
         }
-        if let Some(value) = stat.time_flushing {
-            diskstats
-                .with_label_values(&[device, "time_flushing_ms"])
-                .set(value as f64);
+        match bytes_per_second(sample.sent_bytes, before.sent_bytes, seconds) {
+            Some(rate) => transmit.with_label_values(&labels).set(rate),
+            None => {
+                let _ = transmit.remove_label_values(&labels);
+            }
         }
     }
+
+    // Interfaces that were removed
+    for iface in previous.keys() {
+        let _ = receive.remove_label_values(&[iface.as_str()]);
+        let _ = transmit.remove_label_values(&[iface.as_str()]);
+    }
+    *previous = current;
 }
 
 fn update_netdev(
@@ -381,11 +1033,15 @@ fn update_netdev(
     devs: &std::collections::HashMap<String, procfs::net::DeviceStatus>,
     config: &AppConfig,
 ) {
+    let now = Instant::now();
+    let mut samples = HashMap::new();
     for (name, dev) in devs {
-        if config.ignore_ppp_interfaces && name.starts_with("ppp") {
+        if config.netdev.ignore_ppp_interfaces && name.starts_with("ppp") {
             continue;
         }
-        if config.ignore_veth_interfaces && (name.starts_with("veth") || name.starts_with("br-")) {
+        if config.netdev.ignore_veth_interfaces
+            && (name.starts_with("veth") || name.starts_with("br-"))
+        {
             continue;
         }
         let netdev = &metrics.netdev;
@@ -438,7 +1094,26 @@ fn update_netdev(
         netdev
             .with_label_values(&[iface, "sent_compressed"])
             .set(dev.sent_compressed as f64);
+        samples.insert(
+            name.clone(),
+            NetdevSample {
+                at: now,
+                recv_bytes: dev.recv_bytes,
+                sent_bytes: dev.sent_bytes,
+            },
+        );
     }
+
+    let mut previous = match metrics.netdev_previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    update_netdev_rates(
+        &metrics.netdev_receive_bytes_per_second,
+        &metrics.netdev_transmit_bytes_per_second,
+        &mut previous,
+        samples,
+    );
 }
 
 fn tcp_state_label(state: &TcpState) -> &'static str {
@@ -465,32 +1140,54 @@ fn udp_state_label(state: &UdpState) -> &'static str {
     }
 }
 
-fn update_tcp(metrics: &ProcfsMetrics, entries: &[procfs::net::TcpNetEntry]) {
-    let mut counts: std::collections::HashMap<&'static str, u64> = std::collections::HashMap::new();
-    for entry in entries {
-        *counts.entry(tcp_state_label(&entry.state)).or_insert(0) += 1;
+const TCP_STATES: &[&str] = &[
+    "established",
+    "syn_sent",
+    "syn_recv",
+    "fin_wait_1",
+    "fin_wait_2",
+    "time_wait",
+    "close",
+    "close_wait",
+    "last_ack",
+    "listen",
+    "closing",
+    "new_syn_recv",
+];
+
+const UDP_STATES: &[&str] = &["established", "close"];
+
+/// Sets a count for every known state so states that empty out read 0.
+fn set_state_counts(
+    sockets: &GaugeVec,
+    known: &[&'static str],
+    states: impl Iterator<Item = &'static str>,
+) {
+    let mut counts: std::collections::HashMap<&'static str, u64> =
+        known.iter().map(|state| (*state, 0)).collect();
+    for state in states {
+        *counts.entry(state).or_insert(0) += 1;
     }
 
     for (state, count) in counts {
-        metrics
-            .tcp_sockets
-            .with_label_values(&[state])
-            .set(count as f64);
+        sockets.with_label_values(&[state]).set(count as f64);
     }
 }
 
-fn update_udp(metrics: &ProcfsMetrics, entries: &[procfs::net::UdpNetEntry]) {
-    let mut counts: std::collections::HashMap<&'static str, u64> = std::collections::HashMap::new();
-    for entry in entries {
-        *counts.entry(udp_state_label(&entry.state)).or_insert(0) += 1;
-    }
+fn update_tcp(sockets: &GaugeVec, entries: &[procfs::net::TcpNetEntry]) {
+    set_state_counts(
+        sockets,
+        TCP_STATES,
+        entries.iter().map(|entry| tcp_state_label(&entry.state)),
+    );
+}
 
-    for (state, count) in counts {
-        metrics
-            .udp_sockets
-            .with_label_values(&[state])
-            .set(count as f64);
-    }
+fn update_udp(sockets: &GaugeVec, entries: &[procfs::net::UdpNetEntry]) {
+    set_state_counts(
+        sockets,
+        UDP_STATES,
+        entries.iter().map(|entry| udp_state_label(&entry.state)),
+    );
 }
 
 fn update_arp(metrics: &ProcfsMetrics, entries: &[procfs::net::ARPEntry]) {
@@ -598,7 +1295,7 @@ fn update_snmp(metrics: &ProcfsMetrics, snmp: &procfs::net::Snmp) {
     set("udp_lite_ignored_multi", snmp.udp_lite_ignored_multi);
 }
 
-fn to_snake_case(input: &str) -> String {
+pub(crate) fn to_snake_case(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     let mut chars = input.chars().peekable();
     let mut prev: Option<char> = None;
@@ -621,24 +1318,14 @@ fn to_snake_case(input: &str) -> String {
             let is_lower = ch.is_ascii_lowercase();
             let is_digit = ch.is_ascii_digit();
 
-            if !out.is_empty() {
-                if is_upper
-                    && (prev_is_lower
-                        || prev_is_digit
-                        || (prev_is_upper && next.map(|n| n.is_ascii_lowercase()).unwrap_or(false)))
-                {
-                    if !out.ends_with('_') {
-                        out.push('_');
-                    }
-                } else if is_digit && (prev_is_lower || prev_is_upper) {
-                    if !out.ends_with('_') {
-                        out.push('_');
-                    }
-                } else if is_lower && prev_is_digit {
-                    if !out.ends_with('_') {
-                        out.push('_');
-                    }
-                }
+            let boundary = (is_upper
+                && (prev_is_lower
+                    || prev_is_digit
+                    || (prev_is_upper && next.map(|n| n.is_ascii_lowercase()).unwrap_or(false))))
+                || (is_digit && (prev_is_lower || prev_is_upper))
+                || (is_lower && prev_is_digit);
+            if !out.is_empty() && boundary && !out.ends_with('_') {
+                out.push('_');
             }
         }
 
@@ -751,19 +1438,36 @@ pub fn update_metrics(config: &AppConfig) {
     }
 
     if let Ok(stats) = KernelStats::current() {
-        update_kernel_stats(metrics, &stats);
+        update_kernel_stats(metrics, &stats, !config.cpu_aggregate_only);
+        let cpulist = fs::read_to_string(config.host_path(CPU_ONLINE_PATH)).ok();
+        let online = online_cpu_count(cpulist.as_deref(), stats.cpu_time.len());
+        update_runqueue(metrics, stats.procs_running, online);
+    }
+
+    if let Ok(contents) = fs::read_to_string("/proc/stat") {
+        update_interrupt_totals(metrics, &contents);
+        let mut previous = match metrics.cpu_previous.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        update_cpu_usage(
+            &metrics.cpu_usage_ratio,
+            &mut previous,
+            &contents,
+            !config.cpu_aggregate_only,
+        );
     }
 
     if let Ok(vmstat) = procfs::vmstat() {
-        for (key, value) in vmstat {
-            metrics
-                .vmstat
-                .with_label_values(&[key.as_str()])
-                .set(value as f64);
-        }
+        update_vmstat(metrics, &vmstat, config);
+        update_thp(metrics, &vmstat, &config.host_path(KHUGEPAGED_DIR));
     }
 
-    if let Ok(stats) = procfs::diskstats() {
+    if DISKSTATS_METRICS
+        .iter()
+        .any(|name| config.is_metric_enabled(name))
+        && let Ok(stats) = procfs::diskstats()
+    {
         update_diskstats(metrics, &stats, config);
     }
 
@@ -771,21 +1475,391 @@ pub fn update_metrics(config: &AppConfig) {
         update_netdev(metrics, &devs, config);
     }
 
-    if let Ok(entries) = procfs::net::tcp() {
-        update_tcp(metrics, &entries);
+    // Socket tables are read whole, which is costly with many connections
+    if config.is_metric_enabled("tcp_sockets")
+        && let Ok(entries) = procfs::net::tcp()
+    {
+        update_tcp(&metrics.tcp_sockets, &entries);
+    }
+
+    if config.is_metric_enabled("udp_sockets")
+        && let Ok(entries) = procfs::net::udp()
+    {
+        update_udp(&metrics.udp_sockets, &entries);
+    }
+
+    // Absent when IPv6 is disabled
+    if config.is_metric_enabled("tcp6_sockets")
+        && let Ok(entries) = procfs::net::tcp6()
+    {
+        update_tcp(&metrics.tcp6_sockets, &entries);
     }
 
-    if let Ok(entries) = procfs::net::udp() {
-        update_udp(metrics, &entries);
+    if config.is_metric_enabled("udp6_sockets")
+        && let Ok(entries) = procfs::net::udp6()
+    {
+        update_udp(&metrics.udp6_sockets, &entries);
     }
 
-    if let Ok(entries) = procfs::net::arp() {
+    if config.is_metric_enabled("arp_entries")
+        && let Ok(entries) = procfs::net::arp()
+    {
         update_arp(metrics, &entries);
     }
 
-    if let Ok(snmp) = procfs::net::snmp() {
+    if config.is_metric_enabled("snmp")
+        && let Ok(snmp) = procfs::net::snmp()
+    {
         update_snmp(metrics, &snmp);
     }
 
-    update_netstat(metrics);
+    if config.is_metric_enabled("netstat") {
+        update_netstat(metrics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use procfs::FromBufRead;
+    use prometheus::Opts;
+    use tempfile::TempDir;
+
+    #[test]
+    fn stat_line_total_reads_first_column() {
+        let contents = "cpu  137763 0 18685 435731 1441 0 8 1710 0 0\n\
+                        intr 680178 0 0 0 1189 2\n\
+                        ctxt 1916924\n\
+                        softirq 401378 0 155950 3 14940 0 0 4 0 80 230401\n";
+        assert_eq!(stat_line_total(contents, "intr"), Some(680178));
+        assert_eq!(stat_line_total(contents, "softirq"), Some(401378));
+        assert_eq!(stat_line_total("intr\n", "intr"), None);
+        assert_eq!(stat_line_total(contents, "missing"), None);
+    }
+
+    #[test]
+    fn memory_used_falls_back_without_mem_available() {
+        let contents = "MemTotal:       16000 kB\n\
+                        MemFree:         2000 kB\n\
+                        Buffers:         1000 kB\n\
+                        Cached:          5000 kB\n\
+                        SwapCached:         0 kB\n\
+                        Active:          6000 kB\n\
+                        Inactive:        4000 kB\n\
+                        SwapTotal:       4000 kB\n\
+                        SwapFree:        3000 kB\n\
+                        Dirty:              0 kB\n\
+                        Writeback:          0 kB\n\
+                        Mapped:           100 kB\n\
+                        Slab:             100 kB\n\
+                        CommitLimit:    10000 kB\n\
+                        Committed_AS:    1000 kB\n\
+                        VmallocTotal:  100000 kB\n\
+                        VmallocUsed:        0 kB\n\
+                        VmallocChunk:       0 kB\n";
+        let old = Meminfo::from_buf_read(contents.as_bytes()).unwrap();
+        assert_eq!(old.mem_available, None);
+        assert_eq!(memory_used_bytes(&old), 8000 * 1024);
+
+        let with_available = format!("{contents}MemAvailable:    9000 kB\n");
+        let current = Meminfo::from_buf_read(with_available.as_bytes()).unwrap();
+        assert_eq!(memory_used_bytes(&current), 7000 * 1024);
+    }
+
+    #[test]
+    fn vmstat_counter_classification() {
+        for key in [
+            "pgfault",
+            "pgmajfault",
+            "pswpin",
+            "pswpout",
+            "oom_kill",
+            "nr_dirtied",
+        ] {
+            assert!(is_vmstat_counter(key), "{key}");
+        }
+        for key in [
+            "nr_free_pages",
+            "nr_dirty",
+            "workingset_nodes",
+            "nr_dirty_threshold",
+        ] {
+            assert!(!is_vmstat_counter(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn vmstat_splits_counters_and_honors_allowlist() {
+        let metrics = metrics();
+        let config: AppConfig =
+            toml::from_str("procfs = { vmstat_fields = [\"pgtest_*\", \"nr_test_level\"] }")
+                .unwrap();
+        let vmstat = |events: i64| {
+            HashMap::from([
+                ("pgtest_events".to_string(), events),
+                ("nr_test_level".to_string(), 7),
+                ("nr_test_skipped".to_string(), 1),
+            ])
+        };
+
+        update_vmstat(metrics, &vmstat(100), &config);
+        update_vmstat(metrics, &vmstat(150), &config);
+        let events = &metrics.vmstat_events_total;
+        assert_eq!(events.with_label_values(&["pgtest_events"]).get(), 150);
+        assert_eq!(
+            metrics.vmstat.with_label_values(&["nr_test_level"]).get(),
+            7.0
+        );
+        assert!(
+            metrics
+                .vmstat
+                .remove_label_values(&["pgtest_events"])
+                .is_err()
+        );
+        assert!(
+            metrics
+                .vmstat
+                .remove_label_values(&["nr_test_skipped"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_cpu_ticks_keeps_cpu_ids() {
+        let contents = "cpu  100 0 50 800 10 0 5 0 0 0\n\
+                        cpu0 50 0 25 400 5 0 3 0 0 0\n\
+                        cpu3 50 0 25 400 5 0 2 0 0 0\n\
+                        intr 12345 0 0\n\
+                        cpufreq 1\n";
+        let ticks = parse_cpu_ticks(contents);
+        let labels: Vec<&str> = ticks.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["total", "cpu0", "cpu3"]);
+        assert_eq!(ticks[0].1, vec![100, 0, 50, 800, 10, 0, 5, 0]);
+    }
+
+    #[test]
+    fn cpu_usage_ratios_split_elapsed_time() {
+        let before = [100, 0, 50, 800, 10, 0, 0, 0];
+        let after = [130, 0, 60, 850, 10, 0, 10, 0];
+        let ratios = cpu_usage_ratios(&before, &after).unwrap();
+        assert_eq!(ratios, vec![0.3, 0.0, 0.1, 0.5, 0.0, 0.0, 0.1, 0.0]);
+        assert_eq!(cpu_usage_ratios(&before, &before), None);
+
+        // iowait can go backwards on some kernels
+        let after = [110, 0, 50, 800, 5, 0, 0, 0];
+        assert_eq!(cpu_usage_ratios(&before, &after).unwrap()[0], 1.0);
+    }
+
+    #[test]
+    fn cpu_usage_skips_first_scrape_and_follows_hotplug() {
+        let usage =
+            GaugeVec::new(Opts::new("test_cpu_usage_ratio", "test"), &["cpu", "mode"]).unwrap();
+        let mut previous = HashMap::new();
+        let ratio = |cpu: &str| usage.with_label_values(&[cpu, "idle"]).get();
+        let missing = |cpu: &str| usage.remove_label_values(&[cpu, "idle"]).is_err();
+
+        update_cpu_usage(&usage, &mut previous, "cpu0 10 0 10 80 0 0 0 0\n", true);
+        assert!(missing("cpu0"));
+
+        update_cpu_usage(
+            &usage,
+            &mut previous,
+            "cpu0 10 0 10 180 0 0 0 0\ncpu1 0 0 0 100 0 0 0 0\n",
+            true,
+        );
+        assert_eq!(ratio("cpu0"), 1.0);
+        assert!(missing("cpu1"));
+
+        update_cpu_usage(&usage, &mut previous, "cpu1 0 0 0 200 0 0 0 0\n", true);
+        assert!(missing("cpu0"));
+        assert_eq!(ratio("cpu1"), 1.0);
+    }
+
+    #[test]
+    fn cpu_usage_aggregate_only_keeps_total() {
+        let usage = GaugeVec::new(
+            Opts::new("test_cpu_usage_aggregate_ratio", "test"),
+            &["cpu", "mode"],
+        )
+        .unwrap();
+        let mut previous = HashMap::new();
+        let missing = |cpu: &str| usage.remove_label_values(&[cpu, "idle"]).is_err();
+        let stat = |idle: u64| format!("cpu 0 0 0 {idle} 0 0 0 0\ncpu0 0 0 0 {idle} 0 0 0 0\n");
+
+        update_cpu_usage(&usage, &mut previous, &stat(100), false);
+        update_cpu_usage(&usage, &mut previous, &stat(200), false);
+        assert_eq!(usage.with_label_values(&["total", "idle"]).get(), 1.0);
+        assert!(missing("cpu0"));
+    }
+
+    #[test]
+    fn netdev_rates_skip_first_scrape_resets_and_removed_interfaces() {
+        let receive =
+            GaugeVec::new(Opts::new("test_netdev_rx_rate", "test"), &["interface"]).unwrap();
+        let transmit =
+            GaugeVec::new(Opts::new("test_netdev_tx_rate", "test"), &["interface"]).unwrap();
+        let start = Instant::now();
+        let sample = |seconds: u64, recv_bytes: u64, sent_bytes: u64| NetdevSample {
+            at: start + std::time::Duration::from_secs(seconds),
+            recv_bytes,
+            sent_bytes,
+        };
+        let missing = |gauge: &GaugeVec, iface: &str| gauge.remove_label_values(&[iface]).is_err();
+        let mut previous = HashMap::new();
+
+        let current = HashMap::from([("eth0".to_string(), sample(0, 1000, 500))]);
+        update_netdev_rates(&receive, &transmit, &mut previous, current);
+        assert!(missing(&receive, "eth0"));
+
+        let current = HashMap::from([
+            ("eth0".to_string(), sample(10, 21000, 1500)),
+            ("eth1".to_string(), sample(10, 5, 5)),
+        ]);
+        update_netdev_rates(&receive, &transmit, &mut previous, current);
+        assert_eq!(receive.with_label_values(&["eth0"]).get(), 2000.0);
+        assert_eq!(transmit.with_label_values(&["eth0"]).get(), 100.0);
+        assert!(missing(&receive, "eth1"));
+
+        // eth0 was recreated (counters restarted) and eth1 removed
+        let current = HashMap::from([("eth0".to_string(), sample(20, 100, 1700))]);
+        update_netdev_rates(&receive, &transmit, &mut previous, current);
+        assert!(missing(&receive, "eth0"));
+        assert_eq!(transmit.with_label_values(&["eth0"]).get(), 20.0);
+        assert!(missing(&receive, "eth1"));
+        assert!(missing(&transmit, "eth1"));
+    }
+
+    #[test]
+    fn disk_latency_skips_idle_intervals_and_removed_devices() {
+        let read = GaugeVec::new(Opts::new("test_disk_read_latency", "test"), &["device"]).unwrap();
+        let write =
+            GaugeVec::new(Opts::new("test_disk_write_latency", "test"), &["device"]).unwrap();
+        let sample = |reads, time_reading_ms, writes, time_writing_ms| DiskLatencySample {
+            reads,
+            time_reading_ms,
+            writes,
+            time_writing_ms,
+        };
+        let missing =
+            |gauge: &GaugeVec, device: &str| gauge.remove_label_values(&[device]).is_err();
+        let mut previous = HashMap::new();
+
+        let current = HashMap::from([("sda".to_string(), sample(100, 400, 50, 1000))]);
+        update_disk_latency(&read, &write, &mut previous, current);
+        assert!(missing(&read, "sda"));
+
+        // 10 reads took 20ms in total; no writes completed
+        let current = HashMap::from([
+            ("sda".to_string(), sample(110, 420, 50, 1000)),
+            ("sdb".to_string(), sample(1, 1, 1, 1)),
+        ]);
+        update_disk_latency(&read, &write, &mut previous, current);
+        assert_eq!(read.with_label_values(&["sda"]).get(), 0.002);
+        assert!(missing(&write, "sda"));
+        assert!(missing(&read, "sdb"));
+
+        // sda went idle and sdb was removed
+        let current = HashMap::from([("sda".to_string(), sample(110, 420, 50, 1000))]);
+        update_disk_latency(&read, &write, &mut previous, current);
+        assert!(missing(&read, "sda"));
+        assert!(missing(&read, "sdb"));
+        assert_eq!(average_latency_seconds((5, 10), (0, 0)), None);
+    }
+
+    #[test]
+    fn online_cpu_count_prefers_sysfs_cpulist() {
+        assert_eq!(online_cpu_count(Some("0-3,8-11\n"), 2), 8);
+        assert_eq!(online_cpu_count(Some("0\n"), 4), 1);
+        assert_eq!(online_cpu_count(Some(""), 4), 4);
+        assert_eq!(online_cpu_count(None, 4), 4);
+    }
+
+    #[test]
+    fn counter_delta_tracks_increase_and_restart() {
+        let mut previous = HashMap::new();
+        assert_eq!(counter_delta(&mut previous, "thp_fault_alloc", 40), 40);
+        assert_eq!(counter_delta(&mut previous, "thp_fault_alloc", 55), 15);
+        assert_eq!(counter_delta(&mut previous, "full_scans", 3), 3);
+        assert_eq!(counter_delta(&mut previous, "thp_fault_alloc", 5), 5);
+    }
+
+    #[test]
+    fn read_u64_parses_khugepaged_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("pages_collapsed"), "1234\n").unwrap();
+        assert_eq!(read_u64(&dir.path().join("pages_collapsed")), Some(1234));
+        assert_eq!(read_u64(&dir.path().join("full_scans")), None);
+    }
+
+    #[test]
+    fn state_counts_reset_to_zero() {
+        let sockets = GaugeVec::new(Opts::new("test_sockets", "help"), &["state"]).unwrap();
+        set_state_counts(
+            &sockets,
+            TCP_STATES,
+            ["listen", "established", "established"].into_iter(),
+        );
+        assert_eq!(sockets.with_label_values(&["established"]).get(), 2.0);
+        assert_eq!(sockets.with_label_values(&["time_wait"]).get(), 0.0);
+
+        set_state_counts(&sockets, TCP_STATES, ["listen"].into_iter());
+        assert_eq!(sockets.with_label_values(&["established"]).get(), 0.0);
+        assert_eq!(sockets.with_label_values(&["listen"]).get(), 1.0);
+    }
+
+    #[test]
+    fn diskstats_bytes_use_512_byte_sectors() {
+        // A 4Kn device still reports 512-byte units in /proc/diskstats
+        let stat = procfs::DiskStat::from_line(
+            " 259       0 mocknvme0n1 1200 0 8000 300 600 0 4096 200 0 400 500",
+        )
+        .unwrap();
+        update_diskstats(metrics(), &[stat], &AppConfig::default());
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .diskstats_bytes_read
+                .with_label_values(&["mocknvme0n1"])
+                .get(),
+            8000.0 * 512.0
+        );
+        assert_eq!(
+            metrics
+                .diskstats_bytes_written
+                .with_label_values(&["mocknvme0n1"])
+                .get(),
+            4096.0 * 512.0
+        );
+        assert_eq!(
+            metrics
+                .diskstats
+                .with_label_values(&["mocknvme0n1", "sectors_read"])
+                .get(),
+            8000.0
+        );
+    }
+
+    #[test]
+    fn known_states_cover_every_label() {
+        let tcp = [
+            TcpState::Established,
+            TcpState::SynSent,
+            TcpState::SynRecv,
+            TcpState::FinWait1,
+            TcpState::FinWait2,
+            TcpState::TimeWait,
+            TcpState::Close,
+            TcpState::CloseWait,
+            TcpState::LastAck,
+            TcpState::Listen,
+            TcpState::Closing,
+            TcpState::NewSynRecv,
+        ];
+        for state in &tcp {
+            assert!(TCP_STATES.contains(&tcp_state_label(state)));
+        }
+        for state in &[UdpState::Established, UdpState::Close] {
+            assert!(UDP_STATES.contains(&udp_state_label(state)));
+        }
+    }
 }
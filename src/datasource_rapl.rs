@@ -1,3 +1,4 @@
+use crate::config::AppConfig;
 use prometheus::GaugeVec;
 use std::fs;
 use std::path::Path;
@@ -6,6 +7,8 @@ use std::sync::OnceLock;
 struct RaplMetrics {
     energy_joules: GaugeVec,
     max_energy_joules: GaugeVec,
+    zone_info: GaugeVec,
+    zone_enabled: GaugeVec,
 }
 
 impl RaplMetrics {
@@ -24,6 +27,20 @@ impl RaplMetrics {
                 &["zone", "name"]
             )
             .expect("register rapl_max_energy_joules"),
+
+            zone_info: prometheus::register_gauge_vec!(
+                "rapl_zone_info",
+                "RAPL power domain present (always 1)",
+                &["zone", "name"]
+            )
+            .expect("register rapl_zone_info"),
+
+            zone_enabled: prometheus::register_gauge_vec!(
+                "rapl_zone_enabled",
+                "Whether powercap measurement is enabled for the zone (1 = enabled)",
+                &["zone"]
+            )
+            .expect("register rapl_zone_enabled"),
         }
     }
 }
@@ -42,17 +59,27 @@ fn read_u64(path: &Path) -> Option<u64> {
     read_string(path)?.parse::<u64>().ok()
 }
 
-fn update_rapl_zone(zone_path: &Path, zone_id: &str) {
+/// Exports the counters, info and enabled state of one zone or subzone
+fn update_zone_values(zone_path: &Path, zone_id: &str, name: &str) {
     let metrics = metrics();
 
-    // Read zone name (e.g., "package-0", "core", "uncore", "dram")
-    let name = read_string(&zone_path.join("name")).unwrap_or_else(|| "unknown".to_string());
+    metrics
+        .zone_info
+        .with_label_values(&[zone_id, name])
+        .set(1.0);
+
+    if let Some(enabled) = read_u64(&zone_path.join("enabled")) {
+        metrics
+            .zone_enabled
+            .with_label_values(&[zone_id])
+            .set(if enabled != 0 { 1.0 } else { 0.0 });
+    }
 
     // Read energy counter in microjoules, convert to joules
     if let Some(energy_uj) = read_u64(&zone_path.join("energy_uj")) {
         metrics
             .energy_joules
-            .with_label_values(&[zone_id, &name])
+            .with_label_values(&[zone_id, name])
             .set(energy_uj as f64 / 1_000_000.0);
     }
 
@@ -60,9 +87,15 @@ fn update_rapl_zone(zone_path: &Path, zone_id: &str) {
     if let Some(max_energy_uj) = read_u64(&zone_path.join("max_energy_range_uj")) {
         metrics
             .max_energy_joules
-            .with_label_values(&[zone_id, &name])
+            .with_label_values(&[zone_id, name])
             .set(max_energy_uj as f64 / 1_000_000.0);
     }
+}
+
+fn update_rapl_zone(zone_path: &Path, zone_id: &str) {
+    // Read zone name (e.g., "package-0", "core", "uncore", "dram")
+    let name = read_string(&zone_path.join("name")).unwrap_or_else(|| "unknown".to_string());
+    update_zone_values(zone_path, zone_id, &name);
 
     // Process subzones (e.g., intel-rapl:0:0, intel-rapl:0:1)
     if let Ok(entries) = fs::read_dir(zone_path) {
@@ -77,29 +110,15 @@ fn update_rapl_zone(zone_path: &Path, zone_id: &str) {
                 && entry.path().is_dir()
                 && let Some(subzone_name) = read_string(&entry.path().join("name"))
             {
-                // Read subzone energy
-                if let Some(energy_uj) = read_u64(&entry.path().join("energy_uj")) {
-                    metrics
-                        .energy_joules
-                        .with_label_values(&[&entry_name, &subzone_name])
-                        .set(energy_uj as f64 / 1_000_000.0);
-                }
-
-                // Read subzone max energy range
-                if let Some(max_energy_uj) = read_u64(&entry.path().join("max_energy_range_uj")) {
-                    metrics
-                        .max_energy_joules
-                        .with_label_values(&[&entry_name, &subzone_name])
-                        .set(max_energy_uj as f64 / 1_000_000.0);
-                }
+                update_zone_values(&entry.path(), &entry_name, &subzone_name);
             }
         }
     }
 }
 
-pub fn update_metrics() {
-    let base = Path::new("/sys/class/powercap");
-    let entries = match fs::read_dir(base) {
+pub fn update_metrics(config: &AppConfig) {
+    let base = config.host_path("/sys/class/powercap");
+    let entries = match fs::read_dir(&base) {
         Ok(entries) => entries,
         Err(_) => return,
     };
@@ -210,4 +229,44 @@ mod tests {
 
         update_rapl_zone(&zone_dir, "intel-rapl:0");
     }
+
+    #[test]
+    fn test_update_rapl_zone_info_and_enabled() {
+        let dir = TempDir::new().unwrap();
+        let zone = create_rapl_zone(dir.path(), "amd-rapl:7", "package-7", 1000000, 2000000);
+        fs::write(zone.join("enabled"), "0\n").unwrap();
+        let subzone = create_rapl_zone(&zone, "amd-rapl:7:0", "core", 500000, 2000000);
+        fs::write(subzone.join("enabled"), "1\n").unwrap();
+
+        update_rapl_zone(&zone, "amd-rapl:7");
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .zone_info
+                .with_label_values(&["amd-rapl:7", "package-7"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .zone_info
+                .with_label_values(&["amd-rapl:7:0", "core"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .zone_enabled
+                .with_label_values(&["amd-rapl:7"])
+                .get(),
+            0.0
+        );
+        assert_eq!(
+            metrics
+                .zone_enabled
+                .with_label_values(&["amd-rapl:7:0"])
+                .get(),
+            1.0
+        );
+    }
 }
@@ -0,0 +1,211 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// From linux/route.h
+const RTF_UP: u32 = 0x0001;
+const RTF_REJECT: u32 = 0x0200;
+
+struct RoutesMetrics {
+    routes_total: GaugeVec,
+    default_route: GaugeVec,
+    series: Mutex<Series>,
+}
+
+/// Label sets exported on the previous pass
+#[derive(Default)]
+struct Series {
+    interfaces: HashSet<String>,
+    defaults: HashSet<(String, String)>,
+}
+
+impl RoutesMetrics {
+    fn new() -> Self {
+        Self {
+            routes_total: prometheus::register_gauge_vec!(
+                "network_routes_total",
+                "Number of IPv4 and IPv6 routes using the interface",
+                &["interface"]
+            )
+            .expect("register network_routes_total"),
+            default_route: prometheus::register_gauge_vec!(
+                "network_default_route",
+                "Default route via the interface and gateway (always 1)",
+                &["interface", "gateway"]
+            )
+            .expect("register network_default_route"),
+            series: Mutex::new(Series::default()),
+        }
+    }
+}
+
+static ROUTES_METRICS: OnceLock<RoutesMetrics> = OnceLock::new();
+
+fn metrics() -> &'static RoutesMetrics {
+    ROUTES_METRICS.get_or_init(RoutesMetrics::new)
+}
+
+#[derive(Debug, PartialEq)]
+struct Route {
+    interface: String,
+    is_default: bool,
+    gateway: IpAddr,
+}
+
+fn is_usable(flags: u32) -> bool {
+    flags & RTF_UP != 0 && flags & RTF_REJECT == 0
+}
+
+/// The kernel prints each address as a native-endian u32 holding network-order bytes
+fn parse_ipv4_hex(hex: &str) -> Option<Ipv4Addr> {
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Ipv4Addr::from(value.to_ne_bytes()))
+}
+
+fn parse_ipv6_hex(hex: &str) -> Option<Ipv6Addr> {
+    u128::from_str_radix(hex, 16).ok().map(Ipv6Addr::from)
+}
+
+/// Parses /proc/net/route: Iface Destination Gateway Flags RefCnt Use Metric Mask ...
+fn parse_route(contents: &str) -> Vec<Route> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            if !is_usable(flags) {
+                return None;
+            }
+            let destination = parse_ipv4_hex(fields.get(1)?)?;
+            let mask = parse_ipv4_hex(fields.get(7)?)?;
+            Some(Route {
+                interface: fields[0].to_string(),
+                is_default: destination.is_unspecified() && mask.is_unspecified(),
+                gateway: IpAddr::V4(parse_ipv4_hex(fields.get(2)?)?),
+            })
+        })
+        .collect()
+}
+
+/// Parses /proc/net/ipv6_route: dest plen src plen nexthop metric refcnt use flags iface
+fn parse_ipv6_route(contents: &str) -> Vec<Route> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u32::from_str_radix(fields.get(8)?, 16).ok()?;
+            if !is_usable(flags) {
+                return None;
+            }
+            let destination = parse_ipv6_hex(fields.first()?)?;
+            let prefix_len = u8::from_str_radix(fields.get(1)?, 16).ok()?;
+            Some(Route {
+                interface: fields.get(9)?.to_string(),
+                is_default: destination.is_unspecified() && prefix_len == 0,
+                gateway: IpAddr::V6(parse_ipv6_hex(fields.get(4)?)?),
+            })
+        })
+        .collect()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/net"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let mut routes = Vec::new();
+    if let Ok(contents) = fs::read_to_string(base.join("route")) {
+        routes.extend(parse_route(&contents));
+    }
+    if let Ok(contents) = fs::read_to_string(base.join("ipv6_route")) {
+        routes.extend(parse_ipv6_route(&contents));
+    }
+
+    let metrics = metrics();
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut current = Series::default();
+    for route in &routes {
+        *counts.entry(route.interface.as_str()).or_default() += 1;
+        if route.is_default {
+            current
+                .defaults
+                .insert((route.interface.clone(), route.gateway.to_string()));
+        }
+    }
+    for (interface, count) in counts {
+        metrics
+            .routes_total
+            .with_label_values(&[interface])
+            .set(count as f64);
+        current.interfaces.insert(interface.to_string());
+    }
+    for (interface, gateway) in &current.defaults {
+        metrics
+            .default_route
+            .with_label_values(&[interface, gateway])
+            .set(1.0);
+    }
+
+    let mut series = match metrics.series.lock() {
+        Ok(series) => series,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for interface in series.interfaces.difference(&current.interfaces) {
+        let _ = metrics.routes_total.remove_label_values(&[interface]);
+    }
+    // A changed gateway replaces the old series, so flaps show up as series churn
+    for (interface, gateway) in series.defaults.difference(&current.defaults) {
+        let _ = metrics
+            .default_route
+            .remove_label_values(&[interface, gateway]);
+    }
+    *series = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTE: &str = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                         eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+                         eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+                         wg0\t0000000A\t00000000\t0001\t0\t0\t0\t000000FF\t0\t0\t0\n\
+                         eth1\t0002A8C0\t00000000\t0000\t0\t0\t0\t00FFFFFF\t0\t0\t0\n";
+
+    const IPV6_ROUTE: &str = "fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0\n\
+                              00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003     eth0\n\
+                              00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo\n";
+
+    #[test]
+    fn test_parse_route() {
+        let routes = parse_route(ROUTE);
+        // eth1 is not RTF_UP
+        assert_eq!(routes.len(), 3);
+        assert_eq!(
+            routes[0],
+            Route {
+                interface: "eth0".to_string(),
+                is_default: true,
+                gateway: "192.168.1.1".parse().unwrap(),
+            }
+        );
+        assert!(!routes[1].is_default);
+        assert_eq!(routes[2].interface, "wg0");
+        assert!(!routes[2].is_default);
+    }
+
+    #[test]
+    fn test_parse_ipv6_route() {
+        let routes = parse_ipv6_route(IPV6_ROUTE);
+        // The unreachable default on lo has RTF_REJECT
+        assert_eq!(routes.len(), 2);
+        assert!(!routes[0].is_default);
+        assert!(routes[1].is_default);
+        assert_eq!(routes[1].gateway, "fe80::1".parse::<IpAddr>().unwrap());
+    }
+}
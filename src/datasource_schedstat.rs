@@ -0,0 +1,242 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use crate::cpu_aggregate;
+use prometheus::{CounterVec, IntCounterVec};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+
+const SCHEDSTAT_PATH: &str = "/proc/schedstat";
+
+/// Per-CPU values from a `cpuN` line of /proc/schedstat (versions 15+)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CpuSchedstat {
+    running_ns: u64,
+    waiting_ns: u64,
+    timeslices: u64,
+}
+
+struct SchedstatMetrics {
+    running_seconds_total: CounterVec,
+    waiting_seconds_total: CounterVec,
+    timeslices_total: IntCounterVec,
+    previous: Mutex<HashMap<String, CpuSchedstat>>,
+}
+
+impl SchedstatMetrics {
+    fn new() -> Self {
+        Self {
+            running_seconds_total: prometheus::register_counter_vec!(
+                "schedstat_running_seconds_total",
+                "Time spent running tasks on this CPU in seconds",
+                &["cpu"]
+            )
+            .expect("register schedstat_running_seconds_total"),
+            waiting_seconds_total: prometheus::register_counter_vec!(
+                "schedstat_waiting_seconds_total",
+                "Time tasks spent waiting on the run queue of this CPU in seconds",
+                &["cpu"]
+            )
+            .expect("register schedstat_waiting_seconds_total"),
+            timeslices_total: prometheus::register_int_counter_vec!(
+                "schedstat_timeslices_total",
+                "Number of timeslices run on this CPU",
+                &["cpu"]
+            )
+            .expect("register schedstat_timeslices_total"),
+            previous: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static SCHEDSTAT_METRICS: OnceLock<SchedstatMetrics> = OnceLock::new();
+
+fn metrics() -> &'static SchedstatMetrics {
+    SCHEDSTAT_METRICS.get_or_init(SchedstatMetrics::new)
+}
+
+/// Parses the `cpuN` lines; `domainN` lines are ignored.
+fn parse_schedstat(contents: &str) -> Vec<(String, CpuSchedstat)> {
+    let mut cpus = Vec::new();
+
+    for line in contents.lines() {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let Some(name) = columns.first() else {
+            continue;
+        };
+        if !name.starts_with("cpu") || !name[3..].chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        // cpuN <yld_count> <legacy> <sched_count> <sched_goidle> <ttwu_count>
+        //      <ttwu_local> <running_ns> <waiting_ns> <timeslices>
+        if columns.len() < 10 {
+            continue;
+        }
+
+        let parse = |index: usize| columns[index].parse::<u64>().ok();
+        if let (Some(running_ns), Some(waiting_ns), Some(timeslices)) =
+            (parse(7), parse(8), parse(9))
+        {
+            cpus.push((
+                name.to_string(),
+                CpuSchedstat {
+                    running_ns,
+                    waiting_ns,
+                    timeslices,
+                },
+            ));
+        }
+    }
+
+    cpus
+}
+
+fn remove_series(metrics: &SchedstatMetrics, series: &str) {
+    let _ = metrics.running_seconds_total.remove_label_values(&[series]);
+    let _ = metrics.waiting_seconds_total.remove_label_values(&[series]);
+    let _ = metrics.timeslices_total.remove_label_values(&[series]);
+}
+
+/// `per_cpu` false adds every CPU to a single "total" series
+fn update_from_contents(contents: &str, per_cpu: bool) {
+    let metrics = metrics();
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let cpus = parse_schedstat(contents);
+    for (cpu, current) in &cpus {
+        let last = previous.get(cpu).copied().unwrap_or_default();
+
+        let running = counter_delta(current.running_ns, last.running_ns);
+        let waiting = counter_delta(current.waiting_ns, last.waiting_ns);
+        let timeslices = counter_delta(current.timeslices, last.timeslices);
+
+        let series = if per_cpu { cpu.as_str() } else { "total" };
+        metrics
+            .running_seconds_total
+            .with_label_values(&[series])
+            .inc_by(running as f64 / 1_000_000_000.0);
+        metrics
+            .waiting_seconds_total
+            .with_label_values(&[series])
+            .inc_by(waiting as f64 / 1_000_000_000.0);
+        metrics
+            .timeslices_total
+            .with_label_values(&[series])
+            .inc_by(timeslices);
+
+        previous.insert(cpu.clone(), *current);
+    }
+    cpu_aggregate::remove_other_mode(per_cpu, cpus.iter().map(|(cpu, _)| cpu.as_str()), |cpu| {
+        remove_series(metrics, cpu)
+    });
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let contents = match fs::read_to_string(config.host_path(SCHEDSTAT_PATH)) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+
+    update_from_contents(&contents, !config.cpu_aggregate_only);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Collecting removes the "total" or per-CPU series of the other mode, so
+    // tests that collect must not interleave
+    static COLLECT_LOCK: Mutex<()> = Mutex::new(());
+
+    const MOCK_SCHEDSTAT: &str = r#"version 15
+timestamp 4295521430
+cpu0 0 0 0 0 0 0 1500000000 250000000 1200
+domain0 00000003 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36
+cpu1 0 0 0 0 0 0 3000000000 500000000 2400
+domain0 00000003 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36
+"#;
+
+    #[test]
+    fn test_parse_schedstat_cpu_lines() {
+        let cpus = parse_schedstat(MOCK_SCHEDSTAT);
+        assert_eq!(cpus.len(), 2);
+        assert_eq!(cpus[0].0, "cpu0");
+        assert_eq!(
+            cpus[0].1,
+            CpuSchedstat {
+                running_ns: 1_500_000_000,
+                waiting_ns: 250_000_000,
+                timeslices: 1200,
+            }
+        );
+        assert_eq!(cpus[1].0, "cpu1");
+    }
+
+    #[test]
+    fn test_parse_schedstat_handles_malformed() {
+        assert!(parse_schedstat("").is_empty());
+        assert!(parse_schedstat("cpu0 1 2 3\ncpux 0 0 0 0 0 0 1 2 3").is_empty());
+    }
+
+    #[test]
+    fn test_update_from_contents_accumulates() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let first = "cpu90 0 0 0 0 0 0 1000000000 500000000 10\n";
+        let second = "cpu90 0 0 0 0 0 0 3000000000 500000000 25\n";
+
+        update_from_contents(first, true);
+        update_from_contents(second, true);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .running_seconds_total
+                .with_label_values(&["cpu90"])
+                .get(),
+            3.0
+        );
+        assert_eq!(
+            metrics
+                .waiting_seconds_total
+                .with_label_values(&["cpu90"])
+                .get(),
+            0.5
+        );
+        assert_eq!(
+            metrics.timeslices_total.with_label_values(&["cpu90"]).get(),
+            25
+        );
+    }
+
+    #[test]
+    fn test_update_from_contents_aggregate_only() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let first = "cpu91 0 0 0 0 0 0 1000000000 0 10\ncpu92 0 0 0 0 0 0 0 0 5\n";
+        let second = "cpu91 0 0 0 0 0 0 2000000000 0 20\ncpu92 0 0 0 0 0 0 1000000000 0 5\n";
+
+        update_from_contents(first, false);
+        update_from_contents(second, false);
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .running_seconds_total
+                .with_label_values(&["total"])
+                .get(),
+            3.0
+        );
+        assert_eq!(
+            metrics.timeslices_total.with_label_values(&["total"]).get(),
+            25
+        );
+        assert!(
+            metrics
+                .running_seconds_total
+                .remove_label_values(&["cpu91"])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::config::AppConfig;
+use crate::datasource_procfs::to_snake_case;
+use prometheus::GaugeVec;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+struct SctpMetrics {
+    sctp: GaugeVec,
+}
+
+impl SctpMetrics {
+    fn new() -> Self {
+        Self {
+            sctp: prometheus::register_gauge_vec!(
+                "sctp",
+                "SCTP counters from /proc/net/sctp/snmp",
+                &["field"]
+            )
+            .expect("register sctp"),
+        }
+    }
+}
+
+static SCTP_METRICS: OnceLock<SctpMetrics> = OnceLock::new();
+
+fn metrics() -> &'static SctpMetrics {
+    SCTP_METRICS.get_or_init(SctpMetrics::new)
+}
+
+/// Parses the `Name Value` lines, e.g. `SctpCurrEstab 3` -> `sctp_curr_estab`
+fn parse_snmp(contents: &str) -> Vec<(String, u64)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let value = fields.next()?.parse::<u64>().ok()?;
+            Some((to_snake_case(name), value))
+        })
+        .collect()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/net/sctp/snmp"));
+}
+
+fn update_metrics_from_path(path: &Path) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+    let metrics = metrics();
+    for (field, value) in parse_snmp(&contents) {
+        metrics
+            .sctp
+            .with_label_values(&[field.as_str()])
+            .set(value as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SNMP: &str = "SctpCurrEstab                   \t3\n\
+                        SctpActiveEstabs                \t12\n\
+                        SctpPassiveEstabs               \t7\n\
+                        SctpOutSCTPPacks                \t48211\n\
+                        SctpInSCTPPacks                 \t47980\n\
+                        SctpT1InitExpireds              \t0\n";
+
+    #[test]
+    fn test_parse_snmp() {
+        let fields = parse_snmp(SNMP);
+        assert_eq!(fields[0], ("sctp_curr_estab".to_string(), 3));
+        assert_eq!(fields[3], ("sctp_out_sctp_packs".to_string(), 48211));
+        assert_eq!(fields[5].0, "sctp_t_1_init_expireds");
+        assert_eq!(fields.len(), 6);
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snmp");
+        fs::write(&path, SNMP).unwrap();
+        update_metrics_from_path(&path);
+
+        let sctp = &metrics().sctp;
+        assert_eq!(sctp.with_label_values(&["sctp_curr_estab"]).get(), 3.0);
+        assert_eq!(
+            sctp.with_label_values(&["sctp_in_sctp_packs"]).get(),
+            47980.0
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use crate::counter_reset::counter_delta;
+use prometheus::{Counter, Gauge};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct SelfMetrics {
+    cpu_seconds_total: Counter,
+    resident_memory_bytes: Gauge,
+    open_fds: Gauge,
+    start_time_seconds: Gauge,
+    previous_cpu_ticks: Mutex<Option<u64>>,
+}
+
+impl SelfMetrics {
+    fn new() -> Self {
+        Self {
+            cpu_seconds_total: prometheus::register_counter!(
+                "process_cpu_seconds_total",
+                "Total user and system CPU time spent by the exporter in seconds"
+            )
+            .expect("register process_cpu_seconds_total"),
+            resident_memory_bytes: prometheus::register_gauge!(
+                "process_resident_memory_bytes",
+                "Resident memory size of the exporter in bytes"
+            )
+            .expect("register process_resident_memory_bytes"),
+            open_fds: prometheus::register_gauge!(
+                "process_open_fds",
+                "Number of open file descriptors held by the exporter"
+            )
+            .expect("register process_open_fds"),
+            start_time_seconds: prometheus::register_gauge!(
+                "process_start_time_seconds",
+                "Start time of the exporter since unix epoch in seconds"
+            )
+            .expect("register process_start_time_seconds"),
+            previous_cpu_ticks: Mutex::new(None),
+        }
+    }
+}
+
+static SELF_METRICS: OnceLock<SelfMetrics> = OnceLock::new();
+static CLOCK_TICKS: OnceLock<f64> = OnceLock::new();
+
+fn metrics() -> &'static SelfMetrics {
+    SELF_METRICS.get_or_init(SelfMetrics::new)
+}
+
+/// USER_HZ, the unit of utime/stime/starttime in /proc/<pid>/stat
+fn clock_ticks_per_second() -> f64 {
+    *CLOCK_TICKS.get_or_init(|| {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 { ticks as f64 } else { 100.0 }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct ProcessStat {
+    cpu_ticks: u64,
+    start_ticks: u64,
+}
+
+fn parse_stat(contents: &str) -> Option<ProcessStat> {
+    // comm may contain spaces and parentheses; fields resume after the last ')'
+    let (_, rest) = contents.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    // fields[0] is state (field 3); utime, stime and starttime are fields 14, 15 and 22
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    let start_ticks = fields.get(19)?.parse::<u64>().ok()?;
+    Some(ProcessStat {
+        cpu_ticks: utime + stime,
+        start_ticks,
+    })
+}
+
+fn parse_vm_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kib * 1024)
+}
+
+fn parse_boot_time(stat: &str) -> Option<u64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|value| value.trim().parse::<u64>().ok())
+}
+
+pub fn update_metrics() {
+    update_metrics_from_path(Path::new("/proc"));
+}
+
+fn update_metrics_from_path(proc_root: &Path) {
+    let metrics = metrics();
+    let self_dir = proc_root.join("self");
+    let ticks_per_second = clock_ticks_per_second();
+
+    if let Ok(contents) = fs::read_to_string(self_dir.join("stat"))
+        && let Some(stat) = parse_stat(&contents)
+    {
+        let mut previous = match metrics.previous_cpu_ticks.lock() {
+            Ok(previous) => previous,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let delta = counter_delta(stat.cpu_ticks, previous.unwrap_or(0));
+        metrics
+            .cpu_seconds_total
+            .inc_by(delta as f64 / ticks_per_second);
+        *previous = Some(stat.cpu_ticks);
+
+        if let Ok(proc_stat) = fs::read_to_string(proc_root.join("stat"))
+            && let Some(boot_time) = parse_boot_time(&proc_stat)
+        {
+            metrics
+                .start_time_seconds
+                .set(boot_time as f64 + stat.start_ticks as f64 / ticks_per_second);
+        }
+    }
+
+    if let Ok(status) = fs::read_to_string(self_dir.join("status"))
+        && let Some(rss) = parse_vm_rss_bytes(&status)
+    {
+        metrics.resident_memory_bytes.set(rss as f64);
+    }
+
+    if let Ok(entries) = fs::read_dir(self_dir.join("fd")) {
+        metrics.open_fds.set(entries.count() as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_with_spaces_in_comm() {
+        let contents = "1234 (rs linux) exporter) S 1 1234 1234 0 -1 4194560 500 0 0 0 \
+                        150 50 0 0 20 0 4 0 3000 123456789 2048 18446744073709551615";
+        assert_eq!(
+            parse_stat(contents),
+            Some(ProcessStat {
+                cpu_ticks: 200,
+                start_ticks: 3000,
+            })
+        );
+        assert_eq!(parse_stat("1234 (truncated) S 1"), None);
+    }
+
+    #[test]
+    fn parses_status_and_boot_time() {
+        let status = "Name:\trs-linux-export\nVmSize:\t  100000 kB\nVmRSS:\t    8192 kB\n";
+        assert_eq!(parse_vm_rss_bytes(status), Some(8192 * 1024));
+        assert_eq!(parse_vm_rss_bytes("Name:\tkthreadd\n"), None);
+        assert_eq!(
+            parse_boot_time("cpu  1 2 3 4\nbtime 1700000000\n"),
+            Some(1_700_000_000)
+        );
+    }
+
+    #[test]
+    fn reads_proc_self() {
+        // The exporter's own metrics are collected on every scrape, so assert
+        // against the live process rather than a mock tree.
+        update_metrics();
+        let metrics = metrics();
+        assert!(metrics.resident_memory_bytes.get() > 0.0);
+        assert!(metrics.open_fds.get() > 0.0);
+        assert!(metrics.start_time_seconds.get() > 0.0);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+
+const SLABINFO_PATH: &str = "/proc/slabinfo";
+
+/// Upper bound for [slabinfo] top_n, keeping the slab label bounded
+const MAX_TOP_N: usize = 200;
+
+struct SlabinfoMetrics {
+    objects_active: GaugeVec,
+    objects_total: GaugeVec,
+    size_bytes: GaugeVec,
+    /// Slabs exported last time, so those that left the top N disappear
+    top_slabs: Mutex<HashSet<String>>,
+}
+
+impl SlabinfoMetrics {
+    fn new() -> Self {
+        Self {
+            objects_active: prometheus::register_gauge_vec!(
+                "slab_objects_active",
+                "Objects in use in the slab cache",
+                &["slab"]
+            )
+            .expect("register slab_objects_active"),
+            objects_total: prometheus::register_gauge_vec!(
+                "slab_objects_total",
+                "Objects allocated in the slab cache, in use or free",
+                &["slab"]
+            )
+            .expect("register slab_objects_total"),
+            size_bytes: prometheus::register_gauge_vec!(
+                "slab_size_bytes",
+                "Memory held by the slab cache's objects in bytes (objects x object size)",
+                &["slab"]
+            )
+            .expect("register slab_size_bytes"),
+            top_slabs: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static SLABINFO_METRICS: OnceLock<SlabinfoMetrics> = OnceLock::new();
+
+fn metrics() -> &'static SlabinfoMetrics {
+    SLABINFO_METRICS.get_or_init(SlabinfoMetrics::new)
+}
+
+#[derive(Debug, PartialEq)]
+struct Slab {
+    name: String,
+    active_objects: u64,
+    total_objects: u64,
+    object_size: u64,
+}
+
+impl Slab {
+    fn size_bytes(&self) -> u64 {
+        self.total_objects.saturating_mul(self.object_size)
+    }
+}
+
+/// Parses /proc/slabinfo. Versions 1.x and 2.x both start each line with
+/// `name active_objs num_objs objsize`; they differ in the columns after it.
+/// Returns None for a missing header or an unknown version.
+fn parse_slabinfo(contents: &str) -> Option<Vec<Slab>> {
+    let mut lines = contents.lines();
+    let version = lines.next()?.strip_prefix("slabinfo - version: ")?;
+    let major = version.trim().split('.').next()?;
+    if major != "1" && major != "2" {
+        return None;
+    }
+
+    let slabs = lines
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let mut next = || fields.next()?.parse::<u64>().ok();
+            Some(Slab {
+                name: name.to_string(),
+                active_objects: next()?,
+                total_objects: next()?,
+                object_size: next()?,
+            })
+        })
+        .collect();
+    Some(slabs)
+}
+
+/// The `n` largest slabs by size, largest first
+fn top_slabs(mut slabs: Vec<Slab>, n: usize) -> Vec<Slab> {
+    slabs.sort_by(|a, b| {
+        b.size_bytes()
+            .cmp(&a.size_bytes())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    slabs.truncate(n);
+    slabs
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let Ok(contents) = fs::read_to_string(config.host_path(SLABINFO_PATH)) else {
+        return;
+    };
+    update_from_contents(&contents, config.slabinfo.top_n.min(MAX_TOP_N));
+}
+
+fn update_from_contents(contents: &str, top_n: usize) {
+    let Some(slabs) = parse_slabinfo(contents) else {
+        return;
+    };
+    let metrics = metrics();
+
+    let mut current = HashSet::new();
+    for slab in top_slabs(slabs, top_n) {
+        let labels = [slab.name.as_str()];
+        metrics
+            .objects_active
+            .with_label_values(&labels)
+            .set(slab.active_objects as f64);
+        metrics
+            .objects_total
+            .with_label_values(&labels)
+            .set(slab.total_objects as f64);
+        metrics
+            .size_bytes
+            .with_label_values(&labels)
+            .set(slab.size_bytes() as f64);
+        current.insert(slab.name);
+    }
+
+    let mut top = match metrics.top_slabs.lock() {
+        Ok(top) => top,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for name in top.difference(&current) {
+        let _ = metrics.objects_active.remove_label_values(&[name]);
+        let _ = metrics.objects_total.remove_label_values(&[name]);
+        let _ = metrics.size_bytes.remove_label_values(&[name]);
+    }
+    *top = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLABINFO_V2: &str = "\
+slabinfo - version: 2.1
+# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab> : tunables <limit> <batchcount> <sharedfactor> : slabdata <active_slabs> <num_slabs> <sharedavail>
+test_dentry       180000 190000    192   21    1 : tunables    0    0    0 : slabdata   9048   9048      0
+test_inode_cache   50000  52000    600   26    4 : tunables    0    0    0 : slabdata   2000   2000      0
+test_kmalloc_64     1000   1024     64   64    1 : tunables    0    0    0 : slabdata     16     16      0
+";
+
+    #[test]
+    fn test_parse_slabinfo_v2() {
+        let slabs = parse_slabinfo(SLABINFO_V2).unwrap();
+        assert_eq!(slabs.len(), 3);
+        assert_eq!(
+            slabs[0],
+            Slab {
+                name: "test_dentry".to_string(),
+                active_objects: 180000,
+                total_objects: 190000,
+                object_size: 192,
+            }
+        );
+        assert_eq!(slabs[1].size_bytes(), 52000 * 600);
+    }
+
+    #[test]
+    fn test_parse_slabinfo_v1() {
+        // 1.1: name active_objs num_objs objsize active_slabs num_slabs pagesperslab
+        let contents = "slabinfo - version: 1.1\nkmem_cache 59 78 100 2 2 1\n";
+        let slabs = parse_slabinfo(contents).unwrap();
+        assert_eq!(slabs[0].name, "kmem_cache");
+        assert_eq!(slabs[0].size_bytes(), 7800);
+
+        assert!(parse_slabinfo("slabinfo - version: 3.0\n").is_none());
+        assert!(parse_slabinfo("kmem_cache 59 78 100\n").is_none());
+    }
+
+    #[test]
+    fn test_update_from_contents_keeps_top_n() {
+        update_from_contents(SLABINFO_V2, 2);
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .size_bytes
+                .with_label_values(&["test_inode_cache"])
+                .get(),
+            31_200_000.0
+        );
+        assert_eq!(
+            metrics
+                .objects_active
+                .with_label_values(&["test_dentry"])
+                .get(),
+            180000.0
+        );
+        assert!(
+            metrics
+                .size_bytes
+                .remove_label_values(&["test_kmalloc_64"])
+                .is_err()
+        );
+
+        // Slabs that fall out of the top N disappear
+        update_from_contents(SLABINFO_V2, 1);
+        assert!(
+            metrics
+                .size_bytes
+                .remove_label_values(&["test_dentry"])
+                .is_ok()
+        );
+        assert!(
+            metrics
+                .size_bytes
+                .remove_label_values(&["test_inode_cache"])
+                .is_err()
+        );
+    }
+}
@@ -1,20 +1,90 @@
-use prometheus::GaugeVec;
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use crate::cpu_aggregate;
+use prometheus::{GaugeVec, IntCounterVec};
+use std::collections::HashMap;
 use std::fs;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// (column, metric, help) for the monotonic softnet_stat columns
+const COUNTERS: &[(usize, &str, &str)] = &[
+    (
+        0,
+        "softnet_processed_total",
+        "Packets processed by the CPU's network softirq",
+    ),
+    (
+        1,
+        "softnet_dropped_total",
+        "Packets dropped because the backlog queue was full",
+    ),
+    (
+        2,
+        "softnet_time_squeeze_total",
+        "NAPI runs that exhausted their budget or time with work remaining",
+    ),
+    (
+        9,
+        "softnet_received_rps_total",
+        "Inter-processor interrupts received to process packets steered by RPS",
+    ),
+    (
+        10,
+        "softnet_flow_limit_count_total",
+        "Packets dropped by the RPS flow limit",
+    ),
+];
 
 struct SoftnetMetrics {
     softnet: GaugeVec,
+    counters: HashMap<&'static str, IntCounterVec>,
+    /// (cpu, metric) -> last raw counter value
+    previous_counters: Mutex<HashMap<(String, &'static str), u64>>,
+    dropped_per_second: GaugeVec,
+    time_squeeze_per_second: GaugeVec,
+    /// cpu -> counters from the previous scrape, for the derived rates
+    previous: Mutex<HashMap<String, SoftnetSample>>,
+}
+
+struct SoftnetSample {
+    at: Instant,
+    dropped: u64,
+    time_squeeze: u64,
 }
 
 impl SoftnetMetrics {
     fn new() -> Self {
+        let counters = COUNTERS
+            .iter()
+            .map(|(_, name, help)| {
+                let counter = prometheus::register_int_counter_vec!(*name, *help, &["cpu"])
+                    .unwrap_or_else(|err| panic!("register {name}: {err}"));
+                (*name, counter)
+            })
+            .collect();
         Self {
+            counters,
+            previous_counters: Mutex::new(HashMap::new()),
             softnet: prometheus::register_gauge_vec!(
                 "softnet",
-                "Per-CPU counters from /proc/net/softnet_stat",
+                "Per-CPU queue lengths from /proc/net/softnet_stat",
                 &["cpu", "field"]
             )
             .expect("register softnet"),
+            dropped_per_second: prometheus::register_gauge_vec!(
+                "softnet_dropped_per_second",
+                "Packets dropped per second because the backlog queue was full, since the previous scrape",
+                &["cpu"]
+            )
+            .expect("register softnet_dropped_per_second"),
+            time_squeeze_per_second: prometheus::register_gauge_vec!(
+                "softnet_time_squeeze_per_second",
+                "NAPI runs per second that exhausted their budget or time, since the previous scrape",
+                &["cpu"]
+            )
+            .expect("register softnet_time_squeeze_per_second"),
+            previous: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -33,11 +103,111 @@ fn parse_column(columns: &[&str], index: usize) -> Option<u64> {
     columns.get(index).and_then(|value| parse_hex_u64(value))
 }
 
-pub fn update_metrics() {
-    let contents = match fs::read_to_string("/proc/net/softnet_stat") {
+/// Rate between two readings of a 32-bit kernel counter; None when no time passed
+fn per_second(current: u64, previous: u64, elapsed: Duration) -> Option<f64> {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return None;
+    }
+    Some(counter_delta(current, previous) as f64 / seconds)
+}
+
+/// (column, field) of the queue length gauges exported in `softnet`
+const QUEUE_FIELDS: &[(usize, &str)] = &[
+    (11, "softnet_backlog_len_total"),
+    (13, "softnet_input_qlen"),
+    (14, "softnet_process_qlen"),
+];
+
+/// Adds the CPU's counter increases to the `series` cpu label, which is the
+/// CPU itself or "total" with cpu_aggregate_only
+fn update_counters(metrics: &SoftnetMetrics, cpu: &str, series: &str, columns: &[&str]) {
+    let mut previous = match metrics.previous_counters.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (column, name, _) in COUNTERS {
+        let Some(value) = parse_column(columns, *column) else {
+            continue;
+        };
+        let last = previous
+            .insert((cpu.to_string(), *name), value)
+            .unwrap_or(0);
+        if let Some(counter) = metrics.counters.get(name) {
+            counter
+                .with_label_values(&[series])
+                .inc_by(counter_delta(value, last));
+        }
+    }
+}
+
+/// Per-second dropped and time_squeeze rates of the CPU since the previous
+/// scrape; None on its first scrape or when no time passed
+fn update_rates(
+    metrics: &SoftnetMetrics,
+    cpu: &str,
+    dropped: u64,
+    time_squeeze: u64,
+) -> Option<(f64, f64)> {
+    let now = Instant::now();
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let sample = SoftnetSample {
+        at: now,
+        dropped,
+        time_squeeze,
+    };
+    // The first scrape only records a baseline
+    let last = previous.insert(cpu.to_string(), sample)?;
+    let elapsed = now.duration_since(last.at);
+    Some((
+        per_second(dropped, last.dropped, elapsed)?,
+        per_second(time_squeeze, last.time_squeeze, elapsed)?,
+    ))
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    let contents = match fs::read_to_string(config.host_path("/proc/net/softnet_stat")) {
         Ok(contents) => contents,
         Err(_) => return,
     };
+    update_metrics_from_contents(&contents, !config.cpu_aggregate_only);
+}
+
+fn set_rates(metrics: &SoftnetMetrics, cpu: &str, (dropped, time_squeeze): (f64, f64)) {
+    metrics
+        .dropped_per_second
+        .with_label_values(&[cpu])
+        .set(dropped);
+    metrics
+        .time_squeeze_per_second
+        .with_label_values(&[cpu])
+        .set(time_squeeze);
+}
+
+/// Removes every series of the `cpu` label, a CPU or "total"
+fn remove_series(metrics: &SoftnetMetrics, cpu: &str) {
+    for counter in metrics.counters.values() {
+        let _ = counter.remove_label_values(&[cpu]);
+    }
+    let _ = metrics.dropped_per_second.remove_label_values(&[cpu]);
+    let _ = metrics.time_squeeze_per_second.remove_label_values(&[cpu]);
+    for (_, field) in QUEUE_FIELDS {
+        let _ = metrics.softnet.remove_label_values(&[cpu, field]);
+    }
+    let _ = metrics
+        .softnet
+        .remove_label_values(&[cpu, "softnet_cpu_index"]);
+}
+
+fn update_metrics_from_contents(contents: &str, per_cpu: bool) {
+    let metrics = metrics();
+    // Sums over all CPUs, exported as cpu "total" when !per_cpu
+    let mut total_rates: Option<(f64, f64)> = None;
+    let mut total_queues: HashMap<&str, u64> = HashMap::new();
+    let mut cpus = Vec::new();
 
     for (cpu, line) in contents.lines().enumerate() {
         let columns: Vec<&str> = line.split_whitespace().collect();
@@ -46,37 +216,144 @@ pub fn update_metrics() {
         }
 
         let cpu_label = cpu.to_string();
-        let metric = &metrics().softnet;
         let set_metric = |field: &str, value: u64| {
-            metric
+            metrics
+                .softnet
                 .with_label_values(&[cpu_label.as_str(), field])
                 .set(value as f64);
         };
 
-        if let Some(value) = parse_column(&columns, 0) {
-            set_metric("softnet_processed_counter", value);
-        }
-        if let Some(value) = parse_column(&columns, 1) {
-            set_metric("softnet_dropped_counter", value);
-        }
-        if let Some(value) = parse_column(&columns, 2) {
-            set_metric("softnet_time_squeeze_counter", value);
-        }
-        if let Some(value) = parse_column(&columns, 9) {
-            set_metric("softnet_received_rps_counter", value);
+        let series = if per_cpu { cpu_label.as_str() } else { "total" };
+        update_counters(metrics, &cpu_label, series, &columns);
+        if let (Some(dropped), Some(time_squeeze)) =
+            (parse_column(&columns, 1), parse_column(&columns, 2))
+            && let Some(rates) = update_rates(metrics, &cpu_label, dropped, time_squeeze)
+        {
+            if per_cpu {
+                set_rates(metrics, &cpu_label, rates);
+            } else {
+                let total = total_rates.get_or_insert((0.0, 0.0));
+                total.0 += rates.0;
+                total.1 += rates.1;
+            }
         }
-        if let Some(value) = parse_column(&columns, 10) {
-            set_metric("softnet_flow_limit_count_counter", value);
+        for (column, field) in QUEUE_FIELDS {
+            if let Some(value) = parse_column(&columns, *column) {
+                if per_cpu {
+                    set_metric(field, value);
+                } else {
+                    *total_queues.entry(field).or_default() += value;
+                }
+            }
         }
-        if let Some(value) = parse_column(&columns, 11) {
-            set_metric("softnet_backlog_len_total", value);
-        }
-        set_metric("softnet_cpu_index", cpu as u64);
-        if let Some(value) = parse_column(&columns, 13) {
-            set_metric("softnet_input_qlen", value);
-        }
-        if let Some(value) = parse_column(&columns, 14) {
-            set_metric("softnet_process_qlen", value);
+        if per_cpu {
+            set_metric("softnet_cpu_index", cpu as u64);
         }
+        cpus.push(cpu_label);
+    }
+
+    cpu_aggregate::remove_other_mode(per_cpu, cpus.iter().map(String::as_str), |cpu| {
+        remove_series(metrics, cpu)
+    });
+    if let Some(rates) = total_rates {
+        set_rates(metrics, "total", rates);
+    }
+    for (field, value) in total_queues {
+        metrics
+            .softnet
+            .with_label_values(&["total", field])
+            .set(value as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Collecting removes the "total" or per-CPU series of the other mode, so
+    // tests that collect must not interleave
+    static COLLECT_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_per_second() {
+        assert_eq!(per_second(150, 100, Duration::from_secs(10)), Some(5.0));
+        assert_eq!(per_second(100, 100, Duration::from_millis(500)), Some(0.0));
+        // Counter wrapped or reset: count from zero
+        assert_eq!(
+            per_second(20, 4_294_967_290, Duration::from_secs(2)),
+            Some(10.0)
+        );
+        assert_eq!(per_second(150, 100, Duration::ZERO), None);
+    }
+
+    fn softnet_line(processed: u64, dropped: u64) -> String {
+        format!(
+            "{processed:08x} {dropped:08x} 00000003 00000000 00000000 00000000 00000000 \
+             00000000 00000000 00000004 00000000 00000000 00000000 00000000 00000000\n"
+        )
+    }
+
+    #[test]
+    fn test_counters_are_monotonic() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let processed = &metrics().counters["softnet_processed_total"];
+        let dropped = &metrics().counters["softnet_dropped_total"];
+
+        update_metrics_from_contents(&softnet_line(1000, 5), true);
+        let first = (
+            processed.with_label_values(&["0"]).get(),
+            dropped.with_label_values(&["0"]).get(),
+        );
+        assert!(first.0 >= 1000);
+
+        update_metrics_from_contents(&softnet_line(1500, 5), true);
+        let second = (
+            processed.with_label_values(&["0"]).get(),
+            dropped.with_label_values(&["0"]).get(),
+        );
+        assert!(second.0 >= first.0 + 500);
+        assert!(second.1 >= first.1);
+
+        // A wrapped 32-bit counter must not make the exported counter go backwards
+        update_metrics_from_contents(&softnet_line(10, 0), true);
+        let third = (
+            processed.with_label_values(&["0"]).get(),
+            dropped.with_label_values(&["0"]).get(),
+        );
+        assert!(third.0 >= second.0 + 10);
+        assert!(third.1 >= second.1);
+    }
+
+    #[test]
+    fn test_aggregate_only_sums_queue_lengths() {
+        let _guard = COLLECT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // Columns 11 (backlog_len) and 13/14 (input/process qlen) are gauges
+        let line = |backlog: u64| {
+            format!(
+                "00000000 00000000 00000000 00000000 00000000 00000000 00000000 \
+                 00000000 00000000 00000000 00000000 {backlog:08x} 00000000 00000002 00000001\n"
+            )
+        };
+        let contents = format!("{}{}", line(3), line(4));
+        update_metrics_from_contents(&contents, false);
+
+        let softnet = &metrics().softnet;
+        assert_eq!(
+            softnet
+                .with_label_values(&["total", "softnet_backlog_len_total"])
+                .get(),
+            7.0
+        );
+        assert_eq!(
+            softnet
+                .with_label_values(&["total", "softnet_input_qlen"])
+                .get(),
+            4.0
+        );
+        assert!(
+            softnet
+                .remove_label_values(&["total", "softnet_cpu_index"])
+                .is_err()
+        );
     }
 }
@@ -0,0 +1,184 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct SwapsMetrics {
+    size_bytes: GaugeVec,
+    used_bytes: GaugeVec,
+    devices: Mutex<HashSet<(String, String)>>,
+}
+
+impl SwapsMetrics {
+    fn new() -> Self {
+        Self {
+            size_bytes: prometheus::register_gauge_vec!(
+                "swap_device_size_bytes",
+                "Size of the swap device or file in bytes",
+                &["device", "type"]
+            )
+            .expect("register swap_device_size_bytes"),
+            used_bytes: prometheus::register_gauge_vec!(
+                "swap_device_used_bytes",
+                "Swap space in use on the device or file in bytes",
+                &["device", "type"]
+            )
+            .expect("register swap_device_used_bytes"),
+            devices: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static SWAPS_METRICS: OnceLock<SwapsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static SwapsMetrics {
+    SWAPS_METRICS.get_or_init(SwapsMetrics::new)
+}
+
+#[derive(Debug, PartialEq)]
+struct SwapDevice {
+    device: String,
+    kind: String,
+    size_kib: u64,
+    used_kib: u64,
+}
+
+/// The kernel escapes whitespace and backslashes in paths as `\ooo`
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(value) = field
+                .get(i + 1..i + 4)
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok())
+        {
+            out.push(value);
+            i += 4;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_swaps(contents: &str) -> Vec<SwapDevice> {
+    contents
+        .lines()
+        .skip(1) // Filename Type Size Used Priority
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = unescape_octal(fields.next()?);
+            let kind = fields.next()?.to_string();
+            let size_kib = fields.next()?.parse::<u64>().ok()?;
+            let used_kib = fields.next()?.parse::<u64>().ok()?;
+            Some(SwapDevice {
+                device,
+                kind,
+                size_kib,
+                used_kib,
+            })
+        })
+        .collect()
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/swaps"));
+}
+
+fn update_metrics_from_path(path: &Path) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+    let metrics = metrics();
+
+    let mut current = HashSet::new();
+    for swap in parse_swaps(&contents) {
+        let labels = [swap.device.as_str(), swap.kind.as_str()];
+        metrics
+            .size_bytes
+            .with_label_values(&labels)
+            .set((swap.size_kib * 1024) as f64);
+        metrics
+            .used_bytes
+            .with_label_values(&labels)
+            .set((swap.used_kib * 1024) as f64);
+        current.insert((swap.device, swap.kind));
+    }
+
+    let mut devices = match metrics.devices.lock() {
+        Ok(devices) => devices,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Drop series for devices removed with swapoff
+    for (device, kind) in devices.difference(&current) {
+        let labels = [device.as_str(), kind.as_str()];
+        let _ = metrics.size_bytes.remove_label_values(&labels);
+        let _ = metrics.used_bytes.remove_label_values(&labels);
+    }
+    *devices = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+    use tempfile::TempDir;
+
+    const SWAPS: &str = "Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority\n\
+                         /dev/nvme0n1p3                          partition\t8388604\t\t102400\t\t-2\n\
+                         /swap\\040file                           file\t\t2097148\t\t0\t\t-3\n";
+
+    #[test]
+    fn parses_devices_and_unescapes_paths() {
+        let devices = parse_swaps(SWAPS);
+        assert_eq!(
+            devices,
+            vec![
+                SwapDevice {
+                    device: "/dev/nvme0n1p3".to_string(),
+                    kind: "partition".to_string(),
+                    size_kib: 8388604,
+                    used_kib: 102400,
+                },
+                SwapDevice {
+                    device: "/swap file".to_string(),
+                    kind: "file".to_string(),
+                    size_kib: 2097148,
+                    used_kib: 0,
+                },
+            ]
+        );
+        assert!(parse_swaps("Filename\tType\tSize\tUsed\tPriority\n").is_empty());
+    }
+
+    #[test]
+    fn removes_devices_after_swapoff() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("swaps");
+        fs::write(&path, SWAPS).unwrap();
+        update_metrics_from_path(&path);
+
+        fs::write(
+            &path,
+            "Filename\tType\tSize\tUsed\tPriority\n/dev/nvme0n1p3 partition 8388604 0 -2\n",
+        )
+        .unwrap();
+        update_metrics_from_path(&path);
+
+        let devices: Vec<String> = metrics()
+            .size_bytes
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric().to_vec())
+            .flat_map(|metric| metric.get_label().to_vec())
+            .filter(|label| label.name() == "device")
+            .map(|label| label.value().to_string())
+            .collect();
+        assert!(!devices.contains(&"/swap file".to_string()));
+    }
+}
@@ -1,13 +1,21 @@
+use crate::config::AppConfig;
 use prometheus::{Gauge, GaugeVec};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 
 struct ThermalMetrics {
     zone_temperature_celsius: GaugeVec,
     zone_trip_point_celsius: GaugeVec,
+    zone_trip_point_hysteresis_celsius: GaugeVec,
     cooling_device_cur_state: GaugeVec,
     cooling_device_max_state: GaugeVec,
+    zone_cooling_binding: GaugeVec,
+    zone_enabled: GaugeVec,
+    zone_policy: GaugeVec,
+    /// Policy exported per zone, so a governor change drops the old series
+    zone_policies: Mutex<HashMap<String, String>>,
     zone_count: Gauge,
     cooling_device_count: Gauge,
 }
@@ -29,6 +37,13 @@ impl ThermalMetrics {
             )
             .expect("register thermal_zone_trip_point_celsius"),
 
+            zone_trip_point_hysteresis_celsius: prometheus::register_gauge_vec!(
+                "thermal_zone_trip_point_hysteresis_celsius",
+                "Trip point hysteresis in Celsius",
+                &["zone", "type", "trip_point", "trip_type"]
+            )
+            .expect("register thermal_zone_trip_point_hysteresis_celsius"),
+
             cooling_device_cur_state: prometheus::register_gauge_vec!(
                 "thermal_cooling_device_cur_state",
                 "Current cooling state of the device",
@@ -43,6 +58,29 @@ impl ThermalMetrics {
             )
             .expect("register thermal_cooling_device_max_state"),
 
+            zone_cooling_binding: prometheus::register_gauge_vec!(
+                "thermal_zone_cooling_binding",
+                "Cooling device bound to a thermal zone trip point (always 1)",
+                &["zone", "cooling_device", "trip_point"]
+            )
+            .expect("register thermal_zone_cooling_binding"),
+
+            zone_enabled: prometheus::register_gauge_vec!(
+                "thermal_zone_enabled",
+                "Whether the kernel monitors the thermal zone (1 = enabled, 0 = disabled)",
+                &["zone"]
+            )
+            .expect("register thermal_zone_enabled"),
+
+            zone_policy: prometheus::register_gauge_vec!(
+                "thermal_zone_policy",
+                "Thermal governor of the zone (always 1)",
+                &["zone", "policy"]
+            )
+            .expect("register thermal_zone_policy"),
+
+            zone_policies: Mutex::new(HashMap::new()),
+
             zone_count: prometheus::register_gauge!(
                 "thermal_zone_count",
                 "Number of thermal zones"
@@ -86,6 +124,17 @@ fn update_thermal_zone(zone_path: &Path, zone_name: &str) {
             .set(millidegrees as f64 / 1000.0);
     }
 
+    // mode and policy are missing on platforms without a thermal governor
+    if let Some(mode) = read_string(&zone_path.join("mode")) {
+        metrics
+            .zone_enabled
+            .with_label_values(&[zone_name])
+            .set(if mode == "enabled" { 1.0 } else { 0.0 });
+    }
+    if let Some(policy) = read_string(&zone_path.join("policy")) {
+        update_zone_policy(zone_name, policy);
+    }
+
     // Read trip points
     let entries = match fs::read_dir(zone_path) {
         Ok(entries) => entries,
@@ -111,9 +160,63 @@ fn update_thermal_zone(zone_path: &Path, zone_name: &str) {
                     .zone_trip_point_celsius
                     .with_label_values(&[zone_name, &zone_type, index, &trip_type])
                     .set(millidegrees as f64 / 1000.0);
+
+                let hyst_path = zone_path.join(format!("trip_point_{}_hyst", index));
+                if let Some(hyst_millidegrees) = read_i64(&hyst_path) {
+                    metrics
+                        .zone_trip_point_hysteresis_celsius
+                        .with_label_values(&[zone_name, &zone_type, index, &trip_type])
+                        .set(hyst_millidegrees as f64 / 1000.0);
+                }
             }
         }
+
+        // Match cdevN symlinks to bound cooling devices
+        if let Some(index) = file_name.strip_prefix("cdev")
+            && !index.is_empty()
+            && index.chars().all(|c| c.is_ascii_digit())
+        {
+            update_cooling_binding(zone_path, zone_name, &file_name);
+        }
+    }
+}
+
+fn update_zone_policy(zone_name: &str, policy: String) {
+    let metrics = metrics();
+    let mut policies = match metrics.zone_policies.lock() {
+        Ok(policies) => policies,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if let Some(previous) = policies.get(zone_name)
+        && *previous != policy
+    {
+        let _ = metrics
+            .zone_policy
+            .remove_label_values(&[zone_name, previous.as_str()]);
     }
+    metrics
+        .zone_policy
+        .with_label_values(&[zone_name, policy.as_str()])
+        .set(1.0);
+    policies.insert(zone_name.to_string(), policy);
+}
+
+fn update_cooling_binding(zone_path: &Path, zone_name: &str, cdev: &str) {
+    // cdevN -> ../cooling_deviceM
+    let device = match fs::read_link(zone_path.join(cdev)) {
+        Ok(target) => match target.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    let trip_point = read_string(&zone_path.join(format!("{cdev}_trip_point")))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    metrics()
+        .zone_cooling_binding
+        .with_label_values(&[zone_name, &device, &trip_point])
+        .set(1.0);
 }
 
 fn update_cooling_device(device_path: &Path, device_name: &str) {
@@ -140,9 +243,9 @@ fn update_cooling_device(device_path: &Path, device_name: &str) {
     }
 }
 
-pub fn update_metrics() {
-    let base = Path::new("/sys/class/thermal");
-    let entries = match fs::read_dir(base) {
+pub fn update_metrics(config: &AppConfig) {
+    let base = config.host_path("/sys/class/thermal");
+    let entries = match fs::read_dir(&base) {
         Ok(entries) => entries,
         Err(_) => return,
     };
@@ -178,6 +281,7 @@ pub fn update_metrics() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use prometheus::core::Collector;
     use tempfile::TempDir;
 
     fn create_thermal_zone(
@@ -251,6 +355,109 @@ mod tests {
         update_thermal_zone(&zone, "thermal_zone0");
     }
 
+    #[test]
+    fn test_update_thermal_zone_with_hysteresis() {
+        let dir = TempDir::new().unwrap();
+        let zone = create_thermal_zone(dir.path(), "thermal_zone91", "acpitz", 45000);
+        fs::write(zone.join("trip_point_0_temp"), "70000\n").unwrap();
+        fs::write(zone.join("trip_point_0_type"), "passive\n").unwrap();
+        fs::write(zone.join("trip_point_0_hyst"), "2000\n").unwrap();
+        fs::write(zone.join("trip_point_1_temp"), "95000\n").unwrap();
+        fs::write(zone.join("trip_point_1_type"), "critical\n").unwrap();
+
+        update_thermal_zone(&zone, "thermal_zone91");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .zone_trip_point_hysteresis_celsius
+                .with_label_values(&["thermal_zone91", "acpitz", "0", "passive"])
+                .get(),
+            2.0
+        );
+
+        let hyst_series = metrics.zone_trip_point_hysteresis_celsius.collect()[0]
+            .get_metric()
+            .iter()
+            .filter(|m| m.get_label().iter().any(|l| l.value() == "thermal_zone91"))
+            .count();
+        assert_eq!(hyst_series, 1);
+    }
+
+    #[test]
+    fn test_update_thermal_zone_with_cooling_bindings() {
+        let dir = TempDir::new().unwrap();
+        create_cooling_device(dir.path(), "cooling_device7", "Fan", 0, 3);
+        let zone = create_thermal_zone(dir.path(), "thermal_zone90", "acpitz", 45000);
+        std::os::unix::fs::symlink("../cooling_device7", zone.join("cdev0")).unwrap();
+        fs::write(zone.join("cdev0_trip_point"), "1\n").unwrap();
+        fs::write(zone.join("cdev0_weight"), "0\n").unwrap();
+
+        update_thermal_zone(&zone, "thermal_zone90");
+
+        assert_eq!(
+            metrics()
+                .zone_cooling_binding
+                .with_label_values(&["thermal_zone90", "cooling_device7", "1"])
+                .get(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_update_thermal_zone_mode_and_policy() {
+        let dir = TempDir::new().unwrap();
+        let zone = create_thermal_zone(dir.path(), "thermal_zone92", "acpitz", 45000);
+        fs::write(zone.join("mode"), "disabled\n").unwrap();
+        fs::write(zone.join("policy"), "step_wise\n").unwrap();
+
+        update_thermal_zone(&zone, "thermal_zone92");
+
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .zone_enabled
+                .with_label_values(&["thermal_zone92"])
+                .get(),
+            0.0
+        );
+        assert_eq!(
+            metrics
+                .zone_policy
+                .with_label_values(&["thermal_zone92", "step_wise"])
+                .get(),
+            1.0
+        );
+
+        // Switching governor replaces the policy series
+        fs::write(zone.join("mode"), "enabled\n").unwrap();
+        fs::write(zone.join("policy"), "power_allocator\n").unwrap();
+        update_thermal_zone(&zone, "thermal_zone92");
+        assert_eq!(
+            metrics
+                .zone_enabled
+                .with_label_values(&["thermal_zone92"])
+                .get(),
+            1.0
+        );
+        assert!(
+            metrics
+                .zone_policy
+                .remove_label_values(&["thermal_zone92", "step_wise"])
+                .is_err()
+        );
+
+        // Simpler platforms have neither file
+        let plain = create_thermal_zone(dir.path(), "thermal_zone93", "acpitz", 40000);
+        update_thermal_zone(&plain, "thermal_zone93");
+        assert!(
+            metrics
+                .zone_enabled
+                .remove_label_values(&["thermal_zone93"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_update_cooling_device() {
         let dir = TempDir::new().unwrap();
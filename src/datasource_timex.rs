@@ -0,0 +1,175 @@
+use crate::internal_metrics;
+use crate::logging;
+use crate::runtime::debug_enabled;
+use prometheus::Gauge;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// adjtimex(2) frequency values are in ppm with a 16-bit fractional part
+const FREQ_SCALE: f64 = 65_536.0 * 1_000_000.0;
+
+struct TimexMetrics {
+    offset_seconds: Gauge,
+    frequency_adjustment_ratio: Gauge,
+    maxerror_seconds: Gauge,
+    estimated_error_seconds: Gauge,
+    tick_seconds: Gauge,
+    sync_status: Gauge,
+}
+
+impl TimexMetrics {
+    fn new() -> Self {
+        Self {
+            offset_seconds: prometheus::register_gauge!(
+                "node_timex_offset_seconds",
+                "Time offset between local system and reference clock in seconds"
+            )
+            .expect("register node_timex_offset_seconds"),
+            frequency_adjustment_ratio: prometheus::register_gauge!(
+                "node_timex_frequency_adjustment_ratio",
+                "Local clock frequency adjustment as a ratio (1 means no adjustment)"
+            )
+            .expect("register node_timex_frequency_adjustment_ratio"),
+            maxerror_seconds: prometheus::register_gauge!(
+                "node_timex_maxerror_seconds",
+                "Maximum error in seconds"
+            )
+            .expect("register node_timex_maxerror_seconds"),
+            estimated_error_seconds: prometheus::register_gauge!(
+                "node_timex_estimated_error_seconds",
+                "Estimated error in seconds"
+            )
+            .expect("register node_timex_estimated_error_seconds"),
+            tick_seconds: prometheus::register_gauge!(
+                "node_timex_tick_seconds",
+                "Length of a clock tick in seconds"
+            )
+            .expect("register node_timex_tick_seconds"),
+            sync_status: prometheus::register_gauge!(
+                "node_timex_sync_status",
+                "Whether the clock is synchronized to a reliable source (1 = synchronized)"
+            )
+            .expect("register node_timex_sync_status"),
+        }
+    }
+}
+
+static TIMEX_METRICS: OnceLock<TimexMetrics> = OnceLock::new();
+static TIMEX_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+fn metrics() -> &'static TimexMetrics {
+    TIMEX_METRICS.get_or_init(TimexMetrics::new)
+}
+
+/// Values from adjtimex(2) converted to base units
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TimexReading {
+    offset_seconds: f64,
+    frequency_ratio: f64,
+    maxerror_seconds: f64,
+    estimated_error_seconds: f64,
+    tick_seconds: f64,
+    synchronized: bool,
+}
+
+fn convert(tx: &libc::timex) -> TimexReading {
+    // offset is in nanoseconds with STA_NANO, microseconds otherwise
+    let offset_divisor = if tx.status & libc::STA_NANO != 0 {
+        1_000_000_000.0
+    } else {
+        1_000_000.0
+    };
+
+    TimexReading {
+        offset_seconds: tx.offset as f64 / offset_divisor,
+        frequency_ratio: 1.0 + tx.freq as f64 / FREQ_SCALE,
+        maxerror_seconds: tx.maxerror as f64 / 1_000_000.0,
+        estimated_error_seconds: tx.esterror as f64 / 1_000_000.0,
+        tick_seconds: tx.tick as f64 / 1_000_000.0,
+        synchronized: tx.status & libc::STA_UNSYNC == 0,
+    }
+}
+
+fn read_timex() -> Result<TimexReading, std::io::Error> {
+    let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+    // modes = 0 only queries the kernel clock state
+    let rc = unsafe { libc::adjtimex(&mut tx) };
+    if rc == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(convert(&tx))
+}
+
+pub fn update_metrics() {
+    if TIMEX_UNAVAILABLE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let reading = match read_timex() {
+        Ok(reading) => reading,
+        Err(err) => {
+            // Seccomp profiles in some containers reject adjtimex outright
+            if err.raw_os_error() == Some(libc::EPERM) {
+                TIMEX_UNAVAILABLE.store(true, Ordering::Relaxed);
+            }
+            internal_metrics::collector_error("timex");
+            if debug_enabled() {
+                logging::debug("timex", &format!("timex: adjtimex failed: {err}"));
+            }
+            return;
+        }
+    };
+
+    let metrics = metrics();
+    metrics.offset_seconds.set(reading.offset_seconds);
+    metrics
+        .frequency_adjustment_ratio
+        .set(reading.frequency_ratio);
+    metrics.maxerror_seconds.set(reading.maxerror_seconds);
+    metrics
+        .estimated_error_seconds
+        .set(reading.estimated_error_seconds);
+    metrics.tick_seconds.set(reading.tick_seconds);
+    metrics
+        .sync_status
+        .set(if reading.synchronized { 1.0 } else { 0.0 });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_timex(status: libc::c_int, offset: libc::c_long) -> libc::timex {
+        let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+        tx.status = status;
+        tx.offset = offset;
+        tx.freq = 65_536 * 10;
+        tx.maxerror = 16_000;
+        tx.esterror = 500;
+        tx.tick = 10_000;
+        tx
+    }
+
+    #[test]
+    fn test_convert_microsecond_offset() {
+        let reading = convert(&mock_timex(libc::STA_PLL, 1_500));
+        assert_eq!(reading.offset_seconds, 0.0015);
+        assert!(reading.synchronized);
+        assert!((reading.frequency_ratio - 1.00001).abs() < 1e-12);
+        assert_eq!(reading.maxerror_seconds, 0.016);
+        assert_eq!(reading.estimated_error_seconds, 0.0005);
+        assert_eq!(reading.tick_seconds, 0.01);
+    }
+
+    #[test]
+    fn test_convert_nanosecond_offset() {
+        let reading = convert(&mock_timex(libc::STA_NANO, 1_500));
+        assert_eq!(reading.offset_seconds, 0.0000015);
+    }
+
+    #[test]
+    fn test_convert_unsynchronized() {
+        let reading = convert(&mock_timex(libc::STA_UNSYNC, 0));
+        assert!(!reading.synchronized);
+    }
+}
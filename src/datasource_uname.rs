@@ -0,0 +1,96 @@
+use prometheus::GaugeVec;
+use std::sync::OnceLock;
+
+struct UnameMetrics {
+    info: GaugeVec,
+}
+
+impl UnameMetrics {
+    fn new() -> Self {
+        Self {
+            info: prometheus::register_gauge_vec!(
+                "node_uname_info",
+                "Kernel information from uname(2)",
+                &["sysname", "release", "version", "machine"]
+            )
+            .expect("register node_uname_info"),
+        }
+    }
+}
+
+static UNAME_METRICS: OnceLock<UnameMetrics> = OnceLock::new();
+
+fn metrics() -> &'static UnameMetrics {
+    UNAME_METRICS.get_or_init(UnameMetrics::new)
+}
+
+#[derive(Debug)]
+struct Uname {
+    sysname: String,
+    release: String,
+    version: String,
+    machine: String,
+}
+
+fn field_to_string(field: &[libc::c_char]) -> String {
+    // uname(2) fields are NUL-terminated within their fixed-size buffers
+    let bytes: Vec<u8> = field
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn read_uname() -> Option<Uname> {
+    let mut buf: libc::utsname = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::uname(&mut buf) };
+    if rc != 0 {
+        return None;
+    }
+
+    Some(Uname {
+        sysname: field_to_string(&buf.sysname),
+        release: field_to_string(&buf.release),
+        version: field_to_string(&buf.version),
+        machine: field_to_string(&buf.machine),
+    })
+}
+
+pub fn update_metrics() {
+    let Some(uname) = read_uname() else {
+        return;
+    };
+
+    metrics()
+        .info
+        .with_label_values(&[
+            &uname.sysname,
+            &uname.release,
+            &uname.version,
+            &uname.machine,
+        ])
+        .set(1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_to_string_stops_at_nul() {
+        let field: Vec<libc::c_char> = b"Linux\0garbage"
+            .iter()
+            .map(|b| *b as libc::c_char)
+            .collect();
+        assert_eq!(field_to_string(&field), "Linux");
+    }
+
+    #[test]
+    fn test_read_uname() {
+        let uname = read_uname().expect("uname");
+        assert_eq!(uname.sysname, "Linux");
+        assert!(!uname.release.is_empty());
+        assert!(!uname.machine.is_empty());
+    }
+}
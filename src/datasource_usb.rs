@@ -0,0 +1,195 @@
+use crate::config::AppConfig;
+use prometheus::{Gauge, GaugeVec};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const USB_DEVICES: &str = "/sys/bus/usb/devices";
+/// bDeviceClass of hubs, which say nothing about what is plugged in
+const USB_CLASS_HUB: &str = "09";
+
+struct UsbMetrics {
+    device_info: GaugeVec,
+    device_count: Gauge,
+    /// sysfs device name -> info label values exported last time, so unplugged
+    /// devices disappear
+    exported: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl UsbMetrics {
+    fn new() -> Self {
+        Self {
+            device_info: prometheus::register_gauge_vec!(
+                "usb_device_info",
+                "Attached USB device (always 1, identity in labels)",
+                &[
+                    "bus",
+                    "port",
+                    "vendor_id",
+                    "product_id",
+                    "manufacturer",
+                    "product"
+                ]
+            )
+            .expect("register usb_device_info"),
+            device_count: prometheus::register_gauge!(
+                "usb_device_count",
+                "Number of attached USB devices, not counting hubs"
+            )
+            .expect("register usb_device_count"),
+            exported: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+static USB_METRICS: OnceLock<UsbMetrics> = OnceLock::new();
+
+fn metrics() -> &'static UsbMetrics {
+    USB_METRICS.get_or_init(UsbMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Device directories are named `<bus>-<port>[.<port>...]`; root hubs are
+/// `usb<bus>` and interfaces carry a `:<config>.<interface>` suffix.
+fn is_device_name(name: &str) -> bool {
+    !name.starts_with("usb") && !name.contains(':') && name.contains('-')
+}
+
+/// Info label values of a non-hub device, or None for hubs and unreadable devices
+fn device_labels(path: &Path, name: &str) -> Option<Vec<String>> {
+    if read_string(&path.join("bDeviceClass")).as_deref() == Some(USB_CLASS_HUB) {
+        return None;
+    }
+    let vendor_id = read_string(&path.join("idVendor"))?;
+    let product_id = read_string(&path.join("idProduct"))?;
+    let (bus, port) = name.split_once('-')?;
+    Some(vec![
+        read_string(&path.join("busnum")).unwrap_or_else(|| bus.to_string()),
+        read_string(&path.join("devpath")).unwrap_or_else(|| port.to_string()),
+        vendor_id,
+        product_id,
+        read_string(&path.join("manufacturer")).unwrap_or_default(),
+        read_string(&path.join("product")).unwrap_or_default(),
+    ])
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path(USB_DEVICES), config);
+}
+
+fn update_metrics_from_path(base: &Path, config: &AppConfig) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+    let mut current = HashMap::new();
+    let mut count = 0;
+
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if !is_device_name(&name) {
+            continue;
+        }
+        let Some(labels) = device_labels(&entry.path(), &name) else {
+            continue;
+        };
+        count += 1;
+        if !config.is_usb_device_enabled(&labels[2], &labels[3]) {
+            continue;
+        }
+        let values: Vec<&str> = labels.iter().map(String::as_str).collect();
+        metrics.device_info.with_label_values(&values).set(1.0);
+        current.insert(name, labels);
+    }
+    metrics.device_count.set(count as f64);
+
+    let mut exported = match metrics.exported.lock() {
+        Ok(exported) => exported,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (name, labels) in exported.iter() {
+        if current.get(name) != Some(labels) {
+            let values: Vec<&str> = labels.iter().map(String::as_str).collect();
+            let _ = metrics.device_info.remove_label_values(&values);
+        }
+    }
+    *exported = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_mock_device(base: &Path, name: &str, class: &str, ids: (&str, &str), product: &str) {
+        let path = base.join(name);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("bDeviceClass"), format!("{class}\n")).unwrap();
+        fs::write(path.join("idVendor"), format!("{}\n", ids.0)).unwrap();
+        fs::write(path.join("idProduct"), format!("{}\n", ids.1)).unwrap();
+        fs::write(path.join("busnum"), "3\n").unwrap();
+        fs::write(path.join("devpath"), format!("{}\n", &name[2..])).unwrap();
+        fs::write(path.join("manufacturer"), "Test Corp\n").unwrap();
+        fs::write(path.join("product"), format!("{product}\n")).unwrap();
+    }
+
+    fn info_labels(product: &str) -> [&str; 6] {
+        ["3", "4.2", "0c2e", "0b61", "Test Corp", product]
+    }
+
+    #[test]
+    fn test_is_device_name() {
+        assert!(is_device_name("1-1"));
+        assert!(is_device_name("3-4.2"));
+        assert!(!is_device_name("usb1"));
+        assert!(!is_device_name("1-1:1.0"));
+    }
+
+    #[test]
+    fn test_update_metrics_from_path() {
+        let dir = TempDir::new().unwrap();
+        create_mock_device(
+            dir.path(),
+            "3-4.2",
+            "00",
+            ("0c2e", "0b61"),
+            "Test Barcode Scanner",
+        );
+        create_mock_device(dir.path(), "3-4", USB_CLASS_HUB, ("05e3", "0610"), "Hub");
+        create_mock_device(dir.path(), "usb3", USB_CLASS_HUB, ("1d6b", "0002"), "Root");
+        fs::create_dir_all(dir.path().join("3-4.2:1.0")).unwrap();
+        let config = AppConfig::default();
+
+        update_metrics_from_path(dir.path(), &config);
+        let metrics = metrics();
+        assert_eq!(
+            metrics
+                .device_info
+                .with_label_values(&info_labels("Test Barcode Scanner"))
+                .get(),
+            1.0
+        );
+        assert!(
+            metrics
+                .device_info
+                .remove_label_values(&["3", "4", "05e3", "0610", "Test Corp", "Hub"])
+                .is_err()
+        );
+
+        // Unplugged devices disappear
+        fs::remove_dir_all(dir.path().join("3-4.2")).unwrap();
+        update_metrics_from_path(dir.path(), &config);
+        assert!(
+            metrics
+                .device_info
+                .remove_label_values(&info_labels("Test Barcode Scanner"))
+                .is_err()
+        );
+    }
+}
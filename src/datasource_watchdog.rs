@@ -0,0 +1,237 @@
+use crate::config::AppConfig;
+use prometheus::GaugeVec;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+struct WatchdogMetrics {
+    timeout_seconds: GaugeVec,
+    timeleft_seconds: GaugeVec,
+    pretimeout_seconds: GaugeVec,
+    status: GaugeVec,
+    bootstatus: GaugeVec,
+    /// device -> identity of the series exported last time
+    identities: Mutex<HashMap<String, String>>,
+}
+
+impl WatchdogMetrics {
+    fn new() -> Self {
+        Self {
+            timeout_seconds: prometheus::register_gauge_vec!(
+                "watchdog_timeout_seconds",
+                "Watchdog timeout in seconds",
+                &["device", "identity"]
+            )
+            .expect("register watchdog_timeout_seconds"),
+            timeleft_seconds: prometheus::register_gauge_vec!(
+                "watchdog_timeleft_seconds",
+                "Seconds left before the watchdog resets the system",
+                &["device", "identity"]
+            )
+            .expect("register watchdog_timeleft_seconds"),
+            pretimeout_seconds: prometheus::register_gauge_vec!(
+                "watchdog_pretimeout_seconds",
+                "Seconds before the timeout at which the pretimeout governor runs",
+                &["device", "identity"]
+            )
+            .expect("register watchdog_pretimeout_seconds"),
+            status: prometheus::register_gauge_vec!(
+                "watchdog_status",
+                "Watchdog flags (1 = set): active, nowayout",
+                &["device", "identity", "flag"]
+            )
+            .expect("register watchdog_status"),
+            bootstatus: prometheus::register_gauge_vec!(
+                "watchdog_bootstatus",
+                "WDIOF_* reasons reported for the last boot (0 = normal boot)",
+                &["device", "identity"]
+            )
+            .expect("register watchdog_bootstatus"),
+            identities: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn remove_device(&self, device: &str, identity: &str) {
+        for gauge in [
+            &self.timeout_seconds,
+            &self.timeleft_seconds,
+            &self.pretimeout_seconds,
+            &self.bootstatus,
+        ] {
+            let _ = gauge.remove_label_values(&[device, identity]);
+        }
+        for flag in STATUS_FLAGS {
+            let _ = self.status.remove_label_values(&[device, identity, flag]);
+        }
+    }
+}
+
+const STATUS_FLAGS: &[&str] = &["active", "nowayout"];
+
+static WATCHDOG_METRICS: OnceLock<WatchdogMetrics> = OnceLock::new();
+
+fn metrics() -> &'static WatchdogMetrics {
+    WATCHDOG_METRICS.get_or_init(WatchdogMetrics::new)
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_string(path)?.parse::<u64>().ok()
+}
+
+fn update_watchdog(metrics: &WatchdogMetrics, path: &Path, device: &str) -> String {
+    let identity = read_string(&path.join("identity")).unwrap_or_default();
+    let labels = [device, identity.as_str()];
+
+    // timeleft and pretimeout only exist when the driver supports them
+    for (file, gauge) in [
+        ("timeout", &metrics.timeout_seconds),
+        ("timeleft", &metrics.timeleft_seconds),
+        ("pretimeout", &metrics.pretimeout_seconds),
+        ("bootstatus", &metrics.bootstatus),
+    ] {
+        if let Some(value) = read_u64(&path.join(file)) {
+            gauge.with_label_values(&labels).set(value as f64);
+        }
+    }
+
+    if let Some(state) = read_string(&path.join("state")) {
+        metrics
+            .status
+            .with_label_values(&[device, identity.as_str(), "active"])
+            .set(if state == "active" { 1.0 } else { 0.0 });
+    }
+    if let Some(nowayout) = read_u64(&path.join("nowayout")) {
+        metrics
+            .status
+            .with_label_values(&[device, identity.as_str(), "nowayout"])
+            .set(if nowayout != 0 { 1.0 } else { 0.0 });
+    }
+
+    identity
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/sys/class/watchdog"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    let metrics = metrics();
+
+    let mut current = HashMap::new();
+    for entry in entries.flatten() {
+        let Ok(device) = entry.file_name().into_string() else {
+            continue;
+        };
+        if !device.starts_with("watchdog") {
+            continue;
+        }
+        let identity = update_watchdog(metrics, &entry.path(), &device);
+        current.insert(device, identity);
+    }
+
+    let mut identities = match metrics.identities.lock() {
+        Ok(identities) => identities,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for (device, identity) in identities.iter() {
+        if current.get(device) != Some(identity) {
+            metrics.remove_device(device, identity);
+        }
+    }
+    *identities = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+    use tempfile::TempDir;
+
+    fn create_mock_watchdog(base: &Path, name: &str, files: &[(&str, &str)]) {
+        let dir = base.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, value) in files {
+            fs::write(dir.join(file), format!("{value}\n")).unwrap();
+        }
+    }
+
+    fn devices(gauge: &GaugeVec) -> Vec<String> {
+        gauge
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric().to_vec())
+            .flat_map(|metric| metric.get_label().to_vec())
+            .filter(|label| label.name() == "device")
+            .map(|label| label.value().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_update_metrics_reads_watchdog_attributes() {
+        let dir = TempDir::new().unwrap();
+        create_mock_watchdog(
+            dir.path(),
+            "watchdog7",
+            &[
+                ("identity", "iTCO_wdt"),
+                ("timeout", "30"),
+                ("timeleft", "24"),
+                ("state", "active"),
+                ("nowayout", "1"),
+                ("bootstatus", "0"),
+            ],
+        );
+        update_metrics_from_path(dir.path());
+
+        let metrics = metrics();
+        let labels = ["watchdog7", "iTCO_wdt"];
+        assert_eq!(
+            metrics.timeout_seconds.with_label_values(&labels).get(),
+            30.0
+        );
+        assert_eq!(
+            metrics.timeleft_seconds.with_label_values(&labels).get(),
+            24.0
+        );
+        assert_eq!(
+            metrics
+                .status
+                .with_label_values(&["watchdog7", "iTCO_wdt", "active"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .status
+                .with_label_values(&["watchdog7", "iTCO_wdt", "nowayout"])
+                .get(),
+            1.0
+        );
+        // No pretimeout file: no series
+        assert!(!devices(&metrics.pretimeout_seconds).contains(&"watchdog7".to_string()));
+    }
+
+    #[test]
+    fn test_update_metrics_removes_vanished_devices() {
+        let dir = TempDir::new().unwrap();
+        create_mock_watchdog(
+            dir.path(),
+            "watchdog8",
+            &[("identity", "softdog"), ("timeout", "60")],
+        );
+        update_metrics_from_path(dir.path());
+        assert!(devices(&metrics().timeout_seconds).contains(&"watchdog8".to_string()));
+
+        fs::remove_dir_all(dir.path().join("watchdog8")).unwrap();
+        update_metrics_from_path(dir.path());
+        assert!(!devices(&metrics().timeout_seconds).contains(&"watchdog8".to_string()));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::IntCounterVec;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// (row, zero-based position, metric, help) from the XFS stats format,
+/// documented in Documentation/filesystems/xfs/xfs-stats and xfs_stats.h
+const COUNTERS: &[(&str, usize, &str, &str)] = &[
+    (
+        "extent_alloc",
+        0,
+        "xfs_allocated_extents_total",
+        "Extents allocated",
+    ),
+    (
+        "extent_alloc",
+        1,
+        "xfs_allocated_blocks_total",
+        "Filesystem blocks allocated",
+    ),
+    (
+        "extent_alloc",
+        2,
+        "xfs_freed_extents_total",
+        "Extents freed",
+    ),
+    (
+        "extent_alloc",
+        3,
+        "xfs_freed_blocks_total",
+        "Filesystem blocks freed",
+    ),
+    (
+        "blk_map",
+        0,
+        "xfs_block_map_reads_total",
+        "Block map read operations",
+    ),
+    (
+        "blk_map",
+        1,
+        "xfs_block_map_writes_total",
+        "Block map write operations",
+    ),
+    ("log", 0, "xfs_log_writes_total", "Log buffer writes"),
+    (
+        "log",
+        1,
+        "xfs_log_blocks_total",
+        "512-byte blocks written to the log",
+    ),
+    (
+        "log",
+        2,
+        "xfs_log_noiclogs_total",
+        "Times no in-core log buffer was available",
+    ),
+    ("log", 3, "xfs_log_forces_total", "Log force operations"),
+    ("rw", 0, "xfs_write_calls_total", "write(2) system calls"),
+    ("rw", 1, "xfs_read_calls_total", "read(2) system calls"),
+    ("xpc", 1, "xfs_write_bytes_total", "Bytes written"),
+    ("xpc", 2, "xfs_read_bytes_total", "Bytes read"),
+];
+
+struct XfsMetrics {
+    counters: HashMap<&'static str, IntCounterVec>,
+    /// (device, metric) -> last raw counter value
+    previous: Mutex<HashMap<(String, &'static str), u64>>,
+    devices: Mutex<HashSet<String>>,
+}
+
+impl XfsMetrics {
+    fn new() -> Self {
+        let counters = COUNTERS
+            .iter()
+            .map(|(_, _, name, help)| {
+                let counter = prometheus::register_int_counter_vec!(*name, *help, &["device"])
+                    .unwrap_or_else(|err| panic!("register {name}: {err}"));
+                (*name, counter)
+            })
+            .collect();
+        Self {
+            counters,
+            previous: Mutex::new(HashMap::new()),
+            devices: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static XFS_METRICS: OnceLock<XfsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static XfsMetrics {
+    XFS_METRICS.get_or_init(XfsMetrics::new)
+}
+
+/// Picks the documented positional counters out of an XFS stats file
+fn parse_stats(contents: &str) -> HashMap<&'static str, u64> {
+    let rows: HashMap<&str, Vec<u64>> = contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let row = fields.next()?;
+            let values = fields.filter_map(|v| v.parse::<u64>().ok()).collect();
+            Some((row, values))
+        })
+        .collect();
+
+    COUNTERS
+        .iter()
+        .filter_map(|(row, index, name, _)| Some((*name, *rows.get(row)?.get(*index)?)))
+        .collect()
+}
+
+/// Per-mount stats from /sys/fs/xfs/<device>/stats/stats (Linux 4.6+)
+fn read_device_stats(sys_base: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(sys_base) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let device = entry.file_name().into_string().ok()?;
+            // /sys/fs/xfs/stats holds the global copy of /proc/fs/xfs/stat
+            if device == "stats" {
+                return None;
+            }
+            let contents = fs::read_to_string(entry.path().join("stats/stats")).ok()?;
+            Some((device, contents))
+        })
+        .collect()
+}
+
+fn has_xfs_mount() -> bool {
+    procfs::mounts()
+        .map(|mounts| mounts.iter().any(|mount| mount.fs_vfstype == "xfs"))
+        .unwrap_or(false)
+}
+
+/// Per-mount stats, or the global counters on kernels without them
+fn read_sources(proc_stat: &Path, sys_base: &Path) -> Vec<(String, String)> {
+    let mut sources = read_device_stats(sys_base);
+    if sources.is_empty()
+        && let Ok(contents) = fs::read_to_string(proc_stat)
+    {
+        sources.push((String::new(), contents));
+    }
+    sources
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    // The module stays loaded after the last unmount; report nothing then
+    let sources = if has_xfs_mount() {
+        read_sources(
+            &config.host_path("/proc/fs/xfs/stat"),
+            &config.host_path("/sys/fs/xfs"),
+        )
+    } else {
+        Vec::new()
+    };
+    update_metrics_from_sources(&sources);
+}
+
+fn update_metrics_from_sources(sources: &[(String, String)]) {
+    let metrics = metrics();
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut current = HashSet::new();
+    for (device, contents) in sources {
+        for (name, value) in parse_stats(contents) {
+            let last = previous.insert((device.clone(), name), value).unwrap_or(0);
+            if let Some(counter) = metrics.counters.get(name) {
+                counter
+                    .with_label_values(&[device])
+                    .inc_by(counter_delta(value, last));
+            }
+        }
+        current.insert(device.clone());
+    }
+
+    let mut devices = match metrics.devices.lock() {
+        Ok(devices) => devices,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for device in devices.difference(&current) {
+        for counter in metrics.counters.values() {
+            let _ = counter.remove_label_values(&[device]);
+        }
+    }
+    previous.retain(|(device, _), _| current.contains(device));
+    *devices = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const STATS: &str = "extent_alloc 4260849 125170297 4618726 131131897\n\
+                         abt 0 0 0 0\n\
+                         blk_map 381213360 115456141 10903633 69612322 7448401 507596777 0\n\
+                         log 2380 57420 0 11223 11221\n\
+                         rw 35716 71280\n\
+                         xpc 399724544 92823103 86219234\n\
+                         debug 0\n";
+
+    #[test]
+    fn test_parse_stats() {
+        let stats = parse_stats(STATS);
+        assert_eq!(stats["xfs_allocated_extents_total"], 4260849);
+        assert_eq!(stats["xfs_freed_blocks_total"], 131131897);
+        assert_eq!(stats["xfs_block_map_writes_total"], 115456141);
+        assert_eq!(stats["xfs_log_forces_total"], 11223);
+        assert_eq!(stats["xfs_write_calls_total"], 35716);
+        assert_eq!(stats["xfs_read_calls_total"], 71280);
+        assert_eq!(stats["xfs_write_bytes_total"], 92823103);
+        assert_eq!(stats["xfs_read_bytes_total"], 86219234);
+
+        // Rows missing from older kernels are skipped, not zeroed
+        let partial = parse_stats("rw 1 2\n");
+        assert_eq!(partial.len(), 2);
+        assert!(!partial.contains_key("xfs_read_bytes_total"));
+    }
+
+    #[test]
+    fn test_read_sources_prefers_per_mount_stats() {
+        let dir = TempDir::new().unwrap();
+        let proc_stat = dir.path().join("stat");
+        fs::write(&proc_stat, "rw 999 999\n").unwrap();
+        let sys_base = dir.path().join("xfs");
+        fs::create_dir_all(sys_base.join("stats")).unwrap();
+        fs::write(sys_base.join("stats/stats"), "rw 999 999\n").unwrap();
+
+        // Without per-mount directories the global file is used
+        let sources = read_sources(&proc_stat, &sys_base);
+        assert_eq!(sources, vec![(String::new(), "rw 999 999\n".to_string())]);
+
+        fs::create_dir_all(sys_base.join("sdb1/stats")).unwrap();
+        fs::write(sys_base.join("sdb1/stats/stats"), STATS).unwrap();
+        let sources = read_sources(&proc_stat, &sys_base);
+        assert_eq!(sources, vec![("sdb1".to_string(), STATS.to_string())]);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::config::AppConfig;
+use crate::counter_reset::counter_delta;
+use prometheus::{Gauge, GaugeVec, IntCounter};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Pool states reported by /proc/spl/kstat/zfs/<pool>/state
+const POOL_STATES: &[&str] = &[
+    "ONLINE",
+    "DEGRADED",
+    "FAULTED",
+    "OFFLINE",
+    "UNAVAIL",
+    "REMOVED",
+    "SUSPENDED",
+];
+
+struct ZfsMetrics {
+    arc_size_bytes: Gauge,
+    arc_c_max_bytes: Gauge,
+    arc_hits_total: IntCounter,
+    arc_misses_total: IntCounter,
+    arc_hit_ratio: Gauge,
+    pool_state: GaugeVec,
+    /// (hits, misses) from the previous read
+    previous: Mutex<Option<(u64, u64)>>,
+    pools: Mutex<HashSet<String>>,
+}
+
+impl ZfsMetrics {
+    fn new() -> Self {
+        Self {
+            arc_size_bytes: prometheus::register_gauge!(
+                "zfs_arc_size_bytes",
+                "Current size of the ZFS ARC in bytes"
+            )
+            .expect("register zfs_arc_size_bytes"),
+            arc_c_max_bytes: prometheus::register_gauge!(
+                "zfs_arc_c_max_bytes",
+                "Maximum size the ZFS ARC may grow to in bytes"
+            )
+            .expect("register zfs_arc_c_max_bytes"),
+            arc_hits_total: prometheus::register_int_counter!(
+                "zfs_arc_hits_total",
+                "ZFS ARC lookups served from the cache"
+            )
+            .expect("register zfs_arc_hits_total"),
+            arc_misses_total: prometheus::register_int_counter!(
+                "zfs_arc_misses_total",
+                "ZFS ARC lookups that missed the cache"
+            )
+            .expect("register zfs_arc_misses_total"),
+            arc_hit_ratio: prometheus::register_gauge!(
+                "zfs_arc_hit_ratio",
+                "ZFS ARC hit ratio since the module was loaded (0-1)"
+            )
+            .expect("register zfs_arc_hit_ratio"),
+            pool_state: prometheus::register_gauge_vec!(
+                "zfs_pool_state",
+                "ZFS pool state (1 for the current state, 0 otherwise)",
+                &["pool", "state"]
+            )
+            .expect("register zfs_pool_state"),
+            previous: Mutex::new(None),
+            pools: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+static ZFS_METRICS: OnceLock<ZfsMetrics> = OnceLock::new();
+
+fn metrics() -> &'static ZfsMetrics {
+    ZFS_METRICS.get_or_init(ZfsMetrics::new)
+}
+
+/// Parses a kstat file: a header line, a `name type data` line, then one
+/// value per line
+fn parse_kstat(contents: &str) -> HashMap<&str, u64> {
+    contents
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let value = fields.nth(1)?.parse::<u64>().ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn update_arc(metrics: &ZfsMetrics, contents: &str) {
+    let stats = parse_kstat(contents);
+    if let Some(size) = stats.get("size") {
+        metrics.arc_size_bytes.set(*size as f64);
+    }
+    if let Some(c_max) = stats.get("c_max") {
+        metrics.arc_c_max_bytes.set(*c_max as f64);
+    }
+
+    let (Some(&hits), Some(&misses)) = (stats.get("hits"), stats.get("misses")) else {
+        return;
+    };
+    let mut previous = match metrics.previous.lock() {
+        Ok(previous) => previous,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let (last_hits, last_misses) = previous.unwrap_or((0, 0));
+    metrics
+        .arc_hits_total
+        .inc_by(counter_delta(hits, last_hits));
+    metrics
+        .arc_misses_total
+        .inc_by(counter_delta(misses, last_misses));
+    *previous = Some((hits, misses));
+
+    if hits + misses > 0 {
+        metrics
+            .arc_hit_ratio
+            .set(hits as f64 / (hits + misses) as f64);
+    }
+}
+
+pub fn update_metrics(config: &AppConfig) {
+    update_metrics_from_path(&config.host_path("/proc/spl/kstat/zfs"));
+}
+
+fn update_metrics_from_path(base: &Path) {
+    let Ok(contents) = fs::read_to_string(base.join("arcstats")) else {
+        return;
+    };
+    let metrics = metrics();
+    update_arc(metrics, &contents);
+
+    let mut current = HashSet::new();
+    if let Ok(entries) = fs::read_dir(base) {
+        for entry in entries.flatten() {
+            let Ok(state) = fs::read_to_string(entry.path().join("state")) else {
+                continue;
+            };
+            let Ok(pool) = entry.file_name().into_string() else {
+                continue;
+            };
+            let state = state.trim();
+            for known in POOL_STATES {
+                metrics
+                    .pool_state
+                    .with_label_values(&[pool.as_str(), known])
+                    .set(if *known == state { 1.0 } else { 0.0 });
+            }
+            current.insert(pool);
+        }
+    }
+
+    let mut pools = match metrics.pools.lock() {
+        Ok(pools) => pools,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    // Drop series for exported or destroyed pools
+    for pool in pools.difference(&current) {
+        for known in POOL_STATES {
+            let _ = metrics
+                .pool_state
+                .remove_label_values(&[pool.as_str(), known]);
+        }
+    }
+    *pools = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn arcstats(hits: u64, misses: u64) -> String {
+        format!(
+            "13 1 0x01 123 33472 5858585 8585858\n\
+             name                            type data\n\
+             hits                            4    {hits}\n\
+             misses                          4    {misses}\n\
+             c_max                           4    8589934592\n\
+             size                            4    4294967296\n"
+        )
+    }
+
+    #[test]
+    fn test_parse_kstat() {
+        let contents = arcstats(900, 100);
+        let stats = parse_kstat(&contents);
+        assert_eq!(stats["hits"], 900);
+        assert_eq!(stats["c_max"], 8589934592);
+        assert!(!stats.contains_key("name"));
+    }
+
+    #[test]
+    fn test_update_metrics_reads_arc_and_pools() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("arcstats"), arcstats(900, 100)).unwrap();
+        fs::create_dir_all(dir.path().join("tank")).unwrap();
+        fs::write(dir.path().join("tank/state"), "DEGRADED\n").unwrap();
+        update_metrics_from_path(dir.path());
+
+        let metrics = metrics();
+        assert_eq!(metrics.arc_size_bytes.get(), 4294967296.0);
+        assert_eq!(metrics.arc_c_max_bytes.get(), 8589934592.0);
+        assert_eq!(metrics.arc_hits_total.get(), 900);
+        assert_eq!(metrics.arc_hit_ratio.get(), 0.9);
+        assert_eq!(
+            metrics
+                .pool_state
+                .with_label_values(&["tank", "DEGRADED"])
+                .get(),
+            1.0
+        );
+        assert_eq!(
+            metrics
+                .pool_state
+                .with_label_values(&["tank", "ONLINE"])
+                .get(),
+            0.0
+        );
+
+        fs::write(dir.path().join("arcstats"), arcstats(1500, 500)).unwrap();
+        fs::remove_dir_all(dir.path().join("tank")).unwrap();
+        update_metrics_from_path(dir.path());
+        assert_eq!(metrics.arc_hits_total.get(), 1500);
+        assert_eq!(metrics.arc_misses_total.get(), 500);
+        assert_eq!(metrics.arc_hit_ratio.get(), 0.75);
+        assert!(metrics.pools.lock().unwrap().is_empty());
+    }
+}
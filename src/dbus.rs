@@ -0,0 +1,611 @@
+//! Minimal blocking D-Bus client: EXTERNAL authentication on the system bus
+//! socket, method calls with string arguments and a reader for any reply body.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+const DEFAULT_SYSTEM_BUS: &str = "/run/dbus/system_bus_socket";
+const SYSTEM_BUS_ENV_VAR: &str = "DBUS_SYSTEM_BUS_ADDRESS";
+const TIMEOUT_MS: u64 = 2000;
+/// Replies larger than this are rejected instead of buffered
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+const MESSAGE_METHOD_CALL: u8 = 1;
+const MESSAGE_METHOD_RETURN: u8 = 2;
+const MESSAGE_ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// A decoded D-Bus value; object paths and signatures are kept as `Str`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    Double(f64),
+    Str(String),
+    UnixFd(u32),
+    Array(Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    /// Looks through variant wrappers
+    fn inner(&self) -> &Value {
+        match self {
+            Value::Variant(value) => value.inner(),
+            value => value,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.inner() {
+            Value::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.inner() {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self.inner() {
+            Value::Byte(value) => Some(value.into()),
+            Value::I16(value) => Some(value.into()),
+            Value::U16(value) => Some(value.into()),
+            Value::I32(value) => Some(value.into()),
+            Value::U32(value) => Some(value.into()),
+            Value::I64(value) => Some(value),
+            Value::U64(value) => i64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self.inner() {
+            Value::U64(value) => Some(value),
+            _ => self.as_i64().and_then(|value| u64::try_from(value).ok()),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self.inner() {
+            Value::Double(value) => Some(value),
+            _ => self.as_i64().map(|value| value as f64),
+        }
+    }
+
+    pub fn as_array(&self) -> &[Value] {
+        match self.inner() {
+            Value::Array(items) | Value::Struct(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// Entries of a dictionary (`a{..}`) as key/value pairs
+    pub fn entries(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.as_array().iter().filter_map(|item| match item {
+            Value::DictEntry(key, value) => Some((key.as_ref(), value.inner())),
+            _ => None,
+        })
+    }
+
+    /// Value stored under a string or object path key of a dictionary
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries()
+            .find(|(entry, _)| entry.as_str() == Some(key))
+            .map(|(_, value)| value)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn align_of(code: u8) -> usize {
+    match code {
+        b'y' | b'g' | b'v' => 1,
+        b'n' | b'q' => 2,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 4,
+    }
+}
+
+/// Length of the first complete type in `sig`
+fn single_type_len(sig: &[u8]) -> io::Result<usize> {
+    match sig.first() {
+        Some(b'a') => Ok(1 + single_type_len(&sig[1..])?),
+        Some(&open @ (b'(' | b'{')) => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut len = 1;
+            while sig.get(len) != Some(&close) {
+                if len >= sig.len() {
+                    return Err(invalid("unterminated container in signature"));
+                }
+                len += single_type_len(&sig[len..])?;
+            }
+            Ok(len + 1)
+        }
+        Some(_) => Ok(1),
+        None => Err(invalid("empty signature")),
+    }
+}
+
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        while !self.buf.len().is_multiple_of(alignment) {
+            self.buf.push(0);
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.u8(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn string_field(&mut self, code: u8, type_code: &str, value: &str) {
+        self.align(8);
+        self.u8(code);
+        self.signature(type_code);
+        if type_code == "g" {
+            self.signature(value);
+        } else {
+            self.string(value);
+        }
+    }
+}
+
+/// Little-endian method call whose arguments are all strings
+fn encode_method_call(
+    serial: u32,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    member: &str,
+    args: &[&str],
+) -> Vec<u8> {
+    let mut body = Writer { buf: Vec::new() };
+    for arg in args {
+        body.string(arg);
+    }
+
+    let mut msg = Writer { buf: Vec::new() };
+    msg.buf
+        .extend_from_slice(&[b'l', MESSAGE_METHOD_CALL, 0, 1]);
+    msg.u32(body.buf.len() as u32);
+    msg.u32(serial);
+    // Header field array length, patched once the fields are written
+    msg.u32(0);
+    let fields_start = msg.buf.len();
+    msg.string_field(FIELD_PATH, "o", path);
+    msg.string_field(FIELD_INTERFACE, "s", interface);
+    msg.string_field(FIELD_MEMBER, "s", member);
+    msg.string_field(FIELD_DESTINATION, "s", destination);
+    if !args.is_empty() {
+        msg.string_field(FIELD_SIGNATURE, "g", &"s".repeat(args.len()));
+    }
+    let fields_len = (msg.buf.len() - fields_start) as u32;
+    msg.buf[12..16].copy_from_slice(&fields_len.to_le_bytes());
+    msg.align(8);
+    msg.buf.extend_from_slice(&body.buf);
+    msg.buf
+}
+
+/// Decodes values from one message; offsets are relative to the message start
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn align(&mut self, alignment: usize) -> io::Result<()> {
+        let aligned = self.pos.next_multiple_of(alignment);
+        if aligned > self.buf.len() {
+            return Err(invalid("truncated message"));
+        }
+        self.pos = aligned;
+        Ok(())
+    }
+
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        self.align(N)?;
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| invalid("truncated message"))?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice length"))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take::<4>()?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let bytes = self.take::<8>()?;
+        Ok(if self.little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take::<2>()?;
+        Ok(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn bytes_with_nul(&mut self, len: usize) -> io::Result<String> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("truncated string"))?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn signature(&mut self) -> io::Result<String> {
+        let len = self.take::<1>()?[0] as usize;
+        self.bytes_with_nul(len)
+    }
+
+    /// Decodes one complete type described by `sig`
+    fn value(&mut self, sig: &[u8]) -> io::Result<Value> {
+        Ok(match sig[0] {
+            b'y' => Value::Byte(self.take::<1>()?[0]),
+            b'b' => Value::Bool(self.u32()? != 0),
+            b'n' => Value::I16(self.u16()? as i16),
+            b'q' => Value::U16(self.u16()?),
+            b'i' => Value::I32(self.u32()? as i32),
+            b'u' => Value::U32(self.u32()?),
+            b'h' => Value::UnixFd(self.u32()?),
+            b'x' => Value::I64(self.u64()? as i64),
+            b't' => Value::U64(self.u64()?),
+            b'd' => Value::Double(f64::from_bits(self.u64()?)),
+            b's' | b'o' => {
+                let len = self.u32()? as usize;
+                Value::Str(self.bytes_with_nul(len)?)
+            }
+            b'g' => Value::Str(self.signature()?),
+            b'v' => {
+                let inner = self.signature()?;
+                if single_type_len(inner.as_bytes())? != inner.len() {
+                    return Err(invalid("variant must hold a single type"));
+                }
+                Value::Variant(Box::new(self.value(inner.as_bytes())?))
+            }
+            b'a' => {
+                let len = self.u32()? as usize;
+                let element = &sig[1..1 + single_type_len(&sig[1..])?];
+                self.align(align_of(element[0]))?;
+                let end = self.pos + len;
+                if end > self.buf.len() {
+                    return Err(invalid("truncated array"));
+                }
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.value(element)?);
+                }
+                Value::Array(items)
+            }
+            b'(' => {
+                self.align(8)?;
+                let inner = &sig[1..single_type_len(sig)? - 1];
+                Value::Struct(self.values(inner)?)
+            }
+            b'{' => {
+                self.align(8)?;
+                let key_len = single_type_len(&sig[1..])?;
+                let key = self.value(&sig[1..1 + key_len])?;
+                let value = self.value(&sig[1 + key_len..])?;
+                Value::DictEntry(Box::new(key), Box::new(value))
+            }
+            code => return Err(invalid(&format!("unsupported type code {}", code as char))),
+        })
+    }
+
+    /// Decodes a sequence of complete types
+    fn values(&mut self, mut sig: &[u8]) -> io::Result<Vec<Value>> {
+        let mut values = Vec::new();
+        while !sig.is_empty() {
+            let len = single_type_len(sig)?;
+            values.push(self.value(&sig[..len])?);
+            sig = &sig[len..];
+        }
+        Ok(values)
+    }
+}
+
+/// Reply to a method call
+#[derive(Debug)]
+struct Message {
+    kind: u8,
+    reply_serial: Option<u32>,
+    error_name: Option<String>,
+    body: Vec<Value>,
+}
+
+fn decode_message(buf: &[u8]) -> io::Result<Message> {
+    if buf.len() < 16 {
+        return Err(invalid("truncated header"));
+    }
+    let mut reader = Reader {
+        buf,
+        pos: 4,
+        little_endian: buf[0] == b'l',
+    };
+    let body_len = reader.u32()? as usize;
+    reader.pos = 12;
+    let fields = reader.value(b"a(yv)")?;
+    reader.align(8)?;
+    let body_start = reader.pos;
+
+    let mut message = Message {
+        kind: buf[1],
+        reply_serial: None,
+        error_name: None,
+        body: Vec::new(),
+    };
+    let mut signature = String::new();
+    for field in fields.as_array() {
+        let [code, value] = field.as_array() else {
+            continue;
+        };
+        match code {
+            Value::Byte(FIELD_REPLY_SERIAL) => {
+                message.reply_serial = value.as_u64().map(|serial| serial as u32)
+            }
+            Value::Byte(FIELD_ERROR_NAME) => message.error_name = value.as_str().map(String::from),
+            Value::Byte(FIELD_SIGNATURE) => signature = value.as_str().unwrap_or("").to_string(),
+            _ => {}
+        }
+    }
+
+    let body = buf
+        .get(body_start..body_start + body_len)
+        .ok_or_else(|| invalid("truncated body"))?;
+    // Body alignment is relative to its own start, which is 8-byte aligned
+    let mut reader = Reader {
+        buf: body,
+        pos: 0,
+        little_endian: reader.little_endian,
+    };
+    message.body = reader.values(signature.as_bytes())?;
+    Ok(message)
+}
+
+/// Bytes still to read after the 16-byte fixed header
+fn remaining_len(header: &[u8; 16]) -> io::Result<usize> {
+    let read = |bytes: [u8; 4]| {
+        if header[0] == b'l' {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    };
+    let body_len = read(header[4..8].try_into().expect("slice length")) as usize;
+    let fields_len = read(header[12..16].try_into().expect("slice length")) as usize;
+    let total = (16 + fields_len).next_multiple_of(8) + body_len;
+    if total > MAX_MESSAGE_LEN {
+        return Err(invalid("message too large"));
+    }
+    Ok(total - 16)
+}
+
+fn system_bus_path() -> String {
+    // Only plain unix:path= addresses are supported
+    std::env::var(SYSTEM_BUS_ENV_VAR)
+        .ok()
+        .and_then(|address| {
+            address
+                .split(';')
+                .find_map(|entry| entry.strip_prefix("unix:path="))
+                .map(|path| path.split(',').next().unwrap_or(path).to_string())
+        })
+        .unwrap_or_else(|| DEFAULT_SYSTEM_BUS.to_string())
+}
+
+pub struct Connection {
+    reader: BufReader<UnixStream>,
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Connection {
+    /// Connects and authenticates to the system bus and registers with Hello
+    pub fn system() -> io::Result<Self> {
+        let stream = UnixStream::connect(system_bus_path())?;
+        let timeout = Some(Duration::from_millis(TIMEOUT_MS));
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        let mut connection = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+            serial: 0,
+        };
+        connection.authenticate()?;
+        connection.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            &[],
+        )?;
+        Ok(connection)
+    }
+
+    fn authenticate(&mut self) -> io::Result<()> {
+        let uid = unsafe { libc::geteuid() }.to_string();
+        let hex_uid: String = uid.bytes().map(|byte| format!("{byte:02x}")).collect();
+        self.stream
+            .write_all(format!("\0AUTH EXTERNAL {hex_uid}\r\n").as_bytes())?;
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        if !line.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("D-Bus authentication rejected: {}", line.trim()),
+            ));
+        }
+        self.stream.write_all(b"BEGIN\r\n")
+    }
+
+    /// Calls a method and returns the reply body; D-Bus errors become io errors
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: &[&str],
+    ) -> io::Result<Vec<Value>> {
+        self.serial += 1;
+        let serial = self.serial;
+        self.stream.write_all(&encode_method_call(
+            serial,
+            destination,
+            path,
+            interface,
+            member,
+            args,
+        ))?;
+
+        loop {
+            let mut header = [0u8; 16];
+            self.reader.read_exact(&mut header)?;
+            let mut buf = header.to_vec();
+            buf.resize(16 + remaining_len(&header)?, 0);
+            self.reader.read_exact(&mut buf[16..])?;
+            let message = decode_message(&buf)?;
+
+            // Skip signals such as NameAcquired that arrive before the reply
+            if message.reply_serial != Some(serial) {
+                continue;
+            }
+            match message.kind {
+                MESSAGE_METHOD_RETURN => return Ok(message.body),
+                MESSAGE_ERROR => {
+                    let detail = message.body.first().and_then(Value::as_str).unwrap_or("");
+                    let name = message.error_name.as_deref().unwrap_or("unknown error");
+                    return Err(io::Error::other(format!(
+                        "{interface}.{member} failed: {name} {detail}"
+                    )));
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_type_len() {
+        assert_eq!(single_type_len(b"s").unwrap(), 1);
+        assert_eq!(single_type_len(b"a{sv}i").unwrap(), 5);
+        assert_eq!(single_type_len(b"a{oa{sa{sv}}}").unwrap(), 13);
+        assert_eq!(single_type_len(b"(ub)s").unwrap(), 4);
+        assert!(single_type_len(b"(ub").is_err());
+    }
+
+    #[test]
+    fn test_method_call_round_trip() {
+        let buf = encode_method_call(
+            7,
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            &["org.freedesktop.ModemManager1"],
+        );
+        let header: [u8; 16] = buf[..16].try_into().unwrap();
+        assert_eq!(16 + remaining_len(&header).unwrap(), buf.len());
+
+        let message = decode_message(&buf).unwrap();
+        assert_eq!(message.kind, MESSAGE_METHOD_CALL);
+        assert_eq!(
+            message.body,
+            vec![Value::Str("org.freedesktop.ModemManager1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_decode_dictionary_of_variants() {
+        // a{sv} body {"State": <int32 11>, "Rssi": <double -71.5>}
+        let mut body = Writer { buf: Vec::new() };
+        body.u32(0);
+        body.align(8);
+        let start = body.buf.len();
+        body.string("State");
+        body.signature("i");
+        body.u32(11);
+        body.align(8);
+        body.string("Rssi");
+        body.signature("d");
+        body.align(8);
+        body.buf.extend_from_slice(&(-71.5f64).to_le_bytes());
+        let len = (body.buf.len() - start) as u32;
+        body.buf[..4].copy_from_slice(&len.to_le_bytes());
+
+        let mut reader = Reader {
+            buf: &body.buf,
+            pos: 0,
+            little_endian: true,
+        };
+        let values = reader.values(b"a{sv}").unwrap();
+        let dict = &values[0];
+        assert_eq!(dict.get("State").and_then(Value::as_i64), Some(11));
+        assert_eq!(dict.get("Rssi").and_then(Value::as_f64), Some(-71.5));
+        assert_eq!(dict.get("Missing"), None);
+    }
+}
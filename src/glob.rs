@@ -0,0 +1,50 @@
+/// Matches `text` against a shell-style pattern supporting `*` and `?`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.service", "sshd.service"));
+        assert!(glob_match("user-?.slice", "user-0.slice"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("*.service", "sshd.socket"));
+        assert!(!glob_match("user-?.slice", "user-10.slice"));
+    }
+
+    #[test]
+    fn test_glob_match_metric_names() {
+        assert!(glob_match("interrupts*", "interrupts_total"));
+        assert!(glob_match("*_seconds_total", "cpu_seconds_total"));
+        assert!(glob_match("netdev", "netdev"));
+        assert!(!glob_match("netdev", "netdev_carrier"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("", "x"));
+        assert!(glob_match("", ""));
+    }
+}
@@ -0,0 +1,84 @@
+use prometheus::{Gauge, IntCounterVec};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The exporter's own health, shared by the config loader and collectors
+struct InternalMetrics {
+    config_last_reload_success: Gauge,
+    config_last_reload_timestamp_seconds: Gauge,
+    collector_errors_total: IntCounterVec,
+}
+
+impl InternalMetrics {
+    fn new() -> Self {
+        Self {
+            config_last_reload_success: prometheus::register_gauge!(
+                "config_last_reload_success",
+                "Whether the last configuration load or SIGHUP reload succeeded"
+            )
+            .expect("register config_last_reload_success"),
+            config_last_reload_timestamp_seconds: prometheus::register_gauge!(
+                "config_last_reload_timestamp_seconds",
+                "Unix timestamp of the last configuration load or reload attempt"
+            )
+            .expect("register config_last_reload_timestamp_seconds"),
+            collector_errors_total: prometheus::register_int_counter_vec!(
+                "collector_errors_total",
+                "Errors collectors recovered from, e.g. failed netlink or device queries",
+                &["collector"]
+            )
+            .expect("register collector_errors_total"),
+        }
+    }
+}
+
+static INTERNAL_METRICS: OnceLock<InternalMetrics> = OnceLock::new();
+
+fn metrics() -> &'static InternalMetrics {
+    INTERNAL_METRICS.get_or_init(InternalMetrics::new)
+}
+
+/// Records the outcome of loading the configuration file.
+pub fn record_config_reload(success: bool) {
+    let metrics = metrics();
+    metrics
+        .config_last_reload_success
+        .set(if success { 1.0 } else { 0.0 });
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0);
+    metrics.config_last_reload_timestamp_seconds.set(now);
+}
+
+/// Counts an error a collector logged and recovered from.
+pub fn collector_error(collector: &str) {
+    metrics()
+        .collector_errors_total
+        .with_label_values(&[collector])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collector_error_counts_per_collector() {
+        // A collector name no real collector uses, so parallel tests do not interfere
+        let counter = metrics()
+            .collector_errors_total
+            .with_label_values(&["test_collector"]);
+        let before = counter.get();
+        collector_error("test_collector");
+        collector_error("test_collector");
+        assert_eq!(counter.get(), before + 2);
+    }
+
+    #[test]
+    fn test_record_config_reload() {
+        record_config_reload(false);
+        let metrics = metrics();
+        assert!(metrics.config_last_reload_timestamp_seconds.get() > 0.0);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::LogFormat;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// Switches every later log line to the configured format.
+pub fn set_format(format: LogFormat) {
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// Text mode keeps the historical plain message; fields only appear in JSON.
+fn format_line(json: bool, level: Level, msg: &str, fields: &[(&str, &str)]) -> String {
+    if !json {
+        return msg.to_string();
+    }
+    let mut object = Map::new();
+    object.insert("level".to_string(), Value::from(level.as_str()));
+    object.insert("msg".to_string(), Value::from(msg));
+    for (key, value) in fields {
+        object.insert(key.to_string(), Value::from(*value));
+    }
+    Value::Object(object).to_string()
+}
+
+pub fn log(level: Level, msg: &str, fields: &[(&str, &str)]) {
+    eprintln!(
+        "{}",
+        format_line(JSON_FORMAT.load(Ordering::Relaxed), level, msg, fields)
+    );
+}
+
+pub fn info(msg: &str) {
+    log(Level::Info, msg, &[]);
+}
+
+pub fn warn(msg: &str) {
+    log(Level::Warn, msg, &[]);
+}
+
+pub fn error(msg: &str) {
+    log(Level::Error, msg, &[]);
+}
+
+/// Collector diagnostics; callers gate these on `--debug`.
+pub fn debug(collector: &str, msg: &str) {
+    log(Level::Debug, msg, &[("collector", collector)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line_text_is_plain_message() {
+        assert_eq!(
+            format_line(
+                false,
+                Level::Warn,
+                "Denied /metrics request",
+                &[("client_ip", "10.0.0.1")]
+            ),
+            "Denied /metrics request"
+        );
+    }
+
+    #[test]
+    fn test_format_line_json() {
+        let line = format_line(
+            true,
+            Level::Error,
+            "Collector \"drbd\" panicked",
+            &[("collector", "drbd")],
+        );
+        assert!(!line.contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "error");
+        assert_eq!(value["msg"], "Collector \"drbd\" panicked");
+        assert_eq!(value["collector"], "drbd");
+    }
+}
@@ -1,34 +1,84 @@
 #[macro_use]
 extern crate rocket;
 
+mod access_log;
 mod config;
+mod counter_reset;
+mod cpu_aggregate;
+mod datasource_bcache;
+mod datasource_block;
+mod datasource_bridge;
+mod datasource_btrfs;
+mod datasource_buddyinfo;
+mod datasource_cgroups;
 mod datasource_conntrack;
+mod datasource_cpu_vulns;
 mod datasource_cpufreq;
+mod datasource_custom;
+mod datasource_dmi;
+mod datasource_dmstats;
+mod datasource_drbd;
 mod datasource_edac;
+mod datasource_entropy;
 mod datasource_ethtool;
+mod datasource_fibrechannel;
 mod datasource_filesystems;
 mod datasource_hwmon;
 mod datasource_ipmi;
+mod datasource_ksm;
 mod datasource_mdraid;
+mod datasource_modem;
 mod datasource_netdev_sysfs;
 mod datasource_numa;
 mod datasource_nvme;
+mod datasource_pci;
 mod datasource_power_supply;
+mod datasource_processes;
 mod datasource_procfs;
 mod datasource_rapl;
+mod datasource_routes;
+mod datasource_schedstat;
+mod datasource_sctp;
+mod datasource_self;
+mod datasource_slabinfo;
 mod datasource_softnet;
+mod datasource_swaps;
 mod datasource_thermal;
+mod datasource_timex;
+mod datasource_uname;
+mod datasource_usb;
+mod datasource_watchdog;
+mod datasource_xfs;
+mod datasource_zfs;
+mod dbus;
+mod glob;
+mod internal_metrics;
+mod logging;
+mod ratelimit;
 mod runtime;
+mod samples;
 
 use crate::config::AppConfig;
-use prometheus::{Encoder, IntCounter, TextEncoder};
-use rocket::config::TlsConfig;
-use rocket::Config;
+use crate::logging::Level;
+use crate::ratelimit::{ConcurrencyLimiter, Permit, RateLimiter};
+use crate::samples::{Sample, SampleValue};
+use prometheus::core::Collector;
+use prometheus::proto::{
+    Counter, Gauge as ProtoGauge, LabelPair, Metric, MetricFamily, MetricType,
+};
+use prometheus::{Encoder, Gauge, GaugeVec, Histogram, IntCounter, TextEncoder};
+use rocket::config::{MutualTls, TlsConfig};
+use rocket::figment::Figment;
 use rocket::http::{ContentType, Status};
+use rocket::mtls::Certificate;
+use rocket::mtls::x509::GeneralName;
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::response::status;
+use rocket::{Build, Config, Rocket, Shutdown};
 use serde_json::Value as JsonValue;
-use std::net::IpAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Extracts Bearer token from Authorization header
 pub struct BearerToken(Option<String>);
@@ -46,12 +96,25 @@ impl<'r> FromRequest<'r> for BearerToken {
         Outcome::Success(BearerToken(token))
     }
 }
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
 
 static METRICS_REQUESTS_TOTAL: OnceLock<IntCounter> = OnceLock::new();
 static METRICS_REQUESTS_DENIED_TOTAL: OnceLock<IntCounter> = OnceLock::new();
-static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
-static IS_ROOT: OnceLock<bool> = OnceLock::new();
+static METRICS_REQUESTS_RATELIMITED_TOTAL: OnceLock<IntCounter> = OnceLock::new();
+static METRICS_REQUESTS_OVERLOADED_TOTAL: OnceLock<IntCounter> = OnceLock::new();
+static METRICS_REQUEST_DURATION: OnceLock<Histogram> = OnceLock::new();
+static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+static SCRAPE_LIMITER: ConcurrencyLimiter = ConcurrencyLimiter::new();
+static EXPORTER_BUILD_INFO: OnceLock<GaugeVec> = OnceLock::new();
+static LAST_COLLECTION_TIMESTAMP: OnceLock<Gauge> = OnceLock::new();
+static APP_CONFIG: OnceLock<RwLock<Arc<AppConfig>>> = OnceLock::new();
+static LAST_COLLECTION_FAILED: AtomicBool = AtomicBool::new(false);
+static RESTART_LISTENER: AtomicBool = AtomicBool::new(false);
+/// Collections started so far; drives `interval_multiplier`
+static COLLECTION_COUNT: AtomicU64 = AtomicU64::new(0);
+static COLLECTIONS_IN_FLIGHT: ConcurrencyLimiter = ConcurrencyLimiter::new();
+static SHUTDOWN_STARTED: OnceLock<Instant> = OnceLock::new();
 
 fn metrics_requests_total() -> &'static IntCounter {
     METRICS_REQUESTS_TOTAL.get_or_init(|| {
@@ -73,63 +136,437 @@ fn metrics_requests_denied_total() -> &'static IntCounter {
     })
 }
 
-fn app_config() -> &'static AppConfig {
-    APP_CONFIG.get_or_init(AppConfig::load)
+fn metrics_requests_ratelimited_total() -> &'static IntCounter {
+    METRICS_REQUESTS_RATELIMITED_TOTAL.get_or_init(|| {
+        prometheus::register_int_counter!(
+            "metrics_requests_ratelimited_total",
+            "Total number of /metrics requests rejected by the per-IP rate limit"
+        )
+        .expect("register metrics_requests_ratelimited_total")
+    })
 }
 
-fn is_root() -> bool {
-    *IS_ROOT.get_or_init(|| unsafe { libc::geteuid() == 0 })
+fn metrics_requests_overloaded_total() -> &'static IntCounter {
+    METRICS_REQUESTS_OVERLOADED_TOTAL.get_or_init(|| {
+        prometheus::register_int_counter!(
+            "metrics_requests_overloaded_total",
+            "Total number of /metrics requests rejected by max_concurrent_scrapes"
+        )
+        .expect("register metrics_requests_overloaded_total")
+    })
 }
 
-fn update_metrics() {
-    let config = app_config();
+/// Buckets from 1ms to 5s; collections normally take tens of milliseconds
+const METRICS_REQUEST_DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+fn metrics_request_duration_seconds() -> &'static Histogram {
+    METRICS_REQUEST_DURATION.get_or_init(|| {
+        prometheus::register_histogram!(
+            "metrics_request_duration_seconds",
+            "Time to collect and encode authorized /metrics responses",
+            METRICS_REQUEST_DURATION_BUCKETS.to_vec()
+        )
+        .expect("register metrics_request_duration_seconds")
+    })
+}
+
+fn rate_limiter() -> &'static RateLimiter {
+    RATE_LIMITER.get_or_init(RateLimiter::new)
+}
+
+fn exporter_build_info() -> &'static GaugeVec {
+    EXPORTER_BUILD_INFO.get_or_init(|| {
+        let info = prometheus::register_gauge_vec!(
+            "exporter_build_info",
+            "Exporter build information (always 1)",
+            &["version", "rustc", "git_commit"]
+        )
+        .expect("register exporter_build_info");
+        info.with_label_values(&[
+            env!("CARGO_PKG_VERSION"),
+            env!("RUSTC_VERSION"),
+            env!("GIT_COMMIT"),
+        ])
+        .set(1.0);
+        info
+    })
+}
+
+fn last_collection_timestamp_seconds() -> &'static Gauge {
+    LAST_COLLECTION_TIMESTAMP.get_or_init(|| {
+        prometheus::register_gauge!(
+            "last_collection_timestamp_seconds",
+            "Unix timestamp of the last completed metrics collection"
+        )
+        .expect("register last_collection_timestamp_seconds")
+    })
+}
+
+struct CollectorMetrics {
+    duration_seconds: GaugeVec,
+    success: GaugeVec,
+    last_success_timestamp_seconds: GaugeVec,
+}
+
+static COLLECTOR_METRICS: OnceLock<CollectorMetrics> = OnceLock::new();
+
+fn collector_metrics() -> &'static CollectorMetrics {
+    COLLECTOR_METRICS.get_or_init(|| CollectorMetrics {
+        duration_seconds: prometheus::register_gauge_vec!(
+            "collector_duration_seconds",
+            "Duration of the collector's last run in seconds",
+            &["collector"]
+        )
+        .expect("register collector_duration_seconds"),
+        success: prometheus::register_gauge_vec!(
+            "collector_success",
+            "Whether the collector's last run completed without panicking",
+            &["collector"]
+        )
+        .expect("register collector_success"),
+        last_success_timestamp_seconds: prometheus::register_gauge_vec!(
+            "collector_last_success_timestamp_seconds",
+            "Unix timestamp of the collector's last successful run",
+            &["collector"]
+        )
+        .expect("register collector_last_success_timestamp_seconds"),
+    })
+}
 
-    if config.is_datasource_enabled("procfs") {
-        datasource_procfs::update_metrics(config);
+fn app_config_lock() -> &'static RwLock<Arc<AppConfig>> {
+    APP_CONFIG.get_or_init(|| RwLock::new(Arc::new(AppConfig::load())))
+}
+
+/// Snapshot of the current configuration; replaced wholesale on reload.
+fn app_config() -> Arc<AppConfig> {
+    match app_config_lock().read() {
+        Ok(config) => Arc::clone(&config),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
     }
-    if config.is_datasource_enabled("cpufreq") {
-        datasource_cpufreq::update_metrics();
+}
+
+/// Keys a reload cannot apply: the background collector is started once by
+/// `main`, and without a listener restart the bind and TLS settings stay as
+/// launched. The running collection_interval_seconds is kept.
+fn keep_startup_keys(
+    current: &AppConfig,
+    config: &mut AppConfig,
+    restart: bool,
+) -> Vec<&'static str> {
+    let mut kept = Vec::new();
+    if config.collection_interval_seconds != current.collection_interval_seconds {
+        config.collection_interval_seconds = current.collection_interval_seconds;
+        kept.push("collection_interval_seconds");
     }
-    if config.is_datasource_enabled("softnet") {
-        datasource_softnet::update_metrics();
+    if !restart {
+        if config.bind != current.bind {
+            kept.push("bind");
+        }
+        if config.tls_cert != current.tls_cert {
+            kept.push("tls_cert");
+        }
+        if config.tls_key != current.tls_key {
+            kept.push("tls_key");
+        }
+        if config.tls_ca_path != current.tls_ca_path {
+            kept.push("tls_ca_path");
+        }
     }
-    if config.is_datasource_enabled("conntrack") {
-        datasource_conntrack::update_metrics();
+    kept
+}
+
+/// Returns true when the listener must be restarted to pick up TLS certificates.
+fn reload_config() -> bool {
+    match AppConfig::try_load() {
+        Ok(mut config) => {
+            let mut current = match app_config_lock().write() {
+                Ok(current) => current,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            // Rocket loads certificates once at launch, so renewed or newly
+            // configured certificates need a fresh listener
+            let restart = current.tls_config().is_some() || config.tls_config().is_some();
+            let kept = keep_startup_keys(&current, &mut config, restart);
+            *current = Arc::new(config);
+            internal_metrics::record_config_reload(true);
+            if restart {
+                logging::info("Configuration reloaded, restarting listener to reload TLS.");
+            } else {
+                logging::info("Configuration reloaded.");
+            }
+            if !kept.is_empty() {
+                logging::warn(&format!(
+                    "Changed keys not applied until restart: {}.",
+                    kept.join(", ")
+                ));
+            }
+            restart
+        }
+        Err(err) => {
+            internal_metrics::record_config_reload(false);
+            logging::error(&format!(
+                "Configuration reload failed, keeping previous config: {err}"
+            ));
+            false
+        }
     }
-    if config.is_datasource_enabled("filesystems") {
-        datasource_filesystems::update_metrics(config);
+}
+
+/// SIGHUP reloads the configuration; SIGTERM and SIGINT stop every listener
+/// gracefully, after which `main` waits for in-flight collections.
+async fn handle_signals(shutdowns: Vec<Shutdown>) {
+    use rocket::tokio::signal::unix::{SignalKind, signal};
+
+    let signals = (
+        signal(SignalKind::hangup()),
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    );
+    let (mut hangup, mut terminate, mut interrupt) = match signals {
+        (Ok(hangup), Ok(terminate), Ok(interrupt)) => (hangup, terminate, interrupt),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            logging::error(&format!("Failed to install signal handlers: {err}"));
+            return;
+        }
+    };
+    let name = loop {
+        rocket::tokio::select! {
+            Some(()) = hangup.recv() => {
+                // Subsystem checks touch sysfs; keep them off the async workers
+                let restart = rocket::tokio::task::spawn_blocking(reload_config)
+                    .await
+                    .unwrap_or(false);
+                if restart {
+                    // The relaunched servers install their own handler
+                    RESTART_LISTENER.store(true, Ordering::SeqCst);
+                    for shutdown in shutdowns {
+                        shutdown.notify();
+                    }
+                    return;
+                }
+            }
+            Some(()) = terminate.recv() => break "SIGTERM",
+            Some(()) = interrupt.recv() => break "SIGINT",
+            else => return,
+        }
+    };
+
+    let timeout = app_config().shutdown_timeout_seconds;
+    logging::info(&format!(
+        "Received {name}, stopping listeners (in-flight scrapes get up to {timeout}s)."
+    ));
+    SHUTDOWN_STARTED.get_or_init(Instant::now);
+    for shutdown in shutdowns {
+        shutdown.notify();
     }
-    if config.is_datasource_enabled("hwmon") {
-        datasource_hwmon::update_metrics();
+}
+
+/// Waits until no collection is running or `timeout` has passed; returns
+/// false on timeout. Collectors close their netlink sockets and device files
+/// as they return, so a drained exporter holds no kernel handles.
+fn drain_collections(in_flight: &ConcurrencyLimiter, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while in_flight.active() > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
     }
-    if config.is_datasource_enabled("ipmi") {
-        datasource_ipmi::update_metrics();
+    true
+}
+
+fn finish_shutdown(config: &AppConfig) {
+    let timeout = Duration::from_secs(config.shutdown_timeout_seconds);
+    // The listeners' grace period counts against the same timeout
+    let elapsed = SHUTDOWN_STARTED
+        .get()
+        .map_or(Duration::ZERO, Instant::elapsed);
+    let active = COLLECTIONS_IN_FLIGHT.active();
+    if active > 0 {
+        logging::info(&format!(
+            "Listeners stopped, waiting for {active} in-flight collection(s)."
+        ));
     }
-    if config.is_datasource_enabled("mdraid") {
-        datasource_mdraid::update_metrics();
+    if drain_collections(&COLLECTIONS_IN_FLIGHT, timeout.saturating_sub(elapsed)) {
+        logging::info("Shutdown complete.");
+    } else {
+        logging::warn(&format!(
+            "Shutdown timed out after {}s with {} collection(s) still running.",
+            config.shutdown_timeout_seconds,
+            COLLECTIONS_IN_FLIGHT.active()
+        ));
     }
-    if config.is_datasource_enabled("thermal") {
-        datasource_thermal::update_metrics();
+}
+
+type Collect = fn(&AppConfig);
+
+/// Datasources in collection order; each is skipped while disabled.
+const COLLECTORS: &[(&str, Collect)] = &[
+    ("procfs", datasource_procfs::update_metrics),
+    ("cpufreq", datasource_cpufreq::update_metrics),
+    ("softnet", datasource_softnet::update_metrics),
+    ("conntrack", datasource_conntrack::update_metrics),
+    ("filesystems", datasource_filesystems::update_metrics),
+    ("hwmon", datasource_hwmon::update_metrics),
+    ("ipmi", datasource_ipmi::update_metrics),
+    ("mdraid", datasource_mdraid::update_metrics),
+    ("thermal", datasource_thermal::update_metrics),
+    ("rapl", datasource_rapl::update_metrics),
+    ("power_supply", datasource_power_supply::update_metrics),
+    ("nvme", datasource_nvme::update_metrics),
+    ("pci", datasource_pci::update_metrics),
+    ("usb", datasource_usb::update_metrics),
+    ("edac", datasource_edac::update_metrics),
+    ("netdev_sysfs", datasource_netdev_sysfs::update_metrics),
+    ("bridge", datasource_bridge::update_metrics),
+    ("numa", datasource_numa::update_metrics),
+    ("schedstat", datasource_schedstat::update_metrics),
+    ("cpu_vulns", datasource_cpu_vulns::update_metrics),
+    ("cgroups", datasource_cgroups::update_metrics),
+    ("timex", |_| datasource_timex::update_metrics()),
+    ("uname", |_| datasource_uname::update_metrics()),
+    ("swaps", datasource_swaps::update_metrics),
+    ("buddyinfo", datasource_buddyinfo::update_metrics),
+    ("slabinfo", datasource_slabinfo::update_metrics),
+    ("entropy", datasource_entropy::update_metrics),
+    ("ksm", datasource_ksm::update_metrics),
+    ("dmi", datasource_dmi::update_metrics),
+    ("watchdog", datasource_watchdog::update_metrics),
+    ("block", datasource_block::update_metrics),
+    ("bcache", datasource_bcache::update_metrics),
+    ("dmstats", datasource_dmstats::update_metrics),
+    ("btrfs", datasource_btrfs::update_metrics),
+    ("xfs", datasource_xfs::update_metrics),
+    ("zfs", datasource_zfs::update_metrics),
+    ("processes", datasource_processes::update_metrics),
+    ("routes", datasource_routes::update_metrics),
+    ("sctp", datasource_sctp::update_metrics),
+    ("fibrechannel", datasource_fibrechannel::update_metrics),
+    ("drbd", datasource_drbd::update_metrics),
+    ("modem", |_| datasource_modem::update_metrics()),
+    ("custom", datasource_custom::update_metrics),
+];
+
+/// Runs every enabled collector; returns false when any of them panicked.
+fn update_metrics() -> bool {
+    let config = &*app_config();
+    let collection = COLLECTION_COUNT.fetch_add(1, Ordering::Relaxed);
+    let mut succeeded = true;
+
+    for (name, update) in COLLECTORS {
+        if config.is_datasource_enabled(name) && config.is_collection_due(name, collection) {
+            succeeded &= run_collector(name, || update(config));
+        }
     }
-    if config.is_datasource_enabled("rapl") {
-        datasource_rapl::update_metrics();
+    // The exporter's own process metrics are always collected
+    datasource_self::update_metrics();
+    // TODO: Implementation in progress; ethtool netlink stats disabled for now.
+    succeeded
+}
+
+/// Times one collector and records its outcome for `/collectors`.
+fn run_collector(name: &str, update: impl FnOnce()) -> bool {
+    let started = Instant::now();
+    // A panicking collector must not take the others down with it
+    let succeeded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(update)).is_ok();
+    let metrics = collector_metrics();
+    metrics
+        .duration_seconds
+        .with_label_values(&[name])
+        .set(started.elapsed().as_secs_f64());
+    metrics
+        .success
+        .with_label_values(&[name])
+        .set(if succeeded { 1.0 } else { 0.0 });
+    if succeeded {
+        metrics
+            .last_success_timestamp_seconds
+            .with_label_values(&[name])
+            .set(unix_now());
+    } else {
+        logging::log(
+            Level::Error,
+            &format!("Collector {name} panicked"),
+            &[("collector", name)],
+        );
     }
-    if config.is_datasource_enabled("power_supply") {
-        datasource_power_supply::update_metrics();
+    succeeded
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+fn collect() {
+    let _in_flight = COLLECTIONS_IN_FLIGHT.enter();
+    // A panic outside the collectors must not take the background thread down with it
+    let succeeded = std::panic::catch_unwind(update_metrics).unwrap_or(false);
+    LAST_COLLECTION_FAILED.store(!succeeded, Ordering::Relaxed);
+    if succeeded {
+        last_collection_timestamp_seconds().set(unix_now());
     }
-    if config.is_datasource_enabled("nvme") {
-        datasource_nvme::update_metrics();
+}
+
+/// Healthy unless the last collection failed or, with background collection,
+/// no collection has completed within three intervals.
+fn collection_is_healthy(failed: bool, interval_seconds: u64, last: f64, now: f64) -> bool {
+    if failed {
+        return false;
     }
-    if config.is_datasource_enabled("edac") {
-        datasource_edac::update_metrics();
+    if interval_seconds == 0 || last == 0.0 {
+        return true;
     }
-    if config.is_datasource_enabled("netdev_sysfs") {
-        datasource_netdev_sysfs::update_metrics(config);
+    now - last <= (interval_seconds * 3) as f64
+}
+
+/// Collects on the request thread unless the background collector owns it;
+/// cached responses are never limited by max_concurrent_scrapes.
+#[allow(clippy::result_large_err)]
+fn collect_for_request(config: &AppConfig) -> Result<(), Denied> {
+    if config.collection_interval_seconds != 0 {
+        return Ok(());
     }
-    if config.is_datasource_enabled("numa") {
-        datasource_numa::update_metrics();
+    let limit = config.max_concurrent_scrapes;
+    let _permit = match limit {
+        0 => None,
+        _ => Some(acquire_scrape(&SCRAPE_LIMITER, limit)?),
+    };
+    collect();
+    Ok(())
+}
+
+#[allow(clippy::result_large_err)]
+fn acquire_scrape(limiter: &ConcurrencyLimiter, limit: usize) -> Result<Permit<'_>, Denied> {
+    limiter.try_acquire(limit).ok_or_else(|| {
+        metrics_requests_overloaded_total().inc();
+        status::Custom(
+            Status::ServiceUnavailable,
+            (
+                ContentType::Plain,
+                "too many concurrent scrapes".to_string(),
+            ),
+        )
+    })
+}
+
+fn spawn_background_collector(interval: Duration) {
+    let spawned = std::thread::Builder::new()
+        .name("collector".to_string())
+        .spawn(move || {
+            while SHUTDOWN_STARTED.get().is_none() {
+                let started = Instant::now();
+                collect();
+                std::thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+        });
+    if let Err(err) = spawned {
+        logging::error(&format!("Failed to start background collector: {err}"));
     }
-    // TODO: Implementation in progress; ethtool netlink stats disabled for now.
 }
 
 fn push_json_sample(
@@ -147,195 +584,523 @@ fn push_json_sample(
     samples.push(map);
 }
 
-fn metrics_json_payload() -> String {
-    let families = prometheus::gather();
-    let mut samples: Vec<serde_json::Map<String, JsonValue>> = Vec::new();
+/// Prefixes every family name with `<namespace>_`.
+fn apply_namespace(families: &mut [MetricFamily], namespace: &str) {
+    if namespace.is_empty() {
+        return;
+    }
+    for family in families.iter_mut() {
+        let name = format!("{namespace}_{}", family.name());
+        family.set_name(name);
+    }
+}
 
-    for family in families {
-        let name = family.name();
-        let metric_type = family.get_field_type();
-        for metric in family.get_metric() {
-            let base_labels: Vec<(String, String)> = metric
-                .get_label()
-                .iter()
-                .map(|label| (label.name().to_string(), label.value().to_string()))
-                .collect();
+/// Removes `label` from every series of `family`, summing series that collapse
+/// onto the same remaining label set. Only counters and gauges are aggregated.
+fn drop_label(family: &mut MetricFamily, label: &str) {
+    let metric_type = family.get_field_type();
+    if metric_type != MetricType::COUNTER && metric_type != MetricType::GAUGE {
+        return;
+    }
+    if !family
+        .get_metric()
+        .iter()
+        .any(|metric| metric.get_label().iter().any(|pair| pair.name() == label))
+    {
+        return;
+    }
 
-            match metric_type {
-                prometheus::proto::MetricType::COUNTER => {
-                    let value = JsonValue::from(metric.get_counter().value());
-                    push_json_sample(&mut samples, name, &base_labels, value);
-                }
-                prometheus::proto::MetricType::GAUGE => {
-                    let value = JsonValue::from(metric.get_gauge().value());
-                    push_json_sample(&mut samples, name, &base_labels, value);
-                }
-                prometheus::proto::MetricType::UNTYPED => {
-                    // UNTYPED metrics are not directly supported, skip
-                }
-                prometheus::proto::MetricType::HISTOGRAM => {
-                    let histogram = metric.get_histogram();
-                    for bucket in histogram.get_bucket() {
-                        let mut labels = base_labels.clone();
-                        labels.push(("le".to_string(), bucket.upper_bound().to_string()));
-                        let value = JsonValue::from(bucket.cumulative_count());
-                        let bucket_name = format!("{name}_bucket");
-                        push_json_sample(&mut samples, &bucket_name, &labels, value);
-                    }
-                    let sum_name = format!("{name}_sum");
-                    let count_name = format!("{name}_count");
-                    push_json_sample(
-                        &mut samples,
-                        &sum_name,
-                        &base_labels,
-                        JsonValue::from(histogram.sample_sum()),
-                    );
-                    push_json_sample(
-                        &mut samples,
-                        &count_name,
-                        &base_labels,
-                        JsonValue::from(histogram.sample_count()),
-                    );
-                }
-                prometheus::proto::MetricType::SUMMARY => {
-                    let summary = metric.get_summary();
-                    for quantile in summary.get_quantile() {
-                        let mut labels = base_labels.clone();
-                        labels.push(("quantile".to_string(), quantile.quantile().to_string()));
-                        let value = JsonValue::from(quantile.value());
-                        let quantile_name = format!("{name}_quantile");
-                        push_json_sample(&mut samples, &quantile_name, &labels, value);
-                    }
-                    let sum_name = format!("{name}_sum");
-                    let count_name = format!("{name}_count");
-                    push_json_sample(
-                        &mut samples,
-                        &sum_name,
-                        &base_labels,
-                        JsonValue::from(summary.sample_sum()),
-                    );
-                    push_json_sample(
-                        &mut samples,
-                        &count_name,
-                        &base_labels,
-                        JsonValue::from(summary.sample_count()),
-                    );
+    // Keyed on the remaining labels so merging stays linear in the series count
+    let mut merged: Vec<(Vec<LabelPair>, f64)> = Vec::new();
+    let mut index: HashMap<Vec<(String, String)>, usize> = HashMap::new();
+    for mut metric in family.take_metric() {
+        let value = match metric_type {
+            MetricType::COUNTER => metric.get_counter().value(),
+            _ => metric.get_gauge().value(),
+        };
+        let labels: Vec<LabelPair> = metric
+            .take_label()
+            .into_iter()
+            .filter(|pair| pair.name() != label)
+            .collect();
+
+        let key = labels
+            .iter()
+            .map(|pair| (pair.name().to_string(), pair.value().to_string()))
+            .collect();
+        match index.get(&key) {
+            Some(&position) => merged[position].1 += value,
+            None => {
+                index.insert(key, merged.len());
+                merged.push((labels, value));
+            }
+        }
+    }
+
+    let metrics = merged
+        .into_iter()
+        .map(|(labels, value)| {
+            let mut metric = Metric::from_label(labels);
+            if metric_type == MetricType::COUNTER {
+                let mut counter = Counter::new();
+                counter.set_value(value);
+                metric.set_counter(counter);
+            } else {
+                let mut gauge = ProtoGauge::new();
+                gauge.set_value(value);
+                metric.set_gauge(gauge);
+            }
+            metric
+        })
+        .collect();
+    family.set_metric(metrics);
+}
+
+/// Rules match the name before metric_namespace is applied, or the
+/// namespaced name as it appears in the output.
+fn apply_label_drops(families: &mut [MetricFamily], config: &AppConfig) {
+    let namespace = config.metric_namespace();
+    for rule in &config.label_drop {
+        for family in families.iter_mut().filter(|family| {
+            glob::glob_match(&rule.metric, family.name())
+                || (!namespace.is_empty()
+                    && glob::glob_match(&rule.metric, &format!("{namespace}_{}", family.name())))
+        }) {
+            drop_label(family, &rule.label);
+        }
+    }
+}
+
+/// Appends static_labels to every series; a label the series already carries wins.
+fn apply_static_labels(families: &mut [MetricFamily], config: &AppConfig) {
+    let labels: Vec<(&str, &str)> = config.static_labels().collect();
+    if labels.is_empty() {
+        return;
+    }
+    for family in families.iter_mut() {
+        for metric in family.mut_metric().iter_mut() {
+            let mut pairs = metric.take_label();
+            for (name, value) in &labels {
+                if pairs.iter().any(|pair| pair.name() == *name) {
+                    continue;
                 }
+                let mut pair = LabelPair::new();
+                pair.set_name(name.to_string());
+                pair.set_value(value.to_string());
+                pairs.push(pair);
             }
+            metric.set_label(pairs);
         }
     }
+}
 
-    serde_json::to_string(&samples).unwrap_or_else(|_| "[]".to_string())
+/// Gathers the registry and applies the configured output transformations.
+fn gather(config: &AppConfig) -> Vec<MetricFamily> {
+    let mut families = prometheus::gather();
+    families.retain(|family| config.is_metric_exported(family.name()));
+    apply_label_drops(&mut families, config);
+    apply_static_labels(&mut families, config);
+    apply_namespace(&mut families, config.metric_namespace());
+    families
 }
 
-#[get("/metrics")]
-fn metrics(
-    client_ip: Option<IpAddr>,
-    token: BearerToken,
-) -> Result<(ContentType, String), status::Custom<(ContentType, String)>> {
-    metrics_requests_total().inc();
-    let config = app_config();
+/// Stamps every sample; the text encoder then writes it after the value.
+fn apply_timestamps(families: &mut [MetricFamily], timestamp_ms: i64) {
+    for family in families.iter_mut() {
+        for metric in family.mut_metric().iter_mut() {
+            metric.set_timestamp_ms(timestamp_ms);
+        }
+    }
+}
 
-    // Check token authentication first
-    if !config.is_token_valid(token.0.as_deref()) {
-        if config.log_denied_requests {
-            eprintln!(
-                "Denied /metrics request from {} (invalid token)",
-                client_ip
-                    .map(|ip| ip.to_string())
-                    .unwrap_or_else(|| "<unknown>".to_string())
-            );
+/// Time of the last successful collection, or now before the first one.
+fn collection_timestamp_ms() -> i64 {
+    let last = last_collection_timestamp_seconds().get();
+    let seconds = if last > 0.0 { last } else { unix_now() };
+    (seconds * 1000.0) as i64
+}
+
+fn metrics_text_payload(config: &AppConfig) -> String {
+    let encoder = TextEncoder::new();
+    let mut metric_families = gather(config);
+    if config.emit_timestamps {
+        apply_timestamps(&mut metric_families, collection_timestamp_ms());
+    }
+    let mut buffer = Vec::new();
+    encoder
+        .encode(&metric_families, &mut buffer)
+        .expect("encode metrics");
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Drops the `# HELP` and `# TYPE` lines for `?comments=false`.
+fn strip_comments(payload: &str) -> String {
+    payload
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| [line, "\n"])
+        .collect()
+}
+
+fn gather_samples(config: &AppConfig) -> Vec<Sample> {
+    samples::flatten(&gather(config))
+}
+
+fn samples_to_json(samples: &[Sample]) -> String {
+    let mut objects: Vec<serde_json::Map<String, JsonValue>> = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let value = match sample.value {
+            SampleValue::Float(value) => JsonValue::from(value),
+            SampleValue::Count(value) => JsonValue::from(value),
+        };
+        push_json_sample(&mut objects, &sample.name, &sample.labels, value);
+    }
+    serde_json::to_string(&objects).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn metrics_json_payload(config: &AppConfig) -> String {
+    samples_to_json(&gather_samples(config))
+}
+
+/// CN and SAN DNS names of a verified client certificate.
+fn client_identities(cert: &Certificate<'_>) -> Vec<String> {
+    let mut names: Vec<String> = cert
+        .subject()
+        .common_names()
+        .map(|name| name.to_string())
+        .collect();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            if let GeneralName::DNSName(dns) = name {
+                names.push(dns.to_string());
+            }
         }
+    }
+    names
+}
+
+fn describe_client(client_ip: Option<IpAddr>, identities: &[String]) -> String {
+    let ip = client_ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "<unknown>".to_string());
+    if identities.is_empty() {
+        ip
+    } else {
+        format!("{ip} (client cert {})", identities.join(", "))
+    }
+}
+
+type Denied = status::Custom<(ContentType, String)>;
+
+/// Applies the token, IP allowlist and mutual TLS checks shared by the metrics routes.
+#[allow(clippy::result_large_err)]
+fn authorize(
+    request: &access_log::Request<'_>,
+    config: &AppConfig,
+    token: &BearerToken,
+    client_cert: Option<&Certificate<'_>>,
+) -> Result<(), Denied> {
+    let identities = client_cert.map(client_identities).unwrap_or_default();
+    let deny = |reason: &str, status: Status, body: &str| {
+        access_log::record(
+            config,
+            access_log::Event::Denied,
+            request,
+            status.code,
+            reason,
+            &format!(
+                "Denied {} request from {} ({reason})",
+                request.path,
+                describe_client(request.client_ip, &identities)
+            ),
+        );
         metrics_requests_denied_total().inc();
-        return Err(status::Custom(
-            Status::Unauthorized,
-            (ContentType::Plain, "unauthorized".to_string()),
-        ));
+        Err(status::Custom(
+            status,
+            (ContentType::Plain, body.to_string()),
+        ))
+    };
+
+    // Check token authentication first
+    if !config.is_token_valid(token.0.as_deref()) {
+        return deny("invalid token", Status::Unauthorized, "unauthorized");
     }
 
     // Check IP allowlist
-    let is_allowed = client_ip
+    let is_allowed = request
+        .client_ip
         .map(|ip| config.is_metrics_ip_allowed(ip))
         .unwrap_or(false);
     if !is_allowed {
-        if config.log_denied_requests {
-            eprintln!(
-                "Denied /metrics request from {}",
-                client_ip
-                    .map(|ip| ip.to_string())
-                    .unwrap_or_else(|| "<unknown>".to_string())
+        return deny("ip not allowed", Status::Forbidden, "access denied");
+    }
+
+    // The TLS layer already rejects missing or unverifiable client certificates
+    if config.mtls_ca().is_some() {
+        if client_cert.is_none() {
+            return deny("no client certificate", Status::Forbidden, "access denied");
+        }
+        if !config.is_client_identity_allowed(&identities) {
+            return deny(
+                "client certificate not allowed",
+                Status::Forbidden,
+                "access denied",
             );
         }
-        metrics_requests_denied_total().inc();
+    }
+
+    // Throttle after the ACL so rejected clients cannot use up a bucket
+    check_rate_limit(config, request)
+}
+
+#[allow(clippy::result_large_err)]
+fn check_rate_limit(config: &AppConfig, request: &access_log::Request<'_>) -> Result<(), Denied> {
+    let rate = config.metrics_rate_limit_per_second;
+    if rate > 0.0
+        && let Some(ip) = request.client_ip
+        && !rate_limiter().allow(ip, rate, Instant::now())
+    {
+        metrics_requests_ratelimited_total().inc();
+        access_log::record(
+            config,
+            access_log::Event::Denied,
+            request,
+            Status::TooManyRequests.code,
+            "rate limited",
+            &format!("Rate limited {} request from {ip}", request.path),
+        );
         return Err(status::Custom(
-            Status::Forbidden,
-            (ContentType::Plain, "access denied".to_string()),
+            Status::TooManyRequests,
+            (ContentType::Plain, "too many requests".to_string()),
         ));
     }
+    Ok(())
+}
 
-    update_metrics();
+/// Logs an authorized request with the status its handler answered.
+#[allow(clippy::result_large_err)]
+fn log_allowed<T>(
+    config: &AppConfig,
+    request: &access_log::Request<'_>,
+    response: Result<T, Denied>,
+) -> Result<T, Denied> {
+    let status = match &response {
+        Ok(_) => Status::Ok,
+        Err(denied) => denied.0,
+    };
+    access_log::record(
+        config,
+        access_log::Event::Allowed,
+        request,
+        status.code,
+        "",
+        &format!(
+            "{} {} from {} answered {}",
+            request.method,
+            request.path,
+            describe_client(request.client_ip, &[]),
+            status.code
+        ),
+    );
+    response
+}
 
-    let encoder = TextEncoder::new();
-    let metric_families = prometheus::gather();
-    let mut buffer = Vec::new();
-    encoder
-        .encode(&metric_families, &mut buffer)
-        .expect("encode metrics");
+/// `?comments=false` omits HELP/TYPE lines to save bandwidth.
+#[get("/metrics?<comments>")]
+#[allow(clippy::result_large_err)]
+fn metrics(
+    client_ip: Option<IpAddr>,
+    token: BearerToken,
+    client_cert: Option<Certificate<'_>>,
+    comments: Option<bool>,
+) -> Result<(ContentType, String), Denied> {
+    metrics_requests_total().inc();
+    let config = app_config();
+    let request = access_log::Request {
+        method: "GET",
+        path: "/metrics",
+        client_ip,
+    };
+    authorize(&request, &config, &token, client_cert.as_ref())?;
+
+    let started = Instant::now();
+    let response = collect_for_request(&config).map(|()| {
+        let payload = metrics_text_payload(&config);
+        let payload = match comments {
+            Some(false) => strip_comments(&payload),
+            _ => payload,
+        };
+        metrics_request_duration_seconds().observe(started.elapsed().as_secs_f64());
+        (ContentType::Plain, payload)
+    });
+    log_allowed(&config, &request, response)
+}
+
+/// Reachability probe; answers like GET /metrics without collecting.
+#[head("/metrics")]
+#[allow(clippy::result_large_err)]
+fn metrics_head(
+    client_ip: Option<IpAddr>,
+    token: BearerToken,
+    client_cert: Option<Certificate<'_>>,
+) -> Result<(ContentType, ()), Denied> {
+    let config = app_config();
+    let request = access_log::Request {
+        method: "HEAD",
+        path: "/metrics",
+        client_ip,
+    };
+    authorize(&request, &config, &token, client_cert.as_ref())?;
 
-    Ok((
-        ContentType::Plain,
-        String::from_utf8(buffer).unwrap_or_default(),
-    ))
+    log_allowed(&config, &request, Ok((ContentType::Plain, ())))
 }
 
 #[get("/metrics.json")]
+#[allow(clippy::result_large_err)]
 fn metrics_json(
     client_ip: Option<IpAddr>,
     token: BearerToken,
-) -> Result<(ContentType, String), status::Custom<(ContentType, String)>> {
+    client_cert: Option<Certificate<'_>>,
+) -> Result<(ContentType, String), Denied> {
     metrics_requests_total().inc();
     let config = app_config();
+    let request = access_log::Request {
+        method: "GET",
+        path: "/metrics.json",
+        client_ip,
+    };
+    authorize(&request, &config, &token, client_cert.as_ref())?;
 
-    // Check token authentication first
-    if !config.is_token_valid(token.0.as_deref()) {
-        if config.log_denied_requests {
-            eprintln!(
-                "Denied /metrics.json request from {} (invalid token)",
-                client_ip
-                    .map(|ip| ip.to_string())
-                    .unwrap_or_else(|| "<unknown>".to_string())
-            );
+    let response =
+        collect_for_request(&config).map(|()| (ContentType::JSON, metrics_json_payload(&config)));
+    log_allowed(&config, &request, response)
+}
+
+/// collector label -> value, without creating series for collectors that never ran
+fn collector_values(vec: &GaugeVec) -> HashMap<String, f64> {
+    let mut values = HashMap::new();
+    for family in vec.collect() {
+        for metric in family.get_metric() {
+            if let Some(label) = metric.get_label().first() {
+                values.insert(label.value().to_string(), metric.get_gauge().value());
+            }
         }
-        metrics_requests_denied_total().inc();
-        return Err(status::Custom(
-            Status::Unauthorized,
-            (ContentType::Plain, "unauthorized".to_string()),
-        ));
     }
+    values
+}
 
-    // Check IP allowlist
-    let is_allowed = client_ip
-        .map(|ip| config.is_metrics_ip_allowed(ip))
-        .unwrap_or(false);
-    if !is_allowed {
-        if config.log_denied_requests {
-            eprintln!(
-                "Denied /metrics.json request from {}",
-                client_ip
-                    .map(|ip| ip.to_string())
-                    .unwrap_or_else(|| "<unknown>".to_string())
-            );
-        }
-        metrics_requests_denied_total().inc();
-        return Err(status::Custom(
-            Status::Forbidden,
-            (ContentType::Plain, "access denied".to_string()),
-        ));
+fn collectors_json_payload(config: &AppConfig) -> String {
+    let metrics = collector_metrics();
+    let durations = collector_values(&metrics.duration_seconds);
+    let successes = collector_values(&metrics.success);
+    let timestamps = collector_values(&metrics.last_success_timestamp_seconds);
+
+    let collectors: Vec<JsonValue> = COLLECTORS
+        .iter()
+        .map(|(name, _)| {
+            serde_json::json!({
+                "name": name,
+                "enabled": config.is_datasource_enabled(name),
+                "disabled_reason": config.disabled_reason(name),
+                "last_duration_seconds": durations.get(*name),
+                "last_success": successes.get(*name).map(|value| *value == 1.0),
+                "last_success_timestamp_seconds": timestamps.get(*name),
+            })
+        })
+        .collect();
+    serde_json::to_string(&collectors).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Enabled and disabled collectors with their last run; never collects.
+#[get("/collectors")]
+#[allow(clippy::result_large_err)]
+fn collectors(
+    client_ip: Option<IpAddr>,
+    token: BearerToken,
+    client_cert: Option<Certificate<'_>>,
+) -> Result<(ContentType, String), Denied> {
+    let config = app_config();
+    let request = access_log::Request {
+        method: "GET",
+        path: "/collectors",
+        client_ip,
+    };
+    authorize(&request, &config, &token, client_cert.as_ref())?;
+
+    let response = Ok((ContentType::JSON, collectors_json_payload(&config)));
+    log_allowed(&config, &request, response)
+}
+
+/// Type name as written on the `# TYPE` line.
+fn metric_type_name(metric_type: MetricType) -> &'static str {
+    match metric_type {
+        MetricType::COUNTER => "counter",
+        MetricType::GAUGE => "gauge",
+        MetricType::SUMMARY => "summary",
+        MetricType::HISTOGRAM => "histogram",
+        MetricType::UNTYPED => "untyped",
     }
+}
 
-    update_metrics();
+/// Name, type and help of each exported family, as of the last collection.
+fn metadata_json_payload(config: &AppConfig) -> String {
+    let families: Vec<JsonValue> = gather(config)
+        .iter()
+        .map(|family| {
+            serde_json::json!({
+                "name": family.name(),
+                "type": metric_type_name(family.get_field_type()),
+                "help": family.help(),
+            })
+        })
+        .collect();
+    serde_json::to_string(&families).unwrap_or_else(|_| "[]".to_string())
+}
 
-    Ok((ContentType::JSON, metrics_json_payload()))
+/// Metric family metadata for documentation and dashboards; never collects.
+#[get("/metrics/metadata")]
+#[allow(clippy::result_large_err)]
+fn metrics_metadata(
+    client_ip: Option<IpAddr>,
+    token: BearerToken,
+    client_cert: Option<Certificate<'_>>,
+) -> Result<(ContentType, String), Denied> {
+    let config = app_config();
+    let request = access_log::Request {
+        method: "GET",
+        path: "/metrics/metadata",
+        client_ip,
+    };
+    authorize(&request, &config, &token, client_cert.as_ref())?;
+
+    let response = Ok((ContentType::JSON, metadata_json_payload(&config)));
+    log_allowed(&config, &request, response)
+}
+
+fn health_response() -> status::Custom<&'static str> {
+    let config = app_config();
+    let healthy = collection_is_healthy(
+        LAST_COLLECTION_FAILED.load(Ordering::Relaxed),
+        config.collection_interval_seconds,
+        last_collection_timestamp_seconds().get(),
+        unix_now(),
+    );
+    if healthy {
+        status::Custom(Status::Ok, "ok")
+    } else {
+        status::Custom(Status::ServiceUnavailable, "unhealthy")
+    }
+}
+
+/// Liveness probe; never collects and is not subject to the metrics ACL.
+#[get("/health")]
+fn health() -> status::Custom<&'static str> {
+    health_response()
+}
+
+#[get("/-/healthy")]
+fn healthy() -> status::Custom<&'static str> {
+    health_response()
 }
 
 #[get("/")]
@@ -346,53 +1111,206 @@ fn index() -> &'static str {
 #[catch(404)]
 fn not_found(request: &rocket::Request<'_>) -> &'static str {
     let config = app_config();
-    if config.log_404_requests {
-        let client_ip = request
-            .client_ip()
-            .map(|ip| ip.to_string())
-            .unwrap_or_else(|| "<unknown>".to_string());
-        eprintln!(
-            "404 {} {} from {}",
-            request.method(),
-            request.uri(),
-            client_ip
-        );
-    }
+    let method = request.method().to_string();
+    let path = request.uri().to_string();
+    let client_ip = request.client_ip();
+    access_log::record(
+        &config,
+        access_log::Event::NotFound,
+        &access_log::Request {
+            method: &method,
+            path: &path,
+            client_ip,
+        },
+        Status::NotFound.code,
+        "not found",
+        &format!(
+            "404 {method} {path} from {}",
+            describe_client(client_ip, &[])
+        ),
+    );
     "Not Found"
 }
 
-#[launch]
-fn rocket() -> _ {
+/// Server figment for one listen address; TLS is enabled when both cert and key are set.
+fn server_figment(config: &AppConfig, bind: SocketAddr) -> Figment {
+    let mut figment = Config::figment()
+        .merge(("address", bind.ip().to_string()))
+        .merge(("port", bind.port()))
+        // handle_signals owns SIGTERM/SIGINT so every listener stops together
+        .merge(("shutdown.ctrlc", false))
+        .merge(("shutdown.signals", Vec::<String>::new()))
+        .merge(("shutdown.grace", config.shutdown_timeout_seconds));
+
+    if let Some((cert, key)) = config.tls_config() {
+        let mut tls = TlsConfig::from_paths(cert, key);
+        if let Some(ca) = config.mtls_ca() {
+            tls = tls.with_mutual(MutualTls::from_path(ca).mandatory(true));
+            logging::info(&format!("Mutual TLS enabled with client CA: {ca}"));
+        }
+        figment = figment.merge(("tls", tls));
+        logging::info(&format!("TLS enabled with cert: {cert}"));
+    }
+    figment
+}
+
+fn rocket_at(config: &AppConfig, bind: SocketAddr) -> Rocket<Build> {
+    let _ = exporter_build_info();
+    let _ = metrics_request_duration_seconds();
+    rocket::custom(server_figment(config, bind))
+        .mount(
+            "/",
+            routes![
+                index,
+                health,
+                healthy,
+                metrics,
+                metrics_head,
+                metrics_json,
+                metrics_metadata,
+                collectors
+            ],
+        )
+        .register("/", catchers![not_found])
+}
+
+/// Server on the first bind address, for the local test client.
+#[cfg(test)]
+fn rocket() -> Rocket<Build> {
+    let config = app_config();
+    rocket_at(&config, config.bind_addrs()[0])
+}
+
+/// Rocket serves a single address, so each bind address gets its own server
+/// sharing the routes and global state; returns once they have all stopped.
+async fn serve(config: &AppConfig) -> Result<(), rocket::Error> {
+    let mut servers = Vec::new();
+    for bind in config.bind_addrs() {
+        servers.push(rocket_at(config, bind).ignite().await?);
+    }
+    let shutdowns = servers.iter().map(|server| server.shutdown()).collect();
+    let signals = rocket::tokio::spawn(handle_signals(shutdowns));
+    let launched =
+        rocket::futures::future::try_join_all(servers.into_iter().map(|server| server.launch()))
+            .await;
+    signals.abort();
+    launched.map(|_| ())
+}
+
+/// Families the exporter exports about itself whatever the datasources produce.
+const EXPORTER_FAMILIES: &[&str] = &[
+    "metrics_requests_total",
+    "metrics_requests_denied_total",
+    "metrics_requests_ratelimited_total",
+    "metrics_requests_overloaded_total",
+    "metrics_request_duration_seconds",
+    "exporter_build_info",
+    "last_collection_timestamp_seconds",
+    "collector_duration_seconds",
+    "collector_success",
+    "collector_last_success_timestamp_seconds",
+    "collector_errors_total",
+    "config_last_reload_success",
+    "config_last_reload_timestamp_seconds",
+    "counter_reset_total",
+    "process_cpu_seconds_total",
+    "process_resident_memory_bytes",
+    "process_open_fds",
+    "process_start_time_seconds",
+];
+
+/// Samples in `families` that came from datasources, ignoring EXPORTER_FAMILIES.
+fn collector_sample_count(families: &[MetricFamily], namespace: &str) -> usize {
+    let prefix = format!("{namespace}_");
+    let collected: Vec<MetricFamily> = families
+        .iter()
+        .filter(|family| {
+            let name = match namespace {
+                "" => family.name(),
+                _ => family.name().strip_prefix(&prefix).unwrap_or(family.name()),
+            };
+            !EXPORTER_FAMILIES.contains(&name)
+        })
+        .cloned()
+        .collect();
+    samples::flatten(&collected).len()
+}
+
+/// Exit status of `--check`: collectors must not panic and must export something.
+fn check_exit_code(succeeded: bool, samples: usize) -> i32 {
+    if succeeded && samples > 0 { 0 } else { 1 }
+}
+
+/// Collects once and prints the /metrics payload to stdout.
+fn run_check(config: &AppConfig) -> i32 {
+    let succeeded = std::panic::catch_unwind(update_metrics).unwrap_or(false);
+    let samples = collector_sample_count(&gather(config), config.metric_namespace());
+    print!("{}", metrics_text_payload(config));
+    if !succeeded {
+        logging::error("Check failed: a collector panicked");
+    } else if samples == 0 {
+        logging::error("Check failed: no datasource produced samples");
+    }
+    check_exit_code(succeeded, samples)
+}
+
+#[rocket::main]
+#[allow(clippy::result_large_err)]
+async fn main() -> Result<(), rocket::Error> {
     runtime::init();
     if runtime::debug_enabled() {
-        eprintln!("Debug logging enabled.");
+        logging::info("Debug logging enabled.");
     }
     // Initialize config early to run subsystem availability checks and print messages
-    let _ = app_config();
-    if !is_root() {
-        eprintln!("\x1b[31mNon-root: ethtool stats collection disabled.\x1b[0m");
+    let config = app_config();
+    if runtime::check_mode() {
+        std::process::exit(run_check(&config));
     }
-    let bind = app_config().bind_addr();
-    let mut figment = Config::figment()
-        .merge(("address", bind.ip().to_string()))
-        .merge(("port", bind.port()));
-
-    if let Some((cert, key)) = app_config().tls_config() {
-        figment = figment.merge(("tls", TlsConfig::from_paths(cert, key)));
-        eprintln!("TLS enabled with cert: {cert}");
+    let interval = config.collection_interval_seconds;
+    if interval > 0 {
+        logging::info(&format!("Background collection every {interval}s."));
+        spawn_background_collector(Duration::from_secs(interval));
     }
 
-    rocket::custom(figment)
-        .mount("/", routes![index, metrics, metrics_json])
-        .register("/", catchers![not_found])
+    loop {
+        serve(&app_config()).await?;
+        if !RESTART_LISTENER.swap(false, Ordering::SeqCst) {
+            break;
+        }
+    }
+    finish_shutdown(&app_config());
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::rocket;
-    use rocket::http::Status;
+    use crate::config::AppConfig;
+    use prometheus::core::Collector;
+    use rocket::fairing::AdHoc;
+    use rocket::http::{ContentType, Status};
     use rocket::local::blocking::Client;
-    use std::net::SocketAddr;
+    use rocket::mtls::Certificate;
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn reload_keeps_collection_interval() {
+        let mut current = AppConfig::default();
+        current.collection_interval_seconds = 15;
+        let mut config = AppConfig::default();
+        config.cpu_aggregate_only = true;
+        config.bind = vec!["0.0.0.0:9100".to_string()];
+        let kept = super::keep_startup_keys(&current, &mut config, false);
+        assert_eq!(kept, ["collection_interval_seconds", "bind"]);
+        assert_eq!(config.collection_interval_seconds, 15);
+        assert!(config.cpu_aggregate_only);
+
+        // A listener restart picks up the new bind address
+        let kept = super::keep_startup_keys(&current, &mut config, true);
+        assert!(kept.is_empty());
+    }
 
     #[test]
     fn index_returns_hint() {
@@ -429,6 +1347,451 @@ mod tests {
         assert!(body.contains("metrics_requests_total"));
     }
 
+    #[test]
+    fn metrics_endpoint_observes_request_duration() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let _ = client
+            .get("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+        let response = client
+            .get("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        let body = response.into_string().unwrap_or_default();
+        assert!(body.contains("# TYPE metrics_request_duration_seconds histogram"));
+        assert!(body.contains("metrics_request_duration_seconds_bucket{le=\"0.001\"}"));
+        assert!(body.contains("metrics_request_duration_seconds_bucket{le=\"5\"}"));
+        let count = body
+            .lines()
+            .find_map(|line| line.strip_prefix("metrics_request_duration_seconds_count "))
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(0);
+        assert!(count >= 1);
+    }
+
+    #[test]
+    fn metrics_endpoint_collects_on_demand_by_default() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .get("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        let body = response.into_string().unwrap_or_default();
+        let timestamp = body
+            .lines()
+            .find(|line| line.starts_with("last_collection_timestamp_seconds "))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        assert!(timestamp > 0.0);
+    }
+
+    #[test]
+    fn metrics_endpoint_can_omit_comments() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let body = |uri: &str| {
+            client
+                .get(uri.to_string())
+                .remote(metrics_remote_addr())
+                .dispatch()
+                .into_string()
+                .unwrap_or_default()
+        };
+
+        let with_comments = body("/metrics");
+        assert!(with_comments.contains("# HELP metrics_requests_total "));
+        assert!(with_comments.contains("# TYPE metrics_requests_total counter"));
+        assert!(body("/metrics?comments=true").contains("# TYPE"));
+
+        let without = body("/metrics?comments=false");
+        assert!(
+            without
+                .lines()
+                .any(|line| line.starts_with("metrics_requests_total "))
+        );
+        assert!(!without.lines().any(|line| line.starts_with('#')));
+        assert!(without.ends_with('\n'));
+    }
+
+    #[test]
+    fn metrics_text_payload_applies_namespace() {
+        let config: AppConfig = toml::from_str("metric_namespace = \"rslx\"").unwrap();
+        let _ = super::metrics_requests_total();
+
+        let body = super::metrics_text_payload(&config);
+        assert!(body.contains("# TYPE rslx_metrics_requests_total counter"));
+        assert!(
+            body.lines()
+                .filter(|line| !line.starts_with('#') && !line.is_empty())
+                .all(|line| line.starts_with("rslx_"))
+        );
+    }
+
+    #[test]
+    fn metrics_text_payload_applies_name_filters() {
+        let config: AppConfig = toml::from_str(
+            "metric_allowlist = [\"metrics_requests_*\", \"exporter_*\"]\n\
+             metric_denylist = [\"*_denied_total\"]",
+        )
+        .unwrap();
+        let _ = super::metrics_requests_total();
+        let _ = super::metrics_requests_denied_total();
+
+        let body = super::metrics_text_payload(&config);
+        assert!(body.contains("metrics_requests_total "));
+        assert!(!body.contains("metrics_requests_denied_total"));
+        assert!(!body.contains("last_collection_timestamp_seconds"));
+    }
+
+    #[test]
+    fn drop_label_sums_series() {
+        let vec = prometheus::GaugeVec::new(
+            prometheus::Opts::new("test_label_drop", "help"),
+            &["cpu", "mode"],
+        )
+        .unwrap();
+        vec.with_label_values(&["0", "user"]).set(1.5);
+        vec.with_label_values(&["1", "user"]).set(2.5);
+        vec.with_label_values(&["0", "idle"]).set(4.0);
+
+        let mut family = vec.collect().remove(0);
+        super::drop_label(&mut family, "cpu");
+
+        let mut series: Vec<(String, f64)> = family
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                assert_eq!(metric.get_label().len(), 1);
+                (
+                    metric.get_label()[0].value().to_string(),
+                    metric.get_gauge().value(),
+                )
+            })
+            .collect();
+        series.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            series,
+            vec![("idle".to_string(), 4.0), ("user".to_string(), 4.0)]
+        );
+    }
+
+    #[test]
+    fn metrics_text_payload_applies_label_drop() {
+        let config: AppConfig =
+            toml::from_str("[[label_drop]]\nmetric = \"exporter_build_*\"\nlabel = \"rustc\"")
+                .unwrap();
+        let _ = super::exporter_build_info();
+
+        let body = super::metrics_text_payload(&config);
+        let line = body
+            .lines()
+            .find(|line| line.starts_with("exporter_build_info{"))
+            .expect("build info line");
+        assert!(!line.contains("rustc="));
+        assert!(line.contains("git_commit="));
+    }
+
+    #[test]
+    fn metrics_text_payload_label_drop_matches_namespaced_name() {
+        let config: AppConfig = toml::from_str(
+            "metric_namespace = \"rslx\"\n\
+             [[label_drop]]\nmetric = \"rslx_exporter_build_info\"\nlabel = \"rustc\"",
+        )
+        .unwrap();
+        let _ = super::exporter_build_info();
+
+        let body = super::metrics_text_payload(&config);
+        let line = body
+            .lines()
+            .find(|line| line.starts_with("rslx_exporter_build_info{"))
+            .expect("build info line");
+        assert!(!line.contains("rustc="));
+    }
+
+    #[test]
+    fn metrics_text_payload_applies_static_labels() {
+        let config: AppConfig =
+            toml::from_str("static_labels = { datacenter = \"dc1\", version = \"shadowed\" }")
+                .unwrap();
+        let _ = super::exporter_build_info();
+        let _ = super::metrics_requests_total();
+
+        let body = super::metrics_text_payload(&config);
+        let requests = body
+            .lines()
+            .find(|line| line.starts_with("metrics_requests_total"))
+            .expect("requests line");
+        assert!(requests.contains("datacenter=\"dc1\""));
+        let build_info = body
+            .lines()
+            .find(|line| line.starts_with("exporter_build_info{"))
+            .expect("build info line");
+        assert!(build_info.contains("datacenter=\"dc1\""));
+        assert!(!build_info.contains("version=\"shadowed\""));
+    }
+
+    #[test]
+    fn metrics_text_payload_emits_timestamps() {
+        let _ = super::metrics_requests_total();
+        let requests_line = |config: &AppConfig| {
+            super::metrics_text_payload(config)
+                .lines()
+                .find(|line| line.starts_with("metrics_requests_total "))
+                .expect("requests line")
+                .to_string()
+        };
+
+        assert_eq!(
+            requests_line(&AppConfig::default())
+                .split_whitespace()
+                .count(),
+            2
+        );
+
+        let config: AppConfig = toml::from_str("emit_timestamps = true").unwrap();
+        let line = requests_line(&config);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(fields.len(), 3);
+        let timestamp: i64 = fields[2].parse().unwrap();
+        let now_ms = (super::unix_now() * 1000.0) as i64;
+        assert!(timestamp > 0 && timestamp <= now_ms);
+    }
+
+    #[test]
+    fn samples_to_json_keeps_integer_counts() {
+        let registry = prometheus::Registry::new();
+        let histogram = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new("test_latency_seconds", "help").buckets(vec![0.5, 1.0]),
+        )
+        .expect("histogram");
+        histogram.observe(0.25);
+        registry
+            .register(Box::new(histogram))
+            .expect("register histogram");
+
+        let json = super::samples_to_json(&crate::samples::flatten(&registry.gather()));
+        assert_eq!(
+            json,
+            concat!(
+                r#"[{"_name_":"test_latency_seconds_bucket","_value_":1,"le":"0.5"},"#,
+                r#"{"_name_":"test_latency_seconds_bucket","_value_":1,"le":"1"},"#,
+                r#"{"_name_":"test_latency_seconds_sum","_value_":0.25},"#,
+                r#"{"_name_":"test_latency_seconds_count","_value_":1}]"#
+            )
+        );
+    }
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn read_pem(name: &str) -> Vec<u8> {
+        std::fs::read(fixture(name)).expect("read fixture")
+    }
+
+    /// Performs a blocking HTTPS GET, trusting only the self-signed fixture and
+    /// optionally presenting the fixture client certificate.
+    fn https_get(port: u16, path: &str, client_auth: bool) -> String {
+        use std::io::{Read, Write};
+
+        let mut roots = rustls::RootCertStore::empty();
+        for der in rustls_pemfile::certs(&mut read_pem("tls-cert.pem").as_slice()).expect("pem") {
+            roots
+                .add(&rustls::Certificate(der))
+                .expect("add fixture cert");
+        }
+        let builder = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let client_config = if client_auth {
+            let certs = rustls_pemfile::certs(&mut read_pem("mtls-client-cert.pem").as_slice())
+                .expect("client cert pem")
+                .into_iter()
+                .map(rustls::Certificate)
+                .collect();
+            let key =
+                rustls_pemfile::pkcs8_private_keys(&mut read_pem("mtls-client-key.pem").as_slice())
+                    .expect("client key pem")
+                    .remove(0);
+            builder
+                .with_client_auth_cert(certs, rustls::PrivateKey(key))
+                .expect("client auth")
+        } else {
+            builder.with_no_client_auth()
+        };
+        let server_name = "localhost".try_into().expect("server name");
+        let connection =
+            rustls::ClientConnection::new(Arc::new(client_config), server_name).expect("client");
+        let socket = std::net::TcpStream::connect(("127.0.0.1", port)).expect("connect");
+        let mut stream = rustls::StreamOwned::new(connection, socket);
+        let mut response = String::new();
+        if write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .is_ok()
+        {
+            // Rocket may close without close_notify once the body is sent
+            let _ = stream.read_to_string(&mut response);
+        }
+        response
+    }
+
+    #[get("/whoami")]
+    fn whoami(cert: Certificate<'_>) -> String {
+        super::client_identities(&cert).join(",")
+    }
+
+    /// Launches the TLS server for `config` on an ephemeral port.
+    async fn launch_tls(config: &AppConfig) -> (u16, rocket::Shutdown) {
+        let (port_tx, port_rx) = rocket::tokio::sync::oneshot::channel();
+        let port_tx = std::sync::Mutex::new(Some(port_tx));
+        let server = rocket::custom(super::server_figment(config, config.bind_addrs()[0]))
+            .mount("/", routes![super::health, whoami])
+            .attach(AdHoc::on_liftoff("report port", move |rocket| {
+                if let Some(tx) = port_tx.lock().expect("port sender").take() {
+                    let _ = tx.send((rocket.config().port, rocket.shutdown()));
+                }
+                Box::pin(async {})
+            }))
+            .ignite()
+            .await
+            .expect("ignite");
+        rocket::tokio::spawn(server.launch());
+        port_rx.await.expect("server lifted off")
+    }
+
+    fn tls_config_toml(extra: &str) -> AppConfig {
+        toml::from_str(&format!(
+            "bind = \"127.0.0.1:0\"\ntls_cert_path = \"{}\"\ntls_key_path = \"{}\"\n{extra}",
+            fixture("tls-cert.pem"),
+            fixture("tls-key.pem")
+        ))
+        .expect("parse config")
+    }
+
+    #[rocket::async_test]
+    async fn server_boots_with_self_signed_cert() {
+        let (port, shutdown) = launch_tls(&tls_config_toml("")).await;
+
+        let response =
+            rocket::tokio::task::spawn_blocking(move || https_get(port, "/health", false))
+                .await
+                .expect("https request");
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("ok"));
+
+        shutdown.notify();
+    }
+
+    #[rocket::async_test]
+    async fn mutual_tls_requires_client_certificate() {
+        let config = tls_config_toml(&format!("tls_ca_path = \"{}\"", fixture("mtls-ca.pem")));
+        let (port, shutdown) = launch_tls(&config).await;
+
+        let (anonymous, identified) = rocket::tokio::task::spawn_blocking(move || {
+            (
+                https_get(port, "/health", false),
+                https_get(port, "/whoami", true),
+            )
+        })
+        .await
+        .expect("https requests");
+        assert!(!anonymous.contains("200 OK"), "{anonymous}");
+        assert!(identified.starts_with("HTTP/1.1 200 OK"), "{identified}");
+        assert!(identified.ends_with("prometheus,prometheus.example.com"));
+
+        shutdown.notify();
+    }
+
+    #[test]
+    fn health_endpoints_bypass_acl() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let remote: SocketAddr = "203.0.113.7:40000".parse().expect("valid socket addr");
+        for path in ["/health", "/-/healthy"] {
+            let response = client.get(path).remote(remote).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.into_string().as_deref(), Some("ok"));
+        }
+    }
+
+    #[test]
+    fn check_mode_fails_on_panic_or_empty_output() {
+        assert_eq!(super::check_exit_code(true, 120), 0);
+        assert_eq!(super::check_exit_code(false, 120), 1);
+        assert_eq!(super::check_exit_code(true, 0), 1);
+    }
+
+    #[test]
+    fn check_mode_ignores_exporter_metrics() {
+        // What /metrics holds when every datasource is disabled or empty
+        let registry = prometheus::Registry::new();
+        let build_info = prometheus::GaugeVec::new(
+            prometheus::Opts::new("exporter_build_info", "help"),
+            &["version"],
+        )
+        .unwrap();
+        build_info.with_label_values(&["0.1.0"]).set(1.0);
+        let success = prometheus::GaugeVec::new(
+            prometheus::Opts::new("collector_success", "help"),
+            &["collector"],
+        )
+        .unwrap();
+        success.with_label_values(&["procfs"]).set(1.0);
+        let open_fds = prometheus::Gauge::new("process_open_fds", "help").unwrap();
+        open_fds.set(12.0);
+        let requests = prometheus::Histogram::with_opts(prometheus::HistogramOpts::new(
+            "metrics_request_duration_seconds",
+            "help",
+        ))
+        .unwrap();
+        requests.observe(0.01);
+        registry.register(Box::new(build_info)).unwrap();
+        registry.register(Box::new(success)).unwrap();
+        registry.register(Box::new(open_fds)).unwrap();
+        registry.register(Box::new(requests)).unwrap();
+
+        let mut families = registry.gather();
+        assert!(!crate::samples::flatten(&families).is_empty());
+        assert_eq!(super::collector_sample_count(&families, ""), 0);
+        super::apply_namespace(&mut families, "rslx");
+        assert_eq!(super::collector_sample_count(&families, "rslx"), 0);
+
+        let load = prometheus::Gauge::new("load1", "help").unwrap();
+        registry.register(Box::new(load)).unwrap();
+        let mut families = registry.gather();
+        assert_eq!(super::collector_sample_count(&families, ""), 1);
+        super::apply_namespace(&mut families, "rslx");
+        assert_eq!(super::collector_sample_count(&families, "rslx"), 1);
+    }
+
+    #[test]
+    fn collection_health_reflects_failures_and_staleness() {
+        assert!(super::collection_is_healthy(false, 0, 0.0, 100.0));
+        assert!(!super::collection_is_healthy(true, 0, 90.0, 100.0));
+        assert!(super::collection_is_healthy(false, 10, 75.0, 100.0));
+        assert!(!super::collection_is_healthy(false, 10, 60.0, 100.0));
+        // Background collector has not finished its first pass yet
+        assert!(super::collection_is_healthy(false, 10, 0.0, 100.0));
+    }
+
+    #[test]
+    fn metrics_endpoint_exposes_build_info() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .get("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        let body = response.into_string().unwrap_or_default();
+        let expected = format!("version=\"{}\"", env!("CARGO_PKG_VERSION"));
+        assert!(body.contains("exporter_build_info{"));
+        assert!(body.contains(&expected));
+    }
+
     #[test]
     fn metrics_endpoint_contains_help_and_type() {
         let client = Client::tracked(rocket()).expect("valid rocket instance");
@@ -499,6 +1862,25 @@ mod tests {
         assert_eq!(response.status(), Status::NotFound);
     }
 
+    #[test]
+    fn metrics_head_returns_headers_only() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .head("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::Plain));
+        assert_eq!(response.into_string().unwrap_or_default(), "");
+
+        let response = client
+            .head("/metrics")
+            .remote("10.0.0.1:1234".parse().unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
     #[test]
     fn metrics_endpoint_denies_unlisted_ip() {
         let client = Client::tracked(rocket()).expect("valid rocket instance");
@@ -511,6 +1893,170 @@ mod tests {
         assert_eq!(response.into_string().unwrap_or_default(), "access denied");
     }
 
+    #[test]
+    fn collectors_endpoint_reports_last_run() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        client
+            .get("/metrics")
+            .remote(metrics_remote_addr())
+            .dispatch();
+        let response = client
+            .get("/collectors")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().unwrap_or_default()).unwrap();
+        let procfs = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|collector| collector["name"] == "procfs")
+            .expect("procfs collector listed");
+        assert_eq!(procfs["enabled"], true);
+        assert_eq!(procfs["last_success"], true);
+        assert!(procfs["last_duration_seconds"].as_f64().is_some());
+    }
+
+    #[test]
+    fn collectors_payload_reports_disabled_reason() {
+        let mut config = AppConfig::default();
+        config.disable_datasource("drbd", "DRBD subsystem not available (/proc/drbd)");
+        let body: serde_json::Value =
+            serde_json::from_str(&super::collectors_json_payload(&config)).unwrap();
+        let drbd = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|collector| collector["name"] == "drbd")
+            .expect("drbd collector listed");
+        assert_eq!(drbd["enabled"], false);
+        assert_eq!(
+            drbd["disabled_reason"],
+            "DRBD subsystem not available (/proc/drbd)"
+        );
+    }
+
+    #[test]
+    fn collectors_endpoint_denies_unlisted_ip() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .get("/collectors")
+            .remote("10.0.0.1:1234".parse().unwrap())
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn metrics_metadata_endpoint_lists_families() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .get("/metrics/metadata")
+            .remote(metrics_remote_addr())
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().unwrap_or_default()).unwrap();
+        let families = body.as_array().unwrap();
+        let duration = families
+            .iter()
+            .find(|family| family["name"] == "metrics_request_duration_seconds")
+            .expect("request duration histogram listed");
+        assert_eq!(duration["type"], "histogram");
+        assert!(!duration["help"].as_str().unwrap_or_default().is_empty());
+        let requests = families
+            .iter()
+            .find(|family| family["name"] == "metrics_requests_total")
+            .expect("request counter listed");
+        assert_eq!(requests["type"], "counter");
+    }
+
+    #[test]
+    fn metrics_metadata_endpoint_denies_unlisted_ip() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .get("/metrics/metadata")
+            .remote("10.0.0.1:1234".parse().unwrap())
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn rate_limit_is_per_client_ip() {
+        let config: AppConfig = toml::from_str("metrics_rate_limit_per_second = 1.0").unwrap();
+        let limited: IpAddr = "127.0.0.61".parse().unwrap();
+        let other: IpAddr = "127.0.0.62".parse().unwrap();
+        let before = super::metrics_requests_ratelimited_total().get();
+        let request = |client_ip: IpAddr| super::access_log::Request {
+            method: "GET",
+            path: "/metrics",
+            client_ip: Some(client_ip),
+        };
+
+        assert!(super::check_rate_limit(&config, &request(limited)).is_ok());
+        let denied = super::check_rate_limit(&config, &request(limited))
+            .expect_err("second request within a second is limited");
+        assert_eq!(denied.0, Status::TooManyRequests);
+        assert!(super::check_rate_limit(&config, &request(other)).is_ok());
+        // Disabled by default
+        let config = AppConfig::default();
+        assert!(super::check_rate_limit(&config, &request(limited)).is_ok());
+        assert!(super::metrics_requests_ratelimited_total().get() > before);
+    }
+
+    #[test]
+    fn concurrent_scrapes_over_limit_get_503() {
+        let limiter = super::ConcurrencyLimiter::new();
+        let before = super::metrics_requests_overloaded_total().get();
+
+        let permit = super::acquire_scrape(&limiter, 1).expect("first scrape collects");
+        let denied = super::acquire_scrape(&limiter, 1)
+            .err()
+            .expect("second concurrent scrape is rejected");
+        assert_eq!(denied.0, Status::ServiceUnavailable);
+        assert!(super::metrics_requests_overloaded_total().get() > before);
+
+        drop(permit);
+        assert!(super::acquire_scrape(&limiter, 1).is_ok());
+    }
+
+    #[test]
+    fn drain_waits_for_in_flight_collections() {
+        let in_flight = super::ConcurrencyLimiter::new();
+        assert!(super::drain_collections(&in_flight, Duration::ZERO));
+
+        let collection = in_flight.enter();
+        assert!(!super::drain_collections(
+            &in_flight,
+            Duration::from_millis(20)
+        ));
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                drop(collection);
+            });
+            assert!(super::drain_collections(&in_flight, Duration::from_secs(5)));
+        });
+    }
+
+    #[test]
+    fn server_figment_owns_shutdown_signals() {
+        let mut config = AppConfig::default();
+        config.shutdown_timeout_seconds = 7;
+        let rocket_config: rocket::Config = super::server_figment(&config, config.bind_addrs()[0])
+            .extract()
+            .expect("valid rocket config");
+        assert!(!rocket_config.shutdown.ctrlc);
+        assert!(rocket_config.shutdown.signals.is_empty());
+        assert_eq!(rocket_config.shutdown.grace, 7);
+    }
+
     fn metrics_remote_addr() -> SocketAddr {
         "127.0.0.1:1234".parse().expect("parse remote addr")
     }
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Buckets kept before idle (full) ones are evicted
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token bucket per client IP; the burst equals one second of requests.
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+fn burst(rate: f64) -> f64 {
+    rate.max(1.0)
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for `ip`; false means the request is over the limit.
+    pub fn allow(&self, ip: IpAddr, rate: f64, now: Instant) -> bool {
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&ip) {
+            // A bucket that has refilled completely behaves like a new one
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.refilled).as_secs_f64() * rate
+                    < burst(rate)
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: burst(rate),
+            refilled: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst(rate));
+        bucket.refilled = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// Bounds how many requests collect at the same time.
+pub struct ConcurrencyLimiter {
+    active: AtomicUsize,
+}
+
+/// Slot held while a request collects; released on drop.
+pub struct Permit<'a> {
+    limiter: &'a ConcurrencyLimiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limiter.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub const fn new() -> Self {
+        Self {
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Takes a slot unless `limit` requests already hold one.
+    pub fn try_acquire(&self, limit: usize) -> Option<Permit<'_>> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < limit).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| Permit { limiter: self })
+    }
+
+    /// Takes a slot without a limit, to count work in progress.
+    pub fn enter(&self) -> Permit<'_> {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        Permit { limiter: self }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_allow_limits_burst_and_refills() {
+        let limiter = RateLimiter::new();
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.allow(ip, 2.0, start));
+        assert!(limiter.allow(ip, 2.0, start));
+        assert!(!limiter.allow(ip, 2.0, start));
+
+        // Half a second refills one token at 2/s
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.allow(ip, 2.0, later));
+        assert!(!limiter.allow(ip, 2.0, later));
+    }
+
+    #[test]
+    fn test_allow_is_per_client() {
+        let limiter = RateLimiter::new();
+        let first: IpAddr = "192.0.2.1".parse().unwrap();
+        let second: IpAddr = "192.0.2.2".parse().unwrap();
+        let now = Instant::now();
+
+        assert!(limiter.allow(first, 0.5, now));
+        assert!(!limiter.allow(first, 0.5, now));
+        assert!(limiter.allow(second, 0.5, now));
+    }
+
+    #[test]
+    fn test_concurrency_limiter_releases_on_drop() {
+        let limiter = ConcurrencyLimiter::new();
+        let first = limiter.try_acquire(2).unwrap();
+        let second = limiter.try_acquire(2).unwrap();
+        assert!(limiter.try_acquire(2).is_none());
+
+        drop(first);
+        let third = limiter.try_acquire(2);
+        assert!(third.is_some());
+        drop(second);
+        drop(third);
+        assert_eq!(limiter.active.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_concurrency_limiter_enter_ignores_limit() {
+        let limiter = ConcurrencyLimiter::new();
+        let held = limiter.try_acquire(1).unwrap();
+        let counted = limiter.enter();
+        assert_eq!(limiter.active(), 2);
+        assert!(limiter.try_acquire(2).is_none());
+
+        drop(held);
+        drop(counted);
+        assert_eq!(limiter.active(), 0);
+    }
+
+    #[test]
+    fn test_idle_clients_are_evicted() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+        for index in 0..MAX_TRACKED_CLIENTS as u32 {
+            limiter.allow(IpAddr::from(index.to_be_bytes()), 1.0, now);
+        }
+
+        let later = now + Duration::from_secs(2);
+        assert!(limiter.allow("198.51.100.1".parse().unwrap(), 1.0, later));
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+}
@@ -1,15 +1,161 @@
+use crate::logging;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 
+const CONFIG_ENV_VAR: &str = "EXPORTER_CONFIG";
+
 static DEBUG_ENABLED: OnceLock<bool> = OnceLock::new();
+static CHECK_MODE: OnceLock<bool> = OnceLock::new();
+static STRICT_CONFIG: OnceLock<bool> = OnceLock::new();
+static CONFIG_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
 
 fn parse_debug_flag() -> bool {
     std::env::args().any(|arg| arg == "-d" || arg == "--debug")
 }
 
+fn parse_check_flag(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "--check" || arg == "--once")
+}
+
+fn parse_check_mode() -> bool {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    parse_check_flag(&args)
+}
+
+fn parse_strict_config() -> bool {
+    std::env::args().any(|arg| arg == "--strict-config")
+}
+
+/// A trailing `--config` or one followed by another flag has no value.
+fn parse_config_flag(args: &[String]) -> Result<Option<String>, String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--config" {
+            return match iter.next() {
+                Some(path) if !path.starts_with('-') => Ok(Some(path.clone())),
+                _ => Err("--config requires a path".to_string()),
+            };
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Ok(Some(path.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// CLI flag wins over the environment; None means the implicit ./config.toml.
+/// An explicit but empty path is an error rather than the implicit default.
+fn resolve_config_path(
+    cli: Option<String>,
+    env: Option<String>,
+) -> Result<Option<PathBuf>, String> {
+    match (cli, env) {
+        (Some(path), _) if path.is_empty() => Err("--config requires a path".to_string()),
+        (Some(path), _) => Ok(Some(PathBuf::from(path))),
+        (None, Some(path)) if path.is_empty() => Err(format!("{CONFIG_ENV_VAR} is set but empty")),
+        (None, env) => Ok(env.map(PathBuf::from)),
+    }
+}
+
+fn parse_config_path() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = parse_config_flag(&args)
+        .and_then(|cli| resolve_config_path(cli, std::env::var(CONFIG_ENV_VAR).ok()));
+    match path {
+        Ok(path) => path,
+        Err(err) => {
+            logging::error(&format!("Invalid config path: {err}"));
+            std::process::exit(1);
+        }
+    }
+}
+
 pub fn init() {
     let _ = DEBUG_ENABLED.set(parse_debug_flag());
+    let _ = CHECK_MODE.set(parse_check_mode());
+    let _ = STRICT_CONFIG.set(parse_strict_config());
+    let _ = CONFIG_PATH.set(parse_config_path());
 }
 
 pub fn debug_enabled() -> bool {
     *DEBUG_ENABLED.get_or_init(parse_debug_flag)
 }
+
+/// Collect once, print the metrics and exit instead of serving them
+pub fn check_mode() -> bool {
+    *CHECK_MODE.get_or_init(parse_check_mode)
+}
+
+/// Reject unknown keys and invalid values instead of falling back to defaults
+pub fn strict_config() -> bool {
+    *STRICT_CONFIG.get_or_init(parse_strict_config)
+}
+
+/// Config file explicitly requested via --config or EXPORTER_CONFIG
+pub fn config_path() -> Option<&'static PathBuf> {
+    CONFIG_PATH.get_or_init(parse_config_path).as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_check_flag() {
+        assert!(parse_check_flag(&args(&["--check"])));
+        assert!(parse_check_flag(&args(&[
+            "--config",
+            "/etc/x.toml",
+            "--once"
+        ])));
+        assert!(!parse_check_flag(&args(&["-d"])));
+    }
+
+    #[test]
+    fn test_parse_config_flag() {
+        assert_eq!(
+            parse_config_flag(&args(&["-d", "--config", "/etc/x.toml"])),
+            Ok(Some("/etc/x.toml".to_string()))
+        );
+        assert_eq!(
+            parse_config_flag(&args(&["--config=/etc/y.toml"])),
+            Ok(Some("/etc/y.toml".to_string()))
+        );
+        assert_eq!(parse_config_flag(&args(&["-d"])), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_config_flag_missing_value() {
+        assert!(parse_config_flag(&args(&["--config"])).is_err());
+        assert!(parse_config_flag(&args(&["--config", "--check"])).is_err());
+        // `--config=` is passed on as an empty path and rejected by resolve
+        assert_eq!(
+            parse_config_flag(&args(&["--config="])),
+            Ok(Some(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_resolve_config_path_precedence() {
+        assert_eq!(
+            resolve_config_path(Some("/cli.toml".into()), Some("/env.toml".into())),
+            Ok(Some(PathBuf::from("/cli.toml")))
+        );
+        assert_eq!(
+            resolve_config_path(None, Some("/env.toml".into())),
+            Ok(Some(PathBuf::from("/env.toml")))
+        );
+        assert_eq!(resolve_config_path(None, None), Ok(None));
+    }
+
+    #[test]
+    fn test_resolve_config_path_rejects_empty() {
+        assert!(resolve_config_path(Some(String::new()), None).is_err());
+        assert!(resolve_config_path(Some(String::new()), Some("/env.toml".into())).is_err());
+        assert!(resolve_config_path(None, Some(String::new())).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use prometheus::proto::{MetricFamily, MetricType};
+
+/// Value of a flattened sample. Bucket and sample counts stay integral so
+/// encoders can render them without a fractional part.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleValue {
+    Float(f64),
+    Count(u64),
+}
+
+/// One exposition line: histogram and summary families expand into their
+/// `_bucket`/`_quantile`, `_sum` and `_count` samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: SampleValue,
+}
+
+fn push_sample(
+    samples: &mut Vec<Sample>,
+    name: String,
+    labels: Vec<(String, String)>,
+    value: SampleValue,
+) {
+    samples.push(Sample {
+        name,
+        labels,
+        value,
+    });
+}
+
+pub fn flatten(families: &[MetricFamily]) -> Vec<Sample> {
+    let mut samples = Vec::new();
+
+    for family in families {
+        let name = family.name();
+        let metric_type = family.get_field_type();
+        for metric in family.get_metric() {
+            let base_labels: Vec<(String, String)> = metric
+                .get_label()
+                .iter()
+                .map(|label| (label.name().to_string(), label.value().to_string()))
+                .collect();
+
+            match metric_type {
+                MetricType::COUNTER => {
+                    let value = SampleValue::Float(metric.get_counter().value());
+                    push_sample(&mut samples, name.to_string(), base_labels, value);
+                }
+                MetricType::GAUGE => {
+                    let value = SampleValue::Float(metric.get_gauge().value());
+                    push_sample(&mut samples, name.to_string(), base_labels, value);
+                }
+                MetricType::UNTYPED => {
+                    // UNTYPED metrics are not directly supported, skip
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    for bucket in histogram.get_bucket() {
+                        let mut labels = base_labels.clone();
+                        labels.push(("le".to_string(), bucket.upper_bound().to_string()));
+                        push_sample(
+                            &mut samples,
+                            format!("{name}_bucket"),
+                            labels,
+                            SampleValue::Count(bucket.cumulative_count()),
+                        );
+                    }
+                    push_sample(
+                        &mut samples,
+                        format!("{name}_sum"),
+                        base_labels.clone(),
+                        SampleValue::Float(histogram.sample_sum()),
+                    );
+                    push_sample(
+                        &mut samples,
+                        format!("{name}_count"),
+                        base_labels,
+                        SampleValue::Count(histogram.sample_count()),
+                    );
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let mut labels = base_labels.clone();
+                        labels.push(("quantile".to_string(), quantile.quantile().to_string()));
+                        push_sample(
+                            &mut samples,
+                            format!("{name}_quantile"),
+                            labels,
+                            SampleValue::Float(quantile.value()),
+                        );
+                    }
+                    push_sample(
+                        &mut samples,
+                        format!("{name}_sum"),
+                        base_labels.clone(),
+                        SampleValue::Float(summary.sample_sum()),
+                    );
+                    push_sample(
+                        &mut samples,
+                        format!("{name}_count"),
+                        base_labels,
+                        SampleValue::Count(summary.sample_count()),
+                    );
+                }
+            }
+        }
+    }
+
+    samples
+}
@@ -1,5 +1,6 @@
 use std::fs;
 use std::path::Path;
+use std::process::Command;
 use tempfile::TempDir;
 
 /// Helper to create a mock sysfs structure for testing
@@ -41,6 +42,70 @@ fn create_mock_sysfs(base: &Path) {
     fs::write(rapl.join("name"), "package-0\n").unwrap();
     fs::write(rapl.join("energy_uj"), "123456789\n").unwrap();
     fs::write(rapl.join("max_energy_range_uj"), "262143328850\n").unwrap();
+
+    // Create mock NUMA node
+    let node = base.join("sys/devices/system/node/node0");
+    fs::create_dir_all(node.join("hugepages/hugepages-2048kB")).unwrap();
+    fs::write(
+        node.join("meminfo"),
+        "Node 0 MemTotal:       16384 kB\nNode 0 MemFree:         8192 kB\n",
+    )
+    .unwrap();
+    fs::write(node.join("numastat"), "numa_hit 1000\nnuma_miss 5\n").unwrap();
+    fs::write(node.join("cpulist"), "0-3\n").unwrap();
+    fs::write(node.join("distance"), "10\n").unwrap();
+    fs::write(node.join("hugepages/hugepages-2048kB/nr_hugepages"), "16\n").unwrap();
+    fs::write(
+        node.join("hugepages/hugepages-2048kB/free_hugepages"),
+        "4\n",
+    )
+    .unwrap();
+
+    // Create mock EDAC memory controller
+    let mc = base.join("sys/devices/system/edac/mc/mc0");
+    fs::create_dir_all(mc.join("dimm0")).unwrap();
+    fs::write(mc.join("mc_name"), "Sandy Bridge\n").unwrap();
+    fs::write(mc.join("ce_count"), "3\n").unwrap();
+    fs::write(mc.join("ue_count"), "0\n").unwrap();
+    fs::write(mc.join("dimm0/dimm_label"), "DIMM_A1\n").unwrap();
+    fs::write(mc.join("dimm0/dimm_ce_count"), "2\n").unwrap();
+    fs::write(mc.join("dimm0/dimm_ue_count"), "0\n").unwrap();
+    fs::write(mc.join("dimm0/size"), "8192\n").unwrap();
+}
+
+/// Runs one collection against the mock tree and returns the text payload
+fn collect_from_mock(base: &Path) -> String {
+    fs::create_dir_all(base.join("proc")).unwrap();
+    let config = base.join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "sysfs_root = \"{}\"\nprocfs_root = \"{}\"\n",
+            base.join("sys").display(),
+            base.join("proc").display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rs-linux-exporter"))
+        .arg("--once")
+        .arg("--config")
+        .arg(&config)
+        .output()
+        .expect("run exporter");
+    assert!(output.status.success(), "exporter --once failed");
+    String::from_utf8(output.stdout).expect("utf-8 payload")
+}
+
+/// Value of the sample whose name and (sorted) labels match `series` exactly
+fn sample(payload: &str, series: &str) -> Option<f64> {
+    payload.lines().find_map(|line| {
+        line.strip_prefix(series)?
+            .strip_prefix(' ')?
+            .trim()
+            .parse()
+            .ok()
+    })
 }
 
 #[test]
@@ -131,3 +196,175 @@ fn test_rapl_file_content() {
     let name = fs::read_to_string(dir.path().join("sys/class/powercap/intel-rapl:0/name")).unwrap();
     assert_eq!(name.trim(), "package-0");
 }
+
+#[test]
+fn test_hwmon_collector_scales_millidegrees() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(
+        sample(
+            &payload,
+            r#"hwmon_temperature_celsius{chip="coretemp",sensor="temp_1"}"#
+        ),
+        Some(45.0)
+    );
+}
+
+#[test]
+fn test_thermal_collector() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(sample(&payload, "thermal_zone_count"), Some(1.0));
+    assert_eq!(
+        sample(
+            &payload,
+            r#"thermal_zone_temperature_celsius{type="x86_pkg_temp",zone="thermal_zone0"}"#
+        ),
+        Some(50.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"thermal_zone_trip_point_celsius{trip_point="0",trip_type="critical",type="x86_pkg_temp",zone="thermal_zone0"}"#
+        ),
+        Some(100.0)
+    );
+}
+
+#[test]
+fn test_nvme_collector() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(
+        sample(
+            &payload,
+            r#"nvme_info{device="nvme0",firmware_rev="5B2QGXA7",model="Samsung SSD 980 PRO",serial="S5GXNF0N123456"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&payload, r#"nvme_state{device="nvme0",state="live"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&payload, r#"nvme_state{device="nvme0",state="dead"}"#),
+        Some(0.0)
+    );
+}
+
+#[test]
+fn test_rapl_collector_scales_microjoules() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(
+        sample(
+            &payload,
+            r#"rapl_energy_joules{name="package-0",zone="intel-rapl:0"}"#
+        ),
+        Some(123.456789)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"rapl_max_energy_joules{name="package-0",zone="intel-rapl:0"}"#
+        ),
+        Some(262143.32885)
+    );
+}
+
+#[test]
+fn test_power_supply_collector() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(
+        sample(&payload, r#"power_supply_capacity_percent{name="BAT0"}"#),
+        Some(85.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"power_supply_voltage_volts{name="BAT0",type="now"}"#
+        ),
+        Some(12.5)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"power_supply_status{name="BAT0",status="Discharging"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[test]
+fn test_numa_collector() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(sample(&payload, "numa_node_count"), Some(1.0));
+    assert_eq!(
+        sample(
+            &payload,
+            r#"numa_node_memory_bytes{node="node0",type="MemTotal"}"#
+        ),
+        Some(16384.0 * 1024.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"numa_node_stat_pages{node="node0",type="numa_hit"}"#
+        ),
+        Some(1000.0)
+    );
+    assert_eq!(
+        sample(&payload, r#"numa_node_cpu{cpu="3",node="node0"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"numa_node_hugepages_free{node="node0",size="2048kB"}"#
+        ),
+        Some(4.0)
+    );
+}
+
+#[test]
+fn test_edac_collector() {
+    let dir = TempDir::new().unwrap();
+    create_mock_sysfs(dir.path());
+    let payload = collect_from_mock(dir.path());
+
+    assert_eq!(
+        sample(
+            &payload,
+            r#"edac_mc_info{controller="mc0",mc_name="Sandy Bridge"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"edac_mc_correctable_errors_total{controller="mc0"}"#
+        ),
+        Some(3.0)
+    );
+    assert_eq!(
+        sample(
+            &payload,
+            r#"edac_dimm_correctable_errors_total{controller="mc0",dimm="dimm0",dimm_label="DIMM_A1"}"#
+        ),
+        Some(2.0)
+    );
+}